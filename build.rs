@@ -0,0 +1,100 @@
+//! Compresses the large bundled roms, so they take less space in the test binaries that use this
+//! crate. They're decompressed the first time they're used, see `src/bundled.rs` for the format.
+use std::collections::HashMap;
+use std::path::Path;
+
+/// The roms that are compressed, in `src/roms`
+const COMPRESSED: &[&str] = &["all_instrs.nes", "official_only.nes"];
+
+const MIN_MATCH: usize = 3;
+const MAX_MATCH: usize = MIN_MATCH + 0x7F;
+const MAX_LITERALS: usize = 0x80;
+const MAX_OFFSET: usize = u16::MAX as usize;
+/// How many earlier positions are tried for a match, which keeps the build quick
+const MAX_TRIES: usize = 64;
+
+fn main() {
+    let out_dir = std::env::var("OUT_DIR").expect("cargo sets OUT_DIR");
+    for name in COMPRESSED {
+        let path = Path::new("src/roms").join(name);
+        println!("cargo:rerun-if-changed={}", path.display());
+        let rom = std::fs::read(&path)
+            .unwrap_or_else(|e| panic!("couldn't read {}: {e}", path.display()));
+        std::fs::write(
+            Path::new(&out_dir).join(format!("{name}.lz")),
+            compress(&rom),
+        )
+        .expect("couldn't write the compressed rom");
+    }
+}
+
+/// Compresses `data` into literals and matches: a byte below $80 is followed by that many plus 1
+/// literal bytes, a byte from $80 up copies its lower 7 bits plus 3 bytes from the little endian
+/// offset back in the output that follows it.
+fn compress(data: &[u8]) -> Vec<u8> {
+    let mut out = Vec::new();
+    let mut literals = Vec::new();
+    let mut chains = Chains {
+        latest: HashMap::new(),
+        previous: vec![None; data.len()],
+    };
+
+    let mut i = 0;
+    while i < data.len() {
+        let (mut length, mut offset) = (0, 0);
+        let mut candidate = data
+            .get(i..i + MIN_MATCH)
+            .and_then(|key| chains.latest.get(key).copied());
+        for _ in 0..MAX_TRIES {
+            let Some(start) = candidate.filter(|&start| i - start <= MAX_OFFSET) else {
+                break;
+            };
+            let matching = (0..MAX_MATCH.min(data.len() - i))
+                .take_while(|&n| data[start + n] == data[i + n])
+                .count();
+            if matching > length {
+                (length, offset) = (matching, i - start);
+            }
+            candidate = chains.previous[start];
+        }
+
+        if length >= MIN_MATCH {
+            flush(&mut out, &mut literals);
+            out.push(0x80 | (length - MIN_MATCH) as u8);
+            out.extend_from_slice(&(offset as u16).to_le_bytes());
+        } else {
+            length = 1;
+            literals.push(data[i]);
+        }
+        for position in i..i + length {
+            chains.insert(data, position);
+        }
+        i += length;
+    }
+    flush(&mut out, &mut literals);
+    out
+}
+
+/// Where every 3 bytes were seen in the data so far, to find matches
+struct Chains<'a> {
+    /// the last position of 3 bytes
+    latest: HashMap<&'a [u8], usize>,
+    /// for every position, the position before it where the same 3 bytes start
+    previous: Vec<Option<usize>>,
+}
+
+impl<'a> Chains<'a> {
+    fn insert(&mut self, data: &'a [u8], position: usize) {
+        if let Some(key) = data.get(position..position + MIN_MATCH) {
+            self.previous[position] = self.latest.insert(key, position);
+        }
+    }
+}
+
+fn flush(out: &mut Vec<u8>, literals: &mut Vec<u8>) {
+    for chunk in literals.chunks(MAX_LITERALS) {
+        out.push((chunk.len() - 1) as u8);
+        out.extend_from_slice(chunk);
+    }
+    literals.clear();
+}
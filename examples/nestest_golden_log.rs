@@ -0,0 +1,39 @@
+//! Writes the golden log of nestest, the trace of the harness's own cpu, see `nestest_golden_log`:
+//!
+//! ```text
+//! cargo run --example nestest_golden_log --features selftest -- nestest-golden.log [mesen|fceux]
+//! ```
+use std::process::ExitCode;
+use tudelft_nes_test::{nestest_golden_log, TraceFormat};
+
+fn main() -> ExitCode {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    let format = match args.get(1).map(String::as_str) {
+        None | Some("mesen") => TraceFormat::Mesen,
+        Some("fceux") => TraceFormat::Fceux,
+        Some(other) => {
+            eprintln!("unknown trace format '{other}', expected mesen or fceux");
+            return ExitCode::FAILURE;
+        }
+    };
+    let Some(path) = args.first() else {
+        eprintln!("usage: nestest_golden_log <path> [mesen|fceux]");
+        return ExitCode::FAILURE;
+    };
+
+    let result = nestest_golden_log(format).and_then(|log| {
+        std::fs::write(path, log.join("\n") + "\n")
+            .map(|()| log.len())
+            .map_err(|e| format!("couldn't write {path}: {e}"))
+    });
+    match result {
+        Ok(lines) => {
+            println!("wrote {lines} lines to {path}");
+            ExitCode::SUCCESS
+        }
+        Err(e) => {
+            eprintln!("{e}");
+            ExitCode::FAILURE
+        }
+    }
+}
@@ -0,0 +1,139 @@
+//! Counting the reads and writes of every region of memory while a test runs, see [`MemoryAccesses`]
+use crate::watch::BusAccess;
+use std::fmt;
+use std::ops::AddAssign;
+
+/// A test that runs for this many accesses also runs for more than a frame, in which most roms wait
+/// for vblank by reading $2002
+const FRAME_ACCESSES: u64 = 100_000;
+
+/// How often a region of memory was read and written
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct RegionAccesses {
+    /// The number of reads
+    pub reads: u64,
+    /// The number of writes
+    pub writes: u64,
+}
+
+/// How often the cpu read and wrote the regions of the memory map of the NES during a test, counted
+/// with [`TestableCpu::bus_accesses`](crate::TestableCpu::bus_accesses), see
+/// [`TestResult::memory_accesses`](crate::TestResult::memory_accesses). Counts that are way off, like
+/// no reads of the ppu registers in a test that runs for seconds, are a strong hint about what the cpu
+/// doesn't implement, see [`hints`](Self::hints).
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct MemoryAccesses {
+    /// The internal ram and its mirrors, $0000 to $1FFF
+    pub ram: RegionAccesses,
+    /// The registers of the ppu and their mirrors, $2000 to $3FFF
+    pub ppu_registers: RegionAccesses,
+    /// The registers of the apu, sprite DMA and the controllers, $4000 to $401F
+    pub apu_registers: RegionAccesses,
+    /// The cartridge: the registers of its mapper, its prg ram and its prg rom, $4020 to $FFFF
+    pub cartridge: RegionAccesses,
+}
+
+impl MemoryAccesses {
+    pub(crate) fn count(&mut self, access: BusAccess) {
+        let region = match access.address {
+            0x0000..=0x1FFF => &mut self.ram,
+            0x2000..=0x3FFF => &mut self.ppu_registers,
+            0x4000..=0x401F => &mut self.apu_registers,
+            _ => &mut self.cartridge,
+        };
+        if access.write {
+            region.writes += 1;
+        } else {
+            region.reads += 1;
+        }
+    }
+
+    /// The regions with their names, in the order of the memory map
+    pub fn regions(&self) -> [(&'static str, RegionAccesses); 4] {
+        [
+            ("ram", self.ram),
+            ("ppu registers", self.ppu_registers),
+            ("apu registers", self.apu_registers),
+            ("cartridge", self.cartridge),
+        ]
+    }
+
+    /// The number of reads and writes of all regions together
+    pub fn total(&self) -> RegionAccesses {
+        self.regions()
+            .iter()
+            .fold(RegionAccesses::default(), |total, (_, region)| {
+                RegionAccesses {
+                    reads: total.reads + region.reads,
+                    writes: total.writes + region.writes,
+                }
+            })
+    }
+
+    /// What the counts say about the cpu when they're off: every rom runs from the cartridge and
+    /// uses the stack in ram, and a rom running for more than a frame almost always reads $2002
+    pub fn hints(&self) -> Vec<String> {
+        let mut hints = Vec::new();
+        if self.total().reads == 0 {
+            return hints;
+        }
+
+        if self.cartridge.reads == 0 {
+            hints.push(
+                "the cpu never read the cartridge at $4020-$FFFF, where it runs the rom from: \
+                 does bus_accesses report the reads of opcodes and operands?"
+                    .to_owned(),
+            );
+        }
+        if self.ram.writes == 0 {
+            hints.push(
+                "the cpu never wrote to ram at $0000-$1FFF, which every rom uses for its stack: \
+                 does bus_accesses report writes, and do stores and pushes reach ram?"
+                    .to_owned(),
+            );
+        }
+        if self.ppu_registers.reads == 0 && self.total().reads > FRAME_ACCESSES {
+            hints.push(
+                "the cpu never read the ppu registers at $2000-$3FFF, which most roms read to wait \
+                 for vblank: does the bus map them, and their mirrors?"
+                    .to_owned(),
+            );
+        }
+        hints
+    }
+}
+
+impl AddAssign for RegionAccesses {
+    fn add_assign(&mut self, other: Self) {
+        self.reads += other.reads;
+        self.writes += other.writes;
+    }
+}
+
+impl AddAssign for MemoryAccesses {
+    fn add_assign(&mut self, other: Self) {
+        self.ram += other.ram;
+        self.ppu_registers += other.ppu_registers;
+        self.apu_registers += other.apu_registers;
+        self.cartridge += other.cartridge;
+    }
+}
+
+/// Like `ram: 1200 reads, 300 writes; ppu registers: 0 reads, 2 writes; ...`
+impl fmt::Display for MemoryAccesses {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (i, (name, region)) in self.regions().iter().enumerate() {
+            if i > 0 {
+                write!(f, "; ")?;
+            }
+            write!(
+                f,
+                "{name}: {} reads, {} writes",
+                region.reads, region.writes
+            )?;
+        }
+        Ok(())
+    }
+}
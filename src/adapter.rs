@@ -0,0 +1,229 @@
+//! The [`testable_cpu!`](crate::testable_cpu) macro, which implements [`TestableCpu`](crate::TestableCpu)
+//! with the methods your cpu already has
+
+/// Implements [`TestableCpu`](crate::TestableCpu) for your cpu, with a function or closure for
+/// every method of the trait you want to implement. Closures get the cpu as their first argument,
+/// and can't capture anything:
+/// ```
+/// use std::error::Error;
+/// use tudelft_nes_test::{testable_cpu, Cpu, Ppu};
+///
+/// struct Bus {
+///     ram: [u8; 0x800],
+/// }
+///
+/// impl Bus {
+///     fn read(&self, address: u16) -> u8 {
+///         self.ram[usize::from(address) % 0x800]
+///     }
+/// }
+///
+/// struct MyCpu {
+///     pc: u16,
+///     bus: Bus,
+/// }
+///
+/// impl MyCpu {
+///     fn new(_rom: &[u8]) -> Result<Self, Box<dyn Error + Send + Sync>> {
+///         Ok(Self { pc: 0, bus: Bus { ram: [0; 0x800] } })
+///     }
+/// }
+///
+/// impl Cpu for MyCpu {
+///     // ...
+/// #   fn tick(&mut self, ppu: &mut Ppu) -> Result<(), Box<dyn Error>> { Ok(()) }
+/// #   fn ppu_read_chr_rom(&self, offset: u16) -> u8 { 0 }
+/// #   fn non_maskable_interrupt(&mut self) {}
+/// }
+///
+/// testable_cpu! {
+///     impl TestableCpu for MyCpu {
+///         get_cpu: MyCpu::new,
+///         set_program_counter: |cpu, pc| cpu.pc = pc,
+///         memory_read: |cpu, address| cpu.bus.read(address),
+///         program_counter: |cpu| cpu.pc,
+///     }
+/// }
+/// ```
+///
+/// `get_cpu`, or `new` when creating your cpu can't fail, and `memory_read` are required. When your
+/// constructor returns another error than `Box<dyn Error + Send + Sync>`, like an `anyhow::Error`,
+/// convert it with `get_cpu: |rom| Ok(MyCpu::new(rom)?)`. The optional methods that return whether you implemented
+/// them take a function that doesn't, the macro does that:
+///
+/// | method                 | function                            |
+/// |------------------------|-------------------------------------|
+/// | `get_cpu`              | `fn(&[u8]) -> Result<Self, Box<dyn Error + Send + Sync>>` |
+/// | `new`                  | `fn(&[u8]) -> Self`                 |
+/// | `set_program_counter`  | `fn(&mut Self, u16)`                |
+/// | `memory_read`          | `fn(&Self, u16) -> u8`              |
+/// | `memory_peek`          | `fn(&Self, u16) -> u8`              |
+/// | `supports_mapper`      | `fn(u8) -> bool`                    |
+/// | `program_counter`      | `fn(&Self) -> u16`                  |
+/// | `memory_write`         | `fn(&mut Self, u16, u8)`            |
+/// | `init_ram`             | `fn(&mut Self, &[u8])`              |
+/// | `reset`                | `fn(&mut Self)`                     |
+/// | `supports_dma`         | a `bool` instead of a function      |
+/// | `set_buttons`          | `fn(&mut Self, u8, Buttons)`        |
+/// | `set_region`           | `fn(&mut Self, Region)`             |
+/// | `save_state`           | `fn(&Self) -> Vec<u8>`              |
+/// | `load_state`           | `fn(&mut Self, &[u8]) -> bool`      |
+/// | `bus_accesses`         | `fn(&mut Self, &mut dyn FnMut(BusAccess))` |
+/// | `finished_instruction` | `fn(&Self) -> bool`                 |
+/// | `registers`            | `fn(&Self) -> Registers`            |
+/// | `cycles_executed`      | `fn(&Self) -> u64`                  |
+/// | `frame`                | `fn(&Self) -> Vec<u8>`              |
+#[macro_export]
+macro_rules! testable_cpu {
+    (impl TestableCpu for $cpu:ty { $($method:ident: $function:expr),* $(,)? }) => {
+        impl $crate::TestableCpu for $cpu {
+            $($crate::__testable_cpu_method!($method $function);)*
+        }
+    };
+}
+
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __testable_cpu_method {
+    (get_cpu $function:expr) => {
+        fn get_cpu(
+            rom: &[u8],
+        ) -> ::std::result::Result<
+            Self,
+            ::std::boxed::Box<dyn ::std::error::Error + ::std::marker::Send + ::std::marker::Sync>,
+        > {
+            let function: fn(&[u8]) -> _ = $function;
+            function(rom)
+        }
+    };
+    (new $function:expr) => {
+        fn get_cpu(
+            rom: &[u8],
+        ) -> ::std::result::Result<
+            Self,
+            ::std::boxed::Box<dyn ::std::error::Error + ::std::marker::Send + ::std::marker::Sync>,
+        > {
+            let function: fn(&[u8]) -> Self = $function;
+            Ok(function(rom))
+        }
+    };
+    (set_program_counter $function:expr) => {
+        fn set_program_counter(&mut self, value: u16) {
+            let function: fn(&mut Self, u16) = $function;
+            function(self, value)
+        }
+    };
+    (memory_read $function:expr) => {
+        fn memory_read(&self, address: u16) -> u8 {
+            let function: fn(&Self, u16) -> u8 = $function;
+            function(self, address)
+        }
+    };
+    (memory_peek $function:expr) => {
+        fn memory_peek(&self, address: u16) -> u8 {
+            let function: fn(&Self, u16) -> u8 = $function;
+            function(self, address)
+        }
+    };
+    (supports_mapper $function:expr) => {
+        fn supports_mapper(mapper: u8) -> bool {
+            let function: fn(u8) -> bool = $function;
+            function(mapper)
+        }
+    };
+    (program_counter $function:expr) => {
+        fn program_counter(&self) -> Option<u16> {
+            let function: fn(&Self) -> u16 = $function;
+            Some(function(self))
+        }
+    };
+    (memory_write $function:expr) => {
+        fn memory_write(&mut self, address: u16, value: u8) -> bool {
+            let function: fn(&mut Self, u16, u8) = $function;
+            function(self, address, value);
+            true
+        }
+    };
+    (init_ram $function:expr) => {
+        fn init_ram(&mut self, ram: &[u8]) -> bool {
+            let function: fn(&mut Self, &[u8]) = $function;
+            function(self, ram);
+            true
+        }
+    };
+    (reset $function:expr) => {
+        fn reset(&mut self) -> bool {
+            let function: fn(&mut Self) = $function;
+            function(self);
+            true
+        }
+    };
+    (supports_dma $supported:expr) => {
+        fn supports_dma() -> bool {
+            $supported
+        }
+    };
+    (set_buttons $function:expr) => {
+        fn set_buttons(&mut self, controller: u8, buttons: $crate::Buttons) -> bool {
+            let function: fn(&mut Self, u8, $crate::Buttons) = $function;
+            function(self, controller, buttons);
+            true
+        }
+    };
+    (set_region $function:expr) => {
+        fn set_region(&mut self, region: $crate::Region) {
+            let function: fn(&mut Self, $crate::Region) = $function;
+            function(self, region)
+        }
+    };
+    (save_state $function:expr) => {
+        fn save_state(&self) -> Option<::std::vec::Vec<u8>> {
+            let function: fn(&Self) -> ::std::vec::Vec<u8> = $function;
+            Some(function(self))
+        }
+    };
+    (load_state $function:expr) => {
+        fn load_state(&mut self, state: &[u8]) -> bool {
+            let function: fn(&mut Self, &[u8]) -> bool = $function;
+            function(self, state)
+        }
+    };
+    (bus_accesses $function:expr) => {
+        fn bus_accesses(&mut self, on_access: &mut dyn FnMut($crate::BusAccess)) -> bool {
+            let function: fn(&mut Self, &mut dyn FnMut($crate::BusAccess)) = $function;
+            function(self, on_access);
+            true
+        }
+    };
+    (finished_instruction $function:expr) => {
+        fn finished_instruction(&self) -> Option<bool> {
+            let function: fn(&Self) -> bool = $function;
+            Some(function(self))
+        }
+    };
+    (registers $function:expr) => {
+        fn registers(&self) -> Option<$crate::Registers> {
+            let function: fn(&Self) -> $crate::Registers = $function;
+            Some(function(self))
+        }
+    };
+    (cycles_executed $function:expr) => {
+        fn cycles_executed(&self) -> Option<u64> {
+            let function: fn(&Self) -> u64 = $function;
+            Some(function(self))
+        }
+    };
+    (frame $function:expr) => {
+        fn frame(&self) -> Option<::std::vec::Vec<u8>> {
+            let function: fn(&Self) -> ::std::vec::Vec<u8> = $function;
+            Some(function(self))
+        }
+    };
+    ($method:ident $function:expr) => {
+        compile_error!(concat!(
+            "`",
+            stringify!($method),
+            "` isn't a method of TestableCpu"
+        ));
+    };
+}
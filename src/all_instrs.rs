@@ -1,10 +1,35 @@
-use crate::{TestError, TestableCpu};
+use crate::log_target;
+use crate::report::SubTestResult;
+use crate::status::{blargg_status_at, read_status_string_at, BlarggStatus, StatusAddresses};
+use crate::{TestError, TestSelector, TestableCpu, UnofficialOpcodes};
+use std::borrow::Cow;
 
-pub(crate) fn all_instrs_status_code(cpu: &impl TestableCpu) -> Result<(), TestError> {
-    let status = cpu.memory_read(0x6000);
-    let m1 = cpu.memory_read(0x6001);
-    let m2 = cpu.memory_read(0x6002);
-    let m3 = cpu.memory_read(0x6003);
+/// The instruction groups all_instrs tests, by the name of their single rom
+pub(crate) const INSTR_GROUPS: [(&str, TestSelector); 16] = [
+    ("01-basics", TestSelector::INSTR_BASICS),
+    ("02-implied", TestSelector::INSTR_IMPLIED),
+    ("03-immediate", TestSelector::INSTR_IMMEDIATE),
+    ("04-zero_page", TestSelector::INSTR_ZERO_PAGE),
+    ("05-zp_xy", TestSelector::INSTR_ZP_XY),
+    ("06-absolute", TestSelector::INSTR_ABSOLUTE),
+    ("07-abs_xy", TestSelector::INSTR_ABS_XY),
+    ("08-ind_x", TestSelector::INSTR_IND_X),
+    ("09-ind_y", TestSelector::INSTR_IND_Y),
+    ("10-branches", TestSelector::INSTR_BRANCHES),
+    ("11-stack", TestSelector::INSTR_STACK),
+    ("12-jmp_jsr", TestSelector::INSTR_JMP_JSR),
+    ("13-rts", TestSelector::INSTR_RTS),
+    ("14-rti", TestSelector::INSTR_RTI),
+    ("15-brk", TestSelector::INSTR_BRK),
+    ("16-special", TestSelector::INSTR_SPECIAL),
+];
+
+pub(crate) fn all_instrs_status_code(
+    cpu: &impl TestableCpu,
+    at: &StatusAddresses,
+) -> Result<(), TestError> {
+    let status = cpu.memory_peek(at.status);
+    let [m1, m2, m3] = [0, 1, 2].map(|i| cpu.memory_peek(at.magic.wrapping_add(i)));
 
     if m1 != 0xde || m2 != 0xb0 || m3 != 0x61 {
         return Err(TestError::String(format!(
@@ -12,26 +37,277 @@ pub(crate) fn all_instrs_status_code(cpu: &impl TestableCpu) -> Result<(), TestE
         )));
     }
 
-    if status == 0 {
-        Ok(())
+    let message = format!(
+        "exited with status {status}:\n {}",
+        read_status_string_at(cpu, at)
+    );
+    match status {
+        0 => Ok(()),
+        // the rom is done, and the status is the code of the sub-test that failed
+        1..=0x7F => Err(TestError::SubTests(message)),
+        _ => Err(TestError::String(message)),
+    }
+}
+
+/// Whether the test rom has written its final result
+pub(crate) fn all_instrs_finished(cpu: &impl TestableCpu, at: &StatusAddresses) -> bool {
+    matches!(blargg_status_at(cpu, at), Some(BlarggStatus::Finished(_)))
+}
+
+/// Whether the test rom asks for the reset button to be pressed
+pub(crate) fn reset_requested(cpu: &impl TestableCpu, at: &StatusAddresses) -> bool {
+    blargg_status_at(cpu, at) == Some(BlarggStatus::ResetRequested)
+}
+
+/// Whether the magic sequence is there, which means the rom reports its status
+pub(crate) fn has_status(cpu: &impl TestableCpu, at: &StatusAddresses) -> bool {
+    blargg_status_at(cpu, at).is_some()
+}
+
+/// The group of instr_test-v5 that all_instrs.nes says it runs in `status`, like "Running test 11 of
+/// 16", counting from 1
+pub(crate) fn running_group(status: &str) -> Option<usize> {
+    status
+        .strip_prefix("Running test ")
+        .and_then(|s| s.split_whitespace().next())
+        .and_then(|n| n.parse().ok())
+        .filter(|&n| (1..=INSTR_GROUPS.len()).contains(&n))
+}
+
+/// The category of an unofficial opcode tested by all_instrs, or `None` for official opcodes
+fn unofficial_category(opcode: u8) -> Option<UnofficialOpcodes> {
+    match opcode {
+        0x1A | 0x3A | 0x5A | 0x7A | 0xDA | 0xFA => Some(UnofficialOpcodes::NOPS),
+        0x04 | 0x44 | 0x64 | 0x14 | 0x34 | 0x54 | 0x74 | 0xD4 | 0xF4 => {
+            Some(UnofficialOpcodes::NOPS)
+        }
+        0x80 | 0x82 | 0x89 | 0xC2 | 0xE2 | 0x0C | 0x1C | 0x3C | 0x5C | 0x7C | 0xDC | 0xFC => {
+            Some(UnofficialOpcodes::NOPS)
+        }
+        0xA7 | 0xB7 | 0xAF | 0xBF | 0xA3 | 0xB3 | 0x87 | 0x97 | 0x8F | 0x83 => {
+            Some(UnofficialOpcodes::LAX_SAX)
+        }
+        0xEB | 0x0B | 0x2B | 0x4B | 0x6B | 0xCB => Some(UnofficialOpcodes::IMMEDIATE),
+        0xAB | 0x9C | 0x9E => Some(UnofficialOpcodes::UNSTABLE),
+        // SLO, RLA, SRE, RRA, DCP and ISC in all their addressing modes
+        op if op & 0x03 == 0x03 && !matches!(op & 0xE0, 0x80 | 0xA0) => {
+            Some(UnofficialOpcodes::RMW)
+        }
+        _ => None,
+    }
+}
+
+/// `LDX #3; LDY $25; LDA $12,X; CMP checksums,Y`: the code of the instr_test-v5 shell that compares
+/// the checksum of the results of an instruction with the correct one
+const COMPARE_CHECKSUM: &[u8] = &[0xA2, 0x03, 0xA4, 0x25, 0xB5, 0x12, 0xD9];
+/// `LDA $25; CLC; ADC #4; CMP #size`: moves on to the next instruction in the table. It's preceded by
+/// `LDA instructions,Y` and two subroutine calls.
+const NEXT_INSTRUCTION: &[u8] = &[0xA5, 0x25, 0x18, 0x69, 0x04, 0xC9];
+
+/// The table of the instructions that a test of instr_test-v5 tests, 4 bytes each, and the table with
+/// the checksum of the correct results of every instruction, in a bank of the rom
+pub(crate) struct Table {
+    /// The bank, counting from 0
+    pub(crate) bank: usize,
+    /// Where the tables are in the rom
+    instructions: usize,
+    checksums: usize,
+    /// The number of instructions
+    pub(crate) count: usize,
+}
+
+const BANK_SIZE: usize = 0x4000;
+const BANK_ADDRESS: usize = 0xC000;
+
+impl Table {
+    /// The tables of the tests in an instr_test-v5 rom, one for every bank that has a test with
+    /// them. Roms without these tables have none.
+    pub(crate) fn find_all(rom: &[u8]) -> Vec<Self> {
+        let mut tables = Vec::new();
+        for (i, bank) in rom
+            .get(16..)
+            .unwrap_or_default()
+            .chunks(BANK_SIZE)
+            .enumerate()
+        {
+            let find = |code: &[u8]| bank.windows(code.len()).position(|w| w == code);
+            let (Some(compare), Some(next)) = (find(COMPARE_CHECKSUM), find(NEXT_INSTRUCTION))
+            else {
+                continue;
+            };
+            let read_address =
+                |at: usize| usize::from(u16::from_le_bytes([bank[at], bank[at + 1]]));
+
+            let load = match next.checked_sub(9) {
+                Some(load) if bank[load] == 0xB9 => load,
+                _ => continue,
+            };
+            let instructions = read_address(load + 1).wrapping_sub(BANK_ADDRESS);
+            let checksums =
+                read_address(compare + COMPARE_CHECKSUM.len()).wrapping_sub(BANK_ADDRESS);
+            let count = usize::from(bank[next + NEXT_INSTRUCTION.len()]) / 4;
+            if instructions + 4 * count > BANK_SIZE || checksums + 4 * count > BANK_SIZE {
+                continue;
+            }
+
+            let start = 16 + i * BANK_SIZE;
+            tables.push(Self {
+                bank: i,
+                instructions: start + instructions,
+                checksums: start + checksums,
+                count,
+            });
+        }
+        tables
+    }
+
+    /// The opcode of instruction `i`
+    pub(crate) fn opcode(&self, rom: &[u8], i: usize) -> u8 {
+        rom[self.instructions + 4 * i]
+    }
+
+    /// The name the rom shows for instruction `i`, like "ROL A", when it's in the same bank
+    pub(crate) fn name(&self, rom: &[u8], i: usize) -> Option<String> {
+        let at = self.instructions + 4 * i;
+        let address = usize::from(u16::from_le_bytes([rom[at + 2], rom[at + 3]]));
+        let start = 16 + self.bank * BANK_SIZE + address.checked_sub(BANK_ADDRESS)?;
+        let name = rom.get(start..16 + (self.bank + 1) * BANK_SIZE)?;
+        let end = name.iter().position(|&b| b == 0)?;
+        Some(String::from_utf8_lossy(&name[..end]).into_owned()).filter(|n| !n.is_empty())
+    }
+
+    /// Whether instructions `i` and `j` are the same, with the same checksum
+    pub(crate) fn same(&self, rom: &[u8], i: usize, j: usize) -> bool {
+        let entry = |table: usize, i: usize| &rom[table + 4 * i..table + 4 * i + 4];
+        entry(self.instructions, i) == entry(self.instructions, j)
+            && entry(self.checksums, i) == entry(self.checksums, j)
+    }
+
+    /// Replaces instruction `i` by instruction `by`, together with its checksum, so the rom tests
+    /// that instruction twice instead
+    pub(crate) fn replace(&self, rom: &mut [u8], i: usize, by: usize) {
+        for table in [self.instructions, self.checksums] {
+            rom.copy_within(table + 4 * by..table + 4 * by + 4, table + 4 * i);
+        }
+    }
+}
+
+/// Removes the unofficial opcodes that aren't in `keep` from an instr_test-v5 rom.
+///
+/// Each test in the rom has a table of the instructions it tests, 4 bytes each, and a table with
+/// the checksum of the correct results of every instruction. The instructions that shouldn't be
+/// tested are replaced by the first instruction of the table, together with its checksum, so the
+/// rom tests that instruction twice instead. Roms without these tables are returned unchanged.
+pub(crate) fn without_unofficial(
+    rom: Cow<'static, [u8]>,
+    keep: UnofficialOpcodes,
+) -> Cow<'static, [u8]> {
+    if keep.is_all() {
+        return rom;
+    }
+
+    let mut rom = rom.into_owned();
+    let mut removed = Vec::new();
+    for table in Table::find_all(&rom) {
+        for i in 1..table.count {
+            let opcode = table.opcode(&rom, i);
+            if unofficial_category(opcode).is_some_and(|c| !keep.contains(c)) {
+                table.replace(&mut rom, i, 0);
+                removed.push(format!("{opcode:02X}"));
+            }
+        }
+    }
+
+    if removed.is_empty() {
+        log_target::warn!("couldn't find unofficial opcodes to leave out of the test rom");
     } else {
-        Err(TestError::String(format!(
-            "exited with status {status}:\n {}",
-            read_status_string(cpu)
-        )))
+        log_target::info!("not testing the unofficial opcodes {}", removed.join(" "));
     }
+
+    Cow::Owned(rom)
+}
+
+/// Parses the result of a sub-test from the status text, which the roms show after running it.
+/// The multi-test roms show the name of the sub-test above the verdict, and a failed sub-test shows
+/// what failed above that, like "07 SLO z\n04-zero_page\n\nFailed". The verdict may be followed by
+/// an error code, like "Failed #2". The name is empty when the status doesn't show one.
+pub(crate) fn sub_test_result(status: &str) -> Option<SubTestResult> {
+    let lines: Vec<_> = status
+        .lines()
+        .map(str::trim)
+        .filter(|l| !l.is_empty())
+        .collect();
+
+    let verdict = lines
+        .iter()
+        .position(|&l| l == "Passed" || l == "Failed" || l.starts_with("Failed #"))?;
+    let (name, detail) = match lines[..verdict].split_last() {
+        Some((name, detail)) => (name.to_string(), detail.join("\n")),
+        None => (String::new(), String::new()),
+    };
+
+    Some(SubTestResult {
+        name,
+        passed: lines[verdict] == "Passed",
+        detail: Some(detail).filter(|d| !d.is_empty()),
+    })
 }
 
-pub(crate) fn read_status_string(cpu: &impl TestableCpu) -> String {
-    let mut res = String::new();
-    for i in 0x6004..=0x7000 {
-        let b = cpu.memory_read(i);
-        if b == 0 {
-            break;
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::error::Error;
+    use tudelft_nes_ppu::{Cpu, Ppu};
+
+    /// Only memory, with a status block at $6000
+    struct Memory(Vec<u8>);
+
+    impl Memory {
+        fn with_status(status: u8, text: &str) -> Self {
+            let mut memory = vec![0; 0x10000];
+            memory[0x6000] = status;
+            memory[0x6001..0x6004].copy_from_slice(&[0xde, 0xb0, 0x61]);
+            memory[0x6004..0x6004 + text.len()].copy_from_slice(text.as_bytes());
+            Self(memory)
+        }
+    }
+
+    impl Cpu for Memory {
+        fn tick(&mut self, _ppu: &mut Ppu) -> Result<(), Box<dyn Error>> {
+            Ok(())
+        }
+
+        fn ppu_read_chr_rom(&self, _offset: u16) -> u8 {
+            0
+        }
+
+        fn non_maskable_interrupt(&mut self) {}
+    }
+
+    impl TestableCpu for Memory {
+        fn get_cpu(_rom: &[u8]) -> Result<Self, Box<dyn Error + Send + Sync>> {
+            Ok(Self::with_status(0x80, ""))
+        }
+
+        fn memory_read(&self, address: u16) -> u8 {
+            self.0[usize::from(address)]
         }
+    }
 
-        res.push(char::from_u32(u32::from(b)).unwrap_or('�'))
+    #[test]
+    fn a_rom_that_finished_failed_because_of_its_sub_tests() {
+        let cpu = Memory::with_status(3, "03-immediate\n\nFailed");
+        let status = all_instrs_status_code(&cpu, &StatusAddresses::default());
+        assert!(matches!(status, Err(TestError::SubTests(_))));
     }
 
-    res
+    #[test]
+    fn a_rom_that_is_still_running_did_not_fail_because_of_its_sub_tests() {
+        let cpu = Memory::with_status(0x80, "03-immediate");
+        let status = all_instrs_status_code(&cpu, &StatusAddresses::default());
+        assert!(matches!(status, Err(TestError::String(_))));
+
+        let cpu = Memory::with_status(0, "Passed");
+        assert!(all_instrs_status_code(&cpu, &StatusAddresses::default()).is_ok());
+    }
 }
@@ -0,0 +1,161 @@
+//! A file for every test with everything the harness saw of it, for CI to upload, see
+//! [`TestConfig::artifact_dir`](crate::TestConfig::artifact_dir)
+use crate::log_target;
+use crate::report::{FinalState, Progress, TestReport, TestResult};
+use crate::Reporter;
+use std::fmt::Write;
+use std::path::{Path, PathBuf};
+
+/// Passes everything on to the reporter of the run, and writes the artifact of every test that finished
+pub(crate) struct WithArtifacts<'a> {
+    reporter: &'a mut dyn Reporter,
+    dir: Option<PathBuf>,
+    /// the final states of the cpu in the test that is running, one for every time it was started
+    states: Vec<FinalState>,
+}
+
+impl<'a> WithArtifacts<'a> {
+    pub(crate) fn new(reporter: &'a mut dyn Reporter, dir: Option<&Path>) -> Self {
+        Self {
+            reporter,
+            dir: dir.map(Path::to_path_buf),
+            states: Vec::new(),
+        }
+    }
+}
+
+impl Reporter for WithArtifacts<'_> {
+    fn run_started(&mut self, tests: usize) {
+        self.reporter.run_started(tests)
+    }
+
+    fn test_started(&mut self, name: &str) {
+        self.states.clear();
+        self.reporter.test_started(name)
+    }
+
+    fn progress(&mut self, name: &str, progress: &Progress) {
+        if let (Some(_), Progress::Finished(state)) = (&self.dir, progress) {
+            self.states.push(state.clone());
+        }
+        self.reporter.progress(name, progress)
+    }
+
+    fn test_finished(&mut self, result: &TestResult) {
+        if let Some(dir) = &self.dir {
+            let path = dir.join(file_name(&result.name));
+            if let Err(e) = write(&path, &artifact(result, &self.states)) {
+                log_target::warn!("couldn't write the artifact of {}: {e}", result.name);
+            }
+        }
+        self.reporter.test_finished(result)
+    }
+
+    fn run_finished(&mut self, report: &TestReport) {
+        self.reporter.run_finished(report)
+    }
+}
+
+/// The name of the artifact of test `name`, like `all_instructions__official_only_.txt`
+fn file_name(name: &str) -> String {
+    let name: String = name
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() { c } else { '_' })
+        .collect();
+    format!("{name}.txt")
+}
+
+/// What the harness saw of a test: its result, the sub-tests, the attempts that failed, what was
+/// logged, and the status text and watchpoint hits of every time the cpu was started
+fn artifact(result: &TestResult, states: &[FinalState]) -> String {
+    let mut text = format!("test: {}\n", result.name);
+    let outcome = match (&result.outcome, &result.skipped) {
+        (_, Some(reason)) => format!("skipped, {reason}"),
+        (Ok(()), None) if result.cached => "ok, passed for this build before".to_string(),
+        (Ok(()), None) => "ok".to_string(),
+        (Err(e), None) => format!("FAILED, {e}"),
+    };
+    let _ = writeln!(text, "result: {outcome}");
+    let _ = writeln!(text, "duration: {:.2?}", result.duration);
+
+    if !result.sub_tests.is_empty() {
+        text.push_str("\nsub-tests:\n");
+        for sub_test in &result.sub_tests {
+            let passed = if sub_test.passed { "ok" } else { "FAILED" };
+            let _ = writeln!(text, "    {passed:<6}  {}", sub_test.name);
+            if let Some(detail) = &sub_test.detail {
+                for line in detail.lines() {
+                    let _ = writeln!(text, "            {line}");
+                }
+            }
+        }
+    }
+    if !result.expected_failures.is_empty() {
+        text.push_str("\nallowed to fail:\n");
+        for name in &result.expected_failures {
+            let _ = writeln!(text, "    {name}");
+        }
+    }
+    if !result.failed_attempts.is_empty() {
+        text.push_str("\nattempts that failed before:\n");
+        for e in &result.failed_attempts {
+            let _ = writeln!(text, "    {e}");
+        }
+    }
+    if !result.logs.is_empty() {
+        text.push_str("\nlogged during the test:\n");
+        for line in &result.logs {
+            let _ = writeln!(text, "    {line}");
+        }
+    }
+
+    for (i, state) in states.iter().enumerate() {
+        let _ = writeln!(text, "\nrun {} of the cpu, {} cycles:", i + 1, state.cycles);
+        if state.status.trim().is_empty() {
+            text.push_str("    no status text\n");
+        } else {
+            text.push_str("    status text:\n");
+            for line in state.status.trim_end().lines() {
+                let _ = writeln!(text, "        {line}");
+            }
+        }
+        if !state.watchpoint_hits.is_empty() {
+            text.push_str("    last watchpoint hits:\n");
+            for hit in &state.watchpoint_hits {
+                let _ = writeln!(text, "        {hit}");
+            }
+        }
+        if !state.trace.is_empty() && result.outcome.is_err() {
+            let _ = writeln!(
+                text,
+                "    trace of the last {} instructions:",
+                state.trace.len()
+            );
+            for line in &state.trace {
+                let _ = writeln!(text, "        {line}");
+            }
+        }
+        if let Some(accesses) = &state.memory_accesses {
+            text.push_str("    memory accesses:\n");
+            for (name, region) in accesses.regions() {
+                let _ = writeln!(
+                    text,
+                    "        {name}: {} reads, {} writes",
+                    region.reads, region.writes
+                );
+            }
+            for hint in accesses.hints() {
+                let _ = writeln!(text, "    hint: {hint}");
+            }
+        }
+    }
+
+    text
+}
+
+fn write(path: &Path, text: &str) -> std::io::Result<()> {
+    if let Some(dir) = path.parent() {
+        std::fs::create_dir_all(dir)?;
+    }
+    std::fs::write(path, text)
+}
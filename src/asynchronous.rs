@@ -0,0 +1,202 @@
+//! Running the tests in the background, for async code that can't block on them,
+//! see [`run_tests_async`](crate::run_tests_async) and [`run_tests_async_on`](crate::run_tests_async_on)
+use crate::report::{Progress, TestReport, TestResult};
+use crate::{run_tests_with_reporter, CancelToken, Reporter, TestConfig, TestableCpu};
+use std::collections::VecDeque;
+use std::future::{poll_fn, Future};
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::task::{Context, Poll, Waker};
+
+/// Something that happened in a [`TestRun`], like a [`Reporter`] would hear about it
+#[derive(Debug, Clone)]
+#[non_exhaustive]
+pub enum RunEvent {
+    /// A test started
+    TestStarted(String),
+    /// A running test made progress
+    Progress {
+        /// The name of the test
+        test: String,
+        /// What happened
+        progress: Progress,
+    },
+    /// A test finished
+    TestFinished(TestResult),
+}
+
+#[derive(Default)]
+struct Shared {
+    events: VecDeque<RunEvent>,
+    results: Vec<TestResult>,
+    report: Option<TestReport>,
+    /// whether the tests are done, normally, because they panicked, or because they never ran
+    done: bool,
+    /// the tasks waiting for an event or for the result of a test
+    wakers: Vec<Waker>,
+}
+
+impl Shared {
+    fn push(&mut self, event: RunEvent) {
+        self.events.push_back(event);
+        self.wake();
+    }
+
+    fn wake(&mut self) {
+        for waker in self.wakers.drain(..) {
+            waker.wake();
+        }
+    }
+
+    fn wait(&mut self, cx: &Context<'_>) {
+        if !self.wakers.iter().any(|w| w.will_wake(cx.waker())) {
+            self.wakers.push(cx.waker().clone());
+        }
+    }
+}
+
+/// Passes what the tests report to the [`TestRun`]
+struct Forward(Arc<Mutex<Shared>>);
+
+impl Forward {
+    fn shared(&self) -> MutexGuard<'_, Shared> {
+        lock(&self.0)
+    }
+}
+
+impl Reporter for Forward {
+    fn test_started(&mut self, name: &str) {
+        self.shared().push(RunEvent::TestStarted(name.to_string()));
+    }
+
+    fn progress(&mut self, name: &str, progress: &Progress) {
+        self.shared().push(RunEvent::Progress {
+            test: name.to_string(),
+            progress: progress.clone(),
+        });
+    }
+
+    fn test_finished(&mut self, result: &TestResult) {
+        let mut shared = self.shared();
+        shared.results.push(result.clone());
+        shared.push(RunEvent::TestFinished(result.clone()));
+    }
+
+    fn run_finished(&mut self, report: &TestReport) {
+        self.shared().report = Some(report.clone());
+    }
+}
+
+impl Drop for Forward {
+    fn drop(&mut self) {
+        let mut shared = self.shared();
+        shared.done = true;
+        shared.wake();
+    }
+}
+
+fn lock(shared: &Mutex<Shared>) -> MutexGuard<'_, Shared> {
+    shared.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+/// Running the tests, which blocks until they're done, to run on a thread of your own or the
+/// blocking pool of your executor with [`run_tests_async_on`](crate::run_tests_async_on)
+pub type BlockingJob = Box<dyn FnOnce() + Send>;
+
+/// Tests that run in the background, started with [`run_tests_async`](crate::run_tests_async).
+/// Dropping it cancels the tests.
+pub struct TestRun {
+    shared: Arc<Mutex<Shared>>,
+    cancel: CancelToken,
+}
+
+impl TestRun {
+    pub(crate) fn start<T: TestableCpu>(
+        mut config: TestConfig,
+        spawn: impl FnOnce(BlockingJob),
+    ) -> Self {
+        let cancel = config.cancel.get_or_insert_with(CancelToken::new).clone();
+        let shared = Arc::new(Mutex::new(Shared::default()));
+        let mut forward = Forward(shared.clone());
+        // a job that's dropped without running drops the forward as well, which ends the run
+        spawn(Box::new(move || {
+            run_tests_with_reporter::<T>(&config, &mut forward);
+        }));
+
+        Self { shared, cancel }
+    }
+
+    /// Waits for the next thing that happens in the run, or returns `None` once all tests are done
+    /// and every event was returned
+    pub async fn next_event(&mut self) -> Option<RunEvent> {
+        poll_fn(|cx| {
+            let mut shared = lock(&self.shared);
+            match shared.events.pop_front() {
+                Some(event) => Poll::Ready(Some(event)),
+                None if shared.done => Poll::Ready(None),
+                None => {
+                    shared.wait(cx);
+                    Poll::Pending
+                }
+            }
+        })
+        .await
+    }
+
+    /// Waits for the test called `name` to finish, and returns its result, or `None` when the run
+    /// ended without it, because it wasn't selected or the run was cancelled before it. It's the
+    /// name of [`TestResult::name`] or the id of [`TestResult::id`].
+    ///
+    /// The future doesn't borrow the run, so every test can be awaited on a task of its own, while
+    /// [`next_event`](Self::next_event) streams the progress. A test that takes longer than
+    /// [`TestConfig::timeout`] fails, so that bounds how long it waits for a test that runs.
+    pub fn result(&self, name: &str) -> impl Future<Output = Option<TestResult>> + Send + 'static {
+        let shared = self.shared.clone();
+        let name = name.to_string();
+        poll_fn(move |cx| {
+            let mut shared = lock(&shared);
+            let finished = shared
+                .results
+                .iter()
+                .find(|result| result.name == name || result.id == name);
+            match finished {
+                Some(result) => Poll::Ready(Some(result.clone())),
+                None if shared.done => Poll::Ready(None),
+                None => {
+                    shared.wait(cx);
+                    Poll::Pending
+                }
+            }
+        })
+    }
+
+    /// Waits for all tests to finish, and returns the results of all of them. The events that
+    /// weren't returned by [`next_event`](Self::next_event) yet are dropped.
+    pub async fn report(mut self) -> TestReport {
+        while self.next_event().await.is_some() {}
+        let mut shared = lock(&self.shared);
+        // without a report the thread running the tests panicked
+        let results = std::mem::take(&mut shared.results);
+        shared.report.take().unwrap_or(TestReport {
+            results,
+            ..TestReport::default()
+        })
+    }
+
+    /// Stops the run: the running test fails, and the tests after it don't run. The events and the
+    /// report are still there, up to and including the test that was stopped.
+    pub fn cancel(&self) {
+        self.cancel.cancel();
+    }
+
+    /// A token that cancels the run like [`cancel`](Self::cancel), for a task that doesn't own it,
+    /// like the handler of a request to stop, while another task awaits the [`report`](Self::report)
+    pub fn cancel_token(&self) -> CancelToken {
+        self.cancel.clone()
+    }
+}
+
+impl Drop for TestRun {
+    fn drop(&mut self) {
+        self.cancel.cancel();
+    }
+}
@@ -0,0 +1,335 @@
+//! Grading the emulators of a whole course at once: the tests of every submission in a directory,
+//! each in processes of its own, ranked in a [`Ranking`], see [`grade_submissions`]. And running the
+//! tests of one submission again every time it's built, see [`watch_submission`].
+use crate::grading::GradingProfile;
+use crate::isolation;
+use crate::log_target;
+use crate::ranking::Ranking;
+use crate::report::TestReport;
+use crate::reporter::Reporter;
+use crate::{run_selected_tests, CancelToken, RamInit, Test, TestConfig, TextReporter};
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::{Duration, SystemTime};
+
+/// How long a test of a submission may take when [`TestConfig::timeout`] doesn't say, so a submission
+/// that hangs doesn't stop the others
+const SUBMISSION_TIMEOUT: Duration = Duration::from_secs(10 * 60);
+
+/// How often [`watch_submission`] looks for changes
+const POLL: Duration = Duration::from_millis(500);
+
+/// Runs the tests of the submission at `executable` in processes of their own, with the tests,
+/// filters, timeout, retries and allowed failures of `config`, and reports them to `reporter`.
+///
+/// A submission is a program that runs its own cpu with [`run_tests_with_config`](crate::run_tests_with_config)
+/// or [`run_tests_with_reporter`](crate::run_tests_with_reporter) from its `main`, like a
+/// `src/main.rs` every student adds from a template:
+/// ```no_run
+/// # use tudelft_nes_test::{run_tests_with_config, TestConfig, TestableCpu};
+/// # fn main_of<MyCpu: TestableCpu>() {
+/// run_tests_with_config::<MyCpu>(&TestConfig::default());
+/// # }
+/// ```
+/// It runs every test in a process of its own, like [`TestConfig::isolate`], so a submission that
+/// crashes or hangs fails its test and is killed. The test runs with the configuration of the
+/// submission itself, so settings like [`TestConfig::on_step`] of `config` don't reach it. Without a
+/// [`TestConfig::timeout`] a test may take 10 minutes.
+///
+/// Submissions are executables only: the harness doesn't load a cpu from a library, like a `cdylib`
+/// plugin, because it can't run a cpu of another build of the crate in its own process safely.
+///
+/// Returns an error when the submission doesn't start, or doesn't run its tests with the harness.
+pub fn run_submission(
+    executable: impl AsRef<Path>,
+    config: &TestConfig,
+    reporter: &mut dyn Reporter,
+) -> Result<TestReport, String> {
+    let executable: Arc<Path> = Arc::from(executable.as_ref());
+    let timeout = config.timeout.unwrap_or(SUBMISSION_TIMEOUT);
+    let tests = isolation::list(&executable, Some(timeout))?
+        .into_iter()
+        .map(|(selector, name, id)| {
+            let executable = executable.clone();
+            Test {
+                selector,
+                name,
+                id: id.clone(),
+                run: Box::new(move |name, config, on_progress| {
+                    let seed = match config.ram_init {
+                        RamInit::Random(seed) => seed,
+                        _ => None,
+                    };
+                    isolation::run(
+                        Some(&executable),
+                        &id,
+                        name,
+                        seed,
+                        config.failure_trace.is_some(),
+                        config.timeout,
+                        on_progress,
+                    )
+                }),
+                roms: None,
+            }
+        })
+        .collect();
+
+    // the submission is isolated already, and the cache and checkpoints only know the harness
+    let config = TestConfig {
+        timeout: Some(timeout),
+        isolate: false,
+        cache_dir: None,
+        checkpoint_dir: None,
+        ..config.clone()
+    };
+    Ok(run_selected_tests(tests, &config, reporter))
+}
+
+/// Runs the tests of every submission in `dir`, the executables in it, with [`run_submission`],
+/// and ranks them by their score in `profile`. Libraries in `dir`, like `cdylib` plugins, are
+/// skipped, see [`run_submission`]. A submission is named after its file, without an
+/// extension, and one that couldn't run its tests is last in the ranking with the reason. With a
+/// [`TestConfig::artifact_dir`] the artifacts of every submission go in a directory of its own in it.
+/// The results of the tests are printed to the console while they run:
+/// ```no_run
+/// use tudelft_nes_test::{grade_submissions, GradingProfile, TestConfig, TestSelector};
+/// use std::time::Duration;
+///
+/// let profile = GradingProfile::new()
+///     .test(TestSelector::OFFICIAL_INSTRS, 60.0)
+///     .test(TestSelector::NESTEST, 40.0);
+/// let config = TestConfig {
+///     selector: TestSelector::OFFICIAL_INSTRS | TestSelector::NESTEST,
+///     timeout: Some(Duration::from_secs(60)),
+///     ..TestConfig::default()
+/// };
+/// let ranking = grade_submissions("submissions", &config, profile).unwrap();
+/// std::fs::write("ranking.csv", ranking.csv()).unwrap();
+/// std::fs::write("ranking.json", ranking.json()).unwrap();
+/// ```
+///
+/// Returns an error when `dir` can't be read.
+pub fn grade_submissions(
+    dir: impl AsRef<Path>,
+    config: &TestConfig,
+    profile: GradingProfile,
+) -> io::Result<Ranking> {
+    let mut ranking = Ranking::new(profile);
+    for executable in submissions(dir.as_ref())? {
+        let name = executable
+            .file_stem()
+            .unwrap_or(executable.as_os_str())
+            .to_string_lossy()
+            .into_owned();
+        log_target::info!("grading {name}");
+
+        let config = TestConfig {
+            artifact_dir: config.artifact_dir.as_ref().map(|dir| dir.join(&name)),
+            ..config.clone()
+        };
+        let mut reporter = TextReporter::stdout(config.verbosity);
+        match run_submission(&executable, &config, &mut reporter) {
+            Ok(report) => ranking.add(name, &report),
+            Err(e) => {
+                log_target::warn!("{name} couldn't run its tests: {e}");
+                ranking.add_error(name, e);
+            }
+        }
+    }
+    Ok(ranking)
+}
+
+/// Runs the tests of the submission at `executable` with [`run_submission`] every time it changes,
+/// like when it's built again in another terminal, and gives every report to `on_report`. The
+/// results are printed to the console as well. It also runs them again when a file changes in the
+/// [`TestConfig::rom_dir`] or one of the [`TestConfig::suites`]. The first time it runs them is
+/// right away, or once the executable is there.
+///
+/// After a run in which tests failed, the next run only runs those, so the test you're debugging
+/// runs first. Once they pass, it runs all tests again, to check the others still pass:
+/// ```no_run
+/// use tudelft_nes_test::{watch_submission, CancelToken, TestConfig, TestSelector};
+///
+/// let cancel = CancelToken::new();
+/// let config = TestConfig {
+///     selector: TestSelector::ALL_INSTRS,
+///     cancel: Some(cancel.clone()),
+///     ..TestConfig::default()
+/// };
+/// // runs until something cancels it, like a handler of ctrl-c
+/// watch_submission("target/debug/my-emulator", &config, |report| {
+///     if report.passed() {
+///         println!("all passed, on to the ppu");
+///     }
+/// });
+/// ```
+///
+/// Returns once [`TestConfig::cancel`] is cancelled, and otherwise never.
+pub fn watch_submission(
+    executable: impl AsRef<Path>,
+    config: &TestConfig,
+    mut on_report: impl FnMut(&TestReport),
+) {
+    let executable = executable.as_ref();
+    let cancelled = || {
+        config
+            .cancel
+            .as_ref()
+            .is_some_and(CancelToken::is_cancelled)
+    };
+    let mut run = |filters: &[String]| {
+        let config = match filters {
+            [] => config.clone(),
+            failing => TestConfig {
+                filters: failing.to_vec(),
+                ..config.clone()
+            },
+        };
+        let mut reporter = TextReporter::stdout(config.verbosity);
+        match run_submission(executable, &config, &mut reporter) {
+            Ok(report) => {
+                on_report(&report);
+                Some(report.failures().map(|r| r.id.clone()).collect::<Vec<_>>())
+            }
+            Err(e) => {
+                log_target::warn!("couldn't run the tests of {}: {e}", executable.display());
+                None
+            }
+        }
+    };
+
+    let mut seen = None;
+    let mut failing = Vec::new();
+    while !cancelled() {
+        let files = watched_files(executable, config);
+        if seen.as_ref() == Some(&files) || files[0].1.is_none() {
+            std::thread::sleep(POLL);
+            continue;
+        }
+        // a build that is still being written changes again
+        std::thread::sleep(POLL);
+        if watched_files(executable, config) != files {
+            continue;
+        }
+        seen = Some(files);
+
+        log_target::info!("running the tests of {}", executable.display());
+        let Some(failed) = run(&failing) else {
+            continue;
+        };
+        // the tests that failed pass now, so check that the others still do
+        failing = if failed.is_empty() && !failing.is_empty() && !cancelled() {
+            run(&[]).unwrap_or_default()
+        } else {
+            failed
+        };
+    }
+}
+
+/// The files [`watch_submission`] watches, the executable first, with their size and when they
+/// changed, or `None` when they aren't there
+fn watched_files(
+    executable: &Path,
+    config: &TestConfig,
+) -> Vec<(PathBuf, Option<(u64, SystemTime)>)> {
+    let mut paths = config.suites.clone();
+    if let Some(dir) = &config.rom_dir {
+        let mut dirs = vec![dir.clone()];
+        while let Some(dir) = dirs.pop() {
+            let Ok(entries) = std::fs::read_dir(&dir) else {
+                continue;
+            };
+            for entry in entries.flatten() {
+                match entry.file_type() {
+                    Ok(kind) if kind.is_dir() => dirs.push(entry.path()),
+                    Ok(_) => paths.push(entry.path()),
+                    Err(_) => {}
+                }
+            }
+        }
+    }
+    paths.sort();
+    paths.insert(0, executable.to_path_buf());
+    paths
+        .into_iter()
+        .map(|path| {
+            let changed = std::fs::metadata(&path)
+                .and_then(|metadata| Ok((metadata.len(), metadata.modified()?)))
+                .ok();
+            (path, changed)
+        })
+        .collect()
+}
+
+/// The executables in `dir`, by name. Libraries are executable on some systems, they're skipped.
+fn submissions(dir: &Path) -> io::Result<Vec<PathBuf>> {
+    let mut submissions = Vec::new();
+    for entry in std::fs::read_dir(dir)? {
+        let entry = entry?;
+        let path = entry.path();
+        if !entry.file_type()?.is_file() || !executable(&path)? {
+            continue;
+        }
+        if library(&path) {
+            log_target::warn!(
+                "skipping {}, submissions are executables and libraries aren't loaded",
+                path.display()
+            );
+            continue;
+        }
+        submissions.push(path);
+    }
+    submissions.sort();
+    Ok(submissions)
+}
+
+/// Whether `path` is a dynamic library, like a `cdylib`, by its extension
+fn library(path: &Path) -> bool {
+    path.extension().is_some_and(|e| {
+        ["so", "dylib", "dll"]
+            .iter()
+            .any(|library| e.eq_ignore_ascii_case(library))
+    })
+}
+
+#[cfg(unix)]
+fn executable(path: &Path) -> io::Result<bool> {
+    use std::os::unix::fs::PermissionsExt;
+    Ok(std::fs::metadata(path)?.permissions().mode() & 0o111 != 0)
+}
+
+#[cfg(not(unix))]
+fn executable(path: &Path) -> io::Result<bool> {
+    Ok(path
+        .extension()
+        .is_some_and(|e| e.eq_ignore_ascii_case("exe")))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn a_program_without_the_harness_is_an_error() {
+        let Some(program) = ["/bin/true", "/usr/bin/true"]
+            .into_iter()
+            .map(Path::new)
+            .find(|path| path.exists())
+        else {
+            return;
+        };
+        let mut reporter = TextReporter::new(io::sink(), crate::Verbosity::Quiet);
+        let error = run_submission(program, &TestConfig::default(), &mut reporter).unwrap_err();
+        assert!(error.contains("has no tests"), "{error}");
+    }
+
+    #[test]
+    fn libraries_are_not_submissions() {
+        assert!(library(Path::new("submissions/alice.so")));
+        assert!(library(Path::new("submissions/bob.DLL")));
+        assert!(!library(Path::new("submissions/carol")));
+        assert!(!library(Path::new("submissions/dave.exe")));
+    }
+}
@@ -0,0 +1,81 @@
+//! The large roms bundled with the harness, which are compressed by the build script and
+//! decompressed the first time they're used
+use std::fmt;
+use std::ops::Deref;
+use std::sync::OnceLock;
+
+/// A rom bundled with the harness, which dereferences to its bytes
+pub(crate) struct BundledRom {
+    compressed: &'static [u8],
+    rom: OnceLock<Vec<u8>>,
+}
+
+impl BundledRom {
+    pub(crate) const fn compressed(compressed: &'static [u8]) -> Self {
+        Self {
+            compressed,
+            rom: OnceLock::new(),
+        }
+    }
+}
+
+impl Deref for BundledRom {
+    type Target = [u8];
+
+    fn deref(&self) -> &[u8] {
+        self.rom.get_or_init(|| decompress(self.compressed))
+    }
+}
+
+impl AsRef<[u8]> for BundledRom {
+    fn as_ref(&self) -> &[u8] {
+        self
+    }
+}
+
+impl fmt::Debug for BundledRom {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "BundledRom({} bytes)", self.len())
+    }
+}
+
+/// Decompresses what `build.rs` compressed: a byte below $80 is followed by that many plus 1 literal
+/// bytes, a byte from $80 up copies its lower 7 bits plus 3 bytes from the little endian offset back
+/// in the output that follows it
+fn decompress(compressed: &[u8]) -> Vec<u8> {
+    let mut rom = Vec::new();
+    let mut bytes = compressed.iter().copied();
+    while let Some(token) = bytes.next() {
+        if token < 0x80 {
+            rom.extend(bytes.by_ref().take(usize::from(token) + 1));
+            continue;
+        }
+
+        let length = usize::from(token & 0x7F) + 3;
+        let offset = usize::from(u16::from_le_bytes([
+            bytes.next().unwrap_or_default(),
+            bytes.next().unwrap_or_default(),
+        ]));
+        // the copy can overlap what it's copying, for runs of the same bytes
+        let start = rom.len() - offset;
+        for i in start..start + length {
+            rom.push(rom[i]);
+        }
+    }
+    rom
+}
+
+#[cfg(test)]
+mod tests {
+    #[test]
+    fn the_bundled_roms_decompress_to_the_roms() {
+        assert_eq!(
+            crate::rom_all_instr(),
+            include_bytes!("roms/all_instrs.nes")
+        );
+        assert_eq!(
+            crate::rom_official_only(),
+            include_bytes!("roms/official_only.nes")
+        );
+    }
+}
@@ -0,0 +1,260 @@
+//! Remembering which tests passed for a build of the cpu, so a rerun of the same build can skip
+//! them, see [`TestConfig::cache_dir`](crate::TestConfig::cache_dir)
+use crate::all_instrs::INSTR_GROUPS;
+use crate::log_target;
+use crate::report::{SubTestResult, TestResult};
+use crate::{find_rom, rom_sets, sha256, TestConfig};
+use std::path::{Path, PathBuf};
+
+const MAGIC: &str = "nestest-n passed";
+
+/// What is remembered of a test that passed
+pub(crate) struct Cached {
+    pub(crate) sub_tests: Vec<SubTestResult>,
+    pub(crate) expected_failures: Vec<String>,
+}
+
+pub(crate) struct ResultCache {
+    dir: PathBuf,
+    /// the SHA-256 of the build, of the roms, and of everything in the configuration that changes the
+    /// results
+    key: String,
+}
+
+impl ResultCache {
+    /// The cache of `config`, or `None` when it has no cache directory. A step callback has to
+    /// see every test run, so it disables the cache too.
+    pub(crate) fn of(config: &TestConfig) -> Option<Self> {
+        let dir = config.cache_dir.clone()?;
+        if config.on_step.is_some() {
+            return None;
+        }
+
+        // SHA-256 stays the same across versions of Rust, unlike the hashers of the standard library
+        let mut digests = vec![match &config.fingerprint {
+            Some(fingerprint) => sha256::hex_digest(fingerprint.as_bytes()),
+            None => {
+                let Some(executable) = std::env::current_exe()
+                    .ok()
+                    .and_then(|path| std::fs::read(path).ok())
+                else {
+                    log_target::warn!("couldn't read the test executable to fingerprint it, set TestConfig::fingerprint to cache results");
+                    return None;
+                };
+                sha256::hex_digest(&executable)
+            }
+        }];
+
+        // the maps are hashed in a fixed order, they iterate in a different one in every run
+        let mut budgets: Vec<_> = config.cycle_budgets.iter().collect();
+        budgets.sort_by_key(|(test, _)| test.bits());
+        let mut instructions: Vec<_> = config.instruction_budgets.iter().collect();
+        instructions.sort_by_key(|(test, _)| test.bits());
+        let mut scripts: Vec<_> = config.input_scripts.iter().collect();
+        scripts.sort_by_key(|(test, _)| test.bits());
+        // the custom roms and the roms in the directories count by what's in them, not where they are
+        let custom_roms: Vec<_> = config
+            .custom_roms
+            .iter()
+            .map(|rom| {
+                (
+                    &rom.name,
+                    rom.cycles,
+                    &rom.expectation,
+                    rom.status_addresses,
+                )
+            })
+            .collect();
+        let settings = format!(
+            "{budgets:?} {instructions:?} {scripts:?} {:?} {:?} {:?} {:?} {} {} {:?} {:?} {:?} {:?} {:?} {:?} {custom_roms:?}",
+            config.allowed_failures,
+            config.filters,
+            config.unofficial_opcodes,
+            config.unstable_opcodes,
+            config.check_determinism,
+            config.parallel_singles,
+            config.ram_init,
+            config.stall_chunks,
+            config.watchdog_chunks,
+            config.mirroring,
+            config.region,
+            // a shard runs some roms of a set only, which doesn't say the others pass
+            config.shard,
+        );
+        digests.push(sha256::hex_digest(settings.as_bytes()));
+        for path in rom_files(config) {
+            digests.push(match std::fs::read(&path) {
+                Ok(rom) => sha256::hex_digest(&rom),
+                Err(_) => "missing".to_string(),
+            });
+        }
+
+        Some(Self {
+            dir,
+            key: sha256::hex_digest(digests.join(" ").as_bytes()),
+        })
+    }
+
+    fn path(&self, name: &str) -> PathBuf {
+        let hash = sha256::hex_digest(format!("{} {name}", self.key).as_bytes());
+        let file_name: String = name
+            .chars()
+            .map(|c| if c.is_ascii_alphanumeric() { c } else { '_' })
+            .collect();
+
+        self.dir.join(format!("{file_name}-{}.passed", &hash[..16]))
+    }
+
+    /// What is remembered of test `name`, when it passed for this build before
+    pub(crate) fn load(&self, name: &str) -> Option<Cached> {
+        let text = std::fs::read_to_string(self.path(name)).ok()?;
+        let mut lines = text.lines();
+        if lines.next() != Some(MAGIC) {
+            return None;
+        }
+
+        let mut cached = Cached {
+            sub_tests: Vec::new(),
+            expected_failures: Vec::new(),
+        };
+        for line in lines {
+            match line.split_once(' ')? {
+                ("passed", name) | ("failed", name) => cached.sub_tests.push(SubTestResult {
+                    name: name.to_string(),
+                    passed: line.starts_with("passed"),
+                    detail: None,
+                }),
+                ("expected", name) => cached.expected_failures.push(name.to_string()),
+                _ => return None,
+            }
+        }
+        Some(cached)
+    }
+
+    /// Remembers `result` when the test passed reliably
+    pub(crate) fn store(&self, result: &TestResult) {
+        if !result.passed() || result.skipped.is_some() || result.flaky() {
+            return;
+        }
+
+        let mut text = format!("{MAGIC}\n");
+        for sub_test in &result.sub_tests {
+            let passed = if sub_test.passed { "passed" } else { "failed" };
+            text.push_str(&format!("{passed} {}\n", sub_test.name));
+        }
+        for name in &result.expected_failures {
+            text.push_str(&format!("expected {name}\n"));
+        }
+
+        let path = self.path(&result.name);
+        if let Err(e) = write(&path, &text) {
+            log_target::warn!("couldn't cache the result of {}: {e}", result.name);
+        }
+    }
+}
+
+/// The roms the tests may load instead of the bundled ones, or as a rom of a set or of your own, in
+/// a fixed order. A rom that isn't there counts too, since a test fails without it.
+fn rom_files(config: &TestConfig) -> Vec<PathBuf> {
+    let in_sets = |dir: &Path, sets: &[rom_sets::RomSet]| {
+        sets.iter()
+            .flat_map(|set| set.roms.iter().map(|rom| find_rom(dir, &set.dir, rom)))
+            .collect::<Vec<_>>()
+    };
+
+    let mut files = Vec::new();
+    if let Some(dir) = &config.rom_dir {
+        let bundled = ["all_instrs", "official_only", "nestest", "nrom-test"];
+        let singles = INSTR_GROUPS.iter().map(|(group, _)| group);
+        for name in bundled.iter().chain(singles) {
+            files.push(dir.join(format!("{name}.nes")));
+        }
+        files.extend(in_sets(dir, rom_sets::bundled()));
+    }
+    if let Some(dir) = &config.download_dir {
+        let sets = rom_sets::bundled();
+        files.extend(
+            sets.iter()
+                .flat_map(|set| set.roms.iter().map(|rom| dir.join(&set.dir).join(rom))),
+        );
+    }
+    files.extend(config.custom_roms.iter().map(|rom| rom.path.clone()));
+    for manifest in &config.suites {
+        files.push(manifest.clone());
+        if let Ok(sets) = rom_sets::load(manifest) {
+            let root = manifest.parent().unwrap_or(Path::new(""));
+            files.extend(in_sets(root, &sets));
+        }
+    }
+    files
+}
+
+fn write(path: &Path, text: &str) -> std::io::Result<()> {
+    if let Some(dir) = path.parent() {
+        std::fs::create_dir_all(dir)?;
+    }
+    std::fs::write(path, text)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::temp_dir::TempDir;
+    use crate::{CustomRom, TestSelector};
+
+    fn config(dir: &Path, roms: &[&Path]) -> TestConfig {
+        TestConfig {
+            cache_dir: Some(dir.join("cache")),
+            fingerprint: Some("3f2c1a9".to_string()),
+            custom_roms: roms
+                .iter()
+                .map(|path| CustomRom::new("adc", *path))
+                .collect(),
+            ..TestConfig::default()
+        }
+    }
+
+    fn passed(name: &str) -> TestResult {
+        TestResult {
+            sub_tests: vec![SubTestResult {
+                name: "$0010".to_string(),
+                passed: true,
+                detail: None,
+            }],
+            ..TestResult::of(TestSelector::CUSTOM, name, Ok(()))
+        }
+    }
+
+    #[test]
+    fn the_key_is_of_the_roms_not_of_where_they_are() {
+        let dir = TempDir::new("cache-paths");
+        let (a, b) = (dir.join("a.nes"), dir.join("b.nes"));
+        std::fs::write(&a, b"NES\x1a one").unwrap();
+        std::fs::write(&b, b"NES\x1a one").unwrap();
+
+        let first = ResultCache::of(&config(&dir, &[&a])).unwrap();
+        let moved = ResultCache::of(&config(&dir, &[&b])).unwrap();
+        assert_eq!(first.key, moved.key);
+        assert_eq!(first.key.len(), 64);
+
+        std::fs::write(&b, b"NES\x1a two").unwrap();
+        let changed = ResultCache::of(&config(&dir, &[&b])).unwrap();
+        assert_ne!(first.key, changed.key);
+    }
+
+    #[test]
+    fn a_changed_rom_invalidates_the_cache() {
+        let dir = TempDir::new("cache-stale");
+        let rom = dir.join("adc.nes");
+        std::fs::write(&rom, b"NES\x1a before").unwrap();
+
+        let cache = ResultCache::of(&config(&dir, &[&rom])).unwrap();
+        cache.store(&passed("adc"));
+        let cached = ResultCache::of(&config(&dir, &[&rom])).unwrap().load("adc");
+        assert_eq!(cached.unwrap().sub_tests, passed("adc").sub_tests);
+
+        std::fs::write(&rom, b"NES\x1a after").unwrap();
+        let cache = ResultCache::of(&config(&dir, &[&rom])).unwrap();
+        assert!(cache.load("adc").is_none());
+    }
+}
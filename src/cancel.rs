@@ -0,0 +1,36 @@
+//! Stopping a test run from another thread, see [`TestConfig::cancel`](crate::TestConfig::cancel)
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+
+/// Cancels the test run of the [`TestConfig`](crate::TestConfig) it's in. Clones cancel the same run:
+/// ```
+/// use tudelft_nes_test::{CancelToken, TestConfig};
+///
+/// let cancel = CancelToken::new();
+/// let config = TestConfig {
+///     cancel: Some(cancel.clone()),
+///     ..TestConfig::default()
+/// };
+/// // from another thread, like the handler of ctrl-c
+/// cancel.cancel();
+/// assert!(cancel.is_cancelled());
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct CancelToken(Arc<AtomicBool>);
+
+impl CancelToken {
+    /// A token that isn't cancelled yet
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Stops the test run: the running test fails at its next cycle, and the tests after it don't run
+    pub fn cancel(&self) {
+        self.0.store(true, Ordering::Relaxed);
+    }
+
+    /// Whether [`cancel`](Self::cancel) was called
+    pub fn is_cancelled(&self) -> bool {
+        self.0.load(Ordering::Relaxed)
+    }
+}
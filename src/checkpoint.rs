@@ -0,0 +1,130 @@
+//! Checkpoints of tests with multiple sub-tests, so a rerun can skip the sub-tests that already
+//! passed. They hold the state of the cpu from [`TestableCpu::save_state`](crate::TestableCpu::save_state).
+use crate::sha256;
+use std::io;
+use std::path::{Path, PathBuf};
+
+const MAGIC: &[u8; 4] = b"NTNC";
+
+/// The state of the cpu just after the sub-tests in `passed` passed
+pub(crate) struct Checkpoint {
+    pub(crate) passed: Vec<String>,
+    pub(crate) state: Vec<u8>,
+}
+
+impl Checkpoint {
+    /// Where the checkpoint of running `rom` in test `name` is kept. The hash of the rom is part of
+    /// the name, so a rom set gets a checkpoint per rom and a changed rom doesn't use an old one.
+    pub(crate) fn path(dir: &Path, name: &str, rom: &[u8]) -> PathBuf {
+        let hash = sha256::hex_digest(rom);
+        let name: String = name
+            .chars()
+            .map(|c| if c.is_ascii_alphanumeric() { c } else { '_' })
+            .collect();
+
+        dir.join(format!("{name}-{}.checkpoint", &hash[..16]))
+    }
+
+    /// Reads a checkpoint, or returns `None` when there is none or it's corrupted
+    pub(crate) fn load(path: &Path) -> Option<Self> {
+        let bytes = std::fs::read(path).ok()?;
+        let mut rest = bytes.strip_prefix(MAGIC)?;
+        let mut take = |n: usize| {
+            let (taken, tail) = (rest.get(..n)?, rest.get(n..)?);
+            rest = tail;
+            Some(taken)
+        };
+
+        let count = u16::from_le_bytes(take(2)?.try_into().ok()?);
+        let mut passed = Vec::new();
+        for _ in 0..count {
+            let len = u16::from_le_bytes(take(2)?.try_into().ok()?);
+            passed.push(String::from_utf8(take(len.into())?.to_vec()).ok()?);
+        }
+
+        Some(Self {
+            passed,
+            state: rest.to_vec(),
+        })
+    }
+
+    pub(crate) fn save(&self, path: &Path) -> io::Result<()> {
+        let mut bytes = MAGIC.to_vec();
+        bytes.extend(length(self.passed.len(), "sub-tests")?);
+        for name in &self.passed {
+            bytes.extend(length(name.len(), "bytes in the name of a sub-test")?);
+            bytes.extend(name.as_bytes());
+        }
+        bytes.extend(&self.state);
+
+        if let Some(dir) = path.parent() {
+            std::fs::create_dir_all(dir)?;
+        }
+        std::fs::write(path, bytes)
+    }
+}
+
+/// A length as it's saved, in 2 bytes
+fn length(len: usize, of: &str) -> io::Result<[u8; 2]> {
+    let len = u16::try_from(len).map_err(|_| {
+        io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!("a checkpoint can't hold {len} {of}, at most {}", u16::MAX),
+        )
+    })?;
+    Ok(len.to_le_bytes())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::temp_dir::TempDir;
+
+    #[test]
+    fn checkpoints_round_trip() {
+        let dir = TempDir::new("checkpoint-round-trip");
+        let path = Checkpoint::path(&dir, "all instructions", b"NES\x1a");
+        let checkpoint = Checkpoint {
+            passed: vec!["01-basics".to_string(), "02-implied".to_string()],
+            state: vec![0, 1, 2, 0xFF],
+        };
+        checkpoint.save(&path).unwrap();
+
+        let loaded = Checkpoint::load(&path).unwrap();
+        assert_eq!(loaded.passed, checkpoint.passed);
+        assert_eq!(loaded.state, checkpoint.state);
+    }
+
+    #[test]
+    fn corrupted_checkpoints_are_not_loaded() {
+        let dir = TempDir::new("checkpoint-corrupted");
+        let path = dir.join("corrupted.checkpoint");
+        std::fs::write(&path, b"NTNC\x01\x00\x09\x00basics").unwrap();
+        assert!(Checkpoint::load(&path).is_none());
+        std::fs::write(&path, b"NTNX\x00\x00").unwrap();
+        assert!(Checkpoint::load(&path).is_none());
+    }
+
+    #[test]
+    fn too_long_names_are_an_error() {
+        let dir = TempDir::new("checkpoint-too-long");
+        let checkpoint = Checkpoint {
+            passed: vec!["x".repeat(70_000)],
+            state: Vec::new(),
+        };
+        let error = checkpoint.save(&dir.join("long.checkpoint")).unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::InvalidInput);
+    }
+
+    #[test]
+    fn the_path_is_of_the_rom() {
+        let dir = Path::new("checkpoints");
+        let path = Checkpoint::path(dir, "nes_instr_test/ram", b"one");
+        assert_eq!(
+            path,
+            // the first 16 digits of the SHA-256 of "one"
+            dir.join("nes_instr_test_ram-7692c3ad3540bb80.checkpoint")
+        );
+        assert_ne!(path, Checkpoint::path(dir, "nes_instr_test/ram", b"two"));
+    }
+}
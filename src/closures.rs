@@ -0,0 +1,132 @@
+//! Runs the tests on a cpu that doesn't implement [`TestableCpu`], with closures that do what its
+//! methods would, see [`run_tests_with`](crate::run_tests_with)
+use crate::TestableCpu;
+use std::any::Any;
+use std::error::Error;
+use std::fmt;
+use std::sync::Arc;
+use tudelft_nes_ppu::{Cpu, Ppu};
+
+type NewFn<C> = dyn Fn(&[u8]) -> Result<C, Box<dyn Error + Send + Sync>> + Send + Sync;
+type ReadFn<C> = dyn Fn(&C, u16) -> u8 + Send + Sync;
+type SetPcFn<C> = dyn Fn(&mut C, u16) + Send + Sync;
+
+/// The closures of a cpu of type `C`
+pub(crate) struct Closures<C> {
+    pub(crate) new: Box<NewFn<C>>,
+    pub(crate) memory_read: Box<ReadFn<C>>,
+    pub(crate) set_program_counter: Box<SetPcFn<C>>,
+}
+
+/// Creates a cpu of type `T` for a rom
+type NewCpu<T> = Arc<dyn Fn(&[u8]) -> Result<T, Box<dyn Error + Send + Sync>> + Send + Sync>;
+
+/// The closures of a cpu, in the [`TestConfig`](crate::TestConfig) of the run of
+/// [`run_tests_with`](crate::run_tests_with). [`TestableCpu::get_cpu`] doesn't get a value to take
+/// them from, so the harness creates the cpus of that run with them instead.
+#[derive(Clone)]
+pub struct CpuClosures(Arc<dyn Any + Send + Sync>);
+
+impl CpuClosures {
+    pub(crate) fn new<C: Cpu + 'static>(closures: Closures<C>) -> Self {
+        let closures = Arc::new(closures);
+        let new: NewCpu<ClosureCpu<C>> = Arc::new(move |rom| {
+            Ok(ClosureCpu {
+                cpu: (closures.new)(rom)?,
+                closures: closures.clone(),
+            })
+        });
+        Self(Arc::new(new))
+    }
+
+    /// A cpu of type `T` for `rom`, or `None` when these closures are of another type
+    pub(crate) fn get_cpu<T: 'static>(
+        &self,
+        rom: &[u8],
+    ) -> Option<Result<T, Box<dyn Error + Send + Sync>>> {
+        self.0.downcast_ref::<NewCpu<T>>().map(|new| new(rom))
+    }
+}
+
+impl fmt::Debug for CpuClosures {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("CpuClosures")
+    }
+}
+
+/// A cpu of type `C`, which implements [`TestableCpu`] with the closures it was created with
+pub(crate) struct ClosureCpu<C> {
+    cpu: C,
+    closures: Arc<Closures<C>>,
+}
+
+impl<C: Cpu + 'static> TestableCpu for ClosureCpu<C> {
+    fn get_cpu(_rom: &[u8]) -> Result<Self, Box<dyn Error + Send + Sync>> {
+        // the harness creates these with the CpuClosures of its configuration instead
+        Err("a cpu of run_tests_with can only be tested by run_tests_with".into())
+    }
+
+    fn set_program_counter(&mut self, value: u16) {
+        (self.closures.set_program_counter)(&mut self.cpu, value)
+    }
+
+    fn memory_read(&self, address: u16) -> u8 {
+        (self.closures.memory_read)(&self.cpu, address)
+    }
+}
+
+impl<C: Cpu> Cpu for ClosureCpu<C> {
+    fn tick(&mut self, ppu: &mut Ppu) -> Result<(), Box<dyn Error>> {
+        self.cpu.tick(ppu)
+    }
+
+    fn ppu_read_chr_rom(&self, offset: u16) -> u8 {
+        self.cpu.ppu_read_chr_rom(offset)
+    }
+
+    fn non_maskable_interrupt(&mut self) {
+        self.cpu.non_maskable_interrupt()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct Fake(u8);
+
+    impl Cpu for Fake {
+        fn tick(&mut self, _: &mut Ppu) -> Result<(), Box<dyn Error>> {
+            Ok(())
+        }
+
+        fn ppu_read_chr_rom(&self, _: u16) -> u8 {
+            0
+        }
+
+        fn non_maskable_interrupt(&mut self) {}
+    }
+
+    fn closures(byte: u8) -> CpuClosures {
+        CpuClosures::new(Closures {
+            new: Box::new(move |_| Ok(Fake(byte))),
+            memory_read: Box::new(|cpu: &Fake, _| cpu.0),
+            set_program_counter: Box::new(|_, _| {}),
+        })
+    }
+
+    #[test]
+    fn every_configuration_has_its_own_closures() {
+        let (one, two) = (closures(1), closures(2));
+        let cpu: ClosureCpu<Fake> = one.get_cpu(&[]).unwrap().unwrap();
+        assert_eq!(cpu.memory_read(0), 1);
+        let cpu: ClosureCpu<Fake> = two.get_cpu(&[]).unwrap().unwrap();
+        assert_eq!(cpu.memory_read(0), 2);
+    }
+
+    #[test]
+    fn closures_only_create_their_own_cpu() {
+        assert!(closures(1).get_cpu::<ClosureCpu<u8>>(&[]).is_none());
+        assert!(ClosureCpu::<Fake>::get_cpu(&[]).is_err());
+    }
+}
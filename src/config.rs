@@ -0,0 +1,1199 @@
+//! Loading a [`TestConfig`] from a `nestest-n.toml` file and `NESTEST_N_*` environment variables,
+//! so a CI pipeline can change how the tests run without recompiling
+use crate::{
+    Access, CustomRom, LogCapture, NametableMirroring, RamInit, Region, Shard, StatusAddresses,
+    TestConfig, TestSelector, UnofficialOpcodes, UnstablePolicy, Verbosity, Watchpoint,
+};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+use thiserror::Error;
+
+/// The configuration file [`TestConfig::load`] reads, relative to the current directory.
+/// Set `NESTEST_N_CONFIG` to read another file instead.
+pub const CONFIG_FILE: &str = "nestest-n.toml";
+
+/// Names of the tests, as used in configuration files and environment variables.
+/// The single tests come first, followed by the names of groups of tests.
+const TEST_NAMES: &[(&str, TestSelector)] = &[
+    ("preflight", TestSelector::PREFLIGHT),
+    ("nestest", TestSelector::NESTEST),
+    ("nestest_menu", TestSelector::NESTEST_MENU),
+    ("nestest_reset", TestSelector::NESTEST_RESET),
+    ("all_instrs", TestSelector::ALL_INSTRS),
+    ("official_instrs", TestSelector::OFFICIAL_INSTRS),
+    ("nrom_test", TestSelector::NROM_TEST),
+    ("interrupts", TestSelector::INTERRUPTS),
+    ("wraparound", TestSelector::WRAPAROUND),
+    ("nes_instr_test", TestSelector::NES_INSTR_TEST),
+    ("blargg_ppu_tests", TestSelector::BLARGG_PPU_TESTS),
+    ("vbl_nmi_timing", TestSelector::VBL_NMI_TIMING),
+    ("sprite_overflow", TestSelector::SPRITE_OVERFLOW),
+    ("ppu_open_bus", TestSelector::PPU_OPEN_BUS),
+    ("apu_reset", TestSelector::APU_RESET),
+    ("dmc_dma", TestSelector::DMC_DMA),
+    ("read_joy3", TestSelector::READ_JOY3),
+    ("scanline", TestSelector::SCANLINE),
+    ("smoke", TestSelector::SMOKE),
+    ("custom", TestSelector::CUSTOM),
+    ("instr_basics", TestSelector::INSTR_BASICS),
+    ("instr_implied", TestSelector::INSTR_IMPLIED),
+    ("instr_immediate", TestSelector::INSTR_IMMEDIATE),
+    ("instr_zero_page", TestSelector::INSTR_ZERO_PAGE),
+    ("instr_zp_xy", TestSelector::INSTR_ZP_XY),
+    ("instr_absolute", TestSelector::INSTR_ABSOLUTE),
+    ("instr_abs_xy", TestSelector::INSTR_ABS_XY),
+    ("instr_ind_x", TestSelector::INSTR_IND_X),
+    ("instr_ind_y", TestSelector::INSTR_IND_Y),
+    ("instr_branches", TestSelector::INSTR_BRANCHES),
+    ("instr_stack", TestSelector::INSTR_STACK),
+    ("instr_jmp_jsr", TestSelector::INSTR_JMP_JSR),
+    ("instr_rts", TestSelector::INSTR_RTS),
+    ("instr_rti", TestSelector::INSTR_RTI),
+    ("instr_brk", TestSelector::INSTR_BRK),
+    ("instr_special", TestSelector::INSTR_SPECIAL),
+    ("instr_groups", TestSelector::INSTR_GROUPS),
+    ("all", TestSelector::ALL),
+    ("default", TestSelector::DEFAULT),
+];
+
+/// Names of the categories of unofficial opcodes, as used in configuration files and environment variables
+const UNOFFICIAL_OPCODE_NAMES: &[(&str, UnofficialOpcodes)] = &[
+    ("nops", UnofficialOpcodes::NOPS),
+    ("lax_sax", UnofficialOpcodes::LAX_SAX),
+    ("rmw", UnofficialOpcodes::RMW),
+    ("immediate", UnofficialOpcodes::IMMEDIATE),
+    ("unstable", UnofficialOpcodes::UNSTABLE),
+];
+
+/// Error returned when the configuration file or one of the environment variables is invalid
+#[derive(Debug, Error)]
+pub enum ConfigError {
+    /// The configuration file couldn't be read
+    #[error("couldn't read {}: {source}", path.display())]
+    Io {
+        /// The configuration file
+        path: PathBuf,
+        /// Why it couldn't be read
+        source: std::io::Error,
+    },
+    /// The configuration file isn't valid toml
+    #[error("couldn't parse {}: {source}", path.display())]
+    Toml {
+        /// The configuration file
+        path: PathBuf,
+        /// Where the file is invalid
+        source: toml::de::Error,
+    },
+    /// A setting has a value that isn't allowed
+    #[error("invalid value for {key}: {message}")]
+    Invalid {
+        /// The setting, either a key in the configuration file or an environment variable
+        key: String,
+        /// What is wrong with the value
+        message: String,
+    },
+}
+
+/// How long a test may run: a number of cycles, and maybe a number of instructions as well
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct Budget {
+    pub(crate) cycles: u64,
+    pub(crate) instructions: Option<u64>,
+}
+
+impl TestConfig {
+    /// Loads the configuration from [`CONFIG_FILE`] if it exists, and then applies the
+    /// environment variables on top of it with [`apply_env`](Self::apply_env).
+    ///
+    /// An example configuration file, every key is optional:
+    /// ```toml
+    /// tests = ["official_instrs", "nrom_test"]
+    /// verbosity = "verbose"            # "quiet", "normal" or "verbose"
+    /// allowed_failures = ["03-immediate"]
+    /// filters = ["all_instrs/11-stack", "nestest"]  # ids of tests and sub-tests, instead of `tests`
+    /// rom_dir = "roms"                 # relative to the configuration file
+    /// download_dir = "target/nes-test-roms"  # relative to the configuration file, with the download feature
+    /// download_mirror = "https://example.com/nes-test-roms"  # instead of GitHub
+    /// timeout = 60                     # seconds per test
+    /// check_determinism = true
+    /// parallel_singles = true          # the single roms of all_instrs at the same time, from rom_dir
+    /// localize_failures = true         # narrow a failure of all_instrs down to the instruction that fails
+    /// hints = false                    # leave out the hints of what bug usually causes a failure
+    /// isolate = true                   # every test in a process of its own, so a crash fails only that test
+    /// capture_logs = "harness"         # or "all" or "off", keeps the lines logged during a test with its result
+    /// mirroring = "vertical"           # or "horizontal", instead of the mirroring of the rom
+    /// region = "pal"                   # or "ntsc" or "dendy", with an executor that supports it
+    /// retries = 2                      # times to run a failed test again
+    /// stall_chunks = 50                # run past the budget while the status text changes
+    /// watchdog_chunks = 25             # fail once the status text didn't change for this long
+    /// failure_trace = 100_000          # trace about this many cycles at the end of a failed test
+    /// ram = "random"                   # or "random:<seed>", or a byte like "$FF" for all of it
+    /// pass_threshold = 80               # percentage of the sub-tests that has to pass
+    /// checkpoint_dir = "target/nes-checkpoints"  # relative to the configuration file
+    /// cache_dir = "target/nes-cache"   # relative to the configuration file
+    /// artifact_dir = "target/nes-artifacts"  # relative to the configuration file
+    /// fingerprint = "3f2c1a9"          # the build of your cpu, the test executable by default
+    /// unofficial_opcodes = ["nops", "lax_sax"]  # also "rmw", "immediate" and "unstable"
+    /// unstable_opcodes = "any_variant"  # or "strict" or "skip", how "unstable" is tested
+    /// watchpoints = ["write $4014", "read $2002", "$6000-$6003"]  # reads and writes without a kind
+    /// suites = ["roms/suites.toml"]    # manifests of suites of roms, relative to the configuration file
+    ///
+    /// [cycles]
+    /// all_instrs = 150_000_000
+    ///
+    /// [instructions]                   # on top of the cycles
+    /// nestest = 9_000
+    ///
+    /// [[custom_roms]]                  # runs when the "custom" tests are selected, like tests = ["default", "custom"]
+    /// name = "adc"
+    /// path = "roms/adc.nes"            # relative to the configuration file
+    /// cycles = 100_000
+    /// memory = { "$0010" = [0x42, 0x00], "$0200" = [0xFF] }  # reports its status without it
+    /// status_address = "$7000"         # where it reports its status, instead of $6000
+    /// ```
+    pub fn load() -> Result<Self, ConfigError> {
+        let mut config = match std::env::var_os("NESTEST_N_CONFIG") {
+            Some(path) => Self::from_file(Path::new(&path))?,
+            None if Path::new(CONFIG_FILE).exists() => Self::from_file(Path::new(CONFIG_FILE))?,
+            None => Self::default(),
+        };
+
+        config.apply_env()?;
+        Ok(config)
+    }
+
+    /// Reads the configuration from the toml file at `path`, see [`load`](Self::load) for the format.
+    /// A relative `rom_dir`, `download_dir`, `checkpoint_dir`, `cache_dir`, `artifact_dir`, custom rom path or suite manifest is taken relative to the directory
+    /// of the file.
+    pub fn from_file(path: &Path) -> Result<Self, ConfigError> {
+        let text = std::fs::read_to_string(path).map_err(|source| ConfigError::Io {
+            path: path.to_path_buf(),
+            source,
+        })?;
+        let table = text
+            .parse::<toml::Table>()
+            .map_err(|source| ConfigError::Toml {
+                path: path.to_path_buf(),
+                source,
+            })?;
+
+        let mut config = Self::default();
+        config.apply_toml(&table)?;
+
+        if let Some(parent) = path.parent() {
+            for dir in [
+                &mut config.rom_dir,
+                &mut config.download_dir,
+                &mut config.checkpoint_dir,
+                &mut config.cache_dir,
+                &mut config.artifact_dir,
+            ] {
+                if let Some(relative) = dir.take() {
+                    *dir = Some(parent.join(relative));
+                }
+            }
+            for rom in &mut config.custom_roms {
+                rom.path = parent.join(&rom.path);
+            }
+            for manifest in &mut config.suites {
+                *manifest = parent.join(&*manifest);
+            }
+        }
+
+        Ok(config)
+    }
+
+    /// Overrides settings with the environment variables that are set:
+    ///
+    /// * `NESTEST_N_TESTS`: comma separated names of the tests to run, like `official_instrs,nrom_test`
+    /// * `NESTEST_N_VERBOSITY`: `quiet`, `normal` or `verbose`
+    /// * `NESTEST_N_ALLOWED_FAILURES`: comma separated names of sub-tests that may fail
+    /// * `NESTEST_N_FILTERS`: comma separated ids of the tests and sub-tests to run, like `all_instrs/1?-*,nestest`
+    /// * `NESTEST_N_ROM_DIR`: directory to load test roms from
+    /// * `NESTEST_N_DOWNLOAD_DIR`: directory to download the roms of nes-test-roms into
+    /// * `NESTEST_N_DOWNLOAD_MIRROR`: url to download the roms of nes-test-roms from
+    /// * `NESTEST_N_CHECKPOINT_DIR`: directory to keep checkpoints in
+    /// * `NESTEST_N_CACHE_DIR`: directory to remember the tests that passed in
+    /// * `NESTEST_N_ARTIFACT_DIR`: directory to write a file with everything the harness saw of every test to
+    /// * `NESTEST_N_FINGERPRINT`: what identifies the build of your cpu in the cache
+    /// * `NESTEST_N_TIMEOUT`: the maximum number of seconds a test may run
+    /// * `NESTEST_N_CYCLES_<TEST>`: the cycle budget of a test, like `NESTEST_N_CYCLES_ALL_INSTRS`.
+    ///   `<TEST>` has to be the name of a single test, so a typo or a group like `ALL` is an error instead of a budget that is ignored.
+    /// * `NESTEST_N_INSTRUCTIONS_<TEST>`: the instruction budget of a test, like `NESTEST_N_INSTRUCTIONS_NESTEST`
+    /// * `NESTEST_N_CHECK_DETERMINISM`: `true` or `false`
+    /// * `NESTEST_N_ISOLATE`: `true` or `false`
+    /// * `NESTEST_N_CAPTURE_LOGS`: `off`, `harness` or `all`
+    /// * `NESTEST_N_PARALLEL_SINGLES`: `true` or `false`
+    /// * `NESTEST_N_LOCALIZE_FAILURES`: `true` or `false`
+    /// * `NESTEST_N_HINTS`: `true` or `false`, whether a failure has hints of what usually causes it
+    /// * `NESTEST_N_MIRRORING`: `horizontal` or `vertical`
+    /// * `NESTEST_N_REGION`: `ntsc`, `pal` or `dendy`
+    /// * `NESTEST_N_RETRIES`: how many times to run a failed test again
+    /// * `NESTEST_N_PASS_THRESHOLD`: the percentage of the sub-tests that has to pass for the run to pass
+    /// * `NESTEST_N_STALL_CHUNKS`: after how many chunks of 200k cycles without progress a test that ran out of budget fails
+    /// * `NESTEST_N_WATCHDOG_CHUNKS`: after how many chunks of 200k cycles without progress any such test fails
+    /// * `NESTEST_N_FAILURE_TRACE`: every how many cycles the state of the cpu is saved, to trace the end of a failed test
+    /// * `NESTEST_N_RAM`: what the ram holds before a test runs, `cpu`, `random`, `random:<seed>` or a byte like `$FF`
+    /// * `NESTEST_N_SHARD`: the shard of the tests to run and the number of shards, like `0/4` for the first of four.
+    ///   On GitLab CI with `parallel`, that's `$((CI_NODE_INDEX - 1))/$CI_NODE_TOTAL`.
+    /// * `NESTEST_N_UNOFFICIAL_OPCODES`: comma separated categories of unofficial opcodes to test, like `nops,lax_sax`
+    /// * `NESTEST_N_UNSTABLE_OPCODES`: `strict`, `any_variant` or `skip`
+    /// * `NESTEST_N_WATCHPOINTS`: comma separated watchpoints, like `write $4014,$6000-$6003`
+    /// * `NESTEST_N_SUITES`: comma separated paths of manifests of suites of roms, see [`TestConfig::suites`]
+    pub fn apply_env(&mut self) -> Result<(), ConfigError> {
+        let vars = std::env::vars_os()
+            .filter_map(|(key, value)| Some((key.into_string().ok()?, value.into_string().ok()?)))
+            .collect();
+        self.apply_vars(&vars)
+    }
+
+    /// Overrides settings with the variables of [`apply_env`](Self::apply_env) in `vars`
+    fn apply_vars(&mut self, vars: &HashMap<String, String>) -> Result<(), ConfigError> {
+        let var = |key: &str| vars.get(key).filter(|v| !v.trim().is_empty()).cloned();
+
+        if let Some(tests) = var("NESTEST_N_TESTS") {
+            self.selector = parse_tests("NESTEST_N_TESTS", tests.split(','))?;
+        }
+        if let Some(verbosity) = var("NESTEST_N_VERBOSITY") {
+            self.verbosity = parse_verbosity("NESTEST_N_VERBOSITY", &verbosity)?;
+        }
+        if let Some(allowed) = var("NESTEST_N_ALLOWED_FAILURES") {
+            self.allowed_failures = allowed.split(',').map(|s| s.trim().to_string()).collect();
+        }
+        if let Some(filters) = var("NESTEST_N_FILTERS") {
+            self.filters = filters.split(',').map(|s| s.trim().to_string()).collect();
+        }
+        if let Some(rom_dir) = var("NESTEST_N_ROM_DIR") {
+            self.rom_dir = Some(PathBuf::from(rom_dir));
+        }
+        if let Some(download_dir) = var("NESTEST_N_DOWNLOAD_DIR") {
+            self.download_dir = Some(PathBuf::from(download_dir));
+        }
+        if let Some(mirror) = var("NESTEST_N_DOWNLOAD_MIRROR") {
+            self.download_mirror = Some(mirror.trim().to_string());
+        }
+        if let Some(checkpoint_dir) = var("NESTEST_N_CHECKPOINT_DIR") {
+            self.checkpoint_dir = Some(PathBuf::from(checkpoint_dir));
+        }
+        if let Some(artifact_dir) = var("NESTEST_N_ARTIFACT_DIR") {
+            self.artifact_dir = Some(PathBuf::from(artifact_dir));
+        }
+        if let Some(cache_dir) = var("NESTEST_N_CACHE_DIR") {
+            self.cache_dir = Some(PathBuf::from(cache_dir));
+        }
+        if let Some(fingerprint) = var("NESTEST_N_FINGERPRINT") {
+            self.fingerprint = Some(fingerprint.trim().to_string());
+        }
+        if let Some(timeout) = var("NESTEST_N_TIMEOUT") {
+            let seconds = timeout.trim().parse().map_err(|_| ConfigError::Invalid {
+                key: "NESTEST_N_TIMEOUT".to_string(),
+                message: format!("expected a number of seconds, got '{timeout}'"),
+            })?;
+            self.timeout = Some(parse_timeout("NESTEST_N_TIMEOUT", seconds)?);
+        }
+        if let Some(check) = var("NESTEST_N_CHECK_DETERMINISM") {
+            self.check_determinism = parse_bool("NESTEST_N_CHECK_DETERMINISM", &check)?;
+        }
+        if let Some(parallel) = var("NESTEST_N_PARALLEL_SINGLES") {
+            self.parallel_singles = parse_bool("NESTEST_N_PARALLEL_SINGLES", &parallel)?;
+        }
+        if let Some(localize) = var("NESTEST_N_LOCALIZE_FAILURES") {
+            self.localize_failures = parse_bool("NESTEST_N_LOCALIZE_FAILURES", &localize)?;
+        }
+        if let Some(hints) = var("NESTEST_N_HINTS") {
+            self.no_hints = !parse_bool("NESTEST_N_HINTS", &hints)?;
+        }
+        if let Some(isolate) = var("NESTEST_N_ISOLATE") {
+            self.isolate = parse_bool("NESTEST_N_ISOLATE", &isolate)?;
+        }
+        if let Some(capture) = var("NESTEST_N_CAPTURE_LOGS") {
+            self.capture_logs = parse_log_capture("NESTEST_N_CAPTURE_LOGS", &capture)?;
+        }
+        if let Some(mirroring) = var("NESTEST_N_MIRRORING") {
+            self.mirroring = Some(parse_mirroring("NESTEST_N_MIRRORING", &mirroring)?);
+        }
+        if let Some(region) = var("NESTEST_N_REGION") {
+            self.region = parse_region("NESTEST_N_REGION", &region)?;
+        }
+        if let Some(retries) = var("NESTEST_N_RETRIES") {
+            self.retries = retries.trim().parse().map_err(|_| ConfigError::Invalid {
+                key: "NESTEST_N_RETRIES".to_string(),
+                message: format!("expected a number of retries, got '{retries}'"),
+            })?;
+        }
+        if let Some(threshold) = var("NESTEST_N_PASS_THRESHOLD") {
+            let percentage = threshold.trim().parse().map_err(|_| ConfigError::Invalid {
+                key: "NESTEST_N_PASS_THRESHOLD".to_string(),
+                message: format!("expected a percentage, got '{threshold}'"),
+            })?;
+            self.pass_threshold = Some(parse_percentage("NESTEST_N_PASS_THRESHOLD", percentage)?);
+        }
+        if let Some(chunks) = var("NESTEST_N_STALL_CHUNKS") {
+            self.stall_chunks = Some(chunks.trim().parse().map_err(|_| ConfigError::Invalid {
+                key: "NESTEST_N_STALL_CHUNKS".to_string(),
+                message: format!("expected a number of chunks, got '{chunks}'"),
+            })?);
+        }
+        if let Some(chunks) = var("NESTEST_N_WATCHDOG_CHUNKS") {
+            self.watchdog_chunks =
+                Some(chunks.trim().parse().map_err(|_| ConfigError::Invalid {
+                    key: "NESTEST_N_WATCHDOG_CHUNKS".to_string(),
+                    message: format!("expected a number of chunks, got '{chunks}'"),
+                })?);
+        }
+        if let Some(cycles) = var("NESTEST_N_FAILURE_TRACE") {
+            self.failure_trace = Some(cycles.trim().parse().map_err(|_| ConfigError::Invalid {
+                key: "NESTEST_N_FAILURE_TRACE".to_string(),
+                message: format!("expected a number of cycles, got '{cycles}'"),
+            })?);
+        }
+        if let Some(ram) = var("NESTEST_N_RAM") {
+            self.ram_init = parse_ram_init("NESTEST_N_RAM", &ram)?;
+        }
+        if let Some(shard) = var("NESTEST_N_SHARD") {
+            self.shard = Some(parse_shard("NESTEST_N_SHARD", &shard)?);
+        }
+        if let Some(opcodes) = var("NESTEST_N_UNOFFICIAL_OPCODES") {
+            self.unofficial_opcodes =
+                parse_unofficial_opcodes("NESTEST_N_UNOFFICIAL_OPCODES", opcodes.split(','))?;
+        }
+        if let Some(policy) = var("NESTEST_N_UNSTABLE_OPCODES") {
+            self.unstable_opcodes = parse_unstable_policy("NESTEST_N_UNSTABLE_OPCODES", &policy)?;
+        }
+        if let Some(watchpoints) = var("NESTEST_N_WATCHPOINTS") {
+            self.watchpoints = watchpoints
+                .split(',')
+                .map(|w| parse_watchpoint("NESTEST_N_WATCHPOINTS", w))
+                .collect::<Result<_, _>>()?;
+        }
+        if let Some(suites) = var("NESTEST_N_SUITES") {
+            self.suites = suites
+                .split(',')
+                .map(|path| PathBuf::from(path.trim()))
+                .collect();
+        }
+        let mut keys: Vec<_> = vars.keys().collect();
+        keys.sort();
+        for key in keys {
+            let Some(value) = var(key) else {
+                continue;
+            };
+            if let Some(name) = key.strip_prefix("NESTEST_N_CYCLES_") {
+                let test = parse_budget_test(key, name)?;
+                let cycles =
+                    value
+                        .trim()
+                        .replace('_', "")
+                        .parse()
+                        .map_err(|_| ConfigError::Invalid {
+                            key: key.clone(),
+                            message: format!("expected a number of cycles, got '{value}'"),
+                        })?;
+                self.cycle_budgets.insert(test, cycles);
+            } else if let Some(name) = key.strip_prefix("NESTEST_N_INSTRUCTIONS_") {
+                let test = parse_budget_test(key, name)?;
+                let instructions =
+                    value
+                        .trim()
+                        .replace('_', "")
+                        .parse()
+                        .map_err(|_| ConfigError::Invalid {
+                            key: key.clone(),
+                            message: format!("expected a number of instructions, got '{value}'"),
+                        })?;
+                self.instruction_budgets.insert(test, instructions);
+            }
+        }
+
+        Ok(())
+    }
+
+    fn apply_toml(&mut self, table: &toml::Table) -> Result<(), ConfigError> {
+        for (key, value) in table {
+            let invalid = |message: &str| ConfigError::Invalid {
+                key: key.clone(),
+                message: format!("{message}, got {}", value.type_str()),
+            };
+
+            match key.as_str() {
+                "tests" => {
+                    let tests = match value.as_array() {
+                        Some(tests) => tests
+                            .iter()
+                            .map(|t| t.as_str().ok_or_else(|| invalid("expected test names")))
+                            .collect::<Result<Vec<_>, _>>()?,
+                        None => vec![value
+                            .as_str()
+                            .ok_or_else(|| invalid("expected test names"))?],
+                    };
+                    self.selector = parse_tests(key, tests)?;
+                }
+                "verbosity" => {
+                    let verbosity = value.as_str().ok_or_else(|| invalid("expected a string"))?;
+                    self.verbosity = parse_verbosity(key, verbosity)?;
+                }
+                "allowed_failures" => {
+                    self.allowed_failures = value
+                        .as_array()
+                        .ok_or_else(|| invalid("expected a list of sub-test names"))?
+                        .iter()
+                        .map(|t| {
+                            t.as_str()
+                                .map(str::to_string)
+                                .ok_or_else(|| invalid("expected sub-test names"))
+                        })
+                        .collect::<Result<_, _>>()?;
+                }
+                "filters" => {
+                    self.filters = value
+                        .as_array()
+                        .ok_or_else(|| invalid("expected a list of test ids"))?
+                        .iter()
+                        .map(|t| {
+                            t.as_str()
+                                .map(str::to_string)
+                                .ok_or_else(|| invalid("expected test ids"))
+                        })
+                        .collect::<Result<_, _>>()?;
+                }
+                "rom_dir" => {
+                    let rom_dir = value.as_str().ok_or_else(|| invalid("expected a path"))?;
+                    self.rom_dir = Some(PathBuf::from(rom_dir));
+                }
+                "mirroring" => {
+                    let mirroring = value.as_str().ok_or_else(|| invalid("expected a string"))?;
+                    self.mirroring = Some(parse_mirroring(key, mirroring)?);
+                }
+                "capture_logs" => {
+                    let capture = value.as_str().ok_or_else(|| invalid("expected a string"))?;
+                    self.capture_logs = parse_log_capture(key, capture)?;
+                }
+                "region" => {
+                    let region = value.as_str().ok_or_else(|| invalid("expected a string"))?;
+                    self.region = parse_region(key, region)?;
+                }
+                "retries" => {
+                    self.retries = value
+                        .as_integer()
+                        .and_then(|r| usize::try_from(r).ok())
+                        .ok_or_else(|| invalid("expected a number of retries"))?;
+                }
+                "pass_threshold" => {
+                    let percentage = match (value.as_integer(), value.as_float()) {
+                        (Some(percentage), _) => percentage as f64,
+                        (_, Some(percentage)) => percentage,
+                        _ => return Err(invalid("expected a percentage")),
+                    };
+                    self.pass_threshold = Some(parse_percentage(key, percentage)?);
+                }
+                "stall_chunks" => {
+                    self.stall_chunks = Some(
+                        value
+                            .as_integer()
+                            .and_then(|c| u64::try_from(c).ok())
+                            .ok_or_else(|| invalid("expected a number of chunks"))?,
+                    );
+                }
+                "watchdog_chunks" => {
+                    self.watchdog_chunks = Some(
+                        value
+                            .as_integer()
+                            .and_then(|c| u64::try_from(c).ok())
+                            .ok_or_else(|| invalid("expected a number of chunks"))?,
+                    );
+                }
+                "failure_trace" => {
+                    self.failure_trace = Some(
+                        value
+                            .as_integer()
+                            .and_then(|c| u64::try_from(c).ok())
+                            .ok_or_else(|| invalid("expected a number of cycles"))?,
+                    );
+                }
+                "ram" => {
+                    let ram = value
+                        .as_str()
+                        .ok_or_else(|| invalid("expected cpu, random or a byte"))?;
+                    self.ram_init = parse_ram_init(key, ram)?;
+                }
+                "download_dir" => {
+                    let dir = value.as_str().ok_or_else(|| invalid("expected a path"))?;
+                    self.download_dir = Some(PathBuf::from(dir));
+                }
+                "download_mirror" => {
+                    let mirror = value.as_str().ok_or_else(|| invalid("expected a url"))?;
+                    self.download_mirror = Some(mirror.to_string());
+                }
+                "artifact_dir" => {
+                    let dir = value.as_str().ok_or_else(|| invalid("expected a path"))?;
+                    self.artifact_dir = Some(PathBuf::from(dir));
+                }
+                "cache_dir" => {
+                    let dir = value.as_str().ok_or_else(|| invalid("expected a path"))?;
+                    self.cache_dir = Some(PathBuf::from(dir));
+                }
+                "fingerprint" => {
+                    let fingerprint = value.as_str().ok_or_else(|| invalid("expected a string"))?;
+                    self.fingerprint = Some(fingerprint.to_string());
+                }
+                "checkpoint_dir" => {
+                    let dir = value.as_str().ok_or_else(|| invalid("expected a path"))?;
+                    self.checkpoint_dir = Some(PathBuf::from(dir));
+                }
+                "timeout" => {
+                    let seconds = match (value.as_integer(), value.as_float()) {
+                        (Some(seconds), _) => seconds as f64,
+                        (_, Some(seconds)) => seconds,
+                        _ => return Err(invalid("expected a number of seconds")),
+                    };
+                    self.timeout = Some(parse_timeout(key, seconds)?);
+                }
+                "check_determinism" => {
+                    self.check_determinism = value
+                        .as_bool()
+                        .ok_or_else(|| invalid("expected true or false"))?;
+                }
+                "parallel_singles" => {
+                    self.parallel_singles = value
+                        .as_bool()
+                        .ok_or_else(|| invalid("expected true or false"))?;
+                }
+                "localize_failures" => {
+                    self.localize_failures = value
+                        .as_bool()
+                        .ok_or_else(|| invalid("expected true or false"))?;
+                }
+                "hints" => {
+                    self.no_hints = !value
+                        .as_bool()
+                        .ok_or_else(|| invalid("expected true or false"))?;
+                }
+                "isolate" => {
+                    self.isolate = value
+                        .as_bool()
+                        .ok_or_else(|| invalid("expected true or false"))?;
+                }
+                "unofficial_opcodes" => {
+                    let categories = value
+                        .as_array()
+                        .ok_or_else(|| invalid("expected a list of opcode categories"))?
+                        .iter()
+                        .map(|c| {
+                            c.as_str()
+                                .ok_or_else(|| invalid("expected opcode categories"))
+                        })
+                        .collect::<Result<Vec<_>, _>>()?;
+                    self.unofficial_opcodes = parse_unofficial_opcodes(key, categories)?;
+                }
+                "unstable_opcodes" => {
+                    let policy = value.as_str().ok_or_else(|| invalid("expected a string"))?;
+                    self.unstable_opcodes = parse_unstable_policy(key, policy)?;
+                }
+                "watchpoints" => {
+                    self.watchpoints = value
+                        .as_array()
+                        .ok_or_else(|| invalid("expected a list of watchpoints"))?
+                        .iter()
+                        .map(|w| {
+                            let w = w.as_str().ok_or_else(|| invalid("expected watchpoints"))?;
+                            parse_watchpoint(key, w)
+                        })
+                        .collect::<Result<_, _>>()?;
+                }
+                "suites" => {
+                    self.suites = value
+                        .as_array()
+                        .ok_or_else(|| invalid("expected a list of paths"))?
+                        .iter()
+                        .map(|path| {
+                            path.as_str()
+                                .map(PathBuf::from)
+                                .ok_or_else(|| invalid("expected a list of paths"))
+                        })
+                        .collect::<Result<_, _>>()?;
+                }
+                "custom_roms" => {
+                    self.custom_roms = value
+                        .as_array()
+                        .ok_or_else(|| invalid("expected a list of roms"))?
+                        .iter()
+                        .enumerate()
+                        .map(|(i, rom)| parse_custom_rom(&format!("custom_roms.{i}"), rom))
+                        .collect::<Result<_, _>>()?;
+                }
+                "cycles" => {
+                    let budgets = value
+                        .as_table()
+                        .ok_or_else(|| invalid("expected a table of test names and cycles"))?;
+                    for (name, cycles) in budgets {
+                        let key = format!("cycles.{name}");
+                        let test = parse_budget_test(&key, name)?;
+                        let cycles = cycles
+                            .as_integer()
+                            .and_then(|c| u64::try_from(c).ok())
+                            .ok_or_else(|| ConfigError::Invalid {
+                                key,
+                                message: format!(
+                                    "expected a number of cycles, got {}",
+                                    cycles.type_str()
+                                ),
+                            })?;
+                        self.cycle_budgets.insert(test, cycles);
+                    }
+                }
+                "instructions" => {
+                    let budgets = value.as_table().ok_or_else(|| {
+                        invalid("expected a table of test names and instructions")
+                    })?;
+                    for (name, instructions) in budgets {
+                        let key = format!("instructions.{name}");
+                        let test = parse_budget_test(&key, name)?;
+                        let instructions = instructions
+                            .as_integer()
+                            .and_then(|i| u64::try_from(i).ok())
+                            .ok_or_else(|| ConfigError::Invalid {
+                                key,
+                                message: format!(
+                                    "expected a number of instructions, got {}",
+                                    instructions.type_str()
+                                ),
+                            })?;
+                        self.instruction_budgets.insert(test, instructions);
+                    }
+                }
+                _ => {
+                    return Err(ConfigError::Invalid {
+                        key: key.clone(),
+                        message: "unknown setting".to_string(),
+                    })
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// The number of cycles `test` may run, `default` unless the configuration changes it
+    pub(crate) fn cycle_budget(&self, test: TestSelector, default: u64) -> u64 {
+        self.cycle_budgets.get(&test).copied().unwrap_or(default)
+    }
+
+    /// The number of instructions `test` may run, if the configuration limits them
+    pub(crate) fn instruction_budget(&self, test: TestSelector) -> Option<u64> {
+        self.instruction_budgets.get(&test).copied()
+    }
+
+    /// The categories of unofficial opcodes the roms of instr_test-v5 test: those of
+    /// [`unofficial_opcodes`](Self::unofficial_opcodes), without the unstable ones unless the roms
+    /// test them, see [`UnstablePolicy`]
+    pub(crate) fn rom_opcodes(&self) -> UnofficialOpcodes {
+        match self.unstable_opcodes {
+            UnstablePolicy::Strict => self.unofficial_opcodes,
+            UnstablePolicy::AnyVariant | UnstablePolicy::Skip => {
+                self.unofficial_opcodes - UnofficialOpcodes::UNSTABLE
+            }
+        }
+    }
+
+    /// How long a rom that waits for frames may run for `test`, `default` cycles on an NTSC NES unless
+    /// the configuration changes it. The default grows with the longer frames of the other regions.
+    pub(crate) fn budget(&self, test: TestSelector, default: u64) -> Budget {
+        Budget {
+            cycles: self.cycle_budget(test, self.region.scale(default)),
+            instructions: self.instruction_budget(test),
+        }
+    }
+}
+
+pub(crate) fn parse_test(key: &str, name: &str) -> Result<TestSelector, ConfigError> {
+    let name = name.trim().to_lowercase();
+    TEST_NAMES
+        .iter()
+        .find(|(n, _)| *n == name)
+        .map(|&(_, test)| test)
+        .ok_or_else(|| ConfigError::Invalid {
+            key: key.to_string(),
+            message: format!(
+                "unknown test '{name}', expected one of {}",
+                TEST_NAMES
+                    .iter()
+                    .map(|(n, _)| *n)
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            ),
+        })
+}
+
+/// Parses the name of the test of a budget, which is a single test: a group like `all` has no budget
+/// of its own
+fn parse_budget_test(key: &str, name: &str) -> Result<TestSelector, ConfigError> {
+    let test = parse_test(key, name)?;
+    if test.bits().count_ones() != 1 {
+        return Err(ConfigError::Invalid {
+            key: key.to_string(),
+            message: format!(
+                "'{}' is a group of tests, a budget is for a single test like all_instrs",
+                name.trim().to_lowercase()
+            ),
+        });
+    }
+    Ok(test)
+}
+
+/// The name of a single test, which is also its id
+pub(crate) fn test_name(test: TestSelector) -> Option<&'static str> {
+    TEST_NAMES
+        .iter()
+        .find(|&&(_, t)| t == test)
+        .map(|&(name, _)| name)
+}
+
+/// The names of the single tests `tests` selects
+#[cfg(feature = "serde")]
+pub(crate) fn test_names(tests: TestSelector) -> Vec<&'static str> {
+    TEST_NAMES
+        .iter()
+        .filter(|(_, test)| test.bits().count_ones() == 1 && tests.contains(*test))
+        .map(|&(name, _)| name)
+        .collect()
+}
+
+pub(crate) fn parse_tests<'a>(
+    key: &str,
+    names: impl IntoIterator<Item = &'a str>,
+) -> Result<TestSelector, ConfigError> {
+    names
+        .into_iter()
+        .try_fold(TestSelector::empty(), |tests, name| {
+            Ok(tests | parse_test(key, name)?)
+        })
+}
+
+/// The names of the categories in `opcodes`
+#[cfg(feature = "serde")]
+pub(crate) fn unofficial_opcode_names(opcodes: UnofficialOpcodes) -> Vec<&'static str> {
+    UNOFFICIAL_OPCODE_NAMES
+        .iter()
+        .filter(|(_, category)| opcodes.contains(*category))
+        .map(|&(name, _)| name)
+        .collect()
+}
+
+/// Unlike tests, an empty list is allowed: it only tests the official opcodes
+pub(crate) fn parse_unofficial_opcodes<'a>(
+    key: &str,
+    names: impl IntoIterator<Item = &'a str>,
+) -> Result<UnofficialOpcodes, ConfigError> {
+    names
+        .into_iter()
+        .map(|name| name.trim().to_lowercase())
+        .filter(|name| !name.is_empty())
+        .try_fold(UnofficialOpcodes::empty(), |opcodes, name| {
+            let category = UNOFFICIAL_OPCODE_NAMES
+                .iter()
+                .find(|(n, _)| *n == name)
+                .map(|&(_, category)| category)
+                .ok_or_else(|| ConfigError::Invalid {
+                    key: key.to_string(),
+                    message: format!(
+                        "unknown opcode category '{name}', expected one of {}",
+                        UNOFFICIAL_OPCODE_NAMES
+                            .iter()
+                            .map(|(n, _)| *n)
+                            .collect::<Vec<_>>()
+                            .join(", ")
+                    ),
+                })?;
+            Ok(opcodes | category)
+        })
+}
+
+fn parse_verbosity(key: &str, verbosity: &str) -> Result<Verbosity, ConfigError> {
+    match verbosity.trim().to_lowercase().as_str() {
+        "quiet" => Ok(Verbosity::Quiet),
+        "normal" => Ok(Verbosity::Normal),
+        "verbose" => Ok(Verbosity::Verbose),
+        other => Err(ConfigError::Invalid {
+            key: key.to_string(),
+            message: format!("unknown verbosity '{other}', expected quiet, normal or verbose"),
+        }),
+    }
+}
+
+fn parse_mirroring(key: &str, mirroring: &str) -> Result<NametableMirroring, ConfigError> {
+    match mirroring.trim().to_lowercase().as_str() {
+        "horizontal" => Ok(NametableMirroring::Horizontal),
+        "vertical" => Ok(NametableMirroring::Vertical),
+        other => Err(ConfigError::Invalid {
+            key: key.to_string(),
+            message: format!("unknown mirroring '{other}', expected horizontal or vertical"),
+        }),
+    }
+}
+
+pub(crate) fn parse_region(key: &str, region: &str) -> Result<Region, ConfigError> {
+    match region.trim().to_lowercase().as_str() {
+        "ntsc" => Ok(Region::Ntsc),
+        "pal" => Ok(Region::Pal),
+        "dendy" => Ok(Region::Dendy),
+        other => Err(ConfigError::Invalid {
+            key: key.to_string(),
+            message: format!("unknown region '{other}', expected ntsc, pal or dendy"),
+        }),
+    }
+}
+
+fn parse_unstable_policy(key: &str, policy: &str) -> Result<UnstablePolicy, ConfigError> {
+    match policy.trim().to_lowercase().replace('-', "_").as_str() {
+        "strict" => Ok(UnstablePolicy::Strict),
+        "any_variant" => Ok(UnstablePolicy::AnyVariant),
+        "skip" => Ok(UnstablePolicy::Skip),
+        other => Err(ConfigError::Invalid {
+            key: key.to_string(),
+            message: format!(
+                "unknown way to test unstable opcodes '{other}', expected strict, any_variant or skip"
+            ),
+        }),
+    }
+}
+
+fn parse_log_capture(key: &str, capture: &str) -> Result<LogCapture, ConfigError> {
+    match capture.trim().to_lowercase().as_str() {
+        "off" => Ok(LogCapture::Off),
+        "harness" => Ok(LogCapture::Harness),
+        "all" => Ok(LogCapture::All),
+        other => Err(ConfigError::Invalid {
+            key: key.to_string(),
+            message: format!("unknown log capture '{other}', expected off, harness or all"),
+        }),
+    }
+}
+
+fn parse_bool(key: &str, value: &str) -> Result<bool, ConfigError> {
+    match value.trim().to_lowercase().as_str() {
+        "1" | "true" | "yes" => Ok(true),
+        "0" | "false" | "no" => Ok(false),
+        other => Err(ConfigError::Invalid {
+            key: key.to_string(),
+            message: format!("expected true or false, got '{other}'"),
+        }),
+    }
+}
+
+/// Parses what the ram holds: `cpu`, `random`, `random:<seed>`, or a byte like `$FF` or `0xFF`
+fn parse_ram_init(key: &str, ram: &str) -> Result<RamInit, ConfigError> {
+    let invalid = || ConfigError::Invalid {
+        key: key.to_string(),
+        message: format!("expected cpu, random, random:<seed> or a byte like $FF, got '{ram}'"),
+    };
+
+    let text = ram.trim().to_lowercase();
+    match text.as_str() {
+        "cpu" => Ok(RamInit::Cpu),
+        "random" => Ok(RamInit::Random(None)),
+        _ => {
+            if let Some(seed) = text.strip_prefix("random:") {
+                let seed = seed.trim().parse().map_err(|_| invalid())?;
+                return Ok(RamInit::Random(Some(seed)));
+            }
+            text.strip_prefix('$')
+                .or_else(|| text.strip_prefix("0x"))
+                .and_then(|hex| u8::from_str_radix(hex, 16).ok())
+                .map(RamInit::Filled)
+                .ok_or_else(invalid)
+        }
+    }
+}
+
+fn parse_shard(key: &str, shard: &str) -> Result<Shard, ConfigError> {
+    let invalid = || ConfigError::Invalid {
+        key: key.to_string(),
+        message: format!("expected a shard and the number of shards like 0/4, got '{shard}'"),
+    };
+
+    let (index, count) = shard.trim().split_once('/').ok_or_else(invalid)?;
+    let index: usize = index.trim().parse().map_err(|_| invalid())?;
+    let count: usize = count.trim().parse().map_err(|_| invalid())?;
+    if index >= count {
+        return Err(ConfigError::Invalid {
+            key: key.to_string(),
+            message: format!(
+                "shard {index} doesn't exist, with {count} shards they go from 0 to {}",
+                count.saturating_sub(1)
+            ),
+        });
+    }
+
+    Ok(Shard::new(index, count))
+}
+
+/// Parses a watchpoint like `write $4014`, `read $2002` or `$6000-$6003`, which watches both
+fn parse_watchpoint(key: &str, watchpoint: &str) -> Result<Watchpoint, ConfigError> {
+    let invalid = || {
+        ConfigError::Invalid {
+        key: key.to_string(),
+        message: format!(
+            "expected an address or range like $6000-$6003, optionally after read or write, got '{watchpoint}'"
+        ),
+    }
+    };
+    let address = |text: &str| {
+        let text = text.trim();
+        let hex = text
+            .strip_prefix('$')
+            .or_else(|| text.strip_prefix("0x"))
+            .ok_or_else(invalid)?;
+        u16::from_str_radix(hex, 16).map_err(|_| invalid())
+    };
+
+    let text = watchpoint.trim().to_lowercase();
+    let (access, range) = match text.split_once(' ') {
+        Some(("read", range)) => (Access::Read, range),
+        Some(("write", range)) => (Access::Write, range),
+        Some(_) => return Err(invalid()),
+        None => (Access::ReadWrite, text.as_str()),
+    };
+    let (first, last) = match range.split_once('-') {
+        Some((first, last)) => (address(first)?, address(last)?),
+        None => (address(range)?, address(range)?),
+    };
+    if last < first {
+        return Err(invalid());
+    }
+
+    Ok(Watchpoint {
+        first,
+        last,
+        access,
+    })
+}
+
+fn parse_custom_rom(key: &str, value: &toml::Value) -> Result<CustomRom, ConfigError> {
+    let invalid = |key: &str, message: &str, value: &toml::Value| ConfigError::Invalid {
+        key: key.to_string(),
+        message: format!("{message}, got {}", value.type_str()),
+    };
+    let table = value.as_table().ok_or_else(|| {
+        invalid(
+            key,
+            "expected a table with the name and path of the rom",
+            value,
+        )
+    })?;
+    let string = |field: &str| {
+        let key = format!("{key}.{field}");
+        match table.get(field) {
+            Some(value) => value
+                .as_str()
+                .ok_or_else(|| invalid(&key, "expected a string", value)),
+            None => Err(ConfigError::Invalid {
+                key,
+                message: "missing setting".to_string(),
+            }),
+        }
+    };
+
+    let mut rom = CustomRom::new(string("name")?, string("path")?);
+    for (field, value) in table {
+        let key = format!("{key}.{field}");
+        match field.as_str() {
+            "name" | "path" => {}
+            "status_address" => {
+                let address = match value {
+                    toml::Value::String(address) => parse_address(&key, address)?,
+                    value => value
+                        .as_integer()
+                        .and_then(|a| u16::try_from(a).ok())
+                        .ok_or_else(|| {
+                            invalid(&key, "expected an address like \"$7000\"", value)
+                        })?,
+                };
+                rom.status_addresses = StatusAddresses::at(address);
+            }
+            "cycles" => {
+                rom.cycles = value
+                    .as_integer()
+                    .and_then(|c| u64::try_from(c).ok())
+                    .ok_or_else(|| invalid(&key, "expected a number of cycles", value))?;
+            }
+            "memory" => {
+                let memory = value.as_table().ok_or_else(|| {
+                    invalid(&key, "expected a table of addresses and bytes", value)
+                })?;
+                for (address, bytes) in memory {
+                    let key = format!("{key}.{address}");
+                    let address = parse_address(&key, address)?;
+                    let bytes = bytes
+                        .as_array()
+                        .ok_or_else(|| invalid(&key, "expected a list of bytes", bytes))?
+                        .iter()
+                        .map(|b| {
+                            b.as_integer()
+                                .and_then(|b| u8::try_from(b).ok())
+                                .ok_or_else(|| invalid(&key, "expected bytes", b))
+                        })
+                        .collect::<Result<Vec<_>, _>>()?;
+                    rom = rom.expect_memory(address, &bytes);
+                }
+            }
+            _ => {
+                return Err(ConfigError::Invalid {
+                    key,
+                    message: "unknown setting".to_string(),
+                })
+            }
+        }
+    }
+    Ok(rom)
+}
+
+fn parse_percentage(key: &str, percentage: f64) -> Result<f64, ConfigError> {
+    if (0.0..=100.0).contains(&percentage) {
+        Ok(percentage)
+    } else {
+        Err(ConfigError::Invalid {
+            key: key.to_string(),
+            message: format!("expected a percentage from 0 to 100, got {percentage}"),
+        })
+    }
+}
+
+/// Parses an address like `$7000` or `0x7000`
+pub(crate) fn parse_address(key: &str, address: &str) -> Result<u16, ConfigError> {
+    let text = address.trim();
+    text.strip_prefix('$')
+        .or_else(|| text.strip_prefix("0x"))
+        .and_then(|hex| u16::from_str_radix(hex, 16).ok())
+        .ok_or_else(|| ConfigError::Invalid {
+            key: key.to_string(),
+            message: format!("expected an address like $7000, got '{address}'"),
+        })
+}
+
+fn parse_timeout(key: &str, seconds: f64) -> Result<Duration, ConfigError> {
+    Duration::try_from_secs_f64(seconds).map_err(|_| ConfigError::Invalid {
+        key: key.to_string(),
+        message: format!("expected a positive number of seconds, got {seconds}"),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn toml(text: &str) -> Result<TestConfig, ConfigError> {
+        let mut config = TestConfig::default();
+        config.apply_toml(&text.parse::<toml::Table>().expect("valid toml"))?;
+        Ok(config)
+    }
+
+    fn env(config: &mut TestConfig, vars: &[(&str, &str)]) -> Result<(), ConfigError> {
+        let vars = vars
+            .iter()
+            .map(|&(key, value)| (key.to_string(), value.to_string()))
+            .collect();
+        config.apply_vars(&vars)
+    }
+
+    fn invalid_key(result: Result<(), ConfigError>) -> String {
+        match result {
+            Err(ConfigError::Invalid { key, .. }) => key,
+            result => panic!("expected an invalid setting, got {result:?}"),
+        }
+    }
+
+    #[test]
+    fn budgets_from_the_environment() {
+        let mut config = TestConfig::default();
+        env(
+            &mut config,
+            &[
+                ("NESTEST_N_CYCLES_ALL_INSTRS", "150_000_000"),
+                ("NESTEST_N_INSTRUCTIONS_NESTEST", "9000"),
+                ("NESTEST_N_CYCLES_NESTEST", " "),
+            ],
+        )
+        .unwrap();
+        assert_eq!(
+            config.cycle_budget(TestSelector::ALL_INSTRS, 0),
+            150_000_000
+        );
+        assert_eq!(config.instruction_budget(TestSelector::NESTEST), Some(9000));
+        assert_eq!(config.cycle_budget(TestSelector::NESTEST, 1), 1);
+    }
+
+    #[test]
+    fn budgets_of_unknown_tests_are_invalid() {
+        for key in [
+            "NESTEST_N_CYCLES_ALL",
+            "NESTEST_N_CYCLES_DEFAULT",
+            "NESTEST_N_INSTRUCTIONS_NESTES",
+        ] {
+            let mut config = TestConfig::default();
+            assert_eq!(invalid_key(env(&mut config, &[(key, "1000")])), key);
+        }
+
+        let mut config = TestConfig::default();
+        let result = env(&mut config, &[("NESTEST_N_CYCLES_NESTEST", "many")]);
+        assert_eq!(invalid_key(result), "NESTEST_N_CYCLES_NESTEST");
+    }
+
+    #[test]
+    fn every_single_test_can_have_a_budget() {
+        let single = TEST_NAMES
+            .iter()
+            .take_while(|&&(name, _)| name != "instr_groups");
+        for &(name, test) in single {
+            assert_eq!(parse_budget_test("cycles", name).unwrap(), test);
+        }
+    }
+
+    #[test]
+    fn the_environment_overrides_the_file() {
+        let mut config = toml(
+            r#"
+            tests = ["nestest", "all_instrs"]
+
+            [cycles]
+            all_instrs = 150_000_000
+            nestest = 2_000_000
+            "#,
+        )
+        .unwrap();
+        env(
+            &mut config,
+            &[
+                ("NESTEST_N_TESTS", "nestest"),
+                ("NESTEST_N_CYCLES_NESTEST", "3000000"),
+            ],
+        )
+        .unwrap();
+        assert_eq!(config.selector, TestSelector::NESTEST);
+        assert_eq!(config.cycle_budget(TestSelector::NESTEST, 0), 3_000_000);
+        assert_eq!(
+            config.cycle_budget(TestSelector::ALL_INSTRS, 0),
+            150_000_000
+        );
+    }
+
+    #[test]
+    fn budgets_of_unknown_tests_in_the_file_are_invalid() {
+        let result = toml("[cycles]\nall = 1_000").map(drop);
+        assert_eq!(invalid_key(result), "cycles.all");
+        let result = toml("[instructions]\nnestest = \"many\"").map(drop);
+        assert_eq!(invalid_key(result), "instructions.nestest");
+        let result = toml("cycle_budget = 1_000").map(drop);
+        assert_eq!(invalid_key(result), "cycle_budget");
+    }
+
+    #[test]
+    fn hints_can_be_turned_off() {
+        let mut config = toml("hints = false").unwrap();
+        assert!(config.no_hints);
+        env(&mut config, &[("NESTEST_N_HINTS", "true")]).unwrap();
+        assert!(!config.no_hints);
+        env(&mut config, &[("NESTEST_N_HINTS", "false")]).unwrap();
+        assert!(config.no_hints);
+    }
+}
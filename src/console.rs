@@ -0,0 +1,329 @@
+//! A human-readable report of a test run, printed to the terminal for people who don't have a
+//! logger set up, or written to any other [`Write`]
+#[cfg(feature = "indicatif")]
+use crate::progress_bar::TestProgressBar;
+use crate::report::{Progress, TestReport, TestResult};
+use crate::reporter::Reporter;
+use std::io::{IsTerminal, Write};
+
+/// How much a [`TextReporter`] writes while running tests
+#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Default)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(rename_all = "lowercase")
+)]
+pub enum Verbosity {
+    /// Only write the tests that failed and a final summary line, like `-q`
+    Quiet,
+    /// Write a line for every test and a summary table at the end
+    #[default]
+    Normal,
+    /// Also write the progress of long running tests while they run, like `-v`
+    Verbose,
+}
+
+const GREEN: &str = "\x1b[32m";
+const RED: &str = "\x1b[31m";
+const YELLOW: &str = "\x1b[33m";
+const RESET: &str = "\x1b[0m";
+/// How many lines of [`TestResult::failure_trace`] are shown, the artifact of the test has all of them
+const TRACE_LINES: usize = 20;
+
+/// A [`Reporter`] writing a human-readable report, in the style of `cargo test`.
+/// This is what [`run_tests_with_config`](crate::run_tests_with_config) prints to the console.
+pub struct TextReporter<W: Write = Console> {
+    out: W,
+    verbosity: Verbosity,
+    color: bool,
+    /// whether `out` is a terminal someone is watching
+    #[cfg(feature = "indicatif")]
+    interactive: bool,
+    /// the progress bar of the test that is running
+    #[cfg(feature = "indicatif")]
+    bar: Option<TestProgressBar>,
+}
+
+impl TextReporter<Console> {
+    /// A reporter printing to stdout, using colors when stdout is a terminal
+    pub fn stdout(verbosity: Verbosity) -> Self {
+        let interactive = std::io::stdout().is_terminal();
+
+        Self {
+            out: Console,
+            verbosity,
+            // see https://no-color.org
+            color: interactive && std::env::var_os("NO_COLOR").is_none(),
+            #[cfg(feature = "indicatif")]
+            interactive,
+            #[cfg(feature = "indicatif")]
+            bar: None,
+        }
+    }
+}
+
+/// Prints to stdout with `print!`, so `cargo test` captures what a [`TextReporter`] prints in a
+/// `#[test]` and only shows it when the test fails, like its own output
+#[derive(Debug, Default)]
+pub struct Console;
+
+impl Write for Console {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        print!("{}", String::from_utf8_lossy(buf));
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        std::io::stdout().flush()
+    }
+}
+
+impl<W: Write> TextReporter<W> {
+    /// A reporter writing plain text to `out`, for example a file
+    pub fn new(out: W, verbosity: Verbosity) -> Self {
+        Self {
+            out,
+            verbosity,
+            color: false,
+            #[cfg(feature = "indicatif")]
+            interactive: false,
+            #[cfg(feature = "indicatif")]
+            bar: None,
+        }
+    }
+
+    /// Returns the writer the report was written to
+    pub fn into_inner(self) -> W {
+        self.out
+    }
+
+    fn paint(&self, text: &str, color: &str) -> String {
+        if self.color {
+            format!("{color}{text}{RESET}")
+        } else {
+            text.to_string()
+        }
+    }
+
+    fn status(&self, result: &TestResult) -> String {
+        if !result.passed() {
+            self.paint("FAILED", RED)
+        } else if let Some(reason) = &result.skipped {
+            self.paint(&format!("skipped, {reason}"), YELLOW)
+        } else if result.cached {
+            self.paint("ok, passed for this build before", GREEN)
+        } else if result.flaky() {
+            let attempts = result.failed_attempts.len() + 1;
+            self.paint(&format!("ok, flaky: passed at attempt {attempts}"), YELLOW)
+        } else if !result.expected_failures.is_empty() {
+            let expected = format!(
+                "ok, expected failure of {}",
+                result.expected_failures.join(", ")
+            );
+            self.paint(&expected, YELLOW)
+        } else {
+            self.paint("ok", GREEN)
+        }
+    }
+
+    fn summary_table(&mut self, report: &TestReport) {
+        let width = report
+            .results
+            .iter()
+            .map(|r| r.name.len())
+            .max()
+            .unwrap_or(0)
+            .max("test".len());
+
+        let _ = writeln!(self.out);
+        let _ = writeln!(self.out, " {:width$}  result  duration", "test");
+        let _ = writeln!(self.out, " {:-<width$}  ------  --------", "");
+        for result in &report.results {
+            // pad before painting, the escape codes would throw off the alignment
+            let status = if !result.passed() {
+                self.paint("FAILED", RED)
+            } else if result.skipped.is_some() {
+                self.paint("skip  ", YELLOW)
+            } else if result.flaky() {
+                self.paint("flaky ", YELLOW)
+            } else if result.cached {
+                self.paint("cached", GREEN)
+            } else if !result.expected_failures.is_empty() {
+                self.paint("xfail ", YELLOW)
+            } else {
+                self.paint("ok    ", GREEN)
+            };
+            let _ = writeln!(
+                self.out,
+                " {:width$}  {status}  {:>8}",
+                result.name,
+                format!("{:.2?}", result.duration)
+            );
+        }
+    }
+}
+
+impl<W: Write> Reporter for TextReporter<W> {
+    fn run_started(&mut self, tests: usize) {
+        if self.verbosity > Verbosity::Quiet {
+            let _ = writeln!(
+                self.out,
+                "\nrunning {tests} nes test{}",
+                if tests == 1 { "" } else { "s" }
+            );
+        }
+    }
+
+    fn test_started(&mut self, _name: &str) {
+        #[cfg(feature = "indicatif")]
+        if self.interactive && self.verbosity > Verbosity::Quiet {
+            self.bar = Some(TestProgressBar::new(_name));
+        }
+    }
+
+    fn progress(&mut self, name: &str, progress: &Progress) {
+        #[cfg(feature = "indicatif")]
+        if let Some(bar) = &mut self.bar {
+            bar.update(progress);
+        }
+
+        if self.verbosity == Verbosity::Verbose {
+            let line = match progress {
+                Progress::Status(status) => format!("    {name}: {status}"),
+                Progress::SubTest {
+                    name: sub_test,
+                    passed: true,
+                    ..
+                } => format!("    {name}: {sub_test} passed"),
+                Progress::SubTest {
+                    name: sub_test,
+                    detail,
+                    ..
+                } => match detail {
+                    Some(detail) => {
+                        format!(
+                            "    {name}: {sub_test} failed: {}",
+                            detail.replace('\n', "; ")
+                        )
+                    }
+                    None => format!("    {name}: {sub_test} failed"),
+                },
+                _ => return,
+            };
+
+            #[cfg(feature = "indicatif")]
+            if let Some(bar) = &self.bar {
+                bar.println(&line);
+                return;
+            }
+
+            let _ = writeln!(self.out, "{line}");
+        }
+    }
+
+    fn test_finished(&mut self, result: &TestResult) {
+        #[cfg(feature = "indicatif")]
+        if let Some(bar) = self.bar.take() {
+            bar.finish();
+        }
+
+        if self.verbosity > Verbosity::Quiet || !result.passed() {
+            let status = self.status(result);
+            let _ = writeln!(
+                self.out,
+                "test {} ... {status} ({:.2?})",
+                result.name, result.duration
+            );
+        }
+    }
+
+    fn run_finished(&mut self, report: &TestReport) {
+        if self.verbosity > Verbosity::Quiet && !report.results.is_empty() {
+            self.summary_table(report);
+        }
+
+        let failures: Vec<_> = report.failures().collect();
+        if !failures.is_empty() {
+            let _ = writeln!(self.out, "\nfailures:");
+            for result in &failures {
+                if let Err(e) = &result.outcome {
+                    // the message already says which test failed
+                    let _ = writeln!(self.out, "    {e}");
+                }
+                if !result.watchpoint_hits.is_empty() {
+                    let _ = writeln!(self.out, "      last watchpoint hits:");
+                    for hit in &result.watchpoint_hits {
+                        let _ = writeln!(self.out, "        {hit}");
+                    }
+                }
+                if !result.failure_trace.is_empty() {
+                    let _ = writeln!(self.out, "      last instructions before the failure:");
+                    let last = result.failure_trace.len().saturating_sub(TRACE_LINES);
+                    for line in &result.failure_trace[last..] {
+                        let _ = writeln!(self.out, "        {line}");
+                    }
+                }
+                if !result.logs.is_empty() {
+                    let _ = writeln!(self.out, "      last lines logged during the test:");
+                    let last = result.logs.len().saturating_sub(TRACE_LINES);
+                    for line in &result.logs[last..] {
+                        let _ = writeln!(self.out, "        {line}");
+                    }
+                }
+                if let Some(accesses) = &result.memory_accesses {
+                    let _ = writeln!(self.out, "      memory accesses: {accesses}");
+                    for hint in accesses.hints() {
+                        let _ = writeln!(self.out, "      hint: {hint}");
+                    }
+                }
+            }
+        }
+
+        let flaky: Vec<_> = report.results.iter().filter(|r| r.flaky()).collect();
+        if !flaky.is_empty() {
+            let _ = writeln!(self.out, "\nflaky tests, which failed before they passed:");
+            for result in flaky {
+                for e in &result.failed_attempts {
+                    let _ = writeln!(self.out, "    {e}");
+                }
+            }
+        }
+
+        let skipped = report
+            .results
+            .iter()
+            .filter(|r| r.skipped.is_some())
+            .count();
+        let passed = report.results.len() - failures.len() - skipped;
+        let status = if report.passed() {
+            self.paint("ok", GREEN)
+        } else {
+            self.paint("FAILED", RED)
+        };
+        let _ = writeln!(
+            self.out,
+            "\nnes test result: {status}. {passed} passed; {} failed;{} finished in {:.2?}\n",
+            failures.len(),
+            if skipped > 0 {
+                format!(" {skipped} skipped;")
+            } else {
+                String::new()
+            },
+            report.duration()
+        );
+        if let Some(threshold) = report.pass_threshold {
+            let _ = writeln!(
+                self.out,
+                "{:.1}% of the sub-tests passed, {threshold}% have to\n",
+                report.sub_test_pass_rate()
+            );
+        }
+        if let (Some(seed), false) = (report.ram_seed, failures.is_empty()) {
+            let _ = writeln!(
+                self.out,
+                "the ram was filled from seed {seed}, NESTEST_N_RAM=random:{seed} fills it the same\n"
+            );
+        }
+        let _ = self.out.flush();
+    }
+}
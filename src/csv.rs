@@ -0,0 +1,252 @@
+//! The results of a test run as CSV, a row per test and sub-test, to import into the spreadsheet of
+//! a gradebook, see [`CsvReporter`]
+use crate::grading::GradingProfile;
+use crate::log_target;
+use crate::report::{TestReport, TestResult};
+use crate::reporter::Reporter;
+use std::fmt::Write as _;
+use std::io::Write;
+
+/// The first row, with the names of the columns
+const HEADER: [&str; 7] = [
+    "id",
+    "test",
+    "sub_test",
+    "status",
+    "duration_ms",
+    "weight",
+    "message",
+];
+
+/// A [`Reporter`] writing the results as CSV once all tests ran, a row for every test and then one
+/// for every sub-test of it, with its id, its status, how long it took, its weight in the
+/// [`GradingProfile`] and why it failed. [`TestReport::csv`] gives the same rows.
+///
+/// ```text
+/// id,test,sub_test,status,duration_ms,weight,message
+/// nestest,nestest,,passed,31,20,
+/// official_instrs,all instructions (official only),,failed,4420,60,"cpu didn't pass ..."
+/// official_instrs/01-basics,all instructions (official only),01-basics,passed,,0,
+/// official_instrs/02-implied,all instructions (official only),02-implied,failed,,0,07 SLO z
+/// ```
+///
+/// The status is `passed`, `failed`, `skipped`, `flaky`, `cached` or `expected failure`, and the
+/// duration of a sub-test is empty, since a rom runs all its sub-tests at once. The weight is empty
+/// without a profile, and otherwise the sum of the weights of the criteria for only that test or
+/// sub-test: a criterion for several tests at once isn't in any row, while one for a sub-test that
+/// several tests have is in each of their rows, even though the [`Grade`](crate::Grade) counts it once.
+/// ```no_run
+/// # use tudelft_nes_test::{run_tests_with_reporter, CsvReporter, GradingProfile, TestConfig, TestSelector, TestableCpu};
+/// # fn test<MyCpu: TestableCpu>() -> std::io::Result<()> {
+/// let profile = GradingProfile::new()
+///     .test(TestSelector::OFFICIAL_INSTRS, 60.0)
+///     .test(TestSelector::NESTEST, 20.0);
+/// let file = std::fs::File::create("nes-tests.csv")?;
+/// let mut reporter = CsvReporter::new(file).profile(profile);
+/// run_tests_with_reporter::<MyCpu>(&TestConfig::default(), &mut reporter);
+/// # Ok(())
+/// # }
+/// ```
+pub struct CsvReporter<W: Write> {
+    out: W,
+    profile: Option<GradingProfile>,
+}
+
+impl<W: Write> CsvReporter<W> {
+    /// A reporter writing the rows to `out`, without weights
+    pub fn new(out: W) -> Self {
+        Self { out, profile: None }
+    }
+
+    /// Fills the weight column with the weights of `profile`
+    pub fn profile(mut self, profile: GradingProfile) -> Self {
+        self.profile = Some(profile);
+        self
+    }
+
+    /// Returns the writer the rows were written to
+    pub fn into_inner(self) -> W {
+        self.out
+    }
+}
+
+impl<W: Write> Reporter for CsvReporter<W> {
+    fn run_finished(&mut self, report: &TestReport) {
+        let csv = csv(report, self.profile.as_ref());
+        if let Err(e) = self
+            .out
+            .write_all(csv.as_bytes())
+            .and_then(|()| self.out.flush())
+        {
+            log_target::warn!("couldn't write the results of the tests as csv: {e}");
+        }
+    }
+}
+
+impl TestReport {
+    /// The results as CSV, with the weights of `profile` if there is one, see [`CsvReporter`]
+    pub fn csv(&self, profile: Option<&GradingProfile>) -> String {
+        csv(self, profile)
+    }
+}
+
+/// The status column of a test
+fn status(result: &TestResult) -> &'static str {
+    if !result.passed() {
+        "failed"
+    } else if result.skipped.is_some() {
+        "skipped"
+    } else if result.flaky() {
+        "flaky"
+    } else if result.cached {
+        "cached"
+    } else if !result.expected_failures.is_empty() {
+        "expected failure"
+    } else {
+        "passed"
+    }
+}
+
+fn csv(report: &TestReport, profile: Option<&GradingProfile>) -> String {
+    let weight = |weight: &dyn Fn(&GradingProfile) -> f64| {
+        profile.map(|p| weight(p).to_string()).unwrap_or_default()
+    };
+
+    let mut text = String::new();
+    row(&mut text, &HEADER);
+    for r in &report.results {
+        let message = match (&r.outcome, &r.skipped) {
+            (Err(e), _) => e.as_str(),
+            (Ok(()), Some(reason)) => reason.as_str(),
+            (Ok(()), None) => "",
+        };
+        row(
+            &mut text,
+            &[
+                &r.id,
+                &r.name,
+                "",
+                status(r),
+                &r.duration.as_millis().to_string(),
+                &weight(&|p| p.test_weight(r.test)),
+                message,
+            ],
+        );
+
+        for s in &r.sub_tests {
+            let status = if s.passed {
+                "passed"
+            } else if r.expected_failures.contains(&s.name) {
+                "expected failure"
+            } else {
+                "failed"
+            };
+            row(
+                &mut text,
+                &[
+                    &r.sub_test_id(s),
+                    &r.name,
+                    &s.name,
+                    status,
+                    "",
+                    &weight(&|p| p.sub_test_weight(&s.name)),
+                    s.detail.as_deref().unwrap_or_default(),
+                ],
+            );
+        }
+    }
+    text
+}
+
+/// Adds a row of `fields` to `text`, quoting the fields that need it as RFC 4180 says
+pub(crate) fn row(text: &mut String, fields: &[&str]) {
+    for (i, field) in fields.iter().enumerate() {
+        if i > 0 {
+            text.push(',');
+        }
+        if field.contains([',', '"', '\n', '\r']) {
+            let _ = write!(text, "\"{}\"", field.replace('"', "\"\""));
+        } else {
+            text.push_str(field);
+        }
+    }
+    // spreadsheets expect the line endings of RFC 4180
+    text.push_str("\r\n");
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::report::SubTestResult;
+    use crate::TestSelector;
+
+    #[test]
+    fn fields_are_quoted_as_rfc_4180_says() {
+        let mut text = String::new();
+        row(
+            &mut text,
+            &["plain", "a,b", "say \"hi\"", "two\nlines", "cr\r", ""],
+        );
+        assert_eq!(
+            text,
+            "plain,\"a,b\",\"say \"\"hi\"\"\",\"two\nlines\",\"cr\r\",\r\n"
+        );
+    }
+
+    #[test]
+    fn a_failure_is_the_status_before_anything_else() {
+        let mut result = TestResult::of(TestSelector::NESTEST, "nestest", Err("wrong".to_string()));
+        result.cached = true;
+        result.failed_attempts.push("timed out".to_string());
+        result.expected_failures.push("03-immediate".to_string());
+        assert_eq!(status(&result), "failed");
+
+        result.outcome = Ok(());
+        result.skipped = Some("it needs a cpu that steals cycles for DMA".to_string());
+        assert_eq!(status(&result), "skipped");
+        result.skipped = None;
+        assert_eq!(status(&result), "flaky");
+        result.failed_attempts.clear();
+        assert_eq!(status(&result), "cached");
+        result.cached = false;
+        assert_eq!(status(&result), "expected failure");
+        result.expected_failures.clear();
+        assert_eq!(status(&result), "passed");
+    }
+
+    #[test]
+    fn rows_of_sub_tests_follow_their_test() {
+        let mut result = TestResult::of(TestSelector::ALL_INSTRS, "all_instrs", Ok(()));
+        result.expected_failures.push("03-immediate".to_string());
+        result.sub_tests = vec![
+            SubTestResult {
+                name: "01-basics".to_string(),
+                passed: true,
+                detail: None,
+            },
+            SubTestResult {
+                name: "03-immediate".to_string(),
+                passed: false,
+                detail: Some("6B ARR #n".to_string()),
+            },
+        ];
+        let report = TestReport {
+            results: vec![result],
+            ..TestReport::default()
+        };
+        let profile = GradingProfile::new().sub_test("01-basics", 5.0);
+
+        let csv = report.csv(Some(&profile));
+        let rows: Vec<_> = csv.split("\r\n").collect();
+        assert_eq!(
+            rows,
+            [
+                "id,test,sub_test,status,duration_ms,weight,message",
+                "all_instrs,all_instrs,,expected failure,0,0,",
+                "all_instrs/01-basics,all_instrs,01-basics,passed,,5,",
+                "all_instrs/03-immediate,all_instrs,03-immediate,expected failure,,0,6B ARR #n",
+                "",
+            ]
+        );
+    }
+}
@@ -0,0 +1,132 @@
+//! Your own test roms, which run next to the ones of the harness, see [`TestConfig::custom_roms`](crate::TestConfig::custom_roms)
+use crate::{StatusAddresses, TestableCpu};
+use std::fmt::Write;
+use std::path::PathBuf;
+
+/// A test rom of your own, like a small program that leaves its results in ram. It runs when
+/// [`TestSelector::CUSTOM`](crate::TestSelector::CUSTOM) is selected:
+/// ```
+/// use tudelft_nes_test::{CustomRom, TestConfig, TestSelector};
+///
+/// let config = TestConfig {
+///     selector: TestSelector::DEFAULT.custom(),
+///     custom_roms: vec![CustomRom::new("adc", "roms/adc.nes")
+///         .cycles(100_000)
+///         .expect_memory(0x0010, &[0x42, 0x00])
+///         .expect_memory(0x0200, &[0xFF])],
+///     ..TestConfig::default()
+/// };
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct CustomRom {
+    /// The name of the test, as used in messages
+    pub name: String,
+    /// Where the rom is
+    pub path: PathBuf,
+    /// How many cycles the rom runs, 1 million by default. A rom that reports its status runs until it's done,
+    /// at most this many cycles, and the others run all of them, unless the cpu gets stuck in a loop first.
+    pub cycles: u64,
+    /// What the rom leaves behind when the cpu passes
+    pub expectation: Expectation,
+    /// Where the rom reports its status, when it does that like the roms of blargg do but somewhere else
+    /// than at $6000
+    pub status_addresses: StatusAddresses,
+}
+
+/// How a [`CustomRom`] says whether the cpu passed
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Expectation {
+    /// The rom reports its status like the roms of blargg do, at [`CustomRom::status_addresses`]
+    Status,
+    /// The memory holds these bytes once the rom ran, each range is reported as a sub-test
+    Memory(Vec<ExpectedMemory>),
+}
+
+/// Bytes a [`CustomRom`] leaves in memory, starting at `address`
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ExpectedMemory {
+    /// The address of the first byte
+    pub address: u16,
+    /// The bytes that are expected from `address` on
+    pub bytes: Vec<u8>,
+}
+
+impl CustomRom {
+    /// The rom at `path`, which reports its status like the roms of blargg do, unless you expect memory
+    /// with [`expect_memory`](Self::expect_memory)
+    pub fn new(name: impl Into<String>, path: impl Into<PathBuf>) -> Self {
+        Self {
+            name: name.into(),
+            path: path.into(),
+            cycles: 1_000_000,
+            expectation: Expectation::Status,
+            status_addresses: StatusAddresses::default(),
+        }
+    }
+
+    /// Runs the rom for `cycles` cycles
+    pub fn cycles(self, cycles: u64) -> Self {
+        Self { cycles, ..self }
+    }
+
+    /// Reads the status the rom reports like the roms of blargg do at `status` instead of at $6000
+    pub fn status_at(self, status: u16) -> Self {
+        Self {
+            status_addresses: StatusAddresses::at(status),
+            ..self
+        }
+    }
+
+    /// Also expects `bytes` in memory from `address` on, once the rom ran
+    pub fn expect_memory(mut self, address: u16, bytes: &[u8]) -> Self {
+        let expected = ExpectedMemory {
+            address,
+            bytes: bytes.to_vec(),
+        };
+        match &mut self.expectation {
+            Expectation::Memory(all) => all.push(expected),
+            Expectation::Status => self.expectation = Expectation::Memory(vec![expected]),
+        }
+        self
+    }
+}
+
+impl ExpectedMemory {
+    /// The range of addresses, like `$0010-$0011`
+    pub(crate) fn range(&self) -> String {
+        match self.bytes.len() {
+            0 | 1 => format!("${:04X}", self.address),
+            n => format!(
+                "${:04X}-${:04X}",
+                self.address,
+                self.address.wrapping_add(n as u16 - 1)
+            ),
+        }
+    }
+
+    /// Compares the memory of `cpu` with the expected bytes, and says what's different when they aren't there
+    pub(crate) fn check(&self, cpu: &impl TestableCpu) -> Result<(), String> {
+        let actual: Vec<u8> = (0..self.bytes.len())
+            .map(|i| cpu.memory_peek(self.address.wrapping_add(i as u16)))
+            .collect();
+        if actual == self.bytes {
+            return Ok(());
+        }
+
+        let hex = |bytes: &[u8]| {
+            bytes.iter().fold(String::new(), |mut text, b| {
+                let _ = write!(text, " {b:02X}");
+                text
+            })
+        };
+        Err(format!(
+            "expected{} at {}, found{}",
+            hex(&self.bytes),
+            self.range(),
+            hex(&actual)
+        ))
+    }
+}
@@ -0,0 +1,189 @@
+//! Downloading the roms of nes-test-roms that aren't bundled with this crate the first time a test
+//! needs them, see [`TestConfig::download_dir`](crate::TestConfig::download_dir)
+use crate::log_target;
+use crate::sha256;
+use std::path::{Path, PathBuf};
+
+/// Where the roms are downloaded from, unless [`TestConfig::download_mirror`](crate::TestConfig::download_mirror) says otherwise
+pub(crate) const DEFAULT_MIRROR: &str =
+    "https://raw.githubusercontent.com/christopherpow/nes-test-roms/master";
+
+/// The largest rom that is downloaded, the roms of nes-test-roms are far smaller
+#[cfg(feature = "download")]
+const MAX_SIZE: u64 = 4 * 1024 * 1024;
+
+/// The rom `file_name` in the directory `set_dir` of nes-test-roms, downloaded from `mirror` into
+/// `dir` unless it's there already. When the manifest knows the SHA-256 of the rom, a download that
+/// doesn't have it isn't kept, and a rom that was downloaded before is checked again. When it
+/// doesn't, the SHA-256 of the first download from nes-test-roms is pinned in a file next to the
+/// rom, like `01-implied.nes.sha256`, and the rom is checked against that from then on. A mirror
+/// only serves roms with a SHA-256 to check them against.
+pub(crate) fn rom(
+    dir: &Path,
+    mirror: Option<&str>,
+    set_dir: &str,
+    file_name: &str,
+    sha256: Option<&str>,
+) -> Result<PathBuf, String> {
+    let path = dir.join(set_dir).join(file_name);
+    let pin = pin_path(&path);
+    let pinned = std::fs::read_to_string(&pin).ok();
+    let expected = sha256.or_else(|| pinned.as_deref().map(str::trim));
+    let by = match sha256 {
+        Some(_) => "the manifest".to_string(),
+        None => format!("the pin in {}", pin.display()),
+    };
+    if let Ok(rom) = std::fs::read(&path) {
+        verify(&path.display().to_string(), &rom, expected, &by)?;
+        if expected.is_none() {
+            write_pin(&pin, &rom);
+        }
+        return Ok(path);
+    }
+
+    let mirror = mirror.unwrap_or(DEFAULT_MIRROR);
+    if expected.is_none() && mirror != DEFAULT_MIRROR {
+        return Err(format!(
+            "the manifest doesn't have a sha256 of {file_name}, so it isn't downloaded from the \
+             mirror {mirror}: add its sha256 to the manifest, or download it from nes-test-roms once"
+        ));
+    }
+    let url = format!(
+        "{}/{}/{}",
+        mirror.trim_end_matches('/'),
+        set_dir.replace(' ', "%20"),
+        file_name.replace(' ', "%20")
+    );
+    log_target::info!("downloading {url}");
+    let rom = fetch(&url)?;
+    match expected {
+        Some(_) => verify(&url, &rom, expected, &by)?,
+        None => log_target::warn!(
+            "the manifest doesn't have a sha256 of {file_name}, so {url} is pinned to what it \
+             downloads now, in {}",
+            pin.display()
+        ),
+    }
+
+    // a download that was cut off halfway doesn't end up as the rom
+    let partial = path.with_extension("part");
+    let parent = path.parent().unwrap_or(dir);
+    std::fs::create_dir_all(parent)
+        .and_then(|()| std::fs::write(&partial, &rom))
+        .and_then(|()| std::fs::rename(&partial, &path))
+        .map_err(|e| format!("couldn't save {url} to {}: {e}", path.display()))?;
+    if expected.is_none() {
+        write_pin(&pin, &rom);
+    }
+    Ok(path)
+}
+
+/// Where the SHA-256 of the rom at `path` is pinned when the manifest doesn't have it
+fn pin_path(path: &Path) -> PathBuf {
+    let mut pin = path.as_os_str().to_owned();
+    pin.push(".sha256");
+    PathBuf::from(pin)
+}
+
+/// Pins the SHA-256 of `rom` in `pin`, a rom that can't be pinned is only checked less
+fn write_pin(pin: &Path, rom: &[u8]) {
+    if let Err(e) = std::fs::write(pin, format!("{}\n", sha256::hex_digest(rom))) {
+        log_target::warn!(
+            "couldn't pin the sha256 of the rom in {}: {e}",
+            pin.display()
+        );
+    }
+}
+
+/// Checks that `rom`, from `source`, has the SHA-256 that `by` expects, if there is one
+fn verify(source: &str, rom: &[u8], sha256: Option<&str>, by: &str) -> Result<(), String> {
+    let Some(expected) = sha256 else {
+        return Ok(());
+    };
+    let actual = sha256::hex_digest(rom);
+    if actual.eq_ignore_ascii_case(expected) {
+        Ok(())
+    } else {
+        Err(format!(
+            "{source} has sha256 {actual}, but {by} expects {expected}"
+        ))
+    }
+}
+
+#[cfg(feature = "download")]
+fn fetch(url: &str) -> Result<Vec<u8>, String> {
+    use std::io::Read;
+
+    let response = ureq::get(url)
+        .call()
+        .map_err(|e| format!("couldn't download {url}: {e}"))?;
+    let mut rom = Vec::new();
+    // one byte more than a rom can be, to tell a rom of that size from a larger download
+    response
+        .into_reader()
+        .take(MAX_SIZE + 1)
+        .read_to_end(&mut rom)
+        .map_err(|e| format!("couldn't download {url}: {e}"))?;
+    if rom.len() as u64 > MAX_SIZE {
+        return Err(format!(
+            "{url} is larger than the {}MB a rom can be",
+            MAX_SIZE / 1024 / 1024
+        ));
+    }
+    Ok(rom)
+}
+
+#[cfg(not(feature = "download"))]
+fn fetch(url: &str) -> Result<Vec<u8>, String> {
+    Err(format!(
+        "downloading {url} needs the download feature of tudelft-nes-test"
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::temp_dir::TempDir;
+
+    /// A download directory with a directory for the roms of `set`
+    fn dir(name: &str) -> TempDir {
+        let dir = TempDir::new(&format!("download-{name}"));
+        std::fs::create_dir_all(dir.join("set")).unwrap();
+        dir
+    }
+
+    #[test]
+    fn rejects_a_rom_without_the_sha256_of_the_manifest() {
+        let dir = dir("manifest");
+        std::fs::write(dir.join("set/a.nes"), b"swapped").unwrap();
+        let expected = sha256::hex_digest(b"rom");
+
+        assert!(rom(&dir, None, "set", "a.nes", Some(&expected)).is_err());
+        std::fs::write(dir.join("set/a.nes"), b"rom").unwrap();
+        assert!(rom(&dir, None, "set", "a.nes", Some(&expected)).is_ok());
+    }
+
+    #[test]
+    fn pins_a_rom_the_manifest_has_no_sha256_of() {
+        let dir = dir("pin");
+        std::fs::write(dir.join("set/a.nes"), b"rom").unwrap();
+
+        assert!(rom(&dir, None, "set", "a.nes", None).is_ok());
+        assert_eq!(
+            std::fs::read_to_string(dir.join("set/a.nes.sha256")).unwrap(),
+            format!("{}\n", sha256::hex_digest(b"rom"))
+        );
+        std::fs::write(dir.join("set/a.nes"), b"corrupted").unwrap();
+        assert!(rom(&dir, None, "set", "a.nes", None).is_err());
+    }
+
+    #[test]
+    fn mirrors_only_serve_roms_with_a_sha256() {
+        let dir = dir("mirror");
+        let error = rom(&dir, Some("https://example.com/roms"), "set", "a.nes", None).unwrap_err();
+        assert!(
+            error.contains("isn't downloaded from the mirror"),
+            "{error}"
+        );
+    }
+}
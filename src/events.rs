@@ -0,0 +1,259 @@
+//! A stream of JSON events, one per line, for frontends that show the progress of a run while it
+//! happens, see [`JsonReporter`]
+use crate::accesses::RegionAccesses;
+use crate::log_target;
+use crate::report::{Progress, TestReport, TestResult};
+use crate::reporter::Reporter;
+use std::fmt::Write as _;
+use std::io::Write;
+
+/// A [`Reporter`] writing every event of a run as a line of JSON to `out`, and flushing it right
+/// away, so a grading frontend can follow the run live:
+///
+/// ```text
+/// {"event":"run_started","tests":2}
+/// {"event":"test_started","test":"nestest"}
+/// {"event":"cycles","test":"nestest","done":200000,"budget":1000000}
+/// {"event":"test_finished","test":"nestest","id":"nestest","passed":true,"message":null,"skipped":null,"cached":false,"flaky":false,"duration_ms":31,"sub_tests":[],"memory_accesses":{"ram":{"reads":52660,"writes":2212},"ppu_registers":{"reads":0,"writes":0},"apu_registers":{"reads":0,"writes":5},"cartridge":{"reads":16945,"writes":0}}}
+/// {"event":"test_started","test":"all_instructions (official only)"}
+/// {"event":"status","test":"all_instructions (official only)","status":"01-basics\n"}
+/// {"event":"sub_test","test":"all_instructions (official only)","name":"01-basics","passed":true,"detail":null}
+/// ...
+/// {"event":"run_finished","passed":true,"tests":2,"failed":0,"skipped":0,"ram_seed":null}
+/// ```
+///
+/// Use it with [`run_tests_with_reporter`](crate::run_tests_with_reporter), for example to write
+/// to a pipe the frontend reads from:
+/// ```no_run
+/// # use tudelft_nes_test::{run_tests_with_reporter, JsonReporter, TestConfig, TestableCpu};
+/// # fn test<MyCpu: TestableCpu>() {
+/// let mut reporter = JsonReporter::new(std::io::stdout());
+/// run_tests_with_reporter::<MyCpu>(&TestConfig::default(), &mut reporter);
+/// # }
+/// ```
+///
+/// The final state of the cpu after a test isn't written, since it holds the whole ram. When
+/// writing to `out` fails, the event is dropped and a warning is logged.
+pub struct JsonReporter<W: Write> {
+    out: W,
+}
+
+impl<W: Write> JsonReporter<W> {
+    /// A reporter writing the events to `out`, for example a socket or a file
+    pub fn new(out: W) -> Self {
+        Self { out }
+    }
+
+    /// Returns the writer the events were written to
+    pub fn into_inner(self) -> W {
+        self.out
+    }
+
+    fn emit(&mut self, event: &str, fields: &[(&str, Value<'_>)]) {
+        let mut line = format!("{{\"event\":{}", Value::Str(event));
+        for (key, value) in fields {
+            let _ = write!(line, ",{}:{value}", Value::Str(key));
+        }
+        line.push_str("}\n");
+
+        if let Err(e) = self
+            .out
+            .write_all(line.as_bytes())
+            .and_then(|()| self.out.flush())
+        {
+            log_target::warn!("couldn't write the {event} event: {e}");
+        }
+    }
+}
+
+impl<W: Write> Reporter for JsonReporter<W> {
+    fn run_started(&mut self, tests: usize) {
+        self.emit("run_started", &[("tests", Value::Int(tests as u64))]);
+    }
+
+    fn test_started(&mut self, name: &str) {
+        self.emit("test_started", &[("test", Value::Str(name))]);
+    }
+
+    fn progress(&mut self, name: &str, progress: &Progress) {
+        let test = ("test", Value::Str(name));
+        match progress {
+            Progress::Status(status) => {
+                self.emit("status", &[test, ("status", Value::Str(status))]);
+            }
+            Progress::Cycles { done, budget } => self.emit(
+                "cycles",
+                &[
+                    test,
+                    ("done", Value::Int(*done)),
+                    ("budget", Value::Int(*budget)),
+                ],
+            ),
+            Progress::SubTest {
+                name,
+                passed,
+                detail,
+            } => self.emit(
+                "sub_test",
+                &[
+                    test,
+                    ("name", Value::Str(name)),
+                    ("passed", Value::Bool(*passed)),
+                    ("detail", Value::option(detail.as_deref())),
+                ],
+            ),
+            Progress::Skipped(reason) => {
+                self.emit("skipped", &[test, ("reason", Value::Str(reason))]);
+            }
+            Progress::Finished(_) => {}
+        }
+    }
+
+    fn test_finished(&mut self, result: &TestResult) {
+        let sub_tests = result
+            .sub_tests
+            .iter()
+            .fold(String::from("["), |mut list, s| {
+                if list.len() > 1 {
+                    list.push(',');
+                }
+                let _ = write!(
+                    list,
+                    "{{\"name\":{},\"passed\":{}}}",
+                    Value::Str(&s.name),
+                    s.passed
+                );
+                list
+            })
+            + "]";
+        let memory_accesses = result.memory_accesses.map_or("null".to_owned(), |a| {
+            let region =
+                |r: RegionAccesses| format!("{{\"reads\":{},\"writes\":{}}}", r.reads, r.writes);
+            format!(
+                "{{\"ram\":{},\"ppu_registers\":{},\"apu_registers\":{},\"cartridge\":{}}}",
+                region(a.ram),
+                region(a.ppu_registers),
+                region(a.apu_registers),
+                region(a.cartridge)
+            )
+        });
+
+        self.emit(
+            "test_finished",
+            &[
+                ("test", Value::Str(&result.name)),
+                ("id", Value::Str(&result.id)),
+                ("passed", Value::Bool(result.passed())),
+                ("message", Value::option(result.outcome.as_ref().err())),
+                ("skipped", Value::option(result.skipped.as_deref())),
+                ("cached", Value::Bool(result.cached)),
+                ("flaky", Value::Bool(result.flaky())),
+                (
+                    "duration_ms",
+                    Value::Int(result.duration.as_millis() as u64),
+                ),
+                ("sub_tests", Value::Raw(&sub_tests)),
+                ("memory_accesses", Value::Raw(&memory_accesses)),
+            ],
+        );
+    }
+
+    fn run_finished(&mut self, report: &TestReport) {
+        let count = |f: fn(&TestResult) -> bool| report.results.iter().filter(|r| f(r)).count();
+        self.emit(
+            "run_finished",
+            &[
+                ("passed", Value::Bool(report.passed())),
+                ("tests", Value::Int(report.results.len() as u64)),
+                ("failed", Value::Int(count(|r| !r.passed()) as u64)),
+                ("skipped", Value::Int(count(|r| r.skipped.is_some()) as u64)),
+                ("ram_seed", report.ram_seed.map_or(Value::Null, Value::Int)),
+            ],
+        );
+    }
+}
+
+/// A JSON value in an event
+pub(crate) enum Value<'a> {
+    Str(&'a str),
+    Int(u64),
+    Bool(bool),
+    Null,
+    /// JSON that was written already
+    Raw(&'a str),
+}
+
+impl<'a> Value<'a> {
+    fn option(text: Option<&'a (impl AsRef<str> + ?Sized)>) -> Self {
+        text.map_or(Value::Null, |text| Value::Str(text.as_ref()))
+    }
+}
+
+impl std::fmt::Display for Value<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Value::Str(text) => {
+                f.write_char('"')?;
+                for c in text.chars() {
+                    match c {
+                        '"' => f.write_str("\\\"")?,
+                        '\\' => f.write_str("\\\\")?,
+                        '\n' => f.write_str("\\n")?,
+                        '\r' => f.write_str("\\r")?,
+                        '\t' => f.write_str("\\t")?,
+                        c if c < ' ' => write!(f, "\\u{:04x}", c as u32)?,
+                        c => f.write_char(c)?,
+                    }
+                }
+                f.write_char('"')
+            }
+            Value::Int(n) => write!(f, "{n}"),
+            Value::Bool(b) => write!(f, "{b}"),
+            Value::Null => f.write_str("null"),
+            Value::Raw(json) => f.write_str(json),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::report::SubTestResult;
+    use crate::TestSelector;
+
+    #[test]
+    fn strings_are_escaped() {
+        let text = Value::Str("say \"hi\"\\\n\r\t\u{1}é").to_string();
+        assert_eq!(text, r#""say \"hi\"\\\n\r\t\u0001é""#);
+        assert_eq!(Value::option(None::<&str>).to_string(), "null");
+    }
+
+    #[test]
+    fn every_event_is_a_line() {
+        let mut reporter = JsonReporter::new(Vec::new());
+        let mut result = TestResult::of(
+            TestSelector::ALL_INSTRS,
+            "all_instrs",
+            Err("02-implied\nfailed".to_string()),
+        );
+        result.sub_tests.push(SubTestResult {
+            name: "02-implied".to_string(),
+            passed: false,
+            detail: None,
+        });
+        reporter.progress("all_instrs", &Progress::Status("02-implied\n".to_string()));
+        reporter.test_finished(&result);
+
+        let out = String::from_utf8(reporter.into_inner()).unwrap();
+        let lines: Vec<_> = out.lines().collect();
+        assert_eq!(
+            lines[0],
+            r#"{"event":"status","test":"all_instrs","status":"02-implied\n"}"#
+        );
+        assert_eq!(
+            lines[1],
+            r#"{"event":"test_finished","test":"all_instrs","id":"all_instrs","passed":false,"message":"02-implied\nfailed","skipped":null,"cached":false,"flaky":false,"duration_ms":0,"sub_tests":[{"name":"02-implied","passed":false}],"memory_accesses":null}"#
+        );
+        assert_eq!(lines.len(), 2);
+    }
+}
@@ -0,0 +1,93 @@
+//! In which chunks of cycles the cpu of a test runs, so an emulator that schedules its cpu in steps
+//! of its own can run the tests of this crate in its own loop, see [`Executor`]
+use crate::Region;
+use std::error::Error;
+use std::fmt;
+
+/// The NES of a test, as an [`Executor`] runs it: your cpu on the ppu of `tudelft_nes_ppu`, with
+/// the harness watching it. The cycles run on that ppu in any case, an executor only decides how many
+/// at a time.
+pub trait Machine {
+    /// Runs the cpu for `cycles` cycles. Returns an error once the test is done, or when the cpu
+    /// failed, which the executor has to return from [`Executor::run`] right away.
+    fn run_cycles(&mut self, cycles: usize) -> Result<(), Box<dyn Error>>;
+
+    /// Reads `address` of the memory of the cpu without side effects, like
+    /// [`TestableCpu::memory_peek`](crate::TestableCpu::memory_peek)
+    fn peek(&self, address: u16) -> u8;
+
+    /// The cycles the cpu ran so far in the test
+    fn cycles(&self) -> u64;
+}
+
+/// Runs the cpu of a test for a number of cycles, in chunks. By default that's [`HeadlessExecutor`],
+/// which runs them all at once, but when your emulator schedules the cpu itself, like a frame at a
+/// time, you can run the tests in your own loop with [`TestConfig::executor`](crate::TestConfig::executor).
+/// The roms, the ways they report their results, and the reporting are all the same.
+///
+/// Only the scheduling is yours: every chunk runs your cpu on the ppu of `tudelft_nes_ppu`, since
+/// that's what [`Cpu::tick`](crate::Cpu::tick) is given. An emulator whose own ppu or scheduler
+/// ticks the cpu can't plug that in; its cpu runs the tests on `tudelft_nes_ppu` like any other.
+///
+/// The [`Machine`] the executor gets has to run all `cycles`, in as many steps as it likes. When one
+/// returns an error, it has to stop and return that error, since that's how the harness stops the
+/// cpu once a test is done:
+/// ```
+/// use std::error::Error;
+/// use std::sync::Arc;
+/// use tudelft_nes_test::{Executor, Machine, TestConfig};
+///
+/// /// The cycles of the cpu in a frame of an NTSC NES
+/// const FRAME: usize = 29_781;
+///
+/// struct Frames;
+///
+/// impl Executor for Frames {
+///     fn run(&self, machine: &mut dyn Machine, cycles: usize) -> Result<(), Box<dyn Error>> {
+///         let mut left = cycles;
+///         while left > 0 {
+///             let frame = left.min(FRAME);
+///             machine.run_cycles(frame)?;
+///             left -= frame;
+///             // between the frames the emulator does its own things, like looking at the memory
+///             let status = machine.peek(0x6000);
+///             log::debug!("{} cycles: ${status:02X} at $6000", machine.cycles());
+///         }
+///         Ok(())
+///     }
+/// }
+///
+/// let config = TestConfig {
+///     executor: Some(Arc::new(Frames)),
+///     ..TestConfig::default()
+/// };
+/// ```
+/// The same executor runs every test, each on its own thread, so it's `Send` and `Sync`.
+pub trait Executor: Send + Sync {
+    /// Runs `machine` for `cycles` cycles
+    fn run(&self, machine: &mut dyn Machine, cycles: usize) -> Result<(), Box<dyn Error>>;
+
+    /// Whether the executor runs the cpu like the ppu of an NES of `region` does, with its dots per
+    /// cpu cycle and scanlines per frame, see [`TestConfig::region`](crate::TestConfig::region). The
+    /// sets of roms that time the ppu fail when it doesn't support the region they run in, the tests
+    /// of only the cpu run either way. By default only [`Region::Ntsc`] is supported.
+    fn supports_region(&self, region: Region) -> bool {
+        region == Region::Ntsc
+    }
+}
+
+impl fmt::Debug for dyn Executor {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("Executor")
+    }
+}
+
+/// The default [`Executor`], which runs all cycles at once
+#[derive(Debug, Clone, Copy, Default)]
+pub struct HeadlessExecutor;
+
+impl Executor for HeadlessExecutor {
+    fn run(&self, machine: &mut dyn Machine, cycles: usize) -> Result<(), Box<dyn Error>> {
+        machine.run_cycles(cycles)
+    }
+}
@@ -0,0 +1,102 @@
+//! Picking tests and sub-tests by their ids with glob patterns, see [`TestConfig::filters`](crate::TestConfig::filters)
+
+/// Whether `text` matches `pattern`, in which `*` matches any text and `?` any single character
+fn glob(pattern: &[char], text: &[char]) -> bool {
+    match (pattern.split_first(), text.split_first()) {
+        (None, _) => text.is_empty(),
+        (Some(('*', rest)), _) => {
+            glob(rest, text) || (!text.is_empty() && glob(pattern, &text[1..]))
+        }
+        (Some((p, rest)), Some((t, text))) => (p == &'?' || p == t) && glob(rest, text),
+        (Some(_), None) => false,
+    }
+}
+
+/// Whether `pattern` matches a text that starts with `prefix`
+fn glob_prefix(pattern: &[char], prefix: &[char]) -> bool {
+    match (pattern.split_first(), prefix.split_first()) {
+        (_, None) => true,
+        (None, Some(_)) => false,
+        (Some(('*', rest)), _) => glob_prefix(rest, prefix) || glob_prefix(pattern, &prefix[1..]),
+        (Some((p, rest)), Some((t, prefix))) => (p == &'?' || p == t) && glob_prefix(rest, prefix),
+    }
+}
+
+fn chars(text: &str) -> Vec<char> {
+    text.chars().collect()
+}
+
+/// Whether a pattern of `filters` matches the test with `id` itself, so all of it runs
+pub(crate) fn matches_test(filters: &[String], id: &str) -> bool {
+    let id = chars(id);
+    filters.iter().any(|pattern| glob(&chars(pattern), &id))
+}
+
+/// Whether the test with `id` runs: a pattern matches the test itself, or may match one of its sub-tests
+pub(crate) fn selects_test(filters: &[String], id: &str) -> bool {
+    let sub_tests = chars(&format!("{id}/"));
+    matches_test(filters, id)
+        || filters
+            .iter()
+            .any(|pattern| pattern.contains('/') && glob_prefix(&chars(pattern), &sub_tests))
+}
+
+/// Whether a pattern of `filters` matches sub-test `name` of the test with `id`
+pub(crate) fn matches_sub_test(filters: &[String], id: &str, name: &str) -> bool {
+    let id = chars(&format!("{id}/{name}"));
+    filters.iter().any(|pattern| glob(&chars(pattern), &id))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn filters(patterns: &[&str]) -> Vec<String> {
+        patterns.iter().map(ToString::to_string).collect()
+    }
+
+    #[test]
+    fn glob_patterns() {
+        assert!(glob(&chars("all_instrs/*"), &chars("all_instrs/11-stack")));
+        assert!(glob(&chars("*/11-stack"), &chars("all_instrs/11-stack")));
+        assert!(glob(
+            &chars("all_instrs/0?-*"),
+            &chars("all_instrs/03-immediate")
+        ));
+        assert!(glob(&chars("*"), &chars("")));
+        assert!(!glob(
+            &chars("all_instrs/0?-*"),
+            &chars("all_instrs/11-stack")
+        ));
+        assert!(!glob(&chars("nestest"), &chars("nestest_reset")));
+        assert!(!glob(&chars("nestest?"), &chars("nestest")));
+    }
+
+    #[test]
+    fn glob_prefixes() {
+        assert!(glob_prefix(
+            &chars("all_instrs/11-stack"),
+            &chars("all_instrs/")
+        ));
+        assert!(glob_prefix(&chars("*/11-stack"), &chars("all_instrs/")));
+        assert!(!glob_prefix(&chars("nestest/x"), &chars("all_instrs/")));
+    }
+
+    #[test]
+    fn tests_and_sub_tests() {
+        let filters = filters(&["nestest", "all_instrs/1?-*"]);
+        assert!(matches_test(&filters, "nestest"));
+        assert!(!matches_test(&filters, "all_instrs"));
+        assert!(selects_test(&filters, "all_instrs"));
+        assert!(!selects_test(&filters, "official_only"));
+        assert!(matches_sub_test(&filters, "all_instrs", "11-stack"));
+        assert!(!matches_sub_test(&filters, "all_instrs", "03-immediate"));
+    }
+
+    #[test]
+    fn patterns_without_a_slash_do_not_select_sub_tests() {
+        let filters = filters(&["*stack"]);
+        assert!(!selects_test(&filters, "all_instrs"));
+        assert!(matches_sub_test(&filters, "all_instrs", "11-stack"));
+    }
+}
@@ -0,0 +1,33 @@
+//! A helper for fuzzing how a cpu loads roms, see [`fuzz_get_cpu`]
+use crate::{ines, TestableCpu};
+use tudelft_nes_ppu::run_cpu_headless_for;
+
+/// How many cycles a cpu that loaded the bytes runs
+const FUZZ_CYCLES: usize = 1000;
+
+/// Loads arbitrary `data` as a rom with [`TestableCpu::get_cpu`], and when that works, runs the cpu
+/// for a few cycles and reads its memory and registers. Returning an error for bytes that aren't a
+/// rom is fine, but a panic, like indexing past the end of a header that isn't there, isn't: it's
+/// passed on, so a fuzzer finds the input that causes it. Use it in a fuzz target of `cargo fuzz`,
+/// like `fuzz/fuzz_targets/get_cpu.rs`:
+/// ```text
+/// #![no_main]
+/// libfuzzer_sys::fuzz_target!(|data: &[u8]| {
+///     tudelft_nes_test::fuzz_get_cpu::<my_emulator::MyCpu>(data);
+/// });
+/// ```
+/// Then `cargo fuzz run get_cpu` runs it. Starting with the bundled roms, like [`ROM_NESTEST`](crate::ROM_NESTEST),
+/// in the corpus makes the fuzzer find its way into the header sooner.
+pub fn fuzz_get_cpu<T: TestableCpu>(data: &[u8]) {
+    let Ok(mut cpu) = T::get_cpu(data) else {
+        return;
+    };
+
+    // an error of the cpu while it runs is fine too, it may have loaded a rom without a program
+    let _ = run_cpu_headless_for(&mut cpu, ines::mirroring(data).into(), FUZZ_CYCLES);
+    let _ = cpu.program_counter();
+    let _ = cpu.registers();
+    for address in 0..=u16::MAX {
+        let _ = cpu.memory_peek(address);
+    }
+}
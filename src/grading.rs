@@ -0,0 +1,270 @@
+//! Scoring a test run, for course staff grading emulators
+use crate::report::TestReport;
+use crate::TestSelector;
+use std::fmt;
+
+/// What a part of the score is awarded for
+#[derive(Debug, Clone)]
+enum Criterion {
+    /// Passing a whole test
+    Test(TestSelector),
+    /// Passing a single sub-test of a test rom, by name
+    SubTest(String),
+}
+
+/// Assigns weights to tests and sub-tests, to turn a [`TestReport`] into a numeric [`Grade`].
+///
+/// ```
+/// use tudelft_nes_test::{GradingProfile, TestSelector};
+///
+/// let profile = GradingProfile::new()
+///     .test(TestSelector::OFFICIAL_INSTRS, 60.0)
+///     .test(TestSelector::ALL_INSTRS, 20.0)
+///     .test(TestSelector::NESTEST, 20.0);
+/// ```
+///
+/// Weights are relative: the score is the sum of the weights of everything the cpu passed, as a
+/// percentage of the sum of all weights. A test that didn't run counts as failed.
+#[derive(Debug, Clone, Default)]
+pub struct GradingProfile {
+    weights: Vec<(Criterion, f64)>,
+}
+
+impl GradingProfile {
+    /// A profile without any weights, add them with [`test`](Self::test) and [`sub_test`](Self::sub_test)
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Awards `weight` for passing the test selected by `test`.
+    /// If `test` selects multiple tests, all of them have to pass.
+    pub fn test(mut self, test: TestSelector, weight: f64) -> Self {
+        self.weights.push((Criterion::Test(test), weight));
+        self
+    }
+
+    /// Awards `weight` for passing the sub-test called `name`, like `"01-basics"`, in any test.
+    /// Use this for partial credit on roms that run multiple tests.
+    pub fn sub_test(mut self, name: impl Into<String>, weight: f64) -> Self {
+        self.weights.push((Criterion::SubTest(name.into()), weight));
+        self
+    }
+
+    pub(crate) fn grade(&self, report: &TestReport) -> Grade {
+        let breakdown = self
+            .weights
+            .iter()
+            .map(|(criterion, weight)| {
+                let (name, passed) = match criterion {
+                    Criterion::Test(test) => {
+                        let results: Vec<_> = report
+                            .results
+                            .iter()
+                            .filter(|r| test.contains(r.test))
+                            .collect();
+                        let name = if results.is_empty() {
+                            format!("{test:?}")
+                        } else {
+                            results
+                                .iter()
+                                .map(|r| r.name.as_str())
+                                .collect::<Vec<_>>()
+                                .join(", ")
+                        };
+
+                        // every selected test has to have run and passed, expected failures don't count
+                        let all_ran = results
+                            .iter()
+                            .fold(TestSelector::empty(), |acc, r| acc | r.test)
+                            == *test;
+                        let passed = results.iter().all(|r| {
+                            r.passed() && r.expected_failures.is_empty() && r.skipped.is_none()
+                        });
+                        (name, all_ran && passed)
+                    }
+                    Criterion::SubTest(name) => {
+                        let passed = report
+                            .results
+                            .iter()
+                            .flat_map(|r| &r.sub_tests)
+                            .any(|s| &s.name == name && s.passed);
+                        (name.clone(), passed)
+                    }
+                };
+
+                GradeItem {
+                    name,
+                    weight: *weight,
+                    earned: if passed { *weight } else { 0.0 },
+                }
+            })
+            .collect();
+
+        Grade { breakdown }
+    }
+
+    /// The weight of the criteria for only the test `test`, not those of several tests
+    pub(crate) fn test_weight(&self, test: TestSelector) -> f64 {
+        self.weights
+            .iter()
+            .filter(|(criterion, _)| matches!(criterion, Criterion::Test(t) if *t == test))
+            .fold(0.0, |total, (_, weight)| total + weight)
+    }
+
+    /// The weight of the criteria for the sub-test called `name`
+    pub(crate) fn sub_test_weight(&self, name: &str) -> f64 {
+        self.weights
+            .iter()
+            .filter(|(criterion, _)| matches!(criterion, Criterion::SubTest(n) if n == name))
+            .fold(0.0, |total, (_, weight)| total + weight)
+    }
+}
+
+/// A single line of a [`Grade`]
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct GradeItem {
+    /// What the points are awarded for
+    pub name: String,
+    /// The weight given to this item in the [`GradingProfile`]
+    pub weight: f64,
+    /// `weight` if the cpu passed, otherwise 0
+    pub earned: f64,
+}
+
+/// The score of a test run, created with [`TestReport::grade`]
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Grade {
+    /// How the score was made up, in the order of the [`GradingProfile`]
+    pub breakdown: Vec<GradeItem>,
+}
+
+impl Grade {
+    /// The score as a percentage from 0 to 100
+    pub fn score(&self) -> f64 {
+        let total: f64 = self.breakdown.iter().map(|i| i.weight).sum();
+        if total == 0.0 {
+            return 0.0;
+        }
+
+        self.earned() / total * 100.0
+    }
+
+    /// The sum of the weights of everything the cpu passed
+    pub fn earned(&self) -> f64 {
+        self.breakdown.iter().map(|i| i.earned).sum()
+    }
+}
+
+impl fmt::Display for Grade {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let width = self
+            .breakdown
+            .iter()
+            .map(|i| i.name.len())
+            .max()
+            .unwrap_or(0);
+
+        for item in &self.breakdown {
+            writeln!(
+                f,
+                "{:width$}  {:>6.1} / {:.1}",
+                item.name, item.earned, item.weight
+            )?;
+        }
+        write!(f, "score: {:.1}%", self.score())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::report::{SubTestResult, TestResult};
+
+    fn report(results: Vec<TestResult>) -> TestReport {
+        TestReport {
+            results,
+            ..TestReport::default()
+        }
+    }
+
+    #[test]
+    fn weights_are_relative() {
+        let profile = GradingProfile::new()
+            .test(TestSelector::NESTEST, 30.0)
+            .test(TestSelector::ALL_INSTRS, 10.0);
+        let report = report(vec![
+            TestResult::of(TestSelector::NESTEST, "nestest", Ok(())),
+            TestResult::of(
+                TestSelector::ALL_INSTRS,
+                "all_instrs",
+                Err("01-basics".to_string()),
+            ),
+        ]);
+
+        let grade = profile.grade(&report);
+        assert_eq!(grade.earned(), 30.0);
+        assert_eq!(grade.score(), 75.0);
+        assert_eq!(grade.breakdown[1].name, "all_instrs");
+    }
+
+    #[test]
+    fn every_selected_test_has_to_run() {
+        let profile =
+            GradingProfile::new().test(TestSelector::NESTEST | TestSelector::ALL_INSTRS, 10.0);
+        let ran = report(vec![TestResult::of(
+            TestSelector::NESTEST,
+            "nestest",
+            Ok(()),
+        )]);
+        assert_eq!(profile.grade(&ran).earned(), 0.0);
+
+        let none_ran = profile.grade(&report(Vec::new()));
+        assert_eq!(none_ran.earned(), 0.0);
+        assert_eq!(none_ran.breakdown[0].name, "NESTEST | ALL_INSTRS");
+    }
+
+    #[test]
+    fn expected_failures_and_skipped_tests_earn_nothing() {
+        let profile = GradingProfile::new().test(TestSelector::ALL_INSTRS, 10.0);
+        let mut expected = TestResult::of(TestSelector::ALL_INSTRS, "all_instrs", Ok(()));
+        expected.expected_failures.push("03-immediate".to_string());
+        assert_eq!(profile.grade(&report(vec![expected])).earned(), 0.0);
+
+        let mut skipped = TestResult::of(TestSelector::ALL_INSTRS, "all_instrs", Ok(()));
+        skipped.skipped = Some("it needs a cpu that steals cycles for DMA".to_string());
+        assert_eq!(profile.grade(&report(vec![skipped])).earned(), 0.0);
+    }
+
+    #[test]
+    fn sub_tests_give_partial_credit() {
+        let profile = GradingProfile::new()
+            .sub_test("01-basics", 1.0)
+            .sub_test("02-implied", 1.0);
+        let mut result = TestResult::of(
+            TestSelector::ALL_INSTRS,
+            "all_instrs",
+            Err("02-implied".to_string()),
+        );
+        result.sub_tests = vec![
+            SubTestResult {
+                name: "01-basics".to_string(),
+                passed: true,
+                detail: None,
+            },
+            SubTestResult {
+                name: "02-implied".to_string(),
+                passed: false,
+                detail: None,
+            },
+        ];
+
+        let grade = profile.grade(&report(vec![result]));
+        assert_eq!(grade.score(), 50.0);
+        assert_eq!(
+            GradingProfile::new().grade(&report(Vec::new())).score(),
+            0.0
+        );
+    }
+}
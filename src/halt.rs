@@ -0,0 +1,115 @@
+//! Detects a cpu that got stuck in a tight loop, using [`TestableCpu::program_counter`](crate::TestableCpu::program_counter)
+use std::collections::VecDeque;
+use std::fmt::Write;
+
+/// After how many cycles in the same tight loop the cpu is considered stuck
+pub(crate) const STUCK_CYCLES: u64 = 100_000;
+/// How many bytes of code a loop may span to still count as a tight loop
+const LOOP_SPAN: u16 = 4;
+/// How many program counters leading up to the loop are reported
+const HISTORY_LEN: usize = 16;
+
+pub(crate) struct HaltDetector {
+    /// lowest and highest program counter seen since the cpu entered the current loop
+    low: u16,
+    high: u16,
+    cycles: u64,
+    history: VecDeque<u16>,
+}
+
+impl HaltDetector {
+    pub(crate) fn new(pc: u16) -> Self {
+        Self {
+            low: pc,
+            high: pc,
+            cycles: 0,
+            history: VecDeque::with_capacity(HISTORY_LEN),
+        }
+    }
+
+    /// Observes the program counter after a cycle, returns true when the cpu is stuck
+    pub(crate) fn observe(&mut self, pc: u16) -> bool {
+        let low = self.low.min(pc);
+        let high = self.high.max(pc);
+
+        if high - low < LOOP_SPAN {
+            self.low = low;
+            self.high = high;
+            self.cycles += 1;
+        } else {
+            if self.history.len() == HISTORY_LEN {
+                self.history.pop_front();
+            }
+            self.history.push_back(self.low);
+
+            self.low = pc;
+            self.high = pc;
+            self.cycles = 0;
+        }
+
+        self.cycles >= STUCK_CYCLES
+    }
+
+    /// Describes where the cpu is stuck, and how it got there
+    pub(crate) fn report(&self) -> String {
+        let mut res = format!(
+            "stuck at ${:04X} for {}k cycles",
+            self.low,
+            self.cycles / 1000
+        );
+
+        if !self.history.is_empty() {
+            res.push_str(" (after");
+            for pc in &self.history {
+                let _ = write!(res, " ${pc:04X}");
+            }
+            res.push(')');
+        }
+
+        res
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn a_tight_loop_is_stuck() {
+        let mut detector = HaltDetector::new(0x8000);
+        for pc in [0x8003, 0xC000] {
+            assert!(!detector.observe(pc));
+        }
+        // like BIT $2002 and BPL back to it, waiting for a vblank that never comes
+        for i in 1..STUCK_CYCLES {
+            assert!(!detector.observe(0xC000 + (i % 4) as u16));
+        }
+        assert!(detector.observe(0xC003));
+        assert_eq!(
+            detector.report(),
+            "stuck at $C000 for 100k cycles (after $8000)"
+        );
+    }
+
+    #[test]
+    fn code_that_moves_on_is_not_stuck() {
+        let mut detector = HaltDetector::new(0x8000);
+        for i in 0..2 * STUCK_CYCLES {
+            assert!(!detector.observe(0x8000 + (i % 8) as u16 * 2));
+        }
+    }
+
+    #[test]
+    fn only_the_last_program_counters_are_reported() {
+        let mut detector = HaltDetector::new(0x0000);
+        for pc in (1..=HISTORY_LEN as u16 + 4).map(|i| i * 0x100) {
+            detector.observe(pc);
+        }
+        let report = detector.report();
+        assert!(
+            report.starts_with("stuck at $1400 for 0k cycles (after $0400 $0500"),
+            "{report}"
+        );
+        assert!(report.ends_with(" $1300)"), "{report}");
+    }
+}
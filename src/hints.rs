@@ -0,0 +1,271 @@
+//! What the failures of the tests usually mean: a knowledge base of what failures look like, and the
+//! bug in the cpu that most often causes them. The best matches are added to the message of a test
+//! that failed, as lines that start with `hint: `.
+use crate::report::SubTestResult;
+
+/// The most hints added to a failure, more of them are rarely about the same bug
+const MAX_HINTS: usize = 3;
+
+/// What a failure looks like
+enum Signature {
+    /// The failure message contains this text, like the explanation of an error code of nestest
+    Text(&'static str),
+    /// A word of the failure message, or of what a failed sub-test reported, is this, like the
+    /// mnemonic in "6D ADC abs"
+    Word(&'static str),
+    /// A sub-test with this name failed, like the instruction group `zp_xy` of instr_test-v5, which
+    /// also matches its rom `05-zp_xy.nes`
+    SubTest(&'static str),
+    /// The failure message contains this text, which only says how the test failed, like a cpu that
+    /// got stuck, and not what is wrong with the cpu
+    Symptom(&'static str),
+}
+
+impl Signature {
+    /// How well a match says what went wrong, the lower the better: a failure message says exactly
+    /// what failed, an instruction only which one, a sub-test only roughly where, and a symptom only
+    /// that something failed
+    fn rank(&self) -> u8 {
+        match self {
+            Signature::Text(_) => 0,
+            Signature::Word(_) => 1,
+            Signature::SubTest(_) => 2,
+            Signature::Symptom(_) => 3,
+        }
+    }
+}
+
+const ADC: &str =
+    "ADC sets C when the sum, with the carry, is more than $FF, and V when both operands \
+                   have the same sign and the sum doesn't: (A ^ sum) & (M ^ sum) & $80";
+const SBC: &str = "SBC is ADC with the operand inverted, A + !M + C, so C is set when nothing was \
+                   borrowed, and V is set like for ADC";
+const COMPARE: &str = "CMP, CPX and CPY subtract without storing the result: C is set when the register \
+                       is at least the operand, Z when they are equal, and N is bit 7 of the difference";
+const STATUS_BITS: &str = "the status has no bits 4 (B) and 5 in the cpu: PHP and BRK push it with both \
+                           set, an interrupt with only bit 5 set, and PLP and RTI ignore them when they \
+                           pull it";
+const JMP_INDIRECT: &str =
+    "JMP ($xxFF) reads the high byte of its target from $xx00, not from the next \
+                            page: the 6502 doesn't carry into the high byte of the pointer";
+const ZERO_PAGE_WRAP: &str =
+    "zero page addresses wrap around within the zero page: $FF,X with X = 1 is \
+                              $00, not $0100, and a pointer at $FF has its high byte at $00";
+const PAGE_CROSS: &str =
+    "absolute,X, absolute,Y and (indirect),Y addresses carry into their high byte \
+                          when the index crosses a page, which takes a cycle more for reads";
+const ADDRESS_WRAP: &str = "an address with an index added to it wraps around from $FFFF to $0000";
+const INDIRECT_X: &str =
+    "(indirect,X) adds X to the operand in the zero page, and reads both bytes of \
+                          the pointer from there, wrapping around at $FF";
+const INDIRECT_Y: &str =
+    "(indirect),Y reads the pointer from the zero page at the operand, with its high \
+                          byte at $00 when the operand is $FF, and then adds Y to the pointer";
+const NZ: &str =
+    "loads, transfers other than TXS, and the logic and arithmetic instructions set Z when \
+                  the result is 0 and N to its bit 7";
+const INC_DEC: &str = "INC, DEC, INX, DEX, INY and DEY only change N and Z, not C and V";
+const SHIFTS: &str =
+    "shifts and rotates move the bit that is shifted out into C, ROL and ROR shift the \
+                      old C in, and their accumulator forms don't touch memory";
+const BIT: &str = "BIT copies bits 7 and 6 of the operand into N and V, and sets Z when A AND the \
+                   operand is 0, without changing A";
+const STACK: &str =
+    "the stack is at $0100-$01FF: a push writes to $0100 + S and then decrements S, a \
+                     pull increments S first, and S wraps around within the page";
+const TXS: &str = "TXS doesn't change any flags, unlike TSX, which sets N and Z";
+const JSR: &str =
+    "JSR pushes the address of its own last byte, so 1 less than the return address, \
+                   high byte first";
+const RTS: &str = "RTS pulls the return address that JSR pushed and adds 1 to it";
+const RTI: &str =
+    "RTI pulls the status and then the return address, and unlike RTS doesn't add 1 to it";
+const BRK: &str =
+    "BRK skips the byte after it: it pushes its address plus 2 and the status with bit 4 \
+                   set, sets I and jumps to the address in $FFFE-$FFFF";
+const BRANCHES: &str =
+    "a branch adds its operand as a signed byte to the address of the next instruction, \
+                        and takes a cycle more when taken, two when that is on another page";
+const DECIMAL: &str =
+    "the 6502 of the NES has no decimal mode: ADC and SBC ignore the D flag, which SED \
+                       and CLD still set and clear";
+const UNOFFICIAL_RMW: &str = "the unofficial SLO, RLA, SRE, RRA, DCP and ISC shift or step the memory like \
+                              ASL, ROL, LSR, ROR, DEC and INC, and then ORA, AND, EOR, ADC, CMP or SBC it \
+                              with A. Leave them out with TestConfig::unofficial_opcodes when they aren't \
+                              needed";
+const UNOFFICIAL_LOADS: &str =
+    "the unofficial LAX loads both A and X, and SAX stores A AND X without \
+                                changing any flags";
+const UNOFFICIAL_NOPS: &str =
+    "the unofficial NOPs still read their operand, and take as many bytes and \
+                               cycles as other instructions with the same addressing mode";
+const UNSTABLE: &str = "ATX, SYA and SXA do different things on different consoles, and the roms only pass \
+                        one of them. TestConfig::unstable_opcodes = AnyVariant accepts every documented one";
+const MAGIC: &str =
+    "the rom writes its status to the PRG-RAM at $6000-$7FFF, which had something else \
+                     there: is it mapped, and do writes to it stick?";
+const NO_STATUS: &str = "the rom didn't get far enough to write its status: does the cpu start at the \
+                         address in the reset vector at $FFFC-$FFFD, and is the PRG-RAM at $6000-$7FFF \
+                         mapped?";
+const STUCK: &str = "a cpu that keeps running the same few instructions ran into a jam opcode like $02, \
+                     takes a branch it shouldn't, or waits for a vblank in bit 7 of $2002 that never \
+                     comes";
+const DETERMINISM: &str =
+    "something other than the rom changes what the cpu does, like memory that isn't \
+                           initialized, the order of a HashMap, the time or threads";
+
+/// The knowledge base: what failures look like, and what usually causes them. Where several
+/// signatures have the same cause they share its hint, which is only added once.
+const HINTS: &[(Signature, &str)] = &[
+    // the messages of the harness and the explanations of the codes of nestest
+    (Signature::Text("invalid magic sequence"), MAGIC),
+    (Signature::Text("isn't deterministic"), DETERMINISM),
+    (Signature::Text("decimal mode was turned on"), DECIMAL),
+    (Signature::Text("didn't wrap properly"), JMP_INDIRECT),
+    (Signature::Text("wrapped zeropage"), ZERO_PAGE_WRAP),
+    (Signature::Text("page cross"), PAGE_CROSS),
+    (Signature::Text("ffffh to 0000h"), ADDRESS_WRAP),
+    (Signature::Text("PHP/flags"), STATUS_BITS),
+    (Signature::Text("PLP/flags"), STATUS_BITS),
+    (Signature::Text("pushed the status"), STATUS_BITS),
+    (Signature::Text("didn't set N and Z"), NZ),
+    (Signature::Text("PLA didn't affect Z and N"), NZ),
+    (Signature::Text("messed up overflow or carry"), INC_DEC),
+    (Signature::Text("stored the result in a register"), COMPARE),
+    // the instructions that failed
+    (Signature::Word("ADC"), ADC),
+    (Signature::Word("SBC"), SBC),
+    (Signature::Word("CMP"), COMPARE),
+    (Signature::Word("CPX"), COMPARE),
+    (Signature::Word("CPY"), COMPARE),
+    (Signature::Word("BIT"), BIT),
+    (Signature::Word("ASL"), SHIFTS),
+    (Signature::Word("LSR"), SHIFTS),
+    (Signature::Word("ROL"), SHIFTS),
+    (Signature::Word("ROR"), SHIFTS),
+    (Signature::Word("INC"), INC_DEC),
+    (Signature::Word("DEC"), INC_DEC),
+    (Signature::Word("INX"), INC_DEC),
+    (Signature::Word("DEX"), INC_DEC),
+    (Signature::Word("INY"), INC_DEC),
+    (Signature::Word("DEY"), INC_DEC),
+    (Signature::Word("PHP"), STATUS_BITS),
+    (Signature::Word("PLP"), STATUS_BITS),
+    (Signature::Word("TXS"), TXS),
+    (Signature::Word("TSX"), TXS),
+    (Signature::Text("JMP ($"), JMP_INDIRECT),
+    (Signature::Word("JSR"), JSR),
+    (Signature::Word("RTS"), RTS),
+    (Signature::Word("RTI"), RTI),
+    (Signature::Word("BRK"), BRK),
+    (Signature::Word("SLO"), UNOFFICIAL_RMW),
+    (Signature::Word("RLA"), UNOFFICIAL_RMW),
+    (Signature::Word("SRE"), UNOFFICIAL_RMW),
+    (Signature::Word("RRA"), UNOFFICIAL_RMW),
+    (Signature::Word("DCP"), UNOFFICIAL_RMW),
+    (Signature::Word("ISC"), UNOFFICIAL_RMW),
+    (Signature::Word("ISB"), UNOFFICIAL_RMW),
+    (Signature::Word("LAX"), UNOFFICIAL_LOADS),
+    (Signature::Word("SAX"), UNOFFICIAL_LOADS),
+    (Signature::Word("NOP"), UNOFFICIAL_NOPS),
+    (Signature::Word("ATX"), UNSTABLE),
+    (Signature::Word("SYA"), UNSTABLE),
+    (Signature::Word("SXA"), UNSTABLE),
+    // the instruction groups of instr_test-v5
+    (Signature::SubTest("zp_xy"), ZERO_PAGE_WRAP),
+    (Signature::SubTest("abs_xy"), PAGE_CROSS),
+    (Signature::SubTest("ind_x"), INDIRECT_X),
+    (Signature::SubTest("ind_y"), INDIRECT_Y),
+    (Signature::SubTest("branches"), BRANCHES),
+    (Signature::SubTest("stack"), STACK),
+    (Signature::SubTest("jmp_jsr"), JSR),
+    (Signature::SubTest("rts"), RTS),
+    (Signature::SubTest("rti"), RTI),
+    (Signature::SubTest("brk"), BRK),
+    (Signature::SubTest("special"), JMP_INDIRECT),
+    // how the test failed, when nothing says why
+    (
+        Signature::Symptom("stuck before the rom showed its status"),
+        NO_STATUS,
+    ),
+    (Signature::Symptom("stuck at $"), STUCK),
+];
+
+/// The hints for a test that failed with `message`, after the `sub_tests` it ran, the best first
+pub(crate) fn hints(message: &str, sub_tests: &[SubTestResult]) -> Vec<&'static str> {
+    let failed: Vec<_> = sub_tests.iter().filter(|s| !s.passed).collect();
+    let words: Vec<_> = std::iter::once(message)
+        .chain(failed.iter().filter_map(|s| s.detail.as_deref()))
+        .flat_map(|text| text.split(|c: char| !c.is_ascii_alphanumeric()))
+        .collect();
+
+    let mut matches: Vec<_> = HINTS
+        .iter()
+        .filter(|(signature, _)| match signature {
+            Signature::Text(text) | Signature::Symptom(text) => message.contains(text),
+            Signature::Word(word) => words.contains(word),
+            Signature::SubTest(name) => failed.iter().any(|s| group(&s.name) == *name),
+        })
+        .collect();
+    // stable, so the hints of the same rank stay in the order of the knowledge base
+    matches.sort_by_key(|(signature, _)| signature.rank());
+
+    let mut hints = Vec::new();
+    for (_, hint) in matches {
+        if hints.len() == MAX_HINTS {
+            break;
+        }
+        if !hints.contains(hint) {
+            hints.push(*hint);
+        }
+    }
+    hints
+}
+
+/// The name of a sub-test without the number and extension of its rom, like `zp_xy` of `05-zp_xy.nes`
+fn group(name: &str) -> &str {
+    let name = name.trim();
+    let name = name
+        .rsplit_once('.')
+        .filter(|(_, extension)| matches!(*extension, "nes" | "s"))
+        .map_or(name, |(name, _)| name);
+    match name.split_once('-') {
+        Some((number, group)) if number.bytes().all(|b| b.is_ascii_digit()) => group,
+        _ => name,
+    }
+}
+
+/// `message` with the hints for it, a line each
+pub(crate) fn with_hints(message: String, sub_tests: &[SubTestResult]) -> String {
+    let hints = hints(&message, sub_tests);
+    hints
+        .into_iter()
+        .fold(message, |message, hint| format!("{message}\nhint: {hint}"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn failed(name: &str) -> SubTestResult {
+        SubTestResult {
+            name: name.to_string(),
+            passed: false,
+            detail: None,
+        }
+    }
+
+    #[test]
+    fn matches_the_exact_name_of_a_group() {
+        assert_eq!(hints("failed", &[failed("05-zp_xy")]), [ZERO_PAGE_WRAP]);
+        assert_eq!(hints("failed", &[failed("16-special.nes")]), [JMP_INDIRECT]);
+        assert!(hints("failed", &[failed("my_special_case")]).is_empty());
+        assert!(hints("failed", &[failed("instack")]).is_empty());
+    }
+
+    #[test]
+    fn a_jmp_alone_has_no_hint_of_jmp_indirect() {
+        assert!(!hints("JMP $C5F5 went to $C5F6", &[]).contains(&JMP_INDIRECT));
+        assert!(hints("JMP ($04FF) read the high byte from $0500", &[]).contains(&JMP_INDIRECT));
+    }
+}
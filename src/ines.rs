@@ -0,0 +1,76 @@
+//! Minimal iNES header parsing, used to check a rom's requirements before running it
+use crate::NametableMirroring;
+
+/// Returns the mapper number of an iNES rom, or `None` if `rom` doesn't have an iNES header
+pub(crate) fn mapper_number(rom: &[u8]) -> Option<u8> {
+    if rom.len() < 16 || &rom[0..4] != b"NES\x1a" {
+        return None;
+    }
+
+    Some((rom[6] >> 4) | (rom[7] & 0xF0))
+}
+
+/// The nametable mirroring in the header of an iNES rom, horizontal when the rom doesn't have a header.
+/// The ppu doesn't have four-screen mirroring, so roms asking for it get vertical mirroring; the tests
+/// don't run those unless they're told which mirroring to use, see [`four_screen`].
+pub(crate) fn mirroring(rom: &[u8]) -> NametableMirroring {
+    match mapper_number(rom).and(rom.get(6)) {
+        Some(flags) if flags & 0b1001 != 0 => NametableMirroring::Vertical,
+        _ => NametableMirroring::Horizontal,
+    }
+}
+
+/// Whether the header of an iNES rom asks for four-screen mirroring, which the ppu doesn't have
+pub(crate) fn four_screen(rom: &[u8]) -> bool {
+    mapper_number(rom)
+        .and(rom.get(6))
+        .is_some_and(|flags| flags & 0b1000 != 0)
+}
+
+/// Builds an NROM rom without a program, only with the interrupt vectors pointing to `nmi`, `reset`
+/// and `irq`. Used for tests that put their own program in ram.
+pub(crate) fn vectors_only(nmi: u16, reset: u16, irq: u16) -> Vec<u8> {
+    const PRG_SIZE: usize = 0x4000;
+    const CHR_SIZE: usize = 0x2000;
+
+    let mut rom = vec![0; 16 + PRG_SIZE + CHR_SIZE];
+    rom[0..6].copy_from_slice(b"NES\x1a\x01\x01");
+
+    // the vectors are the last 6 bytes of prg, which is mirrored to $C000-$FFFF
+    let vectors = 16 + PRG_SIZE - 6;
+    for (i, address) in [nmi, reset, irq].into_iter().enumerate() {
+        rom[vectors + 2 * i..vectors + 2 * i + 2].copy_from_slice(&address.to_le_bytes());
+    }
+
+    rom
+}
+
+/// Copies an iNES rom with its reset vector changed to `reset`, so a cpu starts running it there.
+/// The vector is at the end of the last 16KB bank of prg, which is where NROM maps $FFFC.
+/// A rom without an iNES header is returned unchanged.
+pub(crate) fn with_reset_vector(rom: &[u8], reset: u16) -> Vec<u8> {
+    let mut rom = rom.to_vec();
+    if mapper_number(&rom).is_none() {
+        return rom;
+    }
+
+    let trainer = if rom[6] & 0b100 != 0 { 512 } else { 0 };
+    let prg_end = 16 + trainer + usize::from(rom[4]) * 0x4000;
+    if let Some(vector) = rom.get_mut(prg_end - 4..prg_end - 2) {
+        vector.copy_from_slice(&reset.to_le_bytes());
+    }
+    rom
+}
+
+/// The common name of a mapper, as used on the nesdev wiki
+pub(crate) fn mapper_name(mapper: u8) -> &'static str {
+    match mapper {
+        0 => "NROM",
+        1 => "MMC1",
+        2 => "UxROM",
+        3 => "CNROM",
+        4 => "MMC3",
+        7 => "AxROM",
+        _ => "unknown",
+    }
+}
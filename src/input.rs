@@ -0,0 +1,209 @@
+//! Button presses for test roms that need input, like read_joy3. The harness gives them to the cpu
+//! with [`TestableCpu::set_buttons`](crate::TestableCpu::set_buttons) at the frames of the script.
+use crate::Region;
+use bitflags::bitflags;
+
+bitflags! {
+    /// The buttons of a standard controller, in the order in which $4016 and $4017 return them:
+    /// the first read returns `A` in bit 0, the second `B`, and so on
+    #[derive(Default)]
+    #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+    pub struct Buttons: u8 {
+        /// The A button
+        const A      = 0b00000001;
+        /// The B button
+        const B      = 0b00000010;
+        /// The select button
+        const SELECT = 0b00000100;
+        /// The start button
+        const START  = 0b00001000;
+        /// Up on the d-pad
+        const UP     = 0b00010000;
+        /// Down on the d-pad
+        const DOWN   = 0b00100000;
+        /// Left on the d-pad
+        const LEFT   = 0b01000000;
+        /// Right on the d-pad
+        const RIGHT  = 0b10000000;
+    }
+}
+
+/// How many frames [`InputScript::press`] holds the buttons, long enough for roms that only look
+/// at the controller once every few frames
+pub const PRESS_FRAMES: u64 = 5;
+
+/// One of the two controllers of an NES, read from $4016 and $4017
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(rename_all = "lowercase")
+)]
+pub enum Controller {
+    /// The controller in the first port, read from $4016
+    #[default]
+    One,
+    /// The controller in the second port, read from $4017
+    Two,
+}
+
+impl Controller {
+    /// The number of the controller, 1 or 2, which [`TestableCpu::set_buttons`](crate::TestableCpu::set_buttons) gets
+    pub fn number(self) -> u8 {
+        match self {
+            Controller::One => 1,
+            Controller::Two => 2,
+        }
+    }
+
+    pub(crate) fn index(self) -> usize {
+        usize::from(self.number() - 1)
+    }
+}
+
+/// Buttons that go down or up on a controller at the start of a frame
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub(crate) struct InputEvent {
+    pub(crate) frame: u64,
+    pub(crate) controller: Controller,
+    pub(crate) buttons: Buttons,
+    pub(crate) down: bool,
+}
+
+impl InputEvent {
+    /// The cycle at which the frame of the event starts on an NES of `region`
+    pub(crate) fn cycle(&self, region: Region) -> u64 {
+        self.frame * region.cycles_per_two_frames() / 2
+    }
+}
+
+/// When to press which buttons while a test runs, counted in frames since the start of the test:
+/// ```
+/// use tudelft_nes_test::{Buttons, Controller, InputScript};
+///
+/// let script = InputScript::new()
+///     .press(60, Buttons::START)
+///     .hold(120, Buttons::RIGHT | Buttons::A)
+///     .release(180, Buttons::A)
+///     .controller(Controller::Two)
+///     .press(200, Buttons::B);
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct InputScript {
+    events: Vec<InputEvent>,
+    /// the controller the next events are for
+    controller: Controller,
+}
+
+impl InputScript {
+    /// An empty script, which presses buttons on controller 1 until [`controller`](Self::controller) says otherwise
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The buttons after this go to `controller`
+    pub fn controller(mut self, controller: Controller) -> Self {
+        self.controller = controller;
+        self
+    }
+
+    /// Holds down `buttons` at `frame`, until they're released
+    pub fn hold(self, frame: u64, buttons: Buttons) -> Self {
+        self.event(frame, buttons, true)
+    }
+
+    /// Releases `buttons` at `frame`
+    pub fn release(self, frame: u64, buttons: Buttons) -> Self {
+        self.event(frame, buttons, false)
+    }
+
+    /// Presses `buttons` at `frame`, and releases them [`PRESS_FRAMES`] frames later
+    pub fn press(self, frame: u64, buttons: Buttons) -> Self {
+        self.hold(frame, buttons)
+            .release(frame + PRESS_FRAMES, buttons)
+    }
+
+    fn event(mut self, frame: u64, buttons: Buttons, down: bool) -> Self {
+        self.events.push(InputEvent {
+            frame,
+            controller: self.controller,
+            buttons,
+            down,
+        });
+        self
+    }
+
+    /// The events, in the order in which they happen. Events at the same frame keep the order
+    /// in which they were added.
+    pub(crate) fn events(&self) -> Vec<InputEvent> {
+        let mut events = self.events.clone();
+        events.sort_by_key(|e| e.frame);
+        events
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn presses_on_controller_one_by_default() {
+        let events = InputScript::default().press(60, Buttons::START).events();
+        assert_eq!(
+            events,
+            [
+                InputEvent {
+                    frame: 60,
+                    controller: Controller::One,
+                    buttons: Buttons::START,
+                    down: true,
+                },
+                InputEvent {
+                    frame: 60 + PRESS_FRAMES,
+                    controller: Controller::One,
+                    buttons: Buttons::START,
+                    down: false,
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn sorts_the_events_by_frame() {
+        let script = InputScript::new()
+            .release(180, Buttons::A)
+            .hold(120, Buttons::A)
+            .controller(Controller::Two)
+            .hold(120, Buttons::B);
+        let events: Vec<_> = script
+            .events()
+            .iter()
+            .map(|e| (e.frame, e.controller, e.down))
+            .collect();
+        assert_eq!(
+            events,
+            [
+                (120, Controller::One, true),
+                (120, Controller::Two, true),
+                (180, Controller::One, false),
+            ]
+        );
+    }
+
+    #[test]
+    fn indexes_the_held_buttons_by_controller() {
+        assert_eq!(Controller::One.number(), 1);
+        assert_eq!(Controller::Two.number(), 2);
+        assert_eq!(Controller::One.index(), 0);
+        assert_eq!(Controller::Two.index(), 1);
+    }
+
+    #[test]
+    fn starts_a_frame_at_its_cycle_in_the_region() {
+        let event = InputScript::new().hold(2, Buttons::A).events()[0];
+        assert_eq!(event.cycle(Region::Ntsc), 59_561);
+        assert_eq!(event.cycle(Region::Pal), 66_495);
+    }
+}
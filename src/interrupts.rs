@@ -0,0 +1,260 @@
+//! Small programs that test interrupts without a test rom. The harness puts them in ram with
+//! [`TestableCpu::memory_write`](crate::TestableCpu::memory_write) and checks the results they
+//! leave in the zero page with [`TestableCpu::memory_read`](crate::TestableCpu::memory_read).
+
+/// Where the program of a micro test is put, and where the cpu starts running it
+pub(crate) const PROGRAM: u16 = 0x0200;
+/// Where the NMI handler is put, the carrier rom points the NMI vector here
+pub(crate) const NMI_HANDLER: u16 = 0x0300;
+/// Where the IRQ/BRK handler is put, the carrier rom points the IRQ vector here
+pub(crate) const IRQ_HANDLER: u16 = 0x0380;
+
+/// The zero page bytes the programs store their results in, cleared before every test
+pub(crate) const RESULTS: std::ops::Range<u16> = 0x0010..0x0050;
+
+/// A program testing some part of how interrupts work, or of the cpu in general, like the programs
+/// of [`wraparound`](crate::wraparound)
+pub(crate) struct MicroTest {
+    pub(crate) name: &'static str,
+    pub(crate) program: &'static [u8],
+    pub(crate) nmi_handler: &'static [u8],
+    pub(crate) irq_handler: &'static [u8],
+    /// Whether the harness raises an NMI halfway through the test
+    pub(crate) nmi: bool,
+    /// Checks the results, given a way to read memory
+    pub(crate) check: fn(&dyn Fn(u16) -> u8) -> Result<(), String>,
+}
+
+/// Stores what the interrupt handler sees in $11 to $15, and counts how often it ran in $16
+#[rustfmt::skip]
+const IRQ_RECORDER: &[u8] = &[
+    0xE6, 0x16,         // 0380: INC $16
+    0xBA,               // 0382: TSX
+    0x86, 0x11,         // 0383: STX $11
+    0xAD, 0xFD, 0x01,   // 0385: LDA $01FD   pushed status
+    0x85, 0x12,         // 0388: STA $12
+    0xAD, 0xFE, 0x01,   // 038A: LDA $01FE   pushed return address, low byte
+    0x85, 0x13,         // 038D: STA $13
+    0xAD, 0xFF, 0x01,   // 038F: LDA $01FF   pushed return address, high byte
+    0x85, 0x14,         // 0392: STA $14
+    0x08,               // 0394: PHP
+    0x68,               // 0395: PLA
+    0x85, 0x15,         // 0396: STA $15     status inside the handler
+    0x40,               // 0398: RTI
+];
+
+/// Like [`IRQ_RECORDER`], for the NMI: stores what it sees in $31, $32 and $34, and counts in $33
+#[rustfmt::skip]
+const NMI_RECORDER: &[u8] = &[
+    0xE6, 0x33,         // 0300: INC $33
+    0xBA,               // 0302: TSX
+    0x86, 0x31,         // 0303: STX $31
+    0xAD, 0xFD, 0x01,   // 0305: LDA $01FD   pushed status
+    0x85, 0x32,         // 0308: STA $32
+    0x08,               // 030A: PHP
+    0x68,               // 030B: PLA
+    0x85, 0x34,         // 030C: STA $34     status inside the handler
+    0x40,               // 030E: RTI
+];
+
+/// The handler for interrupts a test doesn't expect: `INC $4F` and `RTI`
+pub(crate) const UNEXPECTED: u16 = 0x004F;
+pub(crate) const UNUSED_HANDLER: &[u8] = &[0xE6, 0x4F, 0x40];
+
+/// The micro tests, in the order in which they run: the later ones rely on what the earlier ones test
+pub(crate) const MICRO_TESTS: &[MicroTest] = &[
+    MicroTest {
+        name: "php_plp",
+        #[rustfmt::skip]
+        program: &[
+            0xA2, 0xFF,         // 0200: LDX #$FF
+            0x9A,               // 0202: TXS
+            0xA9, 0xFF,         // 0203: LDA #$FF
+            0x48,               // 0205: PHA
+            0x28,               // 0206: PLP
+            0x08,               // 0207: PHP
+            0x68,               // 0208: PLA
+            0x85, 0x40,         // 0209: STA $40
+            0xA9, 0x00,         // 020B: LDA #$00
+            0x48,               // 020D: PHA
+            0x28,               // 020E: PLP
+            0x08,               // 020F: PHP
+            0x68,               // 0210: PLA
+            0x85, 0x41,         // 0211: STA $41
+            0xE6, 0x42,         // 0213: INC $42
+            0x4C, 0x15, 0x02,   // 0215: JMP $0215
+        ],
+        nmi_handler: UNUSED_HANDLER,
+        irq_handler: UNUSED_HANDLER,
+        nmi: false,
+        check: |read| {
+            if read(0x42) != 1 {
+                return Err("the cpu didn't finish the PHA/PLP/PHP/PLA program".into());
+            }
+            if read(0x40) != 0xFF {
+                return Err(format!(
+                    "PHP pushed ${:02X} after PLP pulled $FF, all flags should be set",
+                    read(0x40)
+                ));
+            }
+            if read(0x41) != 0x30 {
+                return Err(format!(
+                    "PHP pushed ${:02X} after PLP pulled $00, it should push $30: PHP always sets the B flag and bit 5",
+                    read(0x41)
+                ));
+            }
+            Ok(())
+        },
+    },
+    MicroTest {
+        name: "rti",
+        #[rustfmt::skip]
+        program: &[
+            0xA2, 0xFF,         // 0200: LDX #$FF
+            0x9A,               // 0202: TXS
+            0xA9, 0x02,         // 0203: LDA #$02
+            0x48,               // 0205: PHA         return address, high byte
+            0xA9, 0x10,         // 0206: LDA #$10
+            0x48,               // 0208: PHA         return address, low byte
+            0xA9, 0xC3,         // 0209: LDA #$C3
+            0x48,               // 020B: PHA         status with N, V, Z and C set
+            0x40,               // 020C: RTI
+            0x4C, 0x0D, 0x02,   // 020D: JMP $020D
+            0x08,               // 0210: PHP
+            0x68,               // 0211: PLA
+            0x85, 0x20,         // 0212: STA $20
+            0xBA,               // 0214: TSX
+            0x86, 0x21,         // 0215: STX $21
+            0xE6, 0x22,         // 0217: INC $22
+            0x4C, 0x19, 0x02,   // 0219: JMP $0219
+        ],
+        nmi_handler: UNUSED_HANDLER,
+        irq_handler: UNUSED_HANDLER,
+        nmi: false,
+        check: |read| {
+            if read(0x22) != 1 {
+                return Err("RTI didn't return to the address on the stack".into());
+            }
+            // returning to $0211 skips the PHP, so the PLA pulls one byte too many
+            if read(0x21) == 0x00 {
+                return Err(
+                    "RTI returned to the byte after the address on the stack, unlike RTS it doesn't add 1 to it"
+                        .into(),
+                );
+            }
+            if read(0x21) != 0xFF {
+                return Err(format!(
+                    "the stack pointer was ${:02X} after RTI, it should pull 3 bytes: from $FC to $FF",
+                    read(0x21)
+                ));
+            }
+            if read(0x20) & 0xCF != 0xC3 {
+                return Err(format!(
+                    "RTI pulled the status $C3, but afterwards the status was ${:02X}",
+                    read(0x20) & 0xCF
+                ));
+            }
+            Ok(())
+        },
+    },
+    MicroTest {
+        name: "brk",
+        #[rustfmt::skip]
+        program: &[
+            0xA2, 0xFF,         // 0200: LDX #$FF
+            0x9A,               // 0202: TXS
+            0x78,               // 0203: SEI         BRK isn't masked by the I flag
+            0x00,               // 0204: BRK
+            0xEA,               // 0205: NOP         padding byte, skipped by the return address
+            0xA9, 0x01,         // 0206: LDA #$01
+            0x85, 0x10,         // 0208: STA $10
+            0x4C, 0x0A, 0x02,   // 020A: JMP $020A
+        ],
+        nmi_handler: UNUSED_HANDLER,
+        irq_handler: IRQ_RECORDER,
+        nmi: false,
+        check: |read| {
+            if read(0x16) == 0 {
+                return Err("BRK didn't jump to the address in the IRQ/BRK vector at $FFFE".into());
+            }
+            let pushed = u16::from_le_bytes([read(0x13), read(0x14)]);
+            if pushed != 0x0206 {
+                return Err(format!(
+                    "BRK at $0204 pushed ${pushed:04X} as return address, it should push the address of the BRK plus 2"
+                ));
+            }
+            if read(0x11) != 0xFC {
+                return Err(format!(
+                    "the stack pointer was ${:02X} after BRK, it should push 3 bytes: from $FF to $FC",
+                    read(0x11)
+                ));
+            }
+            if read(0x12) & 0x30 != 0x30 {
+                return Err(format!(
+                    "BRK pushed the status ${:02X}, it should push it with the B flag and bit 5 set",
+                    read(0x12)
+                ));
+            }
+            if read(0x15) & 0x04 == 0 {
+                return Err("BRK should set the interrupt disable flag".into());
+            }
+            if read(0x16) != 1 {
+                return Err(format!(
+                    "the BRK handler ran {} times for a single BRK",
+                    read(0x16)
+                ));
+            }
+            if read(0x10) != 1 {
+                return Err("after RTI the cpu didn't continue after the BRK".into());
+            }
+            Ok(())
+        },
+    },
+    MicroTest {
+        name: "nmi",
+        #[rustfmt::skip]
+        program: &[
+            0xA2, 0xFF,         // 0200: LDX #$FF
+            0x9A,               // 0202: TXS
+            0x78,               // 0203: SEI         NMI isn't masked by the I flag
+            0xE6, 0x30,         // 0204: INC $30
+            0x4C, 0x04, 0x02,   // 0206: JMP $0204
+        ],
+        nmi_handler: NMI_RECORDER,
+        irq_handler: UNUSED_HANDLER,
+        nmi: true,
+        check: |read| {
+            if read(0x33) == 0 {
+                return Err("NMI didn't jump to the address in the NMI vector at $FFFA".into());
+            }
+            if read(0x33) != 1 {
+                return Err(format!(
+                    "the NMI handler ran {} times for a single NMI",
+                    read(0x33)
+                ));
+            }
+            if read(0x31) != 0xFC {
+                return Err(format!(
+                    "the stack pointer was ${:02X} in the NMI handler, an NMI should push 3 bytes: from $FF to $FC",
+                    read(0x31)
+                ));
+            }
+            if read(0x32) & 0x30 != 0x20 {
+                return Err(format!(
+                    "NMI pushed the status ${:02X}, it should push it with bit 5 set and the B flag clear",
+                    read(0x32)
+                ));
+            }
+            if read(0x34) & 0x04 == 0 {
+                return Err("NMI should set the interrupt disable flag".into());
+            }
+            // the harness copied the counter in $30 to $35 when it raised the NMI
+            if read(0x30) == read(0x35) {
+                return Err(
+                    "after RTI the cpu didn't continue where the NMI interrupted it".into(),
+                );
+            }
+            Ok(())
+        },
+    },
+];
@@ -0,0 +1,510 @@
+//! Running every test in a child process, so a cpu that crashes the whole process, like with a
+//! segfault in `unsafe` code or an abort, fails its test instead of ending the test run, see
+//! [`TestConfig::isolate`](crate::TestConfig::isolate).
+//!
+//! The child runs this executable again, with the id of the test to run in [`TEST_VAR`]. It sends its
+//! progress and outcome back over its stdout, a message per line, after [`PREFIX`]. The submissions of
+//! [`grade_submissions`](crate::grade_submissions) are other executables that run the same way, which
+//! first list their tests with [`LIST_VAR`].
+use crate::accesses::{MemoryAccesses, RegionAccesses};
+use crate::log_capture;
+use crate::log_target;
+use crate::report::{FinalState, Progress};
+use crate::watch::{BusAccess, WatchpointHit};
+use crate::{Failure, TestSelector};
+use std::io::{BufRead, BufReader, Write};
+use std::path::Path;
+use std::process::{Child, Command, ExitStatus, Stdio};
+use std::sync::mpsc::{self, RecvTimeoutError};
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// The environment variable that tells a child process which test to run
+const TEST_VAR: &str = "NESTEST_N_ISOLATED_TEST";
+/// The environment variable with the seed of the ram of the run, so the child fills it the same
+const SEED_VAR: &str = "NESTEST_N_ISOLATED_SEED";
+/// The environment variable that tells the child to trace the end of the test, when it runs again
+/// after it failed, see [`TestConfig::failure_trace`](crate::TestConfig::failure_trace)
+const TRACE_VAR: &str = "NESTEST_N_ISOLATED_TRACE";
+/// The environment variable that tells a child process to send the tests it has, instead of running one
+const LIST_VAR: &str = "NESTEST_N_ISOLATED_LIST";
+/// Starts the lines the child sends to the harness, its other lines are output of the cpu
+const PREFIX: &str = "@nestest-n\t";
+/// How much longer than [`TestConfig::timeout`](crate::TestConfig::timeout) the child may take,
+/// to start and to send the failure of a test that ran out of time itself, before it's killed
+const GRACE: Duration = Duration::from_secs(5);
+
+/// The id of the test to run when this process is a child process, the seed of the ram, and whether
+/// to trace the end of the test
+pub(crate) fn child_test() -> Option<(String, Option<u64>, bool)> {
+    let id = std::env::var(TEST_VAR).ok()?;
+    let seed = std::env::var(SEED_VAR).ok().and_then(|s| s.parse().ok());
+    let traced = std::env::var_os(TRACE_VAR).is_some();
+    Some((id, seed, traced))
+}
+
+/// Whether this process is a child process that only sends the tests it has
+pub(crate) fn child_lists_tests() -> bool {
+    std::env::var_os(LIST_VAR).is_some()
+}
+
+/// Runs the test `id`, called `name`, in a child process, and passes its progress to `on_progress`.
+/// The child runs `executable`, or this executable again when it's `None`. A child that crashed
+/// fails the test, and one that takes longer than `timeout` is killed.
+pub(crate) fn run(
+    executable: Option<&Path>,
+    id: &str,
+    name: &str,
+    seed: Option<u64>,
+    traced: bool,
+    timeout: Option<Duration>,
+    on_progress: &mut dyn FnMut(&Progress),
+) -> Result<(), Failure> {
+    let mut command = command(executable)?;
+    command.env(TEST_VAR, id);
+    if let Some(seed) = seed {
+        command.env(SEED_VAR, seed.to_string());
+    }
+    if traced {
+        command.env(TRACE_VAR, "1");
+    }
+    let mut child = Process::start(command)?;
+
+    let mut outcome = None;
+    let mut hits = Vec::new();
+    while let Some(message) = child.next_message(timeout).map_err(|()| {
+        format!(
+            "the process of test {name} didn't finish in {:?}, so it was killed, see TestConfig::timeout",
+            timeout.unwrap_or_default()
+        )
+    })? {
+        match parse(&message, &mut hits) {
+            Some(Message::Progress(progress)) => on_progress(&progress),
+            Some(Message::Outcome(result)) => outcome = Some(result),
+            Some(Message::Hit) => {}
+            Some(Message::Log(line)) => log_capture::push(line),
+            _ => log_target::warn!("the test process sent a message that isn't valid: {message}"),
+        }
+    }
+
+    let status = child.wait()?;
+    outcome.unwrap_or_else(|| Err(crashed(name, status).into()))
+}
+
+/// The tests the program at `executable` has, as their selector, name and id, in the order in which
+/// they run. It can take `timeout` to start.
+pub(crate) fn list(
+    executable: &Path,
+    timeout: Option<Duration>,
+) -> Result<Vec<(TestSelector, String, String)>, String> {
+    let mut command = command(Some(executable))?;
+    command.env(LIST_VAR, "1");
+    let mut child = Process::start(command)?;
+
+    let mut tests = Vec::new();
+    let mut hits = Vec::new();
+    while let Some(message) = child.next_message(timeout).map_err(|()| {
+        format!(
+            "{} didn't list its tests in {:?}, so it was killed",
+            executable.display(),
+            timeout.unwrap_or_default()
+        )
+    })? {
+        match parse(&message, &mut hits) {
+            Some(Message::Test(selector, name, id)) => tests.push((selector, name, id)),
+            _ => log_target::warn!("the test process sent a message that isn't valid: {message}"),
+        }
+    }
+
+    let status = child.wait()?;
+    if tests.is_empty() {
+        return Err(match status.code() {
+            Some(0) => format!(
+                "{} has no tests, it has to run them with run_tests_with_config or \
+                 run_tests_with_reporter",
+                executable.display()
+            ),
+            _ => crashed("listing", status),
+        });
+    }
+    Ok(tests)
+}
+
+/// A command that runs `executable` as a child process, or this executable again
+fn command(executable: Option<&Path>) -> Result<Command, String> {
+    let mut command = match executable {
+        Some(executable) => Command::new(executable),
+        None => {
+            let executable = std::env::current_exe().map_err(|e| {
+                format!("couldn't find the test executable to isolate the test: {e}")
+            })?;
+            let mut command = Command::new(executable);
+            command.args(child_args());
+            command
+        }
+    };
+    command.stdin(Stdio::null()).stdout(Stdio::piped());
+    Ok(command)
+}
+
+/// A child process, and the lines it prints
+struct Process {
+    child: Child,
+    lines: mpsc::Receiver<Vec<u8>>,
+    started: Instant,
+}
+
+impl Process {
+    fn start(mut command: Command) -> Result<Self, String> {
+        let mut child = command
+            .spawn()
+            .map_err(|e| format!("couldn't start a process for the test: {e}"))?;
+        let stdout = child
+            .stdout
+            .take()
+            .expect("the stdout of the child is piped");
+        // read on a thread of its own, so a child that hangs without printing can still be killed
+        let (lines, received) = mpsc::channel();
+        thread::spawn(move || {
+            // the cpu may print anything, also bytes that aren't utf-8
+            for line in BufReader::new(stdout).split(b'\n') {
+                let Ok(line) = line else {
+                    break;
+                };
+                if lines.send(line).is_err() {
+                    break;
+                }
+            }
+        });
+        Ok(Self {
+            child,
+            lines: received,
+            started: Instant::now(),
+        })
+    }
+
+    /// The next message the child sends the harness, or `None` once it's done. Its other lines are
+    /// logged. When it runs more than a little longer than `timeout`, it's killed.
+    fn next_message(&mut self, timeout: Option<Duration>) -> Result<Option<String>, ()> {
+        let deadline = timeout.map(|timeout| self.started + timeout + GRACE);
+        loop {
+            let line = match deadline {
+                Some(deadline) => self
+                    .lines
+                    .recv_timeout(deadline.saturating_duration_since(Instant::now())),
+                None => self
+                    .lines
+                    .recv()
+                    .map_err(|_| RecvTimeoutError::Disconnected),
+            };
+            let line = match line {
+                Ok(line) => line,
+                Err(RecvTimeoutError::Disconnected) => return Ok(None),
+                Err(RecvTimeoutError::Timeout) => {
+                    let _ = self.child.kill();
+                    let _ = self.child.wait();
+                    return Err(());
+                }
+            };
+            let line = String::from_utf8_lossy(&line);
+            match line.strip_prefix(PREFIX) {
+                Some(message) => return Ok(Some(message.to_string())),
+                None => log_target::info!("{line}"),
+            }
+        }
+    }
+
+    fn wait(mut self) -> Result<ExitStatus, String> {
+        self.child
+            .wait()
+            .map_err(|e| format!("couldn't wait for the process of the test: {e}"))
+    }
+}
+
+/// The arguments that make the child run the same test function. libtest runs a test on a thread
+/// with the name of the test, otherwise the child gets the arguments of this process.
+fn child_args() -> Vec<String> {
+    match std::thread::current().name() {
+        Some(name) if name != "main" => vec![
+            name.to_string(),
+            "--exact".to_string(),
+            "--nocapture".to_string(),
+            "--test-threads=1".to_string(),
+        ],
+        _ => std::env::args().skip(1).collect(),
+    }
+}
+
+/// Why a child process ended without the outcome of its test
+fn crashed(name: &str, status: ExitStatus) -> String {
+    #[cfg(unix)]
+    {
+        use std::os::unix::process::ExitStatusExt;
+        if let Some(signal) = status.signal() {
+            let signal_name = match signal {
+                4 => " (SIGILL)",
+                6 => " (SIGABRT)",
+                7 => " (SIGBUS)",
+                8 => " (SIGFPE)",
+                9 => " (SIGKILL)",
+                11 => " (SIGSEGV)",
+                _ => "",
+            };
+            return format!("the process of test {name} crashed with signal {signal}{signal_name}");
+        }
+    }
+    match status.code() {
+        Some(0) => format!(
+            "the process of test {name} ended without running it, it has to start the tests from \
+             the same #[test] or main function"
+        ),
+        Some(code) => format!("the process of test {name} crashed with exit code {code}"),
+        None => format!("the process of test {name} crashed"),
+    }
+}
+
+/// In the child: sends `progress` to the harness
+pub(crate) fn send_progress(progress: &Progress) {
+    let lines = match progress {
+        Progress::Status(text) => vec![format!("status\t{}", escape(text))],
+        Progress::Cycles { done, budget } => vec![format!("cycles\t{done}\t{budget}")],
+        Progress::SubTest {
+            name,
+            passed,
+            detail,
+        } => vec![format!(
+            "sub_test\t{}\t{passed}\t{}",
+            escape(name),
+            optional(detail.as_deref())
+        )],
+        Progress::Skipped(reason) => vec![format!("skipped\t{}", escape(reason))],
+        Progress::Finished(state) => {
+            let mut lines: Vec<String> = state
+                .watchpoint_hits
+                .iter()
+                .map(|hit| {
+                    format!(
+                        "hit\t{}\t{}\t{}\t{}\t{}",
+                        hit.access.address,
+                        hit.access.value,
+                        hit.access.write,
+                        hit.cycle,
+                        hit.program_counter
+                            .map_or("-".to_string(), |pc| pc.to_string())
+                    )
+                })
+                .collect();
+            let ram: String = state.ram.iter().map(|b| format!("{b:02x}")).collect();
+            let accesses = state.memory_accesses.map_or("-".to_string(), |accesses| {
+                accesses
+                    .regions()
+                    .iter()
+                    .map(|(_, region)| format!("{},{}", region.reads, region.writes))
+                    .collect::<Vec<_>>()
+                    .join(",")
+            });
+            lines.push(format!(
+                "finished\t{}\t{}\t{ram}\t{accesses}\t{}",
+                state.cycles,
+                escape(&state.status),
+                escape(&state.trace.join("\n"))
+            ));
+            lines
+        }
+    };
+    send(&lines);
+}
+
+/// In the child: sends the lines it captured, see [`TestConfig::capture_logs`](crate::TestConfig::capture_logs)
+pub(crate) fn send_logs(lines: &[String]) {
+    let lines: Vec<String> = lines
+        .iter()
+        .map(|line| format!("log\t{}", escape(line)))
+        .collect();
+    send(&lines);
+}
+
+/// In the child: sends the selector, name and id of every test in `tests` to the harness
+pub(crate) fn send_tests<'a>(tests: impl IntoIterator<Item = (TestSelector, &'a str, &'a str)>) {
+    let lines: Vec<String> = tests
+        .into_iter()
+        .map(|(selector, name, id)| {
+            format!(
+                "test\t{}\t{}\t{}",
+                selector.bits(),
+                escape(name),
+                escape(id)
+            )
+        })
+        .collect();
+    send(&lines);
+}
+
+/// In the child: sends the outcome of the test to the harness
+pub(crate) fn send_outcome(outcome: &Result<(), Failure>) {
+    send(&[match outcome {
+        Ok(()) => "passed".to_string(),
+        Err(e) => format!("failed\t{}\t{}", e.sub_tests, escape(&e.message)),
+    }]);
+}
+
+/// Writes to the stdout of the process itself, which libtest doesn't capture, unlike `println!`
+fn send(lines: &[String]) {
+    let mut out = std::io::stdout().lock();
+    for line in lines {
+        let _ = writeln!(out, "{PREFIX}{line}");
+    }
+    let _ = out.flush();
+}
+
+/// A message of the child
+enum Message {
+    Progress(Progress),
+    Outcome(Result<(), Failure>),
+    /// A watchpoint hit of the final state that follows it
+    Hit,
+    /// A line the child captured
+    Log(String),
+    /// A test the child has, with its selector, name and id
+    Test(TestSelector, String, String),
+}
+
+/// Parses a message of the child, and collects the watchpoint hits in `hits` until the final state
+fn parse(message: &str, hits: &mut Vec<WatchpointHit>) -> Option<Message> {
+    let fields: Vec<&str> = message.split('\t').collect();
+    let progress = match fields.as_slice() {
+        ["status", text] => Progress::Status(unescape(text)),
+        ["cycles", done, budget] => Progress::Cycles {
+            done: done.parse().ok()?,
+            budget: budget.parse().ok()?,
+        },
+        ["sub_test", name, passed, detail] => Progress::SubTest {
+            name: unescape(name),
+            passed: passed.parse().ok()?,
+            detail: detail.strip_prefix('+').map(unescape),
+        },
+        ["skipped", reason] => Progress::Skipped(unescape(reason)),
+        ["hit", address, value, write, cycle, pc] => {
+            hits.push(WatchpointHit {
+                access: BusAccess {
+                    address: address.parse().ok()?,
+                    value: value.parse().ok()?,
+                    write: write.parse().ok()?,
+                },
+                cycle: cycle.parse().ok()?,
+                program_counter: pc.parse().ok(),
+            });
+            return Some(Message::Hit);
+        }
+        ["finished", cycles, status, ram, accesses, trace] => {
+            let ram = (0..ram.len())
+                .step_by(2)
+                .map(|i| u8::from_str_radix(ram.get(i..i + 2)?, 16).ok())
+                .collect::<Option<_>>()?;
+            let trace = unescape(trace);
+            Progress::Finished(FinalState {
+                cycles: cycles.parse().ok()?,
+                status: unescape(status),
+                ram,
+                watchpoint_hits: std::mem::take(hits),
+                memory_accesses: match *accesses {
+                    "-" => None,
+                    counts => Some(parse_accesses(counts)?),
+                },
+                trace: match trace.as_str() {
+                    "" => Vec::new(),
+                    trace => trace.split('\n').map(str::to_string).collect(),
+                },
+            })
+        }
+        ["log", line] => return Some(Message::Log(unescape(line))),
+        ["test", selector, name, id] => {
+            let selector = TestSelector::from_bits(selector.parse().ok()?)?;
+            return Some(Message::Test(selector, unescape(name), unescape(id)));
+        }
+        ["passed"] => return Some(Message::Outcome(Ok(()))),
+        ["failed", sub_tests, e] => {
+            let failure = Failure {
+                message: unescape(e),
+                sub_tests: sub_tests.parse().ok()?,
+            };
+            return Some(Message::Outcome(Err(failure)));
+        }
+        _ => return None,
+    };
+    Some(Message::Progress(progress))
+}
+
+/// Parses the reads and writes of the regions of [`MemoryAccesses::regions`], in that order
+fn parse_accesses(counts: &str) -> Option<MemoryAccesses> {
+    let counts = counts
+        .split(',')
+        .map(|count| count.parse().ok())
+        .collect::<Option<Vec<u64>>>()?;
+    let [ram, ppu_registers, apu_registers, cartridge] = [0, 2, 4, 6].map(|i| RegionAccesses {
+        reads: counts.get(i).copied().unwrap_or_default(),
+        writes: counts.get(i + 1).copied().unwrap_or_default(),
+    });
+    (counts.len() == 8).then_some(MemoryAccesses {
+        ram,
+        ppu_registers,
+        apu_registers,
+        cartridge,
+    })
+}
+
+/// `text` as a field of a message: `+` and the text when there is one, `-` otherwise
+fn optional(text: Option<&str>) -> String {
+    text.map_or("-".to_string(), |text| format!("+{}", escape(text)))
+}
+
+/// `text` without tabs and newlines, which separate the fields and the messages
+fn escape(text: &str) -> String {
+    text.replace('\\', "\\\\")
+        .replace('\t', "\\t")
+        .replace('\n', "\\n")
+        .replace('\r', "\\r")
+}
+
+fn unescape(text: &str) -> String {
+    let mut unescaped = String::with_capacity(text.len());
+    let mut chars = text.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            unescaped.push(c);
+            continue;
+        }
+        match chars.next() {
+            Some('t') => unescaped.push('\t'),
+            Some('n') => unescaped.push('\n'),
+            Some('r') => unescaped.push('\r'),
+            Some(other) => unescaped.push(other),
+            None => {}
+        }
+    }
+    unescaped
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn outcomes_keep_whether_they_are_of_sub_tests() {
+        let mut hits = Vec::new();
+        let message = "failed\ttrue\texited with status 3:\\n 03-immediate";
+        let Some(Message::Outcome(Err(failure))) = parse(message, &mut hits) else {
+            panic!("the outcome wasn't parsed");
+        };
+        assert_eq!(
+            failure,
+            Failure::sub_tests("exited with status 3:\n 03-immediate".to_string())
+        );
+
+        let message = "failed\tfalse\tcpu didn't finish test nestest within the timeout of 1s";
+        let Some(Message::Outcome(Err(failure))) = parse(message, &mut hits) else {
+            panic!("the outcome wasn't parsed");
+        };
+        assert!(!failure.sub_tests);
+    }
+}
@@ -1,45 +1,351 @@
 //! # `tudelft-nes-test`
 //! This is a helper crate for your NES emulator to run various test ROMs
-use crate::all_instrs::{all_instrs_status_code, read_status_string};
+use crate::all_instrs::{
+    all_instrs_finished, all_instrs_status_code, reset_requested, sub_test_result,
+    without_unofficial, INSTR_GROUPS,
+};
 use bitflags::bitflags;
+use std::borrow::Cow;
+use std::cmp::Ordering;
+use std::collections::HashMap;
 use std::error::Error;
+use std::ops::Deref;
+use std::path::{Path, PathBuf};
+use std::sync::mpsc;
+use std::sync::mpsc::{RecvTimeoutError, Sender};
+use std::sync::Arc;
 use std::thread;
 use std::thread::JoinHandle;
+use std::time::{Duration, Instant};
 use thiserror::Error;
-use tudelft_nes_ppu::{run_cpu_headless_for, Cpu, Mirroring};
 
+mod accesses;
+mod adapter;
 mod all_instrs;
+mod artifacts;
+mod asynchronous;
+mod batch;
+mod bundled;
+mod cache;
+mod cancel;
+mod checkpoint;
+mod closures;
+mod config;
+mod console;
+mod csv;
+mod custom;
+mod download;
+mod events;
+mod executor;
+mod filter;
+mod fuzz;
+mod grading;
+mod halt;
+mod hints;
+mod ines;
+mod input;
+mod interrupts;
+mod isolation;
+mod localize;
+mod log_capture;
+mod log_target;
 mod nestest;
+mod panic;
+mod preflight;
+#[cfg(feature = "indicatif")]
+mod progress_bar;
+mod ram_init;
+mod ranking;
+#[cfg(feature = "selftest")]
+mod reference;
+mod region;
+mod report;
+mod reporter;
+mod rewind;
+mod rom_sets;
+mod runner;
+#[cfg(feature = "selftest")]
+mod selftest;
+#[cfg(feature = "serde")]
+mod serialize;
+mod sha256;
+mod single_step;
+mod status;
+mod step;
+mod summary;
+#[cfg(test)]
+mod temp_dir;
+mod trace;
+mod trace_diff;
+mod unstable;
+mod until;
+mod watch;
+mod window;
+mod wraparound;
 
+use crate::artifacts::WithArtifacts;
+use crate::bundled::BundledRom;
+use crate::cache::ResultCache;
+use crate::checkpoint::Checkpoint;
+use crate::closures::{ClosureCpu, Closures, CpuClosures};
+use crate::config::Budget;
+use crate::localize::Rom;
+use crate::log_capture::Capture;
+use crate::log_target::Target;
 use crate::nestest::nestest_status_code;
+use crate::rom_sets::{Protocol, RomSet};
+use crate::runner::{RunOptions, Runner};
+use crate::unstable::{UnstableTest, UNSTABLE_TESTS};
 
-/// Raw bytes for the all_instr rom
+pub use crate::accesses::{MemoryAccesses, RegionAccesses};
+pub use crate::asynchronous::{BlockingJob, RunEvent, TestRun};
+pub use crate::batch::{grade_submissions, run_submission, watch_submission};
+pub use crate::cancel::CancelToken;
+pub use crate::config::{ConfigError, CONFIG_FILE};
+pub use crate::console::{Console, TextReporter, Verbosity};
+pub use crate::csv::CsvReporter;
+pub use crate::custom::{CustomRom, Expectation, ExpectedMemory};
+pub use crate::events::JsonReporter;
+pub use crate::executor::{Executor, HeadlessExecutor, Machine};
+pub use crate::fuzz::fuzz_get_cpu;
+pub use crate::grading::{Grade, GradeItem, GradingProfile};
+pub use crate::input::{Buttons, Controller, InputScript, PRESS_FRAMES};
+pub use crate::log_capture::{install_logger, LogCapture};
+pub use crate::ram_init::RamInit;
+pub use crate::ranking::Ranking;
+pub use crate::region::Region;
+pub use crate::report::{FinalState, Progress, SubTestResult, TestReport, TestResult};
+pub use crate::reporter::Reporter;
+#[cfg(feature = "selftest")]
+pub use crate::selftest::{nestest_golden_log, self_test};
+pub use crate::single_step::{write_single_step_tests, CpuState, SingleStepCase};
+pub use crate::status::{
+    blargg_status, blargg_status_at, nestest_result, read_status_string, read_status_string_at,
+    BlarggStatus, StatusAddresses,
+};
+pub use crate::step::{Step, StepCallback};
+pub use crate::summary::SummaryReporter;
+pub use crate::trace::{Registers, TraceColumns, TraceFormat};
+pub use crate::unstable::UnstablePolicy;
+pub use crate::until::run_until_pc;
+pub use crate::watch::{Access, BusAccess, Watchpoint, WatchpointHit};
+/// The ppu the tests run your cpu on. Implement [`Cpu`] with the types of this crate, instead of with
+/// those of a dependency on `tudelft_nes_ppu` of your own: when that is another version, your cpu
+/// implements the `Cpu` of that version, which isn't the one [`TestableCpu`] needs.
+pub use tudelft_nes_ppu;
+pub use tudelft_nes_ppu::{Cpu, Mirroring, Ppu};
+
+/// Raw bytes for the all_instr rom, which end up uncompressed in your binary. Use [`rom_all_instr`]
+/// instead, which only takes the space of the compressed rom.
+#[deprecated(
+    since = "2.0.0",
+    note = "use rom_all_instr(), which is compressed in the binary"
+)]
 pub const ROM_ALL_INSTR: &[u8] = include_bytes!("roms/all_instrs.nes");
+/// Raw bytes for the official_only rom, which end up uncompressed in your binary. Use
+/// [`rom_official_only`] instead, which only takes the space of the compressed rom.
+#[deprecated(
+    since = "2.0.0",
+    note = "use rom_official_only(), which is compressed in the binary"
+)]
+pub const ROM_OFFICIAL_ONLY: &[u8] = include_bytes!("roms/official_only.nes");
 /// Raw bytes for the nestest rom
 pub const ROM_NESTEST: &[u8] = include_bytes!("roms/nestest.nes");
 /// Raw bytes for the nrom rom
 pub const ROM_NROM_TEST: &[u8] = include_bytes!("roms/nrom-test.nes");
-/// Raw bytes for the official_only rom
-pub const ROM_OFFICIAL_ONLY: &[u8] = include_bytes!("roms/official_only.nes");
+
+/// The all_instr rom, compressed by the build script
+pub(crate) static ALL_INSTR: BundledRom = BundledRom::compressed(include_bytes!(concat!(
+    env!("OUT_DIR"),
+    "/all_instrs.nes.lz"
+)));
+/// The official_only rom, compressed by the build script
+pub(crate) static OFFICIAL_ONLY: BundledRom = BundledRom::compressed(include_bytes!(concat!(
+    env!("OUT_DIR"),
+    "/official_only.nes.lz"
+)));
+
+/// Raw bytes for the all_instr rom, decompressed the first time it's used:
+/// ```
+/// let rom = tudelft_nes_test::rom_all_instr();
+/// assert_eq!(&rom[..4], b"NES\x1a");
+/// ```
+pub fn rom_all_instr() -> &'static [u8] {
+    &ALL_INSTR
+}
+
+/// Raw bytes for the official_only rom, decompressed the first time it's used
+pub fn rom_official_only() -> &'static [u8] {
+    &OFFICIAL_ONLY
+}
 
 /// Implement this trait to run our test on our CPU via the [`run_tests`] function.
+/// The [`testable_cpu!`] macro can implement it with the methods your CPU already has.
 pub trait TestableCpu: Cpu + Sized + 'static {
     /// This function is used by the test suite to get a handle on your CPU
     /// `rom` is a rom file in INES format.
-    fn get_cpu(rom: &[u8]) -> Result<Self, Box<dyn Error>>;
+    /// If your CPU can't run a rom because of its mapper, you can return an [`UnsupportedMapper`] error here.
+    /// Any other error that is `Send` and `Sync` converts to the `Box<dyn Error + Send + Sync>` with `?`,
+    /// like an `io::Error`, a `String` or an `anyhow::Error`, so you don't need an error type just for
+    /// loading roms. A `Box<dyn Error>` doesn't, it may not be `Send`; before version 2.0 this returned one.
+    fn get_cpu(rom: &[u8]) -> Result<Self, Box<dyn Error + Send + Sync>>;
 
-    /// [`set_program_counter`] is used to set the program counter of the cpu to a specific position
-    /// this is needed by some tests.
-    fn set_program_counter(&mut self, value: u16);
+    /// [`set_program_counter`] is used to set the program counter of the cpu to a specific position.
+    /// The tests don't need it anymore: the roms they run start where the tests want them to through
+    /// their reset vector, like nestest at $C000, so a CPU that starts at the address in the reset vector
+    /// doesn't have to implement it. It's still called for CPUs that implemented it before, and by default
+    /// it does nothing.
+    fn set_program_counter(&mut self, _value: u16) {}
 
     /// [`memory_read`] is used to test the succesfulness of tests by seeing if the CPU has expected values
     /// at certain memory locations, it simply takes an address and should return the byte of data at that memory location
     fn memory_read(&self, address: u16) -> u8;
+
+    /// `memory_peek` reads a byte like [`memory_read`](Self::memory_read), but without the side effects a
+    /// read has on the bus, like clearing the vblank flag at $2002, changing the open bus value or a latch
+    /// of the mapper. The harness reads memory with it whenever it looks at your cpu, like when it polls
+    /// the status of a test at $6000, so looking doesn't change what the cpu does. By default it calls
+    /// `memory_read`, which is fine when that doesn't have side effects.
+    fn memory_peek(&self, address: u16) -> u8 {
+        self.memory_read(address)
+    }
+
+    /// `supports_mapper` is asked before a test rom is loaded, so that a test using a mapper you haven't
+    /// implemented yet fails with a clear message instead of with whatever error [`get_cpu`](Self::get_cpu) returns.
+    /// By default, every mapper is assumed to be supported.
+    fn supports_mapper(_mapper: u8) -> bool {
+        true
+    }
+
+    /// `program_counter` lets the test suite see where your CPU is executing. When it's implemented,
+    /// a test fails early when your CPU gets stuck in a tight loop (for example on a jam opcode) and the
+    /// failure says where it got stuck. Returns `None` by default, which disables this check.
+    fn program_counter(&self) -> Option<u16> {
+        None
+    }
+
+    /// `memory_write` lets the test suite write a byte to memory, like your CPU would with a store
+    /// instruction. Tests without a test rom, like [`TestSelector::INTERRUPTS`], use it to put their
+    /// program in ram. Return `true` when you implemented it; by default it returns `false`, which
+    /// makes those tests fail with a message saying they need it.
+    fn memory_write(&mut self, _address: u16, _value: u8) -> bool {
+        false
+    }
+
+    /// `init_ram` fills the internal ram, $0000 to $07FF, with the 2KB of `ram` before a test runs, with
+    /// [`TestConfig::ram_init`]. By default it writes every byte with [`memory_write`](Self::memory_write),
+    /// so you only need it when your cpu fills its ram in another way. Return `false` when it can't;
+    /// the test then runs with the ram the cpu has.
+    fn init_ram(&mut self, ram: &[u8]) -> bool {
+        (0..)
+            .zip(ram)
+            .all(|(address, &value)| self.memory_write(address, value))
+    }
+
+    /// `reset` presses the reset button: your CPU should do what it does on a reset interrupt, like
+    /// jumping to the address in the reset vector at $FFFC, without clearing memory. Test roms that
+    /// check what survives a reset, like those of [`TestSelector::APU_RESET`], ask for it. Return `true`
+    /// when you implemented it; by default it returns `false`, which makes those tests fail with a
+    /// message saying they need it.
+    fn reset(&mut self) -> bool {
+        false
+    }
+
+    /// `supports_dma` says whether your CPU steals cycles for DMA: it stalls for 513 or 514 cycles when
+    /// sprite DMA is started by writing to $4014, and for a few cycles whenever the DMC of the apu fetches a
+    /// sample byte. The tests of [`TestSelector::DMC_DMA`] time this exactly, so they're skipped unless this
+    /// returns `true`. By default it returns `false`.
+    fn supports_dma() -> bool {
+        false
+    }
+
+    /// `set_buttons` sets which buttons are held on `controller`, 1 or 2, so they're returned by the next
+    /// reads of $4016 or $4017. Test roms that need input, like those of [`TestSelector::READ_JOY3`], press
+    /// buttons with it according to an [`InputScript`]. Return `true` when you implemented it; by default
+    /// it returns `false`, which makes those tests fail with a message saying they need it.
+    fn set_buttons(&mut self, _controller: u8, _buttons: Buttons) -> bool {
+        false
+    }
+
+    /// `set_region` tells your CPU which [`Region`] of NES the tests run on, as [`TestConfig::region`] says,
+    /// before it runs, so its apu can run its frame counter and DMC at the rates of that region. The timing
+    /// of the ppu is up to the [`Executor`]. By default it does nothing.
+    fn set_region(&mut self, _region: Region) {}
+
+    /// `save_state` returns the complete state of your CPU, including its memory and that of the mapper,
+    /// in any format [`load_state`](Self::load_state) understands. With [`TestConfig::checkpoint_dir`], the
+    /// test suite saves it after every sub-test that passes, so a rerun can continue after the last one
+    /// that passed. Returns `None` by default, which disables checkpoints.
+    fn save_state(&self) -> Option<Vec<u8>> {
+        None
+    }
+
+    /// `load_state` restores a state returned by [`save_state`](Self::save_state). Return `false` when
+    /// the state can't be loaded, for example because your CPU changed since it was saved; the test
+    /// then starts from the beginning. By default it returns `false`.
+    fn load_state(&mut self, _state: &[u8]) -> bool {
+        false
+    }
+
+    /// `bus_accesses` passes every read and write your CPU did on its bus since it was last called to
+    /// `on_access`, in the order in which they happened. The test suite calls it after every tick, and
+    /// records the accesses to the addresses of [`TestConfig::watchpoints`]. Return `true` when you
+    /// implemented it; by default it returns `false`, and then only the writes to ram can be watched,
+    /// by seeing its values change.
+    fn bus_accesses(&mut self, _on_access: &mut dyn FnMut(BusAccess)) -> bool {
+        false
+    }
+
+    /// `finished_instruction` says whether your CPU finished an instruction in the last tick, so
+    /// [`TestConfig::on_step`] is called once per instruction. Returns `None` by default, and then an
+    /// instruction is taken to be finished whenever the [`program_counter`](Self::program_counter) changes,
+    /// which is only right for a CPU that runs a whole instruction in a single tick.
+    fn finished_instruction(&self) -> Option<bool> {
+        None
+    }
+
+    /// `registers` returns the registers of your CPU, so a [`Step`] has them and trace logs made with
+    /// [`StepCallback::trace_log`] show them. Returns `None` by default, and then they're left out.
+    fn registers(&self) -> Option<Registers> {
+        None
+    }
+
+    /// `cycles_executed` returns the number of cycles your CPU ran since it was created, counted by
+    /// your CPU itself. When it's implemented, [`TestSelector::NESTEST`] checks that the automated tests
+    /// of nestest take as many cycles as in `nestest.log`, and fails with the difference when they don't.
+    /// Returns `None` by default, which disables this check.
+    fn cycles_executed(&self) -> Option<u64> {
+        None
+    }
+
+    /// `frame` returns the frame your ppu drew last, when your emulator has a ppu of its own: the
+    /// 256 × 240 colors of the NES palette, $00 to $3F, a byte per pixel and row by row. Test roms that
+    /// only draw their result are checked by the SHA-256 of a frame, in a suite with
+    /// `protocol = "frames"`, see [`TestConfig::suites`]. Returns `None` by default, which makes those
+    /// tests fail with a message saying they need it.
+    fn frame(&self) -> Option<Vec<u8>> {
+        None
+    }
 }
 
+/// Error you can return from [`TestableCpu::get_cpu`] when the rom uses a mapper your CPU doesn't implement.
+/// The test suite will then report which mapper the test requires.
+#[derive(Debug, Error)]
+#[error("mapper {0} is not supported")]
+pub struct UnsupportedMapper(pub u8);
+
 bitflags! {
-    /// Select which tests you want to run
-    pub struct TestSelector: u32 {
+    /// Select which tests you want to run, either by combining the flags or with the builder methods:
+    /// ```
+    /// use tudelft_nes_test::TestSelector;
+    ///
+    /// let tests = TestSelector::empty().nestest().instr_group(10);
+    /// assert_eq!(tests, TestSelector::NESTEST | TestSelector::INSTR_BRANCHES);
+    /// ```
+    /// There are more than 32 tests, so since version 2.0 the bits are a `u64` instead of a `u32`. Code
+    /// that calls [`bits`](Self::bits) or [`from_bits`](Self::from_bits) with a `u32` needs a `u64`
+    /// now, selecting tests with the flags and the builder methods works like it did.
+    pub struct TestSelector: u64 {
         /// `NESTEST` is a pretty much all inclusive test suite for a NES CPU. It was designed to test almost every combination of flags, instructions,
         /// and registers. Some of these tests are very difficult.
         /// More information about this test ROM can be found [here](https://github.com/christopherpow/nes-test-roms/blob/master/other/nestest.txt)
@@ -57,7 +363,161 @@ bitflags! {
         /// The source for this rom can be found [here](https://gitlab.ewi.tudelft.nl/software-fundamentals/nes-nrom-test/-/blob/main/src/init.s)
         const NROM_TEST       = 0b00001000;
 
-        /// This test selector runs all available tests
+        /// `INTERRUPTS` runs small programs testing BRK, RTI, NMI and the flags that are pushed on the stack.
+        /// They don't need a test rom, so they give quick feedback before trying the interrupt test roms,
+        /// but they do need [`TestableCpu::memory_write`] to put the programs in memory.
+        const INTERRUPTS      = 0b00010000;
+
+        /// `NES_INSTR_TEST` runs the newer nes_instr_test roms of blargg, which test some behaviour
+        /// instr_test-v5 doesn't, like the decimal flag on the NES: it can be set, but ADC and SBC ignore it.
+        /// Every rom is reported as a sub-test. These roms aren't bundled with this crate: put the
+        /// `nes_instr_test/rom_singles` directory in [`TestConfig::rom_dir`], or the roms themselves.
+        /// More information about these roms can be found [here](https://github.com/christopherpow/nes-test-roms/tree/master/nes_instr_test)
+        const NES_INSTR_TEST  = 1 << 5;
+
+        /// `VBL_NMI_TIMING` runs the older vbl_nmi_timing roms of blargg, from 2005. They test the
+        /// timing of the vblank flag and the NMI in smaller steps than ppu_vbl_nmi, so they help to find
+        /// out what's wrong when that suite fails completely. The roms need a ppu, and aren't bundled
+        /// with this crate: put the `vbl_nmi_timing` directory in [`TestConfig::rom_dir`], or the roms themselves.
+        /// More information about these roms can be found [here](https://github.com/christopherpow/nes-test-roms/tree/master/vbl_nmi_timing)
+        const VBL_NMI_TIMING  = 1 << 6;
+
+        /// `SPRITE_OVERFLOW` runs the sprite_overflow_tests roms of blargg, which test when the ppu sets
+        /// the sprite overflow flag, including its buggy sprite evaluation on real hardware. Like
+        /// [`VBL_NMI_TIMING`](Self::VBL_NMI_TIMING) they need a ppu, every rom is reported as a sub-test and the roms
+        /// aren't bundled: put the `sprite_overflow_tests` directory in [`TestConfig::rom_dir`], or the roms themselves.
+        /// More information about these roms can be found [here](https://github.com/christopherpow/nes-test-roms/tree/master/sprite_overflow_tests)
+        const SPRITE_OVERFLOW = 1 << 7;
+
+        /// `PPU_OPEN_BUS` runs the ppu_open_bus rom of blargg, which tests the values read from write-only
+        /// ppu registers and from the unused bits of the others, and how they decay over time.
+        /// Waiting for the decay takes many seconds of nes time, so this test has a large cycle budget of
+        /// 40M cycles; a [`TestConfig::timeout`] has to leave room for that. The rom isn't bundled:
+        /// put the `ppu_open_bus` directory in [`TestConfig::rom_dir`], or the rom itself.
+        /// More information about this rom can be found [here](https://github.com/christopherpow/nes-test-roms/tree/master/ppu_open_bus)
+        const PPU_OPEN_BUS    = 1 << 8;
+
+        /// `APU_RESET` runs roms of the apu_reset set of blargg, which test the state of the apu after
+        /// power on and after a reset. They press the reset button halfway, so they need [`TestableCpu::reset`].
+        /// The roms aren't bundled: put the `apu_reset` directory in [`TestConfig::rom_dir`], or the roms themselves.
+        /// More information about these roms can be found [here](https://github.com/christopherpow/nes-test-roms/tree/master/apu_reset)
+        const APU_RESET       = 1 << 9;
+
+        /// `DMC_DMA` runs the sprdma_and_dmc_dma and dmc_dma_during_read4 roms, which test how the cycles stolen
+        /// by sprite DMA and DMC DMA interact with each other and with reads of the cpu. This is an advanced
+        /// suite for cpus that implement DMA, see [`TestableCpu::supports_dma`]; for the others it's skipped.
+        /// The roms aren't bundled: put their directories in [`TestConfig::rom_dir`], or the roms themselves.
+        /// More information about these roms can be found [here](https://github.com/christopherpow/nes-test-roms/tree/master/dmc_dma_during_read4)
+        const DMC_DMA         = 1 << 10;
+
+        /// `READ_JOY3` runs the test_buttons rom of the read_joy3 set, which asks for every button of controller 1
+        /// to be pressed in turn. The harness presses them, so this needs [`TestableCpu::set_buttons`]; the
+        /// button presses can be changed with [`TestConfig::input_scripts`]. The rom isn't bundled: put the
+        /// `read_joy3` directory in [`TestConfig::rom_dir`], or the rom itself.
+        /// More information about this rom can be found [here](https://github.com/christopherpow/nes-test-roms/tree/master/read_joy3)
+        const READ_JOY3       = 1 << 11;
+
+        /// `SMOKE` is a quick check that finishes in well under a second, for a pre-commit hook or while working on
+        /// your cpu: it runs the start of nestest, and the bundled official_only rom until it finished `01-basics`.
+        /// Leave the full tests to CI, since this doesn't test most instructions.
+        const SMOKE           = 1 << 12;
+
+        /// `CUSTOM` runs your own test roms, those of [`TestConfig::custom_roms`] and of the suites of
+        /// [`TestConfig::suites`] that don't say otherwise. It isn't in [`DEFAULT`](Self::DEFAULT), so
+        /// select it next to the other tests, like `TestSelector::DEFAULT.custom()`.
+        const CUSTOM          = 1 << 13;
+
+        /// `NESTEST_MENU` runs nestest like on a console, from its reset vector, instead of jumping to the
+        /// automated tests at $C000 like `NESTEST` does. The harness presses Start in its menu to run the
+        /// tests of the official instructions, and Select and Start to run those of the unofficial ones,
+        /// so this needs [`TestableCpu::set_buttons`] but not [`TestableCpu::set_program_counter`]. The menu
+        /// waits for vblank and the NMI of the ppu, which `NESTEST` skips. The button presses can be
+        /// changed with [`TestConfig::input_scripts`].
+        const NESTEST_MENU    = 1 << 14;
+
+        /// `SCANLINE` runs the scanline rom of Quietust, an advanced ppu test of the timing of the scanlines:
+        /// it changes the scroll and the palette in the middle of scanlines, which only looks right when
+        /// the cpu and the ppu are in step to the cycle. The rom only draws its result on the screen, so
+        /// it can't be checked without a window: [`run_tests_with_window`] shows it, and the headless
+        /// tests skip it. The rom isn't bundled: put the `scanline` directory in [`TestConfig::rom_dir`],
+        /// or the rom itself.
+        /// More information about this rom can be found [here](https://github.com/christopherpow/nes-test-roms/tree/master/scanline)
+        const SCANLINE        = 1 << 15;
+
+        /// `BLARGG_PPU_TESTS` runs the blargg_ppu_tests of 2005, short tests of the memories of the ppu: reading
+        /// and writing palette ram, vram through $2006 and $2007 and sprite ram, the palette at power up, and
+        /// when the vblank flag is cleared. They catch the common mistakes in $2006 and $2007 before the longer
+        /// ppu tests. Like [`VBL_NMI_TIMING`](Self::VBL_NMI_TIMING) every rom is reported as a sub-test and the
+        /// roms aren't bundled: put the `blargg_ppu_tests_2005.09.15b` directory in [`TestConfig::rom_dir`],
+        /// or the roms themselves.
+        /// More information about these roms can be found [here](https://github.com/christopherpow/nes-test-roms/tree/master/blargg_ppu_tests_2005.09.15b)
+        const BLARGG_PPU_TESTS = 1 << 32;
+
+        /// `PREFLIGHT` checks your implementation of [`TestableCpu`] before the test roms run, with a
+        /// small rom of its own: that [`TestableCpu::get_cpu`] loads it, that [`TestableCpu::memory_read`]
+        /// and [`TestableCpu::memory_peek`] read its prg rom, that the cpu starts at the reset vector, and that [`TestableCpu::set_program_counter`]
+        /// makes it continue elsewhere. The optional methods are checked when you implemented them. A test rom
+        /// failing in a confusing way often turns out to be a broken adapter rather than a broken cpu, which
+        /// this says right away. It runs first, in a few milliseconds, when you select it: it isn't in
+        /// [`DEFAULT`](Self::DEFAULT), so the tests that already ran don't change.
+        const PREFLIGHT       = 1 << 33;
+
+        /// `NESTEST_RESET` runs the automated tests of nestest like `NESTEST`, but presses the reset button
+        /// with [`TestableCpu::reset`] at a random cycle while they run. The cpu has to jump to the reset
+        /// vector, which points at $C000, and pass all tests when they run again from the start. No test
+        /// rom resets in the middle of an instruction like this, so it catches a reset that leaves the cpu
+        /// halfway through the instruction it was running, or that doesn't go to the reset vector at all.
+        /// The cycle of the reset is in the message when it fails, and whether the cpu went to $C000 is only
+        /// seen with [`TestableCpu::program_counter`].
+        const NESTEST_RESET   = 1 << 34;
+
+        /// `WRAPAROUND` runs small programs testing where addresses wrap around: the stack pointer from
+        /// $00 to $FF and back, zero page indexing like `LDA $F5,X`, pointers at $FF of `($FF),Y` and
+        /// `($FE,X)`, and `JMP ($xxFF)`, which reads the high byte of the address from the start of the
+        /// same page. Like [`INTERRUPTS`](Self::INTERRUPTS) they don't need a test rom, so they catch
+        /// these mistakes in milliseconds, but they do need [`TestableCpu::memory_write`].
+        const WRAPAROUND      = 1 << 35;
+
+        /// The `INSTR_*` tests each run a single group of the instructions `ALL_INSTRS` tests,
+        /// so you can focus on one of them. They use the `rom_singles` of instr_test-v5, which aren't
+        /// bundled with this crate: put them in [`TestConfig::rom_dir`].
+        /// More information about these roms can be found [here](https://github.com/christopherpow/nes-test-roms/tree/master/instr_test-v5/rom_singles)
+        const INSTR_BASICS    = 1 << 16;
+        /// Runs `02-implied.nes`, see [`INSTR_BASICS`](Self::INSTR_BASICS)
+        const INSTR_IMPLIED   = 1 << 17;
+        /// Runs `03-immediate.nes`, see [`INSTR_BASICS`](Self::INSTR_BASICS)
+        const INSTR_IMMEDIATE = 1 << 18;
+        /// Runs `04-zero_page.nes`, see [`INSTR_BASICS`](Self::INSTR_BASICS)
+        const INSTR_ZERO_PAGE = 1 << 19;
+        /// Runs `05-zp_xy.nes`, see [`INSTR_BASICS`](Self::INSTR_BASICS)
+        const INSTR_ZP_XY     = 1 << 20;
+        /// Runs `06-absolute.nes`, see [`INSTR_BASICS`](Self::INSTR_BASICS)
+        const INSTR_ABSOLUTE  = 1 << 21;
+        /// Runs `07-abs_xy.nes`, see [`INSTR_BASICS`](Self::INSTR_BASICS)
+        const INSTR_ABS_XY    = 1 << 22;
+        /// Runs `08-ind_x.nes`, see [`INSTR_BASICS`](Self::INSTR_BASICS)
+        const INSTR_IND_X     = 1 << 23;
+        /// Runs `09-ind_y.nes`, see [`INSTR_BASICS`](Self::INSTR_BASICS)
+        const INSTR_IND_Y     = 1 << 24;
+        /// Runs `10-branches.nes`, see [`INSTR_BASICS`](Self::INSTR_BASICS)
+        const INSTR_BRANCHES  = 1 << 25;
+        /// Runs `11-stack.nes`, see [`INSTR_BASICS`](Self::INSTR_BASICS)
+        const INSTR_STACK     = 1 << 26;
+        /// Runs `12-jmp_jsr.nes`, see [`INSTR_BASICS`](Self::INSTR_BASICS)
+        const INSTR_JMP_JSR   = 1 << 27;
+        /// Runs `13-rts.nes`, see [`INSTR_BASICS`](Self::INSTR_BASICS)
+        const INSTR_RTS       = 1 << 28;
+        /// Runs `14-rti.nes`, see [`INSTR_BASICS`](Self::INSTR_BASICS)
+        const INSTR_RTI       = 1 << 29;
+        /// Runs `15-brk.nes`, see [`INSTR_BASICS`](Self::INSTR_BASICS)
+        const INSTR_BRK       = 1 << 30;
+        /// Runs `16-special.nes`, see [`INSTR_BASICS`](Self::INSTR_BASICS)
+        const INSTR_SPECIAL   = 1 << 31;
+
+        /// All of the `INSTR_*` groups
+        const INSTR_GROUPS    = 0xffff << 16;
+
+        /// This test selector runs the `NESTEST`, `ALL_INSTRS` and `NROM_TEST` tests
         const ALL             = Self::NESTEST.bits | Self::ALL_INSTRS.bits | Self::NROM_TEST.bits;
 
         /// This test selector runs a default selection of tests: `OFFICIAL_INSTRS` and `NROM_TEST`
@@ -65,176 +525,2875 @@ bitflags! {
     }
 }
 
+impl TestSelector {
+    /// Also selects [`NESTEST`](Self::NESTEST)
+    pub fn nestest(self) -> Self {
+        self | Self::NESTEST
+    }
+
+    /// Also selects [`ALL_INSTRS`](Self::ALL_INSTRS)
+    pub fn all_instrs(self) -> Self {
+        self | Self::ALL_INSTRS
+    }
+
+    /// Also selects [`OFFICIAL_INSTRS`](Self::OFFICIAL_INSTRS)
+    pub fn official_instrs(self) -> Self {
+        self | Self::OFFICIAL_INSTRS
+    }
+
+    /// Also selects [`NROM_TEST`](Self::NROM_TEST)
+    pub fn nrom_test(self) -> Self {
+        self | Self::NROM_TEST
+    }
+
+    /// Also selects [`INTERRUPTS`](Self::INTERRUPTS)
+    pub fn interrupts(self) -> Self {
+        self | Self::INTERRUPTS
+    }
+
+    /// Also selects [`WRAPAROUND`](Self::WRAPAROUND)
+    pub fn wraparound(self) -> Self {
+        self | Self::WRAPAROUND
+    }
+
+    /// Also selects [`NES_INSTR_TEST`](Self::NES_INSTR_TEST)
+    pub fn nes_instr_test(self) -> Self {
+        self | Self::NES_INSTR_TEST
+    }
+
+    /// Also selects [`VBL_NMI_TIMING`](Self::VBL_NMI_TIMING)
+    pub fn vbl_nmi_timing(self) -> Self {
+        self | Self::VBL_NMI_TIMING
+    }
+
+    /// Also selects [`SPRITE_OVERFLOW`](Self::SPRITE_OVERFLOW)
+    pub fn sprite_overflow(self) -> Self {
+        self | Self::SPRITE_OVERFLOW
+    }
+
+    /// Also selects [`PPU_OPEN_BUS`](Self::PPU_OPEN_BUS)
+    pub fn ppu_open_bus(self) -> Self {
+        self | Self::PPU_OPEN_BUS
+    }
+
+    /// Also selects [`APU_RESET`](Self::APU_RESET)
+    pub fn apu_reset(self) -> Self {
+        self | Self::APU_RESET
+    }
+
+    /// Also selects [`DMC_DMA`](Self::DMC_DMA)
+    pub fn dmc_dma(self) -> Self {
+        self | Self::DMC_DMA
+    }
+
+    /// Also selects [`READ_JOY3`](Self::READ_JOY3)
+    pub fn read_joy3(self) -> Self {
+        self | Self::READ_JOY3
+    }
+
+    /// Also selects [`SMOKE`](Self::SMOKE)
+    pub fn smoke(self) -> Self {
+        self | Self::SMOKE
+    }
+
+    /// Also selects [`NESTEST_MENU`](Self::NESTEST_MENU)
+    pub fn nestest_menu(self) -> Self {
+        self | Self::NESTEST_MENU
+    }
+
+    /// Also selects [`BLARGG_PPU_TESTS`](Self::BLARGG_PPU_TESTS)
+    pub fn blargg_ppu_tests(self) -> Self {
+        self | Self::BLARGG_PPU_TESTS
+    }
+
+    /// Also selects [`SCANLINE`](Self::SCANLINE)
+    pub fn scanline(self) -> Self {
+        self | Self::SCANLINE
+    }
+
+    /// Also selects [`CUSTOM`](Self::CUSTOM)
+    pub fn custom(self) -> Self {
+        self | Self::CUSTOM
+    }
+
+    /// Also selects [`PREFLIGHT`](Self::PREFLIGHT)
+    pub fn preflight(self) -> Self {
+        self | Self::PREFLIGHT
+    }
+
+    /// Also selects [`NESTEST_RESET`](Self::NESTEST_RESET)
+    pub fn nestest_reset(self) -> Self {
+        self | Self::NESTEST_RESET
+    }
+
+    /// Also selects a single instruction group, by the number of its rom: from 1 for
+    /// [`INSTR_BASICS`](Self::INSTR_BASICS) to 16 for [`INSTR_SPECIAL`](Self::INSTR_SPECIAL)
+    ///
+    /// # Panics
+    /// When there is no group with that number
+    pub fn instr_group(self, group: usize) -> Self {
+        match group.checked_sub(1).and_then(|i| INSTR_GROUPS.get(i)) {
+            Some(&(_, test)) => self | test,
+            None => panic!("there is no instruction group {group}, they go from 1 to 16"),
+        }
+    }
+}
+
 impl Default for TestSelector {
     fn default() -> Self {
         Self::DEFAULT
     }
 }
 
+bitflags! {
+    /// Categories of unofficial opcodes, to choose which ones `ALL_INSTRS` and the `INSTR_*` groups
+    /// test with [`TestConfig::unofficial_opcodes`]. The official opcodes are always tested.
+    pub struct UnofficialOpcodes: u8 {
+        /// The NOPs that skip 0, 1 or 2 operand bytes, sometimes called DOP and TOP
+        const NOPS      = 0b00000001;
+        /// LAX, which loads A and X, and SAX, which stores A & X
+        const LAX_SAX   = 0b00000010;
+        /// SLO, RLA, SRE, RRA, DCP and ISC, which combine a read-modify-write instruction with an ALU instruction
+        const RMW       = 0b00000100;
+        /// ANC, ALR, ARR, AXS and the unofficial SBC, which combine two instructions on an immediate operand
+        const IMMEDIATE = 0b00001000;
+        /// ATX, SYA and SXA, whose results depend on analog effects in a real NES, see
+        /// [`TestConfig::unstable_opcodes`] for how they're tested
+        const UNSTABLE  = 0b00010000;
+    }
+}
+
+/// By default, all unofficial opcodes are tested
+impl Default for UnofficialOpcodes {
+    fn default() -> Self {
+        Self::all()
+    }
+}
+
+/// Configures a test run started with [`run_tests_with_config`].
+/// Use [`TestConfig::load`] to read it from a configuration file and environment variables.
+#[derive(Debug, Clone, Default)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(default)
+)]
+pub struct TestConfig {
+    /// Which tests to run
+    pub selector: TestSelector,
+    /// How much [`run_tests_with_config`] prints to the console while the tests run
+    pub verbosity: Verbosity,
+    /// Names of sub-tests your cpu doesn't have to pass, like `"03-immediate"` for an assignment that
+    /// doesn't require unofficial opcodes. When only these fail, the test passes and is reported as an
+    /// expected failure. That's only when the rom ran to its end and reported them as failed: a test
+    /// that also timed out, panicked or got stuck still fails. Note that the test roms stop at the first
+    /// sub-test that fails, so the sub-tests after it don't run.
+    pub allowed_failures: Vec<String>,
+    /// Ids of the tests and sub-tests to run, instead of the ones of the [`selector`](Self::selector). A test's id
+    /// is its name in configuration files, like `"all_instrs"`, the name of its set of roms, like `"vbl_nmi_timing"`,
+    /// or the name of a custom rom, and a sub-test's id is that of its test, a `/` and its name, like
+    /// `"all_instrs/11-stack"`. In an id, `*` matches any text and `?` any single character, so `"instr_*"` runs all
+    /// instruction groups and `"*/03-*"` every sub-test that starts with `03-`.
+    ///
+    /// When only some sub-tests of a test are picked, the test still runs as a whole, since a rom runs all its
+    /// sub-tests, but its result only has the picked ones, and it passes when they passed. A rom stops at the first
+    /// sub-test that fails, so a picked sub-test may not run because of one before it. See [`run_tests_filtered`].
+    pub filters: Vec<String>,
+    /// The maximum number of cycles a test may run, for the tests that need a different budget
+    /// than the default
+    #[cfg_attr(feature = "serde", serde(with = "crate::serialize::per_test"))]
+    pub cycle_budgets: HashMap<TestSelector, u64>,
+    /// The maximum number of instructions a test may run on top of its cycle budget, for the tests that
+    /// need one. Unlike cycles, instructions don't depend on how accurately the cpu times them, and they
+    /// are easy to compare to a trace: the automated tests of nestest are about the 9000 lines of its log.
+    /// A test that runs out of them stops and fails like one that ran out of cycles. It needs
+    /// [`TestableCpu::finished_instruction`] or [`TestableCpu::program_counter`].
+    #[cfg_attr(feature = "serde", serde(with = "crate::serialize::per_test"))]
+    pub instruction_budgets: HashMap<TestSelector, u64>,
+    /// How long a test may run before it fails. A cpu that takes longer keeps running in the
+    /// background, since there is no way to stop it, unless the test runs in a child process with
+    /// [`isolate`](Self::isolate), which is killed when it takes a few seconds longer than this.
+    pub timeout: Option<Duration>,
+    /// A directory to load the test roms from, instead of using the ones bundled with this crate.
+    /// Roms that aren't in this directory are still taken from the bundled ones.
+    pub rom_dir: Option<PathBuf>,
+    /// A directory to download the roms of nes-test-roms into the first time a test needs them, when
+    /// they aren't in the [`rom_dir`](Self::rom_dir), so tests like `VBL_NMI_TIMING` run without
+    /// a copy of nes-test-roms. Every later run uses the roms in it. A rom of which the manifest of
+    /// its set knows the SHA-256 is checked against it, also when it was downloaded before. Of the
+    /// other roms the SHA-256 of the first download from nes-test-roms is pinned next to them, like
+    /// `scanline.nes.sha256`, and every later run checks them against that. It needs the `download`
+    /// feature.
+    pub download_dir: Option<PathBuf>,
+    /// Where [`download_dir`](Self::download_dir) downloads from instead of the nes-test-roms
+    /// repository on GitHub, a url with the same directories, like a copy on your own server. Only
+    /// roms of which the manifest knows the SHA-256 are downloaded from it, or ones that were pinned
+    /// from nes-test-roms before, since nothing says that another rom of a mirror is the right one.
+    pub download_mirror: Option<String>,
+    /// Runs every test twice and fails it when the runs end differently: with another result, after
+    /// another number of cycles or with other memory contents. This catches a cpu that depends on
+    /// something it shouldn't, like uninitialized memory, before it passes locally and fails elsewhere.
+    pub check_determinism: bool,
+    /// Runs `ALL_INSTRS` and `OFFICIAL_INSTRS` with the 16 single roms of instr_test-v5 in the
+    /// [`rom_dir`](Self::rom_dir) instead of with all_instrs.nes and official_only.nes, all at the same
+    /// time on cpus of their own, which takes a fraction of the time on a machine with many cores.
+    /// Every rom is a sub-test. A [`on_step`](Self::on_step) callback sees the steps of all roms mixed.
+    pub parallel_singles: bool,
+    /// Narrows a failure of `ALL_INSTRS`, `OFFICIAL_INSTRS` or an `INSTR_*` group down to the instruction
+    /// that fails, and adds it to the failure. The single rom of the group that failed runs again on its
+    /// own, when it's in the [`rom_dir`](Self::rom_dir), and then with half of the instructions it tests,
+    /// and half of that half, until one is left. The failure shows the first time the rom ran that
+    /// instruction: its operands, and the registers before and after it, which needs
+    /// [`TestableCpu::program_counter`] and [`TestableCpu::registers`]. A failure takes about a dozen
+    /// runs of the rom longer.
+    pub localize_failures: bool,
+    /// Leaves out the lines that start with `hint: ` from the message of a test that failed, which
+    /// say what bug in a cpu usually causes a failure like it. They're only added to the final
+    /// failure of a test, not to its [`TestResult::failed_attempts`].
+    pub no_hints: bool,
+    /// Runs every test in a child process, which runs the test executable again for only that test and
+    /// reports back over a pipe. A cpu that crashes the whole process, like with a segfault in `unsafe`
+    /// code, a stack overflow or an abort, then fails its test instead of ending the test run. The
+    /// tests have to be started from the same `#[test]` or `main` function, with the same configuration.
+    /// What the cpu prints in the child is logged at `info` under the target of its test.
+    pub isolate: bool,
+    /// Keeps the lines that are logged during a test in [`TestResult::logs`] instead of passing them to
+    /// the logger, so the lines of tests that run at the same time, like the `#[test]` functions of
+    /// cargo test, aren't mixed up. [`LogCapture::All`] keeps those of your cpu as well, up to the maximum
+    /// level of the `log` crate, which the harness doesn't change. For those it installs a logger when
+    /// there is none, use [`install_logger`] to keep your own.
+    ///
+    /// The harness logs under the target of the test that runs, and of the sub-test it's at, like
+    /// `tudelft_nes_test::official_instrs` and `tudelft_nes_test::all_instrs::group11`, and under
+    /// `tudelft_nes_test` outside of tests. So with `env_logger`,
+    /// `RUST_LOG=tudelft_nes_test=warn,tudelft_nes_test::all_instrs::group11=info` only shows the
+    /// progress of group 11 of instr_test-v5.
+    pub capture_logs: LogCapture,
+    /// What the internal ram holds before a test runs, the ram the cpu has by default. Random bytes or
+    /// `$FF` everywhere catch cpus and tests that only pass because ram starts out as zeros, see [`RamInit`].
+    /// It needs [`TestableCpu::memory_write`] or [`TestableCpu::init_ram`].
+    pub ram_init: RamInit,
+    /// The categories of unofficial opcodes that `ALL_INSTRS` and the `INSTR_*` groups test, all of them by default.
+    /// For example, `UnofficialOpcodes::all() - UnofficialOpcodes::UNSTABLE` leaves out the unstable ones.
+    pub unofficial_opcodes: UnofficialOpcodes,
+    /// How the unstable unofficial opcodes are tested: by the roms, which only pass the variant they
+    /// expect, by the harness, which passes every documented variant, or not at all, see [`UnstablePolicy`].
+    pub unstable_opcodes: UnstablePolicy,
+    /// The buttons to press during the tests that need input, instead of the ones the harness presses.
+    /// Use it when your copy of a test rom asks for other buttons, or at other times.
+    #[cfg_attr(feature = "serde", serde(with = "crate::serialize::per_test"))]
+    pub input_scripts: HashMap<TestSelector, InputScript>,
+    /// A directory to keep checkpoints in, if your cpu implements [`TestableCpu::save_state`]. When a test
+    /// with multiple sub-tests fails, the next run continues after the last sub-test that passed, instead of
+    /// running them all again. The checkpoint is removed once the test passes. Checkpoints aren't used
+    /// with [`check_determinism`](Self::check_determinism), which has to run the tests from the start.
+    pub checkpoint_dir: Option<PathBuf>,
+    /// A directory to write a text file to for every test, with everything the harness saw of it: the
+    /// result, the sub-tests, the attempts that failed, and the status text and watchpoint hits of every
+    /// time the cpu was started. CI can upload them, so you see exactly what the grader saw.
+    pub artifact_dir: Option<PathBuf>,
+    /// Test roms of your own, which only run when [`TestSelector::CUSTOM`] is selected
+    pub custom_roms: Vec<CustomRom>,
+    /// Manifests of suites of test roms of your own, which run like the sets of blargg's roms: every rom
+    /// of a suite is a sub-test. A suite is a `[[suite]]` table, which says what the roms are, how they
+    /// report their result, and how long they may run, so a new set of roms only takes a few lines:
+    /// ```toml
+    /// [[suite]]
+    /// name = "my_ppu_tests"
+    /// dir = "roms/ppu"                 # relative to the manifest
+    /// roms = ["01-vblank.nes", "02-sprite0.nes"]
+    /// protocol = "status"              # or "result_code" with result_address = "$00F8", or "visual"
+    ///                                  # or "frames" with frame = 600 and frame_sha256 of every rom
+    /// status_address = "$7000"         # where the roms report their status, $6000 by default
+    /// cycles = 20_000_000              # per rom, 10 million by default
+    /// instructions = 5_000_000         # on top of the cycles, unlimited by default
+    /// dma = false                      # whether the roms need TestableCpu::supports_dma
+    /// presses = ["60 start", "120 a+b"]  # buttons on controller 1, at these frames
+    /// test = "custom"                  # the test that selects it, custom by default
+    /// regions = ["ntsc"]               # the regions the roms work in, all of them by default
+    /// ```
+    /// The suites of nes-test-roms the harness knows about are defined the same way, in `src/rom_sets.toml`.
+    /// A manifest that can't be read shows up as a test that fails.
+    pub suites: Vec<PathBuf>,
+    /// A directory to remember the tests that passed in, so running the same build of your cpu again skips
+    /// them, with the same configuration and the same roms. A rom that changed runs the tests again, also
+    /// when it's at the same path. A skipped test is reported as passed, with the sub-tests it passed
+    /// before. Tests that failed, were skipped or were flaky always run again.
+    pub cache_dir: Option<PathBuf>,
+    /// What identifies the build of your cpu for [`cache_dir`](Self::cache_dir), like the hash of its git
+    /// commit and the changes on top of it. Without it, the cache uses the contents of the test executable,
+    /// which changes whenever your code or the tests change.
+    pub fingerprint: Option<String>,
+    /// Only runs this part of the selected tests, so CI can split them over multiple runners, see [`run_tests_sharded`]
+    pub shard: Option<Shard>,
+    /// How many times a test that failed is run again, 0 by default. A test passes when any of its attempts
+    /// passes, but the failed attempts are kept in [`TestResult::failed_attempts`], so a flaky cpu, for
+    /// example one with threads racing each other, still shows up in the report.
+    pub retries: usize,
+    /// Lets the tests that show their progress at $6004, like `ALL_INSTRS`, run past their cycle budget while
+    /// that text keeps changing. They fail once it didn't change for this many chunks of 200k cycles, after
+    /// the budget ran out. This keeps a correct but slow cpu from failing only because of the budget.
+    pub stall_chunks: Option<u64>,
+    /// Stops the tests that show their progress at $6004 once that text didn't change for this many chunks of
+    /// 200k cycles, within their cycle budget, so a cpu that stopped making progress fails right away and says
+    /// after which sub-test it stopped, instead of running out of the budget.
+    pub watchdog_chunks: Option<u64>,
+    /// Addresses of which the accesses are recorded while the tests run, like `Watchpoint::writes(0x6001)`.
+    /// The last hits before a test ended are in [`TestResult::watchpoint_hits`], and are shown when it fails,
+    /// which helps to find out which code wrote a wrong value. Without [`TestableCpu::bus_accesses`], only
+    /// the writes to ram are seen.
+    pub watchpoints: Vec<Watchpoint>,
+    /// Traces the end of the tests that fail, without tracing all of them, which is too slow and too
+    /// large. The state of the cpu is saved with [`TestableCpu::save_state`] every this many cycles,
+    /// and once a test is done, the cpu goes back to the last but one state it saved and runs to the
+    /// end again, with a line of trace for every instruction. A failed test keeps that trace in
+    /// [`TestResult::failure_trace`], so it has at least this many and at most twice as many cycles
+    /// of trace. 100,000 cycles is about 30,000 instructions. The ppu isn't saved and starts over,
+    /// and button presses and resets aren't replayed, so a trace of a rom that waits for them can
+    /// differ a little from the run it traces. It needs [`TestableCpu::load_state`] as well, and
+    /// [`TestableCpu::program_counter`].
+    pub failure_trace: Option<u64>,
+    /// A function that is called after every instruction of the cpu with what it looks like, see [`Step`].
+    /// When it returns an error the test stops and fails, so it can be used as a conditional breakpoint or to
+    /// compare the cpu to a trace. It needs [`TestableCpu::finished_instruction`] or [`TestableCpu::program_counter`].
+    #[cfg_attr(feature = "serde", serde(skip))]
+    pub on_step: Option<StepCallback>,
+    /// Runs the cpu in the tests in chunks of cycles of its own, instead of all at once with
+    /// [`HeadlessExecutor`], for an emulator that schedules its cpu itself, see [`Executor`]. The cpu
+    /// still runs on the ppu of `tudelft_nes_ppu`.
+    #[cfg_attr(feature = "serde", serde(skip))]
+    pub executor: Option<Arc<dyn Executor>>,
+    /// The nametable mirroring of the ppu, instead of the one in the header of the rom. The bundled roms don't
+    /// depend on it, but your own roms in [`rom_dir`](Self::rom_dir) may. The ppu doesn't have four-screen
+    /// mirroring, so a rom asking for it fails unless this says which mirroring to run it with.
+    pub mirroring: Option<NametableMirroring>,
+    /// The region of the NES the tests run on, NTSC by default. Sets of roms that only work on an NTSC NES,
+    /// like [`TestSelector::VBL_NMI_TIMING`], are skipped on the others, and the default cycle budgets of
+    /// the roms that wait for frames grow with the longer frames of the region. The ppu of
+    /// [`HeadlessExecutor`] is an NTSC one, so the sets that time the ppu, which list the regions
+    /// they work in, need an [`Executor`] of your own that supports the region, see
+    /// [`Executor::supports_region`]. The other tests only test the cpu, and run on any executor.
+    pub region: Region,
+    /// Makes the run pass when at least this percentage of the sub-tests passes, from 0 to 100, for
+    /// partial credit. Tests without sub-tests count as a single sub-test,
+    /// see [`TestReport::sub_test_pass_rate`].
+    pub pass_threshold: Option<f64>,
+    /// Stops the test run once it's cancelled: the running test fails, and the tests after it don't run
+    #[cfg_attr(feature = "serde", serde(skip))]
+    pub cancel: Option<CancelToken>,
+    /// The closures that [`run_tests_with`] creates the cpus of its tests with, which only it sets
+    #[doc(hidden)]
+    #[cfg_attr(feature = "serde", serde(skip))]
+    pub closures: Option<CpuClosures>,
+}
+
+/// How the ppu mirrors its nametables, see [`TestConfig::mirroring`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(rename_all = "lowercase")
+)]
+pub enum NametableMirroring {
+    /// The nametables at $2000 and $2400 are the same, like the ones at $2800 and $2C00, for vertical scrolling
+    Horizontal,
+    /// The nametables at $2000 and $2800 are the same, like the ones at $2400 and $2C00, for horizontal scrolling
+    Vertical,
+}
+
+impl From<NametableMirroring> for Mirroring {
+    fn from(mirroring: NametableMirroring) -> Self {
+        match mirroring {
+            NametableMirroring::Horizontal => Mirroring::Horizontal,
+            NametableMirroring::Vertical => Mirroring::Vertical,
+        }
+    }
+}
+
+/// One of `count` parts of the selected tests, numbered from 0, see [`run_tests_sharded`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Shard {
+    /// Which part this is, from 0 to `count - 1`
+    pub index: usize,
+    /// The number of parts the tests are split in
+    pub count: usize,
+}
+
+impl Shard {
+    /// Part `index` of `count` parts
+    ///
+    /// # Panics
+    /// When `index` isn't smaller than `count`
+    pub fn new(index: usize, count: usize) -> Self {
+        assert!(
+            index < count,
+            "shard {index} doesn't exist, with {count} shards they go from 0 to {}",
+            count.saturating_sub(1)
+        );
+        Self { index, count }
+    }
+
+    /// The tests of this shard: every `count`th test, starting at `index`, where every rom of a set
+    /// of roms counts as a test of its own. A set some of whose roms are in another shard only runs
+    /// the roms of this shard. The same selection is always split the same way, and every test and
+    /// rom ends up in exactly one shard.
+    fn select(&self, tests: Vec<Test>) -> Vec<Test> {
+        let mut selected = Vec::new();
+        let mut position = 0;
+        for mut test in tests {
+            match test.roms.take() {
+                Some(roms) if roms.count > 0 => {
+                    let mine: Vec<_> = (0..roms.count)
+                        .filter(|i| (position + i) % self.count == self.index)
+                        .collect();
+                    position += roms.count;
+                    if !mine.is_empty() {
+                        if mine.len() < roms.count {
+                            test.run = (roms.only)(mine);
+                        }
+                        selected.push(test);
+                    }
+                }
+                _ => {
+                    if position % self.count == self.index {
+                        selected.push(test);
+                    }
+                    position += 1;
+                }
+            }
+        }
+        selected
+    }
+}
+
 /// The main function of this crate, run this with your CPU as generic parameter and a [`TestSelector`] to run the tests
 pub fn run_tests<T: TestableCpu>(selector: TestSelector) -> Result<(), String> {
-    if selector.contains(TestSelector::NROM_TEST) {
-        nrom_test::<T>()?;
-    }
+    let config = TestConfig {
+        selector,
+        ..TestConfig::default()
+    };
 
-    if selector.contains(TestSelector::OFFICIAL_INSTRS) {
-        all_instrs::<T>(true)?;
+    for test in selected_tests::<T>(selector) {
+        (test.run)(&test.name, &config, &mut |_| {})?;
     }
 
-    if selector.contains(TestSelector::ALL_INSTRS) {
-        all_instrs::<T>(false)?;
+    Ok(())
+}
+
+/// Like [`run_tests`], but for a cpu that doesn't implement [`TestableCpu`], with closures doing what
+/// its required methods would: creating the cpu for a rom, reading its memory and setting its program
+/// counter. Handy for a quick experiment, or when your cpu is a type from another crate.
+/// It still has to implement [`Cpu`], which the ppu needs to run it.
+///
+/// ```no_run
+/// # use tudelft_nes_test::{run_tests_with, Cpu, Ppu, TestSelector};
+/// # struct Bus;
+/// # impl Bus { fn read(&self, _: u16) -> u8 { 0 } }
+/// # struct MyCpu { pc: u16, bus: Bus }
+/// # impl MyCpu { fn new(_: &[u8]) -> Result<Self, String> { todo!() } }
+/// # impl Cpu for MyCpu {
+/// #     fn tick(&mut self, _: &mut Ppu) -> Result<(), Box<dyn std::error::Error>> { Ok(()) }
+/// #     fn ppu_read_chr_rom(&self, _: u16) -> u8 { 0 }
+/// #     fn non_maskable_interrupt(&mut self) {}
+/// # }
+/// run_tests_with(
+///     |rom| MyCpu::new(rom),
+///     |cpu, address| cpu.bus.read(address),
+///     |cpu, pc| cpu.pc = pc,
+///     TestSelector::DEFAULT,
+/// )
+/// .unwrap();
+/// ```
+///
+pub fn run_tests_with<C, E>(
+    new: impl Fn(&[u8]) -> Result<C, E> + Send + Sync + 'static,
+    memory_read: impl Fn(&C, u16) -> u8 + Send + Sync + 'static,
+    set_program_counter: impl Fn(&mut C, u16) + Send + Sync + 'static,
+    selector: TestSelector,
+) -> Result<(), String>
+where
+    C: Cpu + 'static,
+    E: Into<Box<dyn Error + Send + Sync>>,
+{
+    let closures = Closures {
+        new: Box::new(move |rom| new(rom).map_err(Into::into)),
+        memory_read: Box::new(memory_read),
+        set_program_counter: Box::new(set_program_counter),
+    };
+    let config = TestConfig {
+        selector,
+        closures: Some(CpuClosures::new(closures)),
+        ..TestConfig::default()
+    };
+
+    for test in selected_tests::<ClosureCpu<C>>(selector) {
+        (test.run)(&test.name, &config, &mut |_| {})?;
     }
 
-    if selector.contains(TestSelector::NESTEST) {
-        nestest::<T>()?;
+    Ok(())
+}
+
+/// Like [`run_tests`], but only runs shard `shard_index` of `shard_count` shards of the selected tests.
+/// Run every shard, for example each on its own CI runner, to run all selected tests. The roms of a
+/// set like [`TestSelector::VBL_NMI_TIMING`] are split over the shards one by one, like the instruction
+/// groups and the singles of `INSTR_*`. [`TestSelector::ALL_INSTRS`] is a single rom, which always runs
+/// in one shard, select the `INSTR_*` groups instead to split it.
+///
+/// ```no_run
+/// # use tudelft_nes_test::{run_tests_sharded, TestSelector, TestableCpu};
+/// # fn test<MyCpu: TestableCpu>() {
+/// // on the second of three runners
+/// run_tests_sharded::<MyCpu>(TestSelector::ALL, 1, 3).unwrap();
+/// # }
+/// ```
+///
+/// # Panics
+/// When `shard_index` isn't smaller than `shard_count`
+pub fn run_tests_sharded<T: TestableCpu>(
+    selector: TestSelector,
+    shard_index: usize,
+    shard_count: usize,
+) -> Result<(), String> {
+    let shard = Shard::new(shard_index, shard_count);
+    let config = TestConfig {
+        selector,
+        shard: Some(shard),
+        ..TestConfig::default()
+    };
+
+    for test in shard.select(selected_tests::<T>(selector)) {
+        (test.run)(&test.name, &config, &mut |_| {})?;
     }
+
     Ok(())
 }
 
-/// Tests the emulator using "all_instrs.nes" or "official_only.nes":
-/// https://github.com/christopherpow/nes-test-roms/tree/master/instr_test-v5
-fn all_instrs<T: TestableCpu + 'static>(only_official: bool) -> Result<(), String> {
-    let (rom, limit) = if only_official {
-        (ROM_OFFICIAL_ONLY, 350)
-    } else {
-        (ROM_ALL_INSTR, 500)
+/// Like [`run_tests`], but runs the tests and sub-tests of which the id matches one of `patterns`,
+/// instead of those of a [`TestSelector`], and prints their results to the console like
+/// [`run_tests_with_config`]. See [`TestConfig::filters`] for the ids and patterns.
+///
+/// ```no_run
+/// # use tudelft_nes_test::{run_tests_filtered, TestableCpu};
+/// # fn test<MyCpu: TestableCpu>() {
+/// // only the stack instructions of all_instrs, and nestest
+/// run_tests_filtered::<MyCpu>(&["all_instrs/11-stack", "nestest"]).unwrap();
+/// # }
+/// ```
+///
+/// Use [`TestConfig::load`] instead to take the filters from the `NESTEST_N_FILTERS` environment
+/// variable, so they can be changed without recompiling. Returns an error when no test matches.
+pub fn run_tests_filtered<T: TestableCpu>(patterns: &[impl AsRef<str>]) -> Result<(), String> {
+    let config = TestConfig {
+        filters: patterns.iter().map(|p| p.as_ref().to_string()).collect(),
+        ..TestConfig::default()
     };
 
-    let handle = thread::spawn(move || {
-        // TODO: make initial program counter obsolete by modifying nestest
-        let mut cpu = T::get_cpu(rom).map_err(|i| TestError::Custom(i.to_string()))?;
-        let mut prev = String::new();
+    let report = run_tests_with_config::<T>(&config);
+    if report.results.is_empty() {
+        return Err(format!(
+            "no test has an id that matches {}",
+            config.filters.join(", ")
+        ));
+    }
+    report.into_result()
+}
 
-        for i in 0..limit {
-            if let Err(e1) = run_cpu_headless_for(&mut cpu, Mirroring::Horizontal, 200_000) {
-                if let Err(e2) = all_instrs_status_code(&cpu) {
-                    return Err(TestError::Custom(format!(
-                        "{e1}, possibly due to a test that didn't pass: '{e2}'"
-                    )));
-                } else {
-                    return Err(TestError::Custom(format!("{e1}")));
-                }
-            }
+/// Like [`run_tests`], but keeps running the other tests when one fails, prints the results to the
+/// console and returns the results of all tests in a [`TestReport`].
+pub fn run_tests_with_config<T: TestableCpu>(config: &TestConfig) -> TestReport {
+    run_tests_with_reporter::<T>(config, &mut TextReporter::stdout(config.verbosity))
+}
+
+/// Like [`run_tests_with_config`], but reports the results to `reporter` instead of to the console.
+/// For example, use a [`TextReporter`] to write the report to a file.
+pub fn run_tests_with_reporter<T: TestableCpu>(
+    config: &TestConfig,
+    reporter: &mut dyn Reporter,
+) -> TestReport {
+    // a child process of TestConfig::isolate only runs its test, and sends the harness that started it the results
+    if let Some((id, seed, traced)) = isolation::child_test() {
+        let tests = all_tests::<T>(config);
+        let mut config = config.clone();
+        if let Some(seed) = seed {
+            config.ram_init = RamInit::Random(Some(seed));
+        }
+        if !traced {
+            config.failure_trace = None;
+        }
+        let capture = Capture::start(config.capture_logs);
+        let entered = capture.as_ref().map(Capture::enter);
+        let _target = Target::of_test(&id).enter();
+        let outcome = match tests.iter().find(|test| test.id == id) {
+            Some(test) => (test.run)(&test.name, &config, &mut isolation::send_progress),
+            None => Err(format!("there is no test {id} to run in this process").into()),
+        };
+        drop(entered);
+        if let Some(capture) = capture {
+            isolation::send_logs(&capture.lines());
+        }
+        isolation::send_outcome(&outcome);
+        std::process::exit(0);
+    }
+    // a submission of grade_submissions first tells the harness which tests it has
+    if isolation::child_lists_tests() {
+        let tests = all_tests::<T>(config);
+        isolation::send_tests(
+            tests
+                .iter()
+                .map(|test| (test.selector, test.name.as_str(), test.id.as_str())),
+        );
+        std::process::exit(0);
+    }
 
-            let status = read_status_string(&cpu);
+    run_selected_tests(all_tests::<T>(config), config, reporter)
+}
 
-            if status.contains("Failed") {
-                break;
-            }
+/// Every test there is for `T`, the bundled ones, the custom roms and the suites of `config`
+fn all_tests<T: TestableCpu>(config: &TestConfig) -> Vec<Test> {
+    let mut tests = selected_tests::<T>(TestSelector::all());
+    tests.extend(custom_tests::<T>(&config.custom_roms));
+    tests.extend(suite_tests::<T>(&config.suites));
+    tests
+}
 
-            let status = status.split('\n').next().unwrap().trim().to_string();
-            if !status.is_empty() && status != prev {
-                log::info!("{:05}k cycles passed: {}", i * 200, status);
-            }
-            prev = status;
+/// Runs the tests of `tests` that [`TestConfig::selector`] or [`TestConfig::filters`] pick, and
+/// reports them to `reporter`
+fn run_selected_tests(
+    mut tests: Vec<Test>,
+    config: &TestConfig,
+    reporter: &mut dyn Reporter,
+) -> TestReport {
+    if config.filters.is_empty() {
+        tests.retain(|test| config.selector.contains(test.selector));
+    } else {
+        tests.retain(|test| filter::selects_test(&config.filters, &test.id));
+        if tests.is_empty() {
+            log_target::warn!(
+                "no test has an id that matches {}",
+                config.filters.join(", ")
+            );
+        }
+    }
+    if let Some(shard) = &config.shard {
+        tests = shard.select(tests);
+    }
+    // every test of the run fills its ram from the same seed, which the report has to reproduce it
+    let seeded;
+    let config = match config.ram_init.with_seed() {
+        init if init != config.ram_init => {
+            seeded = TestConfig {
+                ram_init: init,
+                ..config.clone()
+            };
+            &seeded
         }
+        _ => config,
+    };
+    let mut report = TestReport {
+        pass_threshold: config.pass_threshold,
+        ram_seed: match config.ram_init {
+            RamInit::Random(seed) => seed,
+            _ => None,
+        },
+        ..TestReport::default()
+    };
+    if let Some(seed) = report.ram_seed {
+        log_target::info!("filling the ram with random bytes from seed {seed}");
+    }
 
-        let result = run_cpu_headless_for(&mut cpu, Mirroring::Horizontal, 200_000);
+    let cancelled = || {
+        config
+            .cancel
+            .as_ref()
+            .is_some_and(CancelToken::is_cancelled)
+    };
 
-        match result {
-            Err(e1) => {
-                if let Err(e2) = all_instrs_status_code(&cpu) {
-                    Err(TestError::Custom(format!(
-                        "{e1}, possibly due to a test that didn't pass: '{e2}'"
-                    )))
-                } else {
-                    Err(TestError::Custom(format!("{e1}")))
-                }
+    let cache = ResultCache::of(config);
+    let mut reporter = WithArtifacts::new(reporter, config.artifact_dir.as_deref());
+
+    reporter.run_started(tests.len());
+    for test in tests {
+        if cancelled() {
+            break;
+        }
+        reporter.test_started(&test.name);
+        if let Some(cached) = cache.as_ref().and_then(|cache| cache.load(&test.name)) {
+            let result = TestResult {
+                test: test.selector,
+                name: test.name,
+                id: test.id,
+                outcome: Ok(()),
+                duration: Duration::ZERO,
+                sub_tests: cached.sub_tests,
+                expected_failures: cached.expected_failures,
+                skipped: None,
+                failed_attempts: Vec::new(),
+                watchpoint_hits: Vec::new(),
+                memory_accesses: None,
+                failure_trace: Vec::new(),
+                logs: Vec::new(),
+                cached: true,
+            };
+            reporter.test_finished(&result);
+            report.results.push(result);
+            continue;
+        }
+        let start = Instant::now();
+        let mut failed_attempts = Vec::new();
+        let mut attempt = run_attempt(&test, config, &mut reporter);
+        while let Err(e) = &attempt.outcome {
+            if failed_attempts.len() >= config.retries || cancelled() {
+                break;
             }
-            Ok(()) => all_instrs_status_code(&cpu),
+            log::warn!(
+                target: &Target::of_test(&test.id).name(),
+                "{} failed, trying again: {e}",
+                test.name
+            );
+            failed_attempts.push(e.clone());
+            attempt = run_attempt(&test, config, &mut reporter);
         }
-    });
 
-    process_handle(
-        &format!(
-            "all instructions{}",
-            if only_official {
-                " (official only)"
-            } else {
-                ""
-            }
-        ),
-        handle,
-    )
+        let outcome = match attempt.outcome {
+            Err(e) if !config.no_hints => Err(hints::with_hints(e, &attempt.sub_tests)),
+            outcome => outcome,
+        };
+        let result = TestResult {
+            test: test.selector,
+            name: test.name,
+            id: test.id,
+            outcome,
+            duration: start.elapsed(),
+            sub_tests: attempt.sub_tests,
+            expected_failures: attempt.expected_failures,
+            skipped: attempt.skipped,
+            failed_attempts,
+            watchpoint_hits: attempt.watchpoint_hits,
+            memory_accesses: attempt.memory_accesses,
+            failure_trace: attempt.failure_trace,
+            logs: attempt.logs,
+            cached: false,
+        };
+        if let Some(cache) = &cache {
+            cache.store(&result);
+        }
+        reporter.test_finished(&result);
+        report.results.push(result);
+    }
+    reporter.run_finished(&report);
+
+    report
 }
 
-/// Runs the nestest rom:
-/// https://github.com/christopherpow/nes-test-roms/blob/master/other/nestest.nes
-fn nestest<T: TestableCpu + 'static>() -> Result<(), String> {
-    let rom = ROM_NESTEST;
+/// Runs the first of the selected tests that has a rom to look at in a window, like your emulator
+/// would, so you can watch what the rom draws while it runs. Test roms like those of blargg show
+/// their progress on the screen, which is often the quickest way to see where things go wrong.
+/// It runs the rom of the first instruction group or set of roms that is selected, and of a set
+/// only the first rom, since the window can only show one.
+///
+/// The result of the rom is logged at `info`, or at `warn` when it failed, under the target of the
+/// crate once it is done, so install a logger like `env_logger` to see it. After that the cpu stops
+/// and the window keeps showing what the rom drew last, until you close it. There are no cycle
+/// budgets or timeouts.
+/// Like the window of the ppu, this has to be called on the main thread.
+///
+/// ```no_run
+/// # use tudelft_nes_test::{run_tests_with_window, TestConfig, TestSelector, TestableCpu};
+/// # fn test<MyCpu: TestableCpu>() {
+/// let config = TestConfig {
+///     selector: TestSelector::OFFICIAL_INSTRS,
+///     ..TestConfig::default()
+/// };
+/// run_tests_with_window::<MyCpu>(&config).unwrap();
+/// # }
+/// ```
+///
+/// Returns an error when none of the selected tests has a rom to show, or the rom can't be loaded.
+pub fn run_tests_with_window<T: TestableCpu>(config: &TestConfig) -> Result<(), String> {
+    window::run::<T>(config)
+}
 
-    let handle = thread::spawn(|| {
-        // TODO: make initial program counter obsolete by modifying nestest
-        let mut cpu = T::get_cpu(rom).map_err(|i| TestError::Custom(i.to_string()))?;
-        cpu.set_program_counter(0xC000);
-        let result = run_cpu_headless_for(&mut cpu, Mirroring::Horizontal, 1_000_000);
+/// Like [`run_tests_with_reporter`], but runs the tests on a thread of their own and returns right
+/// away, so async code like a grading service can await the results without blocking its executor.
+/// It works with any executor. Set [`TestConfig::timeout`] to limit how long each test may take.
+///
+/// ```no_run
+/// # use tudelft_nes_test::{run_tests_async, RunEvent, TestConfig, TestableCpu};
+/// # async fn grade<MyCpu: TestableCpu>() {
+/// let mut run = run_tests_async::<MyCpu>(TestConfig::default());
+/// while let Some(event) = run.next_event().await {
+///     if let RunEvent::TestFinished(result) = event {
+///         println!("{}: {}", result.name, if result.passed() { "ok" } else { "FAILED" });
+///     }
+/// }
+/// # }
+/// ```
+///
+/// Use [`TestRun::result`] to wait for the result of one test, [`TestRun::report`] to only wait for
+/// the results of all tests, and [`TestRun::cancel`] or [`TestRun::cancel_token`] to stop them.
+pub fn run_tests_async<T: TestableCpu>(config: TestConfig) -> TestRun {
+    TestRun::start::<T>(config, |job| {
+        std::thread::spawn(job);
+    })
+}
 
-        match result {
-            Err(e1) => {
-                if let Err(e2) =
-                    nestest_status_code(cpu.memory_read(0x0002), cpu.memory_read(0x0003))
-                {
-                    Err(TestError::Custom(format!(
-                        "{e1}, possibly due to a test that didn't pass: '{e2}'"
-                    )))
-                } else {
-                    Err(TestError::Custom(format!("{e1}")))
-                }
-            }
-            Ok(()) => nestest_status_code(cpu.memory_read(0x0002), cpu.memory_read(0x0003)),
+/// Like [`run_tests_async`], but `spawn` runs the tests, like the blocking pool of your executor
+/// instead of a thread of their own:
+///
+/// ```no_run
+/// # use tudelft_nes_test::{run_tests_async_on, TestConfig, TestableCpu};
+/// # mod tokio { pub mod task { pub fn spawn_blocking(f: impl FnOnce() + Send + 'static) { f() } } }
+/// # async fn grade<MyCpu: TestableCpu>() {
+/// let run = run_tests_async_on::<MyCpu>(TestConfig::default(), |job| {
+///     tokio::task::spawn_blocking(job);
+/// });
+/// let nestest = run.result("nestest");
+/// let stop = run.cancel_token();
+/// if nestest.await.is_some_and(|result| !result.passed()) {
+///     // the rest won't pass either
+///     stop.cancel();
+/// }
+/// let report = run.report().await;
+/// # }
+/// ```
+///
+/// A job that's dropped without running, like when the executor shuts down, ends the run without
+/// results.
+pub fn run_tests_async_on<T: TestableCpu>(
+    config: TestConfig,
+    spawn: impl FnOnce(BlockingJob),
+) -> TestRun {
+    TestRun::start::<T>(config, spawn)
+}
+
+/// What came out of running a test once
+struct Attempt {
+    outcome: Result<(), String>,
+    sub_tests: Vec<SubTestResult>,
+    expected_failures: Vec<String>,
+    skipped: Option<String>,
+    watchpoint_hits: Vec<WatchpointHit>,
+    memory_accesses: Option<MemoryAccesses>,
+    failure_trace: Vec<String>,
+    logs: Vec<String>,
+}
+
+/// Runs a test once, and returns its outcome with the trace of its end when it failed with
+/// [`TestConfig::failure_trace`]. Going back to trace the end takes as long as the end took, so only
+/// a test that failed runs again for it.
+fn run_once(
+    test: &Test,
+    config: &TestConfig,
+    on_progress: &mut dyn FnMut(&Progress),
+) -> (Result<(), Failure>, Vec<String>) {
+    let outcome = run_traced(test, config, false, on_progress);
+    if outcome.is_ok() || config.failure_trace.is_none() {
+        return (outcome, Vec::new());
+    }
+
+    // the progress of the second run was seen in the first, only its trace is new
+    let mut trace = Vec::new();
+    let _ = run_traced(test, config, true, &mut |progress| {
+        if let Progress::Finished(state) = progress {
+            // the last time the cpu ran is where a test fails
+            trace.clone_from(&state.trace);
         }
     });
+    (outcome, trace)
+}
 
-    process_handle("nestest", handle)
+/// Runs a test once, and traces the end of every time the cpu runs when `traced`
+fn run_traced(
+    test: &Test,
+    config: &TestConfig,
+    traced: bool,
+    on_progress: &mut dyn FnMut(&Progress),
+) -> Result<(), Failure> {
+    if config.isolate {
+        let seed = match config.ram_init {
+            RamInit::Random(seed) => seed,
+            _ => None,
+        };
+        isolation::run(
+            None,
+            &test.id,
+            &test.name,
+            seed,
+            traced,
+            config.timeout,
+            on_progress,
+        )
+    } else if traced || config.failure_trace.is_none() {
+        (test.run)(&test.name, config, on_progress)
+    } else {
+        let config = TestConfig {
+            failure_trace: None,
+            ..config.clone()
+        };
+        (test.run)(&test.name, &config, on_progress)
+    }
 }
 
-/// runs our own nrom test rom
-/// https://gitlab.ewi.tudelft.nl/software-fundamentals/nes-nrom-test
-fn nrom_test<T: TestableCpu + 'static>() -> Result<(), String> {
-    let rom = ROM_NROM_TEST;
+/// Runs a test once, or twice when checking determinism, and applies the filters and allowed failures
+fn run_attempt(test: &Test, config: &TestConfig, reporter: &mut dyn Reporter) -> Attempt {
+    // the sub-tests that aren't picked are left out when only some of them are
+    let picked = |name: &str| {
+        config.filters.is_empty()
+            || filter::matches_test(&config.filters, &test.id)
+            || filter::matches_sub_test(&config.filters, &test.id, name)
+    };
+    let mut sub_tests = Vec::new();
+    let mut unpicked_failed = false;
+    let mut final_state = None;
+    let mut skipped = None;
+    let mut watchpoint_hits = Vec::new();
+    let mut memory_accesses: Option<MemoryAccesses> = None;
+    let capture = Capture::start(config.capture_logs);
+    let entered = capture.as_ref().map(Capture::enter);
+    let _target = Target::of_test(&test.id).enter();
+    let (mut outcome, mut failure_trace) = run_once(test, config, &mut |progress| {
+        match progress {
+            Progress::SubTest { name, passed, .. } if !picked(name) => {
+                unpicked_failed |= !passed;
+                return;
+            }
+            Progress::SubTest {
+                name,
+                passed,
+                detail,
+            } => sub_tests.push(SubTestResult {
+                name: name.clone(),
+                passed: *passed,
+                detail: detail.clone(),
+            }),
+            Progress::Finished(state) => {
+                watchpoint_hits.extend_from_slice(&state.watchpoint_hits);
+                // a test can start the cpu more than once, like for every group of all_instrs
+                if let Some(accesses) = state.memory_accesses {
+                    *memory_accesses.get_or_insert_with(MemoryAccesses::default) += accesses;
+                }
+                final_state = Some(state.clone());
+            }
+            Progress::Skipped(reason) => skipped = Some(reason.clone()),
+            _ => {}
+        }
+        reporter.progress(&test.name, progress)
+    });
 
-    let handle = thread::spawn(|| {
-        let mut cpu = T::get_cpu(rom).map_err(|i| TestError::Custom(i.to_string()))?;
-        run_cpu_headless_for(&mut cpu, Mirroring::Horizontal, 10)
-            .map_err(|i| TestError::Custom(i.to_string()))?;
+    if config.check_determinism {
+        let mut second_state = None;
+        let second_outcome = run_traced(test, config, false, &mut |progress| {
+            if let Progress::Finished(state) = progress {
+                second_state = Some(state.clone());
+            }
+        });
 
-        if cpu.memory_read(0x42) != 0x43 {
-            Err(TestError::String(
-                "memory location 0x42 is wrong after executing nrom_test".to_owned(),
-            ))
-        } else if cpu.memory_read(0x43) != 0x6A {
-            Err(TestError::String(
-                "memory location 0x43 is wrong after executing nrom_test".to_owned(),
+        let difference = if outcome != second_outcome {
+            Some(format!(
+                "it {} the first time and {} the second time",
+                describe_outcome(&outcome),
+                describe_outcome(&second_outcome)
             ))
         } else {
-            Ok(())
+            final_state
+                .zip(second_state)
+                .and_then(|(first, second)| first.difference(&second))
+        };
+
+        if let Some(difference) = difference {
+            outcome = Err(format!(
+                "cpu isn't deterministic in test {}: {difference}",
+                test.name
+            )
+            .into());
         }
-    });
+    }
 
-    process_handle("nrom_test", handle)
-}
+    let only_sub_tests =
+        !config.filters.is_empty() && !filter::matches_test(&config.filters, &test.id);
+    if only_sub_tests {
+        outcome = only_picked(outcome, &test.name, &sub_tests, unpicked_failed);
+    }
 
-#[derive(Debug, Error)]
-enum TestError {
-    #[error("{0}")]
-    Custom(String),
-    #[error("{0}")]
-    String(String),
+    let (outcome, expected_failures) =
+        allow_failures(outcome, &sub_tests, &config.allowed_failures);
+
+    let outcome = outcome.map_err(|e| e.message);
+    if outcome.is_ok() {
+        failure_trace.clear();
+    }
+    drop(entered);
+
+    Attempt {
+        outcome,
+        sub_tests,
+        expected_failures,
+        skipped,
+        watchpoint_hits,
+        memory_accesses,
+        failure_trace,
+        logs: capture.map(|c| c.lines()).unwrap_or_default(),
+    }
 }
 
-fn process_handle(name: &str, handle: JoinHandle<Result<(), TestError>>) -> Result<(), String> {
-    match handle.join() {
-        // <- waits for the thread to complete or panic
-        Ok(Ok(_)) => {
-            log::info!("{name} finished succesfully");
+/// Passes a test of which only some sub-tests are picked when they all ran and passed, and it only
+/// failed because of sub-tests that aren't picked. A test that timed out, panicked or got stuck still
+/// fails, as does one of which none of the picked sub-tests ran.
+fn only_picked(
+    outcome: Result<(), Failure>,
+    name: &str,
+    picked: &[SubTestResult],
+    unpicked_failed: bool,
+) -> Result<(), Failure> {
+    match outcome {
+        _ if picked.is_empty() => Err(format!(
+            "none of the picked sub-tests of {name} ran{}",
+            outcome.err().map(|e| format!(": {e}")).unwrap_or_default()
+        )
+        .into()),
+        Err(failure) if failure.sub_tests && unpicked_failed && picked.iter().all(|s| s.passed) => {
             Ok(())
         }
-        Ok(Err(e)) => match e {
-            TestError::Custom(e) => Err(format!(
-                "cpu failed while running test {name} with custom error message {e}"
-            )),
-            TestError::String(e) => Err(format!("cpu didn't pass test {name}: '{e}'")),
+        outcome => outcome,
+    }
+}
+
+/// Passes a test that only failed because of sub-tests in `allowed_failures`, and returns those
+/// sub-tests. That's only when the test ran to its end and failed because of the sub-tests it
+/// reported as failed: a test that timed out, panicked or got stuck still fails, also when the
+/// sub-tests that failed are allowed to.
+fn allow_failures(
+    outcome: Result<(), Failure>,
+    sub_tests: &[SubTestResult],
+    allowed_failures: &[String],
+) -> (Result<(), Failure>, Vec<String>) {
+    let failed: Vec<_> = sub_tests
+        .iter()
+        .filter(|s| !s.passed)
+        .map(|s| s.name.clone())
+        .collect();
+    let allowed = !failed.is_empty() && failed.iter().all(|f| allowed_failures.contains(f));
+    match outcome {
+        Err(failure) if failure.sub_tests && allowed => (Ok(()), failed),
+        outcome => (outcome, Vec::new()),
+    }
+}
+
+fn describe_outcome(outcome: &Result<(), Failure>) -> String {
+    match outcome {
+        Ok(()) => "passed".to_string(),
+        Err(e) => format!("failed with '{e}'"),
+    }
+}
+
+/// Runs a test with the given name and configuration, passing its progress to the closure
+type TestFn = dyn Fn(&str, &TestConfig, &mut dyn FnMut(&Progress)) -> Result<(), Failure>;
+
+/// A test that can be selected with a [`TestSelector`]
+struct Test {
+    selector: TestSelector,
+    name: String,
+    /// The stable id by which [`TestConfig::filters`] picks the test
+    id: String,
+    run: Box<TestFn>,
+    /// The roms of a test of a set of roms, which [`Shard::select`] splits over the shards
+    roms: Option<SetRoms>,
+}
+
+/// The roms of a set, and how to run some of them only
+struct SetRoms {
+    count: usize,
+    /// The test that only runs the roms with these indices
+    only: Box<dyn Fn(Vec<usize>) -> Box<TestFn>>,
+}
+
+/// The test of a set of roms, which the shards split by rom
+fn rom_set_test<T: TestableCpu>(set: impl Deref<Target = RomSet> + Clone + 'static) -> Test {
+    let only = set.clone();
+    Test {
+        selector: set.selector,
+        name: set.name.clone(),
+        id: set.name.clone(),
+        roms: Some(SetRoms {
+            count: set.roms.len(),
+            only: Box::new(move |indices| {
+                let set = only.clone();
+                Box::new(move |name, config, on_progress| {
+                    rom_set::<T>(name, &set, Some(&indices), config, on_progress)
+                })
+            }),
+        }),
+        run: Box::new(move |name, config, on_progress| {
+            rom_set::<T>(name, &set, None, config, on_progress)
+        }),
+    }
+}
+
+/// The tests selected by `selector`, in the order in which they are run
+fn selected_tests<T: TestableCpu>(selector: TestSelector) -> Vec<Test> {
+    let mut tests = vec![
+        Test {
+            selector: TestSelector::PREFLIGHT,
+            name: "preflight".to_string(),
+            id: "preflight".to_string(),
+            run: Box::new(preflight::<T>),
+            roms: None,
+        },
+        Test {
+            selector: TestSelector::SMOKE,
+            name: "smoke".to_string(),
+            id: "smoke".to_string(),
+            run: Box::new(smoke::<T>),
+            roms: None,
         },
+        Test {
+            selector: TestSelector::NROM_TEST,
+            name: "nrom_test".to_string(),
+            id: "nrom_test".to_string(),
+            run: Box::new(nrom_test::<T>),
+            roms: None,
+        },
+        Test {
+            selector: TestSelector::OFFICIAL_INSTRS,
+            name: "all instructions (official only)".to_string(),
+            id: "official_instrs".to_string(),
+            run: Box::new(|name, config, on_progress| {
+                all_instrs::<T>(name, true, config, on_progress)
+            }),
+            roms: None,
+        },
+        Test {
+            selector: TestSelector::ALL_INSTRS,
+            name: "all instructions".to_string(),
+            id: "all_instrs".to_string(),
+            run: Box::new(|name, config, on_progress| {
+                all_instrs::<T>(name, false, config, on_progress)
+            }),
+            roms: None,
+        },
+    ];
+
+    for &(group, selector) in &INSTR_GROUPS {
+        tests.push(Test {
+            selector,
+            name: format!("instr_test {group}"),
+            id: config::test_name(selector).unwrap_or(group).to_string(),
+            run: Box::new(move |name, config, on_progress| {
+                instr_group::<T>(name, group, selector, config, on_progress)
+            }),
+            roms: None,
+        });
+    }
+
+    for set in rom_sets::bundled() {
+        tests.push(rom_set_test::<T>(set));
+    }
+
+    tests.push(Test {
+        selector: TestSelector::INTERRUPTS,
+        name: "interrupts".to_string(),
+        id: "interrupts".to_string(),
+        run: Box::new(|name, config, on_progress| {
+            let tests = interrupts::MICRO_TESTS;
+            micro_tests::<T>(
+                name,
+                tests,
+                true,
+                TestSelector::INTERRUPTS,
+                config,
+                on_progress,
+            )
+        }),
+        roms: None,
+    });
+
+    tests.push(Test {
+        selector: TestSelector::WRAPAROUND,
+        name: "wraparound".to_string(),
+        id: "wraparound".to_string(),
+        run: Box::new(|name, config, on_progress| {
+            let tests = wraparound::MICRO_TESTS;
+            micro_tests::<T>(
+                name,
+                tests,
+                false,
+                TestSelector::WRAPAROUND,
+                config,
+                on_progress,
+            )
+        }),
+        roms: None,
+    });
+
+    tests.push(Test {
+        selector: TestSelector::NESTEST,
+        name: "nestest".to_string(),
+        id: "nestest".to_string(),
+        run: Box::new(nestest::<T>),
+        roms: None,
+    });
+
+    tests.push(Test {
+        selector: TestSelector::NESTEST_RESET,
+        name: "nestest (reset)".to_string(),
+        id: "nestest_reset".to_string(),
+        run: Box::new(nestest_reset::<T>),
+        roms: None,
+    });
+
+    tests.push(Test {
+        selector: TestSelector::NESTEST_MENU,
+        name: "nestest (menu)".to_string(),
+        id: "nestest_menu".to_string(),
+        run: Box::new(nestest_menu::<T>),
+        roms: None,
+    });
+
+    tests
+        .into_iter()
+        .filter(|test| selector.contains(test.selector))
+        .collect()
+}
+
+/// Tests the emulator using "all_instrs.nes" or "official_only.nes":
+/// https://github.com/christopherpow/nes-test-roms/tree/master/instr_test-v5
+fn all_instrs<T: TestableCpu + 'static>(
+    name: &str,
+    only_official: bool,
+    config: &TestConfig,
+    on_progress: &mut dyn FnMut(&Progress),
+) -> Result<(), Failure> {
+    let result = if config.parallel_singles {
+        parallel_singles::<T>(name, only_official, config, on_progress)
+    } else {
+        let (rom, budget) = if only_official {
+            let rom = load_rom(config, "official_only.nes", &OFFICIAL_ONLY)?;
+            (
+                rom,
+                config.budget(TestSelector::OFFICIAL_INSTRS, 70_000_000),
+            )
+        } else {
+            let rom = load_rom(config, "all_instrs.nes", &ALL_INSTR)?;
+            let rom = without_unofficial(rom, config.rom_opcodes());
+            (rom, config.budget(TestSelector::ALL_INSTRS, 100_000_000))
+        };
+        let keep = if only_official {
+            UnofficialOpcodes::empty()
+        } else {
+            config.rom_opcodes()
+        };
+        let copy = config.localize_failures.then(|| rom.clone());
+        let mut group = None;
+        let _target = Target::current().map(|t| t.groups().enter());
+        let result = blargg_test::<T>(
+            name,
+            rom,
+            budget,
+            None,
+            StatusAddresses::default(),
+            config,
+            &mut |progress| {
+                group = localize::group_of(progress).or(group);
+                on_progress(progress);
+            },
+        );
+        match &copy {
+            Some(rom) => {
+                let rom = Rom::All(rom, budget);
+                localize::localized::<T>(result, name, group, rom, keep, config)
+            }
+            None => result,
+        }
+    };
+    if only_official {
+        return result;
+    }
+    let unstable = unstable_opcodes::<T>(name, None, config, on_progress);
+    result.and(unstable)
+}
+
+/// Tests a single group of instructions using one of the `rom_singles` of instr_test-v5, like
+/// "10-branches.nes", which has to be in the rom directory:
+/// https://github.com/christopherpow/nes-test-roms/tree/master/instr_test-v5/rom_singles
+fn instr_group<T: TestableCpu + 'static>(
+    name: &str,
+    group: &str,
+    selector: TestSelector,
+    config: &TestConfig,
+    on_progress: &mut dyn FnMut(&Progress),
+) -> Result<(), Failure> {
+    let rom = single_rom(name, group, config)?;
+    let rom = without_unofficial(Cow::Owned(rom), config.rom_opcodes());
+    let budget = config.budget(selector, 20_000_000);
+
+    let copy = config.localize_failures.then(|| rom.clone());
+    let result = blargg_test::<T>(
+        name,
+        rom,
+        budget,
+        None,
+        StatusAddresses::default(),
+        config,
+        on_progress,
+    );
+    let result = match &copy {
+        Some(rom) => {
+            let index = INSTR_GROUPS.iter().position(|&(g, _)| g == group);
+            let (rom, keep) = (Rom::Single(rom), config.rom_opcodes());
+            localize::localized::<T>(result, name, index, rom, keep, config)
+        }
+        None => result,
+    };
+    let unstable = unstable_opcodes::<T>(name, Some(group), config, on_progress);
+    result.and(unstable)
+}
+
+/// Reads the single rom of instr_test-v5 of `group` from the rom directory
+fn single_rom(name: &str, group: &str, config: &TestConfig) -> Result<Vec<u8>, String> {
+    let file_name = format!("{group}.nes");
+    match &config.rom_dir {
+        Some(dir) => std::fs::read(dir.join(&file_name))
+            .map_err(|e| format!("couldn't read rom {}: {e}", dir.join(&file_name).display())),
+        None => Err(format!(
+            "{name} needs {file_name} from instr_test-v5/rom_singles, set a rom directory to load it from"
+        )),
+    }
+}
+
+/// Runs the single roms of instr_test-v5 at the same time, each on a cpu of its own, instead of
+/// all_instrs.nes or official_only.nes, see [`TestConfig::parallel_singles`]. Every rom is a
+/// sub-test, and the roms run on as many threads as the machine has cores.
+fn parallel_singles<T: TestableCpu + 'static>(
+    name: &str,
+    only_official: bool,
+    config: &TestConfig,
+    on_progress: &mut dyn FnMut(&Progress),
+) -> Result<(), Failure> {
+    // official_only.nes is all_instrs.nes without the unofficial opcodes
+    let keep = if only_official {
+        UnofficialOpcodes::empty()
+    } else {
+        config.rom_opcodes()
+    };
+    let roms = INSTR_GROUPS
+        .iter()
+        .map(|&(group, selector)| {
+            let rom = without_unofficial(Cow::Owned(single_rom(name, group, config)?), keep);
+            Ok((group, rom, config.budget(selector, 20_000_000)))
+        })
+        .collect::<Result<Vec<_>, String>>()?;
+
+    let workers = thread::available_parallelism().map_or(1, |n| n.get().min(roms.len()));
+    let next = std::sync::atomic::AtomicUsize::new(0);
+    let (sender, receiver) = mpsc::channel();
+    let mut results: Vec<Option<Result<(), Failure>>> = vec![None; roms.len()];
+    let capture = Capture::current();
+    let target = Target::current();
+    thread::scope(|scope| {
+        for _ in 0..workers {
+            let (sender, roms, next, capture, target) =
+                (sender.clone(), &roms, &next, &capture, &target);
+            scope.spawn(move || {
+                let _entered = capture.as_ref().map(Capture::enter);
+                loop {
+                    let i = next.fetch_add(1, std::sync::atomic::Ordering::Relaxed);
+                    let Some((group, rom, budget)) = roms.get(i) else {
+                        break;
+                    };
+                    let _target = target
+                        .as_ref()
+                        .map(|t| t.sub_test(&log_target::group(i + 1)).enter());
+                    let progress = sender.clone();
+                    let result = blargg_test::<T>(
+                        &format!("instr_test {group}"),
+                        rom.clone(),
+                        *budget,
+                        None,
+                        StatusAddresses::default(),
+                        config,
+                        &mut |p| {
+                            let _ = progress.send((i, Err(p.clone())));
+                        },
+                    );
+                    let result = localize::localized::<T>(
+                        result,
+                        name,
+                        Some(i),
+                        Rom::Single(rom),
+                        keep,
+                        config,
+                    );
+                    let _ = sender.send((i, Ok(result)));
+                }
+            });
+        }
+        drop(sender);
+
+        // the roms report their own sub-tests, the names of the groups are used instead
+        for (i, message) in receiver {
+            match message {
+                Err(progress @ Progress::Finished(_)) => on_progress(&progress),
+                Err(_) => {}
+                Ok(result) => {
+                    on_progress(&Progress::SubTest {
+                        name: roms[i].0.to_string(),
+                        passed: result.is_ok(),
+                        detail: result.as_ref().err().map(ToString::to_string),
+                    });
+                    results[i] = Some(result);
+                }
+            }
+        }
+    });
+
+    let mut failures: Vec<Failure> = roms
+        .iter()
+        .zip(results)
+        .filter_map(|((group, ..), result)| match result {
+            Some(Ok(())) => None,
+            Some(Err(e)) => Some(e.prefixed(group)),
+            None => Some(format!("{group}: it didn't finish").into()),
+        })
+        .collect();
+    match failures.len() {
+        0 => Ok(()),
+        1 => Err(failures.remove(0)),
+        n => Err(Failure::joined(
+            format!("{n} of the {} single roms of {name} failed:", roms.len()),
+            &failures,
+        )),
+    }
+}
+
+/// Runs every rom of a [`RomSet`] from the rom directory, and reports each of them as a sub-test.
+/// The roms after one that failed still run, so the sub-tests show everything that failed.
+fn rom_set<T: TestableCpu + 'static>(
+    name: &str,
+    set: &RomSet,
+    only: Option<&[usize]>,
+    config: &TestConfig,
+    on_progress: &mut dyn FnMut(&Progress),
+) -> Result<(), Failure> {
+    if set.needs_dma && !T::supports_dma() {
+        on_progress(&Progress::Skipped(
+            "it needs a cpu that steals cycles for DMA, see TestableCpu::supports_dma".to_string(),
+        ));
+        return Ok(());
+    }
+    if !set.regions.contains(&config.region) {
+        on_progress(&Progress::Skipped(format!(
+            "its roms don't work on a {} NES, see TestConfig::region",
+            config.region
+        )));
+        return Ok(());
+    }
+    // a set that works in some regions only times the ppu, which has to run like the one of the region
+    let executor = config.executor.as_deref().unwrap_or(&HeadlessExecutor);
+    if set.regions.len() < Region::ALL.len() && !executor.supports_region(config.region) {
+        return Err(format!(
+            "its roms time the ppu of a {} NES, but the executor doesn't support it, see Executor::supports_region",
+            config.region
+        )
+        .into());
+    }
+    if let Protocol::Visual = set.protocol {
+        on_progress(&Progress::Skipped(
+            "it only shows its result on the screen, watch it with run_tests_with_window"
+                .to_string(),
+        ));
+        return Ok(());
+    }
+
+    let budget = config.budget(set.selector, set.cycles);
+    let budget = Budget {
+        instructions: budget.instructions.or(set.instructions),
+        ..budget
+    };
+    let input = match config.input_scripts.get(&set.selector) {
+        Some(script) => Some(script.clone()),
+        None => set.input.clone(),
+    };
+
+    let mut failures = Vec::new();
+    let mut ran = 0;
+    for (index, file_name) in set.roms.iter().enumerate() {
+        if only.is_some_and(|only| !only.contains(&index)) {
+            continue;
+        }
+        ran += 1;
+        let path = rom_path(name, set, file_name, config)?;
+        let rom = std::fs::read(&path)
+            .map_err(|e| format!("couldn't read rom {}: {e}", path.display()))?;
+        let rom_name = file_name.trim_end_matches(".nes");
+        let _target = Target::current().map(|t| t.sub_test(rom_name).enter());
+
+        let rom = Cow::Owned(rom);
+        let input = input.clone();
+        let result = match set.protocol {
+            // the roms report their own name as sub-test, the name of the file is used instead
+            Protocol::Status => {
+                let on_progress = &mut |progress: &Progress| {
+                    if !matches!(progress, Progress::SubTest { .. }) {
+                        on_progress(progress);
+                    }
+                };
+                blargg_test::<T>(
+                    name,
+                    rom,
+                    budget,
+                    input,
+                    set.status_addresses,
+                    config,
+                    on_progress,
+                )
+            }
+            Protocol::ResultCode(address) => {
+                result_code_test::<T>(name, rom, address, budget, input, config, on_progress)
+            }
+            Protocol::Frames(frame) => {
+                let expected = set.frame_sha256.get(file_name).map(String::as_str);
+                frame_test::<T>(
+                    name,
+                    rom,
+                    frame,
+                    expected,
+                    budget,
+                    input,
+                    config,
+                    on_progress,
+                )
+            }
+            Protocol::Visual => unreachable!("roms that only show their result are skipped"),
+        };
+        on_progress(&Progress::SubTest {
+            name: rom_name.to_string(),
+            passed: result.is_ok(),
+            detail: result.as_ref().err().map(ToString::to_string),
+        });
+
+        if let Err(e) = result {
+            failures.push(e.prefixed(rom_name));
+        }
+    }
+
+    match failures.len() {
+        0 => Ok(()),
+        1 => Err(failures.remove(0)),
+        n => Err(Failure::joined(
+            format!("{n} of the {ran} roms of {name} failed:"),
+            &failures,
+        )),
+    }
+}
+
+/// Where a rom of a set is: in the directory of the manifest of a suite of your own, or in the rom
+/// directory. A rom of nes-test-roms that isn't in the rom directory is downloaded when there is a
+/// [`TestConfig::download_dir`].
+fn rom_path(
+    name: &str,
+    set: &RomSet,
+    file_name: &str,
+    config: &TestConfig,
+) -> Result<PathBuf, String> {
+    if let Some(root) = &set.root {
+        return Ok(find_rom(root, &set.dir, file_name));
+    }
+    if let Some(rom_dir) = &config.rom_dir {
+        let path = find_rom(rom_dir, &set.dir, file_name);
+        if path.exists() || config.download_dir.is_none() {
+            return Ok(path);
+        }
+    }
+    match &config.download_dir {
+        Some(dir) => download::rom(
+            dir,
+            config.download_mirror.as_deref(),
+            &set.dir,
+            file_name,
+            set.sha256.get(file_name).map(String::as_str),
+        ),
+        None => Err(format!(
+            "{name} needs the roms in {} of nes-test-roms, set a rom directory to load them from, \
+             or a download directory to download them into",
+            set.dir
+        )),
+    }
+}
+
+/// Where a rom of a set is in the rom directory: in the directory it has in nes-test-roms when
+/// that exists, or else directly in the rom directory
+pub(crate) fn find_rom(rom_dir: &Path, set_dir: &str, file_name: &str) -> PathBuf {
+    let path = rom_dir.join(set_dir).join(file_name);
+    if path.exists() {
+        path
+    } else {
+        rom_dir.join(file_name)
+    }
+}
+
+/// Runs a test rom that reports its result at `at`, like the roms of blargg do at $6000
+fn blargg_test<T: TestableCpu + 'static>(
+    name: &str,
+    rom: Cow<'static, [u8]>,
+    budget: Budget,
+    input: Option<InputScript>,
+    at: StatusAddresses,
+    config: &TestConfig,
+    on_progress: &mut dyn FnMut(&Progress),
+) -> Result<(), Failure> {
+    let Budget {
+        cycles,
+        instructions,
+    } = budget;
+    let limit = cycles.div_ceil(200_000);
+    check_mapper::<T>(name, &rom)?;
+    check_mirroring(name, config, &rom)?;
+    let checkpoint = match &config.checkpoint_dir {
+        Some(dir) if !config.check_determinism => Some(Checkpoint::path(dir, name, &rom)),
+        _ => None,
+    };
+    let stall_chunks = config.stall_chunks;
+    let watchdog_chunks = config.watchdog_chunks;
+
+    let options = RunOptions::of(config, &rom)
+        .instruction_budget(instructions)
+        .status_addresses(at);
+    run_test(name, config.timeout, on_progress, move |progress| {
+        let mut runner = Runner::new(load_cpu::<T>(&options, &rom)?, &progress, &options);
+        if let Some(input) = &input {
+            runner = runner.with_input(input)?;
+        }
+
+        let mut passed = Vec::new();
+        if let Some(saved) = checkpoint.as_deref().and_then(Checkpoint::load) {
+            if runner.cpu.load_state(&saved.state) {
+                log_target::info!(
+                    "continuing after {} from a checkpoint",
+                    saved.passed.join(", ")
+                );
+                for name in &saved.passed {
+                    let _ = progress.send(Progress::SubTest {
+                        name: name.clone(),
+                        passed: true,
+                        detail: None,
+                    });
+                }
+                passed = saved.passed;
+            }
+        }
+
+        let mut prev = String::new();
+        let mut prev_sub_test = None;
+        // returns whether the status shows that a sub-test failed
+        let mut report_sub_test = |cpu: &T, status: &str| {
+            let sub_test = sub_test_result(status);
+            let failed = sub_test.as_ref().is_some_and(|s| !s.passed);
+            if sub_test.is_some() && sub_test != prev_sub_test {
+                if let Some(s) = unreported(sub_test.clone(), &passed) {
+                    if s.passed {
+                        passed.push(s.name.clone());
+                        save_checkpoint(checkpoint.as_deref(), cpu, &passed);
+                    }
+                    let _ = progress.send(Progress::SubTest {
+                        name: s.name,
+                        passed: s.passed,
+                        detail: s.detail,
+                    });
+                }
+                prev_sub_test = sub_test;
+            }
+            failed
+        };
+
+        // the chunk in which the status text last changed
+        let mut last_change = 0;
+        let mut prev_text = String::new();
+        for i in 0.. {
+            let progressing = stall_chunks.is_some_and(|n| i - last_change < n);
+            if i >= limit && !progressing {
+                break;
+            }
+
+            if let Err(e1) = runner.run_for(200_000) {
+                if let Err(e2) = all_instrs_status_code(&runner.cpu, &at) {
+                    return Err(TestError::Custom(format!(
+                        "{e1}, possibly due to a test that didn't pass: '{e2}'"
+                    )));
+                } else {
+                    return Err(TestError::Custom(e1));
+                }
+            }
+
+            let _ = progress.send(Progress::Cycles {
+                done: (i + 1) * 200_000,
+                budget: cycles.max((i + 1) * 200_000),
+            });
+
+            if runner.stuck() || all_instrs_finished(&runner.cpu, &at) {
+                break;
+            }
+
+            if reset_requested(&runner.cpu, &at) {
+                // the rom wants the button to be pressed after at least 100ms, this is about 110ms
+                runner.run_for(200_000).map_err(TestError::Custom)?;
+                if !runner.cpu.reset() {
+                    return Err(TestError::String(
+                        "this rom needs TestableCpu::reset to press the reset button".to_owned(),
+                    ));
+                }
+                log_target::info!("{:05}k cycles passed: pressed reset", (i + 1) * 200);
+                continue;
+            }
+
+            let status = read_status_string_at(&runner.cpu, &at);
+            if report_sub_test(&runner.cpu, &status) {
+                break;
+            }
+            if status != prev_text {
+                last_change = i;
+                prev_text.clone_from(&status);
+            }
+            if let Some(n) = watchdog_chunks.filter(|&n| i - last_change >= n) {
+                let stuck = match prev_text.lines().map(str::trim).rfind(|l| !l.is_empty()) {
+                    Some(last) => format!("stuck after '{last}'"),
+                    None => "stuck before the rom showed its status".to_owned(),
+                };
+                return Err(TestError::String(format!(
+                    "no progress for {n} chunks, {stuck}"
+                )));
+            }
+
+            let status = status.split('\n').next().unwrap().trim().to_string();
+            if !status.is_empty() && status != prev {
+                log_target::running(&status);
+                log_target::info!("{:05}k cycles passed: {}", i * 200, status);
+                let _ = progress.send(Progress::Status(status.clone()));
+            }
+            prev = status;
+        }
+
+        // when the rom stopped at a failure, give it some time to finish writing its status
+        let result = if all_instrs_finished(&runner.cpu, &at) {
+            Ok(())
+        } else {
+            runner.run_for(200_000)
+        };
+        report_sub_test(&runner.cpu, &read_status_string_at(&runner.cpu, &at));
+
+        if let (Some(path), Ok(())) = (&checkpoint, &result) {
+            if all_instrs_status_code(&runner.cpu, &at).is_ok() {
+                let _ = std::fs::remove_file(path);
+            }
+        }
+
+        match result {
+            Err(e1) => {
+                if let Err(e2) = all_instrs_status_code(&runner.cpu, &at) {
+                    Err(TestError::Custom(format!(
+                        "{e1}, possibly due to a test that didn't pass: '{e2}'"
+                    )))
+                } else {
+                    Err(TestError::Custom(e1))
+                }
+            }
+            Ok(()) => runner.explain(all_instrs_status_code(&runner.cpu, &at)),
+        }
+    })
+}
+
+/// Runs a test rom that stores a result code at `address` when it's done, 1 meaning that it passed.
+/// These roms don't say when they're done, so they run for all `cycles`, unless the cpu gets stuck
+/// in the loop at the end of the rom.
+fn result_code_test<T: TestableCpu + 'static>(
+    name: &str,
+    rom: Cow<'static, [u8]>,
+    address: u16,
+    budget: Budget,
+    input: Option<InputScript>,
+    config: &TestConfig,
+    on_progress: &mut dyn FnMut(&Progress),
+) -> Result<(), Failure> {
+    let Budget {
+        cycles,
+        instructions,
+    } = budget;
+    let limit = cycles.div_ceil(200_000);
+    check_mapper::<T>(name, &rom)?;
+    check_mirroring(name, config, &rom)?;
+
+    let options = RunOptions::of(config, &rom).instruction_budget(instructions);
+    run_test(name, config.timeout, on_progress, move |progress| {
+        let mut runner = Runner::new(load_cpu::<T>(&options, &rom)?, &progress, &options);
+        if let Some(input) = &input {
+            runner = runner.with_input(input)?;
+        }
+        for i in 0..limit {
+            runner.run_for(200_000).map_err(TestError::Custom)?;
+            let _ = progress.send(Progress::Cycles {
+                done: (i + 1) * 200_000,
+                budget: cycles,
+            });
+
+            if runner.stuck() {
+                break;
+            }
+        }
+
+        // these roms end in a loop, so being stuck only explains a rom that didn't finish
+        match runner.cpu.memory_peek(address) {
+            1 => Ok(()),
+            0 => runner.explain(Err(TestError::String(format!(
+                "the rom didn't store a result code at ${address:04X}"
+            )))),
+            code => Err(TestError::SubTests(format!(
+                "failed with result code {code}, the readme of the rom explains what it means"
+            ))),
+        }
+    })
+}
+
+/// Runs a test rom that only draws its result for `frame` frames, and compares the SHA-256 of the
+/// frame it drew last to `expected`
+#[allow(clippy::too_many_arguments)]
+fn frame_test<T: TestableCpu + 'static>(
+    name: &str,
+    rom: Cow<'static, [u8]>,
+    frame: u64,
+    expected: Option<&str>,
+    budget: Budget,
+    input: Option<InputScript>,
+    config: &TestConfig,
+    on_progress: &mut dyn FnMut(&Progress),
+) -> Result<(), Failure> {
+    let expected = expected
+        .ok_or_else(|| {
+            "the suite has no frame_sha256 of the rom to compare its frame with".to_string()
+        })?
+        .to_string();
+    check_mapper::<T>(name, &rom)?;
+    check_mirroring(name, config, &rom)?;
+
+    let cycles = frame * config.region.cycles_per_two_frames() / 2;
+    let options = RunOptions::of(config, &rom).instruction_budget(budget.instructions);
+    run_test(name, config.timeout, on_progress, move |progress| {
+        let mut runner = Runner::new(load_cpu::<T>(&options, &rom)?, &progress, &options);
+        if let Some(input) = &input {
+            runner = runner.with_input(input)?;
+        }
+        let mut done = 0;
+        // a rom that shows its result stops in a loop, which leaves the frame as it is
+        while done < cycles && !runner.stuck() {
+            let chunk = (cycles - done).min(200_000);
+            runner
+                .run_for(usize::try_from(chunk).unwrap_or(usize::MAX))
+                .map_err(TestError::Custom)?;
+            done += chunk;
+            let _ = progress.send(Progress::Cycles {
+                done,
+                budget: cycles,
+            });
+        }
+
+        let Some(pixels) = runner.cpu.frame() else {
+            return Err(TestError::String(
+                "this rom is checked by the frame it draws, which needs TestableCpu::frame"
+                    .to_owned(),
+            ));
+        };
+        if pixels.len() != 256 * 240 {
+            return Err(TestError::String(format!(
+                "TestableCpu::frame returned {} bytes, instead of the 61440 of 256 × 240 pixels",
+                pixels.len()
+            )));
+        }
+        let drawn = sha256::hex_digest(&pixels);
+        if drawn == expected {
+            Ok(())
+        } else {
+            Err(TestError::SubTests(format!(
+                "the frame it drew after {frame} frames has sha256 {drawn}, instead of {expected}"
+            )))
+        }
+    })
+}
+
+/// Saves the state of `cpu` after the sub-tests in `passed`, when it has a state to save
+fn save_checkpoint<T: TestableCpu>(path: Option<&Path>, cpu: &T, passed: &[String]) {
+    let (Some(path), Some(state)) = (path, cpu.save_state()) else {
+        return;
+    };
+
+    let checkpoint = Checkpoint {
+        passed: passed.to_vec(),
+        state,
+    };
+    if let Err(e) = checkpoint.save(path) {
+        log_target::warn!("couldn't save checkpoint {}: {e}", path.display());
+    }
+}
+
+/// The sub-test that `status` shows, when it wasn't reported yet. After a checkpoint the rom still
+/// shows the last sub-test that passed before it, which is in `passed` already.
+fn unreported(sub_test: Option<SubTestResult>, passed: &[String]) -> Option<SubTestResult> {
+    let reported = |s: &SubTestResult| s.passed && passed.contains(&s.name);
+    sub_test.filter(|s| !s.name.is_empty() && !reported(s))
+}
+
+/// Runs the nestest rom:
+/// https://github.com/christopherpow/nes-test-roms/blob/master/other/nestest.nes
+fn nestest<T: TestableCpu + 'static>(
+    name: &str,
+    config: &TestConfig,
+    on_progress: &mut dyn FnMut(&Progress),
+) -> Result<(), Failure> {
+    let rom = nestest_rom(config)?;
+    let cycles = config.cycle_budget(TestSelector::NESTEST, 1_000_000) as usize;
+    check_mapper::<T>(name, &rom)?;
+
+    let options = RunOptions::of(config, &rom)
+        .instruction_budget(config.instruction_budget(TestSelector::NESTEST));
+    run_test(name, config.timeout, on_progress, move |progress| {
+        let mut runner =
+            Runner::new(load_cpu::<T>(&options, &rom)?, &progress, &options).watch_pc(NESTEST_END);
+        // the rom already starts there, but cpus that don't reset on creation need it
+        runner.cpu.set_program_counter(0xC000);
+        let start = runner.cpu.cycles_executed();
+        if start.is_some() {
+            runner = runner.stop_at(NESTEST_END);
+        }
+        let result = runner.run_for(cycles);
+        let cpu = &runner.cpu;
+
+        match result {
+            Err(e1) => {
+                if let Err(e2) =
+                    nestest_status_code(cpu.memory_peek(0x0002), cpu.memory_peek(0x0003))
+                {
+                    Err(TestError::Custom(format!(
+                        "{e1}, possibly due to a test that didn't pass: '{e2}'"
+                    )))
+                } else {
+                    Err(TestError::Custom(e1))
+                }
+            }
+            Ok(()) => {
+                runner.explain(nestest_status_code(
+                    cpu.memory_peek(0x0002),
+                    cpu.memory_peek(0x0003),
+                ))?;
+                // the status is only written when a test fails, so it's fine as well when they didn't all run
+                if runner.out_of_instructions() && runner.visited() == Some(false) {
+                    return runner
+                        .explain(Err(TestError::String("the tests didn't finish".to_owned())));
+                }
+                match (start, cpu.cycles_executed()) {
+                    (Some(start), Some(end)) if runner.reached_stop() => {
+                        nestest_cycles(end.wrapping_sub(start)).map_err(TestError::String)
+                    }
+                    _ => Ok(()),
+                }
+            }
+        }
+    })
+}
+
+/// Runs the automated tests of nestest, and presses the reset button at a random cycle while they
+/// run, after which they have to run again from $C000 and pass
+fn nestest_reset<T: TestableCpu + 'static>(
+    name: &str,
+    config: &TestConfig,
+    on_progress: &mut dyn FnMut(&Progress),
+) -> Result<(), Failure> {
+    use std::collections::hash_map::RandomState;
+    use std::hash::{BuildHasher, Hasher};
+
+    let rom = nestest_rom(config)?;
+    let cycles = config.cycle_budget(TestSelector::NESTEST_RESET, 1_000_000) as usize;
+    check_mapper::<T>(name, &rom)?;
+    // somewhere in the tests, which take about 26k cycles, a hash with a random key is random enough
+    let reset_at = 100 + RandomState::new().build_hasher().finish() as usize % 25_000;
+
+    let options = RunOptions::of(config, &rom)
+        .instruction_budget(config.instruction_budget(TestSelector::NESTEST_RESET));
+    run_test(name, config.timeout, on_progress, move |progress| {
+        let mut runner = Runner::new(load_cpu::<T>(&options, &rom)?, &progress, &options)
+            .watch_pc(0xC000)
+            .stop_at(NESTEST_END);
+        runner.cpu.set_program_counter(0xC000);
+        runner.run_for(reset_at).map_err(TestError::Custom)?;
+        if runner.reached_stop() {
+            return Err(TestError::Custom(format!(
+                "the tests finished in fewer than {reset_at} cycles, before the reset"
+            )));
+        }
+        if !runner.reset() {
+            return Err(TestError::String(
+                "this test needs TestableCpu::reset to press the reset button".to_owned(),
+            ));
+        }
+
+        let during = |e: &dyn std::fmt::Display| format!("after a reset at cycle {reset_at}: {e}");
+        runner
+            .run_for(cycles)
+            .map_err(|e| TestError::Custom(during(&e)))?;
+        let cpu = &runner.cpu;
+        runner
+            .explain(nestest_status_code(
+                cpu.memory_peek(0x0002),
+                cpu.memory_peek(0x0003),
+            ))
+            .map_err(|e| TestError::Custom(during(&e)))?;
+
+        match runner.visited() {
+            Some(false) => Err(TestError::Custom(during(
+                &"the cpu didn't go to $C000, where the reset vector points",
+            ))),
+            Some(true) if !runner.reached_stop() => Err(TestError::Custom(during(&format!(
+                "the tests didn't get to their end at ${NESTEST_END:04X} in {cycles} cycles"
+            )))),
+            _ => Ok(()),
+        }
+    })
+}
+
+/// Runs nestest from its reset vector, pressing the buttons that run its tests in the menu
+fn nestest_menu<T: TestableCpu + 'static>(
+    name: &str,
+    config: &TestConfig,
+    on_progress: &mut dyn FnMut(&Progress),
+) -> Result<(), Failure> {
+    let rom = load_rom(config, "nestest.nes", ROM_NESTEST)?;
+    let cycles = config.cycle_budget(TestSelector::NESTEST_MENU, 10_000_000);
+    let input = match config.input_scripts.get(&TestSelector::NESTEST_MENU) {
+        Some(script) => script.clone(),
+        None => nestest_menu_input(),
+    };
+    check_mapper::<T>(name, &rom)?;
+
+    let options = RunOptions::of(config, &rom)
+        .instruction_budget(config.instruction_budget(TestSelector::NESTEST_MENU));
+    run_test(name, config.timeout, on_progress, move |progress| {
+        let mut runner = Runner::new(load_cpu::<T>(&options, &rom)?, &progress, &options)
+            .with_input(&input)?
+            .watch_pc(NESTEST_FIRST_TEST);
+        // the menu doesn't say when the tests are done, so the rom runs for all cycles
+        for i in 0..cycles.div_ceil(200_000) {
+            runner.run_for(200_000).map_err(TestError::Custom)?;
+            let _ = progress.send(Progress::Cycles {
+                done: (i + 1) * 200_000,
+                budget: cycles,
+            });
+
+            if runner.stuck() {
+                break;
+            }
+        }
+
+        // the result codes are 0 before the tests ran as well
+        if runner.visited() == Some(false) {
+            return runner.explain(Err(TestError::String(
+                "the tests in the menu of nestest didn't run, did the rom see the buttons that were pressed?"
+                    .to_owned(),
+            )));
+        }
+
+        let cpu = &runner.cpu;
+        runner.explain(nestest_status_code(
+            cpu.memory_peek(0x0002),
+            cpu.memory_peek(0x0003),
+        ))
+    })
+}
+
+/// Presses Start in the menu of nestest to run the tests of the official instructions, and then Select
+/// to go to the page of the unofficial ones and Start to run those, a few seconds apart
+fn nestest_menu_input() -> InputScript {
+    InputScript::new()
+        .press(60, Buttons::START)
+        .press(180, Buttons::SELECT)
+        .press(240, Buttons::START)
+}
+
+/// nestest with its reset vector pointing to the automated tests at $C000, instead of to its menu
+fn nestest_rom(config: &TestConfig) -> Result<Cow<'static, [u8]>, String> {
+    let rom = load_rom(config, "nestest.nes", ROM_NESTEST)?;
+    Ok(Cow::Owned(ines::with_reset_vector(&rom, 0xC000)))
+}
+
+/// The first test of nestest, of the branch instructions, which both the menu and $C000 run
+const NESTEST_FIRST_TEST: u16 = 0xC72D;
+/// Where the automated tests of nestest end, at the `RTS` of the last line of `nestest.log`
+const NESTEST_END: u16 = 0xC66E;
+/// The cycles from $C000 to [`NESTEST_END`] in `nestest.log`, which starts at cycle 7 and ends at 26554
+const NESTEST_LOG_CYCLES: u64 = 26_554 - 7;
+
+/// Compares the cycles the cpu says it took for the automated tests of nestest with those of `nestest.log`
+fn nestest_cycles(cycles: u64) -> Result<(), String> {
+    match cycles.cmp(&NESTEST_LOG_CYCLES) {
+        Ordering::Equal => Ok(()),
+        Ordering::Less => Err(format!(
+            "the tests passed in {cycles} cycles, {} fewer than the {NESTEST_LOG_CYCLES} of nestest.log",
+            NESTEST_LOG_CYCLES - cycles
+        )),
+        Ordering::Greater => Err(format!(
+            "the tests passed in {cycles} cycles, {} more than the {NESTEST_LOG_CYCLES} of nestest.log",
+            cycles - NESTEST_LOG_CYCLES
+        )),
+    }
+}
+
+/// Runs the first instructions of nestest, and official_only until it finished its first sub-test,
+/// which tests the basics of the instructions the shell of the rom needs
+fn smoke<T: TestableCpu + 'static>(
+    name: &str,
+    config: &TestConfig,
+    on_progress: &mut dyn FnMut(&Progress),
+) -> Result<(), Failure> {
+    /// enough for the branch and flag tests of nestest, a few hundred instructions
+    const NESTEST_CYCLES: usize = 2_000;
+
+    let nestest = nestest_rom(config)?;
+    let official_only = load_rom(config, "official_only.nes", &OFFICIAL_ONLY)?;
+    let cycles = config.cycle_budget(TestSelector::SMOKE, 10_000_000);
+    check_mapper::<T>(name, &nestest)?;
+    check_mapper::<T>(name, &official_only)?;
+
+    let nestest_options = RunOptions::of(config, &nestest);
+    let options = RunOptions::of(config, &official_only)
+        .instruction_budget(config.instruction_budget(TestSelector::SMOKE));
+    let at = StatusAddresses::default();
+    run_test(name, config.timeout, on_progress, move |progress| {
+        let mut runner = Runner::new(
+            load_cpu::<T>(&nestest_options, &nestest)?,
+            &progress,
+            &nestest_options,
+        );
+        runner.cpu.set_program_counter(0xC000);
+        runner.run_for(NESTEST_CYCLES).map_err(TestError::Custom)?;
+        let cpu = &runner.cpu;
+        let result = runner.explain(nestest_status_code(
+            cpu.memory_peek(0x0002),
+            cpu.memory_peek(0x0003),
+        ));
+        let _ = progress.send(Progress::SubTest {
+            name: "nestest".to_string(),
+            passed: result.is_ok(),
+            detail: result.as_ref().err().map(ToString::to_string),
+        });
+        result.map_err(|e| TestError::SubTests(e.to_string()))?;
+        drop(runner);
+
+        let mut runner = Runner::new(
+            load_cpu::<T>(&options, &official_only)?,
+            &progress,
+            &options,
+        );
+        for _ in 0..cycles.div_ceil(200_000) {
+            runner.run_for(200_000).map_err(TestError::Custom)?;
+            if runner.stuck() || all_instrs_finished(&runner.cpu, &at) {
+                break;
+            }
+
+            if let Some(sub_test) = sub_test_result(&read_status_string_at(&runner.cpu, &at)) {
+                let _ = progress.send(Progress::SubTest {
+                    name: sub_test.name.clone(),
+                    passed: sub_test.passed,
+                    detail: sub_test.detail.clone(),
+                });
+                return match sub_test.detail {
+                    _ if sub_test.passed => Ok(()),
+                    Some(detail) => {
+                        Err(TestError::SubTests(format!("{}: {detail}", sub_test.name)))
+                    }
+                    None => Err(TestError::SubTests(format!("{} failed", sub_test.name))),
+                };
+            }
+        }
+
+        // the rom stopped before it reported the result of its first sub-test
+        runner.explain(
+            all_instrs_status_code(&runner.cpu, &at).and(Err(TestError::String(
+                "official_only didn't finish its first sub-test".to_owned(),
+            ))),
+        )
+    })
+}
+
+/// Checks the implementation of [`TestableCpu`] with a small rom, a sub-test per method
+fn preflight<T: TestableCpu + 'static>(
+    name: &str,
+    config: &TestConfig,
+    on_progress: &mut dyn FnMut(&Progress),
+) -> Result<(), Failure> {
+    use preflight::{prg_mismatch, JUMP, JUMP_RESULT, RESET, RESET_LOOP, RESET_RESULT, WRITE_TEST};
+    /// the programs take a few cycles, the rest is for cpus that take a while to start
+    const CYCLES: usize = 1_000;
+
+    let rom = preflight::rom();
+    check_mapper::<T>(name, &rom)?;
+
+    let options = RunOptions::of(config, &rom);
+    run_test(name, config.timeout, on_progress, move |progress| {
+        let check = |sub_test: &str, result: Result<(), String>| {
+            let _ = progress.send(Progress::SubTest {
+                name: sub_test.to_string(),
+                passed: result.is_ok(),
+                detail: result.as_ref().err().cloned(),
+            });
+            result.map_err(|e| TestError::SubTests(format!("{sub_test}: {e}")))
+        };
+
+        let cpu = load_cpu::<T>(&options, &rom).map_err(|e| {
+            TestError::String(format!(
+                "get_cpu: it failed to load an NROM rom with 16KB of prg and 8KB of chr: {e}"
+            ))
+        });
+        check(
+            "get_cpu",
+            cpu.as_ref().map(|_| ()).map_err(ToString::to_string),
+        )?;
+        let mut cpu = cpu?;
+
+        check(
+            "memory_read",
+            match prg_mismatch(&rom, |address| cpu.memory_read(address)) {
+                Some((address, expected, actual)) => Err(format!(
+                    "memory_read(${address:04X}) returned ${actual:02X}, but the prg rom has ${expected:02X} there: \
+                     it should read the prg rom from $8000 on, with 16KB of prg mirrored at $C000"
+                )),
+                None => Ok(()),
+            },
+        )?;
+        check(
+            "memory_peek",
+            match prg_mismatch(&rom, |address| cpu.memory_peek(address)) {
+                Some((address, expected, actual)) => Err(format!(
+                    "memory_peek(${address:04X}) returned ${actual:02X}, but the prg rom has ${expected:02X} there: \
+                     it should read the same as memory_read, without the side effects of a read"
+                )),
+                None => Ok(()),
+            },
+        )?;
+
+        let (address, value) = WRITE_TEST;
+        if cpu.memory_write(address, value) {
+            let read = cpu.memory_read(address);
+            check(
+                "memory_write",
+                match read {
+                    _ if read == value => Ok(()),
+                    _ => Err(format!(
+                        "after memory_write(${address:04X}, ${value:02X}), memory_read(${address:04X}) returned ${read:02X}"
+                    )),
+                },
+            )?;
+        }
+
+        let mut runner = Runner::new(cpu, &progress, &options);
+        runner.run_for(CYCLES).map_err(TestError::Custom)?;
+        let (address, value) = RESET_RESULT;
+        let read = runner.cpu.memory_peek(address);
+        check(
+            "reset vector",
+            match read {
+                _ if read == value => Ok(()),
+                _ => Err(format!(
+                    "the rom points the reset vector at ${RESET:04X}, where it stores ${value:02X} in ${address:04X}, \
+                     but after {CYCLES} cycles ${address:04X} holds ${read:02X}: the cpu should start at the address \
+                     in $FFFC and $FFFD"
+                )),
+            },
+        )?;
+
+        if let Some(pc) = runner.cpu.program_counter() {
+            check(
+                "program_counter",
+                match pc {
+                    _ if (RESET_LOOP..RESET_LOOP + 3).contains(&pc) => Ok(()),
+                    _ => Err(format!(
+                        "it returned ${pc:04X} while the cpu runs the loop at ${RESET_LOOP:04X}"
+                    )),
+                },
+            )?;
+        }
+        if let Some(registers) = runner.cpu.registers() {
+            check(
+                "registers",
+                match registers.a {
+                    _ if registers.a == value => Ok(()),
+                    a => Err(format!(
+                        "the accumulator is ${a:02X} after `LDA #${value:02X}`, and the cpu stored ${read:02X}"
+                    )),
+                },
+            )?;
+        }
+        drop(runner);
+
+        let mut runner = Runner::new(load_cpu::<T>(&options, &rom)?, &progress, &options);
+        runner.cpu.set_program_counter(JUMP);
+        runner.run_for(CYCLES).map_err(TestError::Custom)?;
+        let cpu = &runner.cpu;
+        let (address, value) = JUMP_RESULT;
+        match (cpu.memory_peek(address), cpu.memory_peek(RESET_RESULT.0)) {
+            (read, _) if read == value => check("set_program_counter", Ok(()))?,
+            // it's optional, and does nothing by default
+            (_, read) if read == RESET_RESULT.1 => log_target::info!("set_program_counter didn't change where the cpu runs, so the tests that need it won't pass"
+            ),
+            (read, _) => check(
+                "set_program_counter",
+                Err(format!(
+                    "after set_program_counter(${JUMP:04X}) the cpu didn't run the program there, \
+                     which stores ${value:02X} in ${address:04X}: it holds ${read:02X}. It didn't run \
+                     the program at the reset vector either."
+                )),
+            )?,
+        }
+
+        Ok(())
+    })
+}
+
+/// The tests of the roms in [`TestConfig::custom_roms`]
+fn custom_tests<T: TestableCpu>(roms: &[CustomRom]) -> Vec<Test> {
+    roms.iter()
+        .map(|rom| {
+            let custom = rom.clone();
+            Test {
+                selector: TestSelector::CUSTOM,
+                name: rom.name.clone(),
+                id: rom.name.clone(),
+                run: Box::new(move |name, config, on_progress| {
+                    custom_rom::<T>(name, &custom, config, on_progress)
+                }),
+                roms: None,
+            }
+        })
+        .collect()
+}
+
+/// The tests of the suites in the manifests of [`TestConfig::suites`]
+fn suite_tests<T: TestableCpu>(manifests: &[PathBuf]) -> Vec<Test> {
+    let mut tests = Vec::new();
+    for path in manifests {
+        match rom_sets::load(path) {
+            Ok(sets) => {
+                for set in sets {
+                    tests.push(rom_set_test::<T>(Arc::new(set)));
+                }
+            }
+            Err(e) => {
+                let message = format!("couldn't load the suites: {e}");
+                tests.push(Test {
+                    selector: TestSelector::CUSTOM,
+                    name: path.display().to_string(),
+                    id: path.display().to_string(),
+                    run: Box::new(move |_, _, _| Err(message.clone().into())),
+                    roms: None,
+                });
+            }
+        }
+    }
+    tests
+}
+
+/// Runs one of your own test roms
+fn custom_rom<T: TestableCpu + 'static>(
+    name: &str,
+    custom: &CustomRom,
+    config: &TestConfig,
+    on_progress: &mut dyn FnMut(&Progress),
+) -> Result<(), Failure> {
+    let rom = std::fs::read(&custom.path)
+        .map_err(|e| format!("couldn't read rom {}: {e}", custom.path.display()))?;
+    let expected = match &custom.expectation {
+        Expectation::Status => {
+            return blargg_test::<T>(
+                name,
+                Cow::Owned(rom),
+                Budget {
+                    cycles: custom.cycles,
+                    instructions: config.instruction_budget(TestSelector::CUSTOM),
+                },
+                None,
+                custom.status_addresses,
+                config,
+                on_progress,
+            )
+        }
+        Expectation::Memory(expected) => expected.clone(),
+    };
+    let cycles = custom.cycles;
+    check_mapper::<T>(name, &rom)?;
+    check_mirroring(name, config, &rom)?;
+
+    let options = RunOptions::of(config, &rom)
+        .instruction_budget(config.instruction_budget(TestSelector::CUSTOM))
+        .status_addresses(custom.status_addresses);
+    run_test(name, config.timeout, on_progress, move |progress| {
+        let mut runner = Runner::new(load_cpu::<T>(&options, &rom)?, &progress, &options);
+        for i in 0..cycles.div_ceil(200_000) {
+            let chunk = (cycles - i * 200_000).min(200_000);
+            runner.run_for(chunk as usize).map_err(TestError::Custom)?;
+            let _ = progress.send(Progress::Cycles {
+                done: i * 200_000 + chunk,
+                budget: cycles,
+            });
+
+            // a program that is done usually ends in a loop
+            if runner.stuck() {
+                break;
+            }
+        }
+
+        let mut failures = Vec::new();
+        for memory in &expected {
+            let result = memory.check(&runner.cpu);
+            let _ = progress.send(Progress::SubTest {
+                name: memory.range(),
+                passed: result.is_ok(),
+                detail: result.as_ref().err().cloned(),
+            });
+            if let Err(e) = result {
+                failures.push(e);
+            }
+        }
+
+        match failures.is_empty() {
+            true => Ok(()),
+            false => Err(TestError::SubTests(failures.join(", "))),
+        }
+    })
+}
+
+/// runs our own nrom test rom
+/// https://gitlab.ewi.tudelft.nl/software-fundamentals/nes-nrom-test
+fn nrom_test<T: TestableCpu + 'static>(
+    name: &str,
+    config: &TestConfig,
+    on_progress: &mut dyn FnMut(&Progress),
+) -> Result<(), Failure> {
+    let rom = load_rom(config, "nrom-test.nes", ROM_NROM_TEST)?;
+    let cycles = config.cycle_budget(TestSelector::NROM_TEST, 10) as usize;
+    check_mapper::<T>(name, &rom)?;
+
+    let options = RunOptions::of(config, &rom)
+        .instruction_budget(config.instruction_budget(TestSelector::NROM_TEST));
+    run_test(name, config.timeout, on_progress, move |progress| {
+        let mut runner = Runner::new(load_cpu::<T>(&options, &rom)?, &progress, &options);
+        runner.run_for(cycles).map_err(TestError::Custom)?;
+        let cpu = &runner.cpu;
+
+        if cpu.memory_peek(0x42) != 0x43 {
+            Err(TestError::String(
+                "memory location 0x42 is wrong after executing nrom_test".to_owned(),
+            ))
+        } else if cpu.memory_peek(0x43) != 0x6A {
+            Err(TestError::String(
+                "memory location 0x43 is wrong after executing nrom_test".to_owned(),
+            ))
+        } else {
+            Ok(())
+        }
+    })
+}
+
+/// Runs micro tests like those of the `interrupts` module, each on a fresh cpu, as the test of `selector`.
+/// When the tests rely on the ones before them, `in_order`, the first one that fails ends the test.
+/// Otherwise they all run, and the test fails with every one that failed once they did.
+fn micro_tests<T: TestableCpu + 'static>(
+    name: &str,
+    tests: &'static [interrupts::MicroTest],
+    in_order: bool,
+    selector: TestSelector,
+    config: &TestConfig,
+    on_progress: &mut dyn FnMut(&Progress),
+) -> Result<(), Failure> {
+    use interrupts::{IRQ_HANDLER, NMI_HANDLER, PROGRAM, RESULTS, UNEXPECTED};
+
+    let rom = ines::vectors_only(NMI_HANDLER, PROGRAM, IRQ_HANDLER);
+    check_mapper::<T>(name, &rom)?;
+
+    let options =
+        RunOptions::of(config, &rom).instruction_budget(config.instruction_budget(selector));
+    let test_name = name.to_string();
+    run_test(name, config.timeout, on_progress, move |progress| {
+        let mut failures = Vec::new();
+        for test in tests {
+            let mut runner = Runner::new(load_cpu::<T>(&options, &rom)?, &progress, &options);
+            let cpu = &mut runner.cpu;
+
+            let writes = RESULTS
+                .map(|address| (address, 0))
+                .chain((PROGRAM..).zip(test.program.iter().copied()))
+                .chain((NMI_HANDLER..).zip(test.nmi_handler.iter().copied()))
+                .chain((IRQ_HANDLER..).zip(test.irq_handler.iter().copied()));
+            for (address, value) in writes {
+                if !cpu.memory_write(address, value) {
+                    return Err(TestError::String(
+                        "this test needs TestableCpu::memory_write to put its programs in memory"
+                            .to_owned(),
+                    ));
+                }
+            }
+            cpu.set_program_counter(PROGRAM);
+
+            runner.run_for(500).map_err(TestError::Custom)?;
+            if test.nmi {
+                // lets the test see whether the cpu continues where it was interrupted
+                let counter = runner.cpu.memory_peek(0x30);
+                runner.cpu.memory_write(0x35, counter);
+                runner.cpu.non_maskable_interrupt();
+                runner.run_for(500).map_err(TestError::Custom)?;
+            }
+
+            let cpu = &runner.cpu;
+            let result = if cpu.memory_peek(UNEXPECTED) != 0 {
+                Err("an interrupt happened that the test didn't cause".to_owned())
+            } else {
+                (test.check)(&|address| cpu.memory_peek(address))
+            };
+
+            let _ = progress.send(Progress::SubTest {
+                name: test.name.to_string(),
+                passed: result.is_ok(),
+                detail: result.as_ref().err().cloned(),
+            });
+            if let Err(e) = result {
+                failures.push(format!("{}: {e}", test.name));
+                if in_order {
+                    break;
+                }
+            }
+        }
+
+        match failures.as_slice() {
+            [] => Ok(()),
+            [failure] => Err(TestError::SubTests(failure.clone())),
+            _ => Err(TestError::SubTests(format!(
+                "{} of the {} micro tests of {test_name} failed:\n{}",
+                failures.len(),
+                tests.len(),
+                failures.join("\n")
+            ))),
+        }
+    })
+}
+
+/// Tests the unstable opcodes that the roms of instr_test-v5 leave out when the harness tests them
+/// itself, accepting every documented variant of them, see [`UnstablePolicy::AnyVariant`]. Those of
+/// `group`, or all of them without a group. Every opcode is a sub-test.
+fn unstable_opcodes<T: TestableCpu + 'static>(
+    name: &str,
+    group: Option<&str>,
+    config: &TestConfig,
+    on_progress: &mut dyn FnMut(&Progress),
+) -> Result<(), Failure> {
+    if config.unstable_opcodes != UnstablePolicy::AnyVariant
+        || !config
+            .unofficial_opcodes
+            .contains(UnofficialOpcodes::UNSTABLE)
+    {
+        return Ok(());
+    }
+    let tests: Vec<(&UnstableTest, Vec<u8>)> = UNSTABLE_TESTS
+        .iter()
+        .filter(|test| group.map_or(true, |group| test.group == group))
+        .map(|test| (test, test.rom()))
+        .collect();
+    let Some((_, rom)) = tests.first() else {
+        return Ok(());
+    };
+    check_mapper::<T>(name, rom)?;
+
+    let options = RunOptions::of(config, rom)
+        .instruction_budget(config.instruction_budget(TestSelector::ALL_INSTRS));
+    run_test(name, config.timeout, on_progress, move |progress| {
+        for (test, rom) in tests {
+            let mut runner = Runner::new(load_cpu::<T>(&options, &rom)?, &progress, &options);
+            runner.run_for(5_000).map_err(TestError::Custom)?;
+
+            let cpu = &runner.cpu;
+            let result = test.check(&|address| cpu.memory_peek(address));
+            let _ = progress.send(Progress::SubTest {
+                name: test.name.to_string(),
+                passed: result.is_ok(),
+                detail: result.as_ref().err().cloned(),
+            });
+            result.map_err(|e| TestError::SubTests(format!("{}: {e}", test.name)))?;
+        }
+
+        Ok(())
+    })
+}
+
+/// Runs `test` on its own thread, so a panicking cpu can't take the rest of the tests down with it.
+/// The failure of a panicking cpu says where it panicked, with a backtrace of the code of the cpu.
+/// The progress the test sends is passed to `on_progress` while it runs.
+/// When the test runs longer than `timeout`, it fails and is left running in the background.
+fn run_test<F>(
+    name: &str,
+    timeout: Option<Duration>,
+    on_progress: &mut dyn FnMut(&Progress),
+    test: F,
+) -> Result<(), Failure>
+where
+    F: FnOnce(Sender<Progress>) -> Result<(), TestError> + Send + 'static,
+{
+    #[cfg(feature = "tracing")]
+    let span = tracing::info_span!("nes_test", test = name);
+    #[cfg(feature = "tracing")]
+    let test = {
+        // so events of the cpu itself end up in the span of the test as well
+        let span = span.clone();
+        move |sender: Sender<Progress>| span.in_scope(|| test(sender))
+    };
+    #[cfg(feature = "tracing")]
+    let _entered = span.enter();
+
+    // the lines the test logs on its thread are those of the test as well
+    let capture = Capture::current();
+    let target = Target::current();
+    let test = move |sender: Sender<Progress>| {
+        let _entered = capture.as_ref().map(Capture::enter);
+        let _target = target.as_ref().map(Target::enter);
+        test(sender)
+    };
+
+    let (sender, receiver) = mpsc::channel();
+    let handle = thread::spawn(move || panic::catch(|| test(sender)));
+
+    let deadline = timeout.map(|timeout| Instant::now() + timeout);
+    let mut timed_out = false;
+    // the channel closes once the test finished or panicked
+    loop {
+        let progress = match deadline {
+            Some(deadline) => {
+                match receiver.recv_timeout(deadline.saturating_duration_since(Instant::now())) {
+                    Ok(progress) => progress,
+                    Err(RecvTimeoutError::Timeout) => {
+                        timed_out = true;
+                        break;
+                    }
+                    Err(RecvTimeoutError::Disconnected) => break,
+                }
+            }
+            None => match receiver.recv() {
+                Ok(progress) => progress,
+                Err(_) => break,
+            },
+        };
+
+        #[cfg(feature = "tracing")]
+        trace_progress(&progress);
+        on_progress(&progress);
+    }
+
+    let result = match timeout {
+        Some(timeout) if timed_out => {
+            Err(format!("cpu didn't finish test {name} within the timeout of {timeout:?}").into())
+        }
+        _ => process_handle(name, handle),
+    };
+
+    #[cfg(feature = "tracing")]
+    match &result {
+        Ok(()) => tracing::info!(passed = true, "test finished"),
+        Err(e) => tracing::warn!(passed = false, error = %e, "test finished"),
+    }
+
+    result
+}
+
+#[cfg(feature = "tracing")]
+fn trace_progress(progress: &Progress) {
+    match progress {
+        Progress::Status(status) => tracing::info!(status = %status, "status changed"),
+        Progress::Cycles { done, budget } => {
+            tracing::debug!(cycles = done, budget = budget, "cycles run")
+        }
+        Progress::SubTest {
+            name,
+            passed,
+            detail,
+        } => {
+            tracing::info!(sub_test = %name, passed = passed, detail = ?detail, "sub-test finished")
+        }
+        Progress::Finished(state) => tracing::debug!(cycles = state.cycles, "cpu finished"),
+        Progress::Skipped(reason) => tracing::info!(reason = %reason, "test skipped"),
+    }
+}
+
+/// Checks whether the cpu supports the mapper of `rom`, before a test on that rom is started
+fn check_mapper<T: TestableCpu>(name: &str, rom: &[u8]) -> Result<(), String> {
+    match ines::mapper_number(rom) {
+        Some(mapper) if !T::supports_mapper(mapper) => Err(mapper_requirement(name, mapper)),
+        _ => Ok(()),
+    }
+}
+
+/// Checks whether the ppu has the mirroring `rom` asks for, before a test on that rom is started. A
+/// rom asking for four-screen mirroring would run with vertical mirroring, and fail because of the
+/// ppu instead of the cpu, so it only runs with the mirroring of [`TestConfig::mirroring`].
+fn check_mirroring(name: &str, config: &TestConfig, rom: &[u8]) -> Result<(), String> {
+    if config.mirroring.is_none() && ines::four_screen(rom) {
+        return Err(format!(
+            "{name} asks for four-screen mirroring, which the ppu of tudelft_nes_ppu doesn't have, \
+             set TestConfig::mirroring to run it with another mirroring"
+        ));
+    }
+    Ok(())
+}
+
+fn mapper_requirement(name: &str, mapper: u8) -> String {
+    format!(
+        "{name} requires mapper {mapper} ({}), which your cpu doesn't support",
+        ines::mapper_name(mapper)
+    )
+}
+
+/// Reads `file_name` from the rom directory of `config`, or uses the `bundled` rom if it isn't there.
+/// A compressed rom is only decompressed when it's used.
+fn load_rom<B: AsRef<[u8]> + ?Sized>(
+    config: &TestConfig,
+    file_name: &str,
+    bundled: &'static B,
+) -> Result<Cow<'static, [u8]>, String> {
+    match &config.rom_dir {
+        Some(dir) if dir.join(file_name).exists() => {
+            let path = dir.join(file_name);
+            std::fs::read(&path)
+                .map(Cow::Owned)
+                .map_err(|e| format!("couldn't read rom {}: {e}", path.display()))
+        }
+        _ => Ok(Cow::Borrowed(bundled.as_ref())),
+    }
+}
+
+fn load_cpu<T: TestableCpu>(options: &RunOptions, rom: &[u8]) -> Result<T, TestError> {
+    let cpu = match &options.closures {
+        Some(closures) => closures.get_cpu(rom).unwrap_or_else(|| T::get_cpu(rom)),
+        None => T::get_cpu(rom),
+    };
+    cpu.map_err(|e| match e.downcast_ref::<UnsupportedMapper>() {
+        Some(UnsupportedMapper(mapper)) => TestError::UnsupportedMapper(*mapper),
+        None => TestError::Custom(e.to_string()),
+    })
+}
+
+#[derive(Debug, Error)]
+enum TestError {
+    #[error("{0}")]
+    Custom(String),
+    #[error("{0}")]
+    String(String),
+    /// The test ran to its end, and failed because of the sub-tests it reported as failed
+    #[error("{0}")]
+    SubTests(String),
+    #[error("mapper {0} is not supported")]
+    UnsupportedMapper(u8),
+}
+
+impl TestError {
+    /// The same kind of error, with `f` applied to its message
+    fn map(self, f: impl FnOnce(String) -> String) -> Self {
+        match self {
+            TestError::Custom(e) => TestError::Custom(f(e)),
+            TestError::String(e) => TestError::String(f(e)),
+            TestError::SubTests(e) => TestError::SubTests(f(e)),
+            TestError::UnsupportedMapper(mapper) => {
+                TestError::String(f(format!("mapper {mapper} is not supported")))
+            }
+        }
+    }
+}
+
+/// Why a test failed: the message of [`TestResult::outcome`], and whether it's only because of its sub-tests
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct Failure {
+    pub(crate) message: String,
+    /// Whether the test ran to its end and failed because of the sub-tests it reported as failed, the
+    /// only failure [`TestConfig::allowed_failures`] can allow. A timeout, a panic, or a cpu that got
+    /// stuck or returned an error is never allowed.
+    pub(crate) sub_tests: bool,
+}
+
+impl Failure {
+    /// A failure of the sub-tests the test reported as failed
+    pub(crate) fn sub_tests(message: String) -> Self {
+        Self {
+            message,
+            sub_tests: true,
+        }
+    }
+
+    /// The same failure, of the part `part` of a test
+    fn prefixed(self, part: &str) -> Self {
+        Self {
+            message: format!("{part}: {}", self.message),
+            ..self
+        }
+    }
+
+    /// The failures of the parts of a test as one, a line each after `summary`. It's only because of
+    /// sub-tests when all of them are.
+    fn joined(summary: String, failures: &[Failure]) -> Self {
+        let mut message = summary;
+        for failure in failures {
+            message.push('\n');
+            message.push_str(&failure.message);
+        }
+        Self {
+            message,
+            sub_tests: failures.iter().all(|f| f.sub_tests),
+        }
+    }
+}
+
+impl From<String> for Failure {
+    fn from(message: String) -> Self {
+        Self {
+            message,
+            sub_tests: false,
+        }
+    }
+}
+
+impl From<Failure> for String {
+    fn from(failure: Failure) -> Self {
+        failure.message
+    }
+}
+
+impl std::fmt::Display for Failure {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.message)
+    }
+}
+
+fn process_handle(
+    name: &str,
+    handle: JoinHandle<Result<Result<(), TestError>, panic::Panic>>,
+) -> Result<(), Failure> {
+    match handle.join() {
+        // <- waits for the thread to complete or panic
+        Ok(Ok(Ok(_))) => {
+            log_target::info!("{name} finished succesfully");
+            Ok(())
+        }
+        Ok(Err(panic)) => {
+            Err(format!("cpu implementation panicked while running test {name}: {panic}").into())
+        }
+        Ok(Ok(Err(e))) => Err(match e {
+            TestError::Custom(e) => {
+                format!("cpu failed while running test {name} with custom error message {e}").into()
+            }
+            TestError::String(e) => format!("cpu didn't pass test {name}: '{e}'").into(),
+            TestError::SubTests(e) => {
+                Failure::sub_tests(format!("cpu didn't pass test {name}: '{e}'"))
+            }
+            TestError::UnsupportedMapper(mapper) => mapper_requirement(name, mapper).into(),
+        }),
         Err(e) => {
             let err_msg = match (e.downcast_ref::<&str>(), e.downcast_ref::<String>()) {
                 (Some(&s), _) => s,
@@ -242,9 +3401,242 @@ fn process_handle(name: &str, handle: JoinHandle<Result<(), TestError>>) -> Resu
                 (None, None) => "<No panic info>",
             };
 
-            Err(format!(
-                "cpu implementation panicked while running test {name}: {err_msg}"
-            ))
+            Err(format!("cpu implementation panicked while running test {name}: {err_msg}").into())
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sub_test(name: &str, passed: bool) -> SubTestResult {
+        SubTestResult {
+            name: name.to_string(),
+            passed,
+            detail: None,
         }
     }
+
+    #[test]
+    fn allowed_failures_pass_the_test() {
+        let sub_tests = [sub_test("01-basics", true), sub_test("03-immediate", false)];
+        let failure = Failure::sub_tests("exited with status 3".to_string());
+        let allowed = ["03-immediate".to_string()];
+
+        let (outcome, expected) = allow_failures(Err(failure), &sub_tests, &allowed);
+        assert_eq!(outcome, Ok(()));
+        assert_eq!(expected, ["03-immediate"]);
+    }
+
+    #[test]
+    fn other_failures_fail_the_test() {
+        let sub_tests = [
+            sub_test("03-immediate", false),
+            sub_test("04-zero_page", false),
+        ];
+        let failure = Failure::sub_tests("exited with status 4".to_string());
+        let allowed = ["03-immediate".to_string()];
+
+        let (outcome, expected) = allow_failures(Err(failure.clone()), &sub_tests, &allowed);
+        assert_eq!(outcome, Err(failure));
+        assert!(expected.is_empty());
+    }
+
+    #[test]
+    fn allowed_failure_with_a_timeout_fails_the_test() {
+        let sub_tests = [sub_test("01-basics", true), sub_test("03-immediate", false)];
+        let timeout = Failure::from(
+            "cpu didn't finish test all instructions within the timeout of 10s".to_string(),
+        );
+        let allowed = ["03-immediate".to_string()];
+
+        let (outcome, expected) = allow_failures(Err(timeout.clone()), &sub_tests, &allowed);
+        assert_eq!(outcome, Err(timeout));
+        assert!(expected.is_empty());
+    }
+
+    #[test]
+    fn failures_of_sub_tests_that_are_not_picked_pass_the_test() {
+        let picked = [sub_test("01-basics", true)];
+        let failure = Failure::sub_tests("exited with status 3".to_string());
+
+        assert_eq!(
+            only_picked(Err(failure), "all instructions", &picked, true),
+            Ok(())
+        );
+    }
+
+    #[test]
+    fn failures_of_picked_sub_tests_fail_the_test() {
+        let picked = [sub_test("01-basics", true), sub_test("03-immediate", false)];
+        let failure = Failure::sub_tests("exited with status 3".to_string());
+
+        let outcome = only_picked(Err(failure.clone()), "all instructions", &picked, false);
+        assert_eq!(outcome, Err(failure));
+    }
+
+    #[test]
+    fn errors_of_a_test_with_picked_sub_tests_fail_it() {
+        let picked = [sub_test("01-basics", true)];
+        let timeout = Failure::from("cpu didn't finish test all instructions".to_string());
+        let panic = Failure::from("cpu panicked: index out of bounds".to_string());
+
+        let outcome = only_picked(Err(timeout.clone()), "all instructions", &picked, true);
+        assert_eq!(outcome, Err(timeout));
+        let outcome = only_picked(Err(panic.clone()), "all instructions", &picked, false);
+        assert_eq!(outcome, Err(panic));
+        // the rom reporting a failure without a sub-test that failed is an error of its own
+        let failure = Failure::sub_tests("exited with status 3".to_string());
+        let outcome = only_picked(Err(failure.clone()), "all instructions", &picked, false);
+        assert_eq!(outcome, Err(failure));
+    }
+
+    #[test]
+    fn a_test_of_which_no_picked_sub_test_ran_fails() {
+        let outcome = only_picked(Ok(()), "all instructions", &[], false);
+        assert_eq!(
+            outcome.unwrap_err().message,
+            "none of the picked sub-tests of all instructions ran"
+        );
+    }
+
+    #[test]
+    fn sub_tests_from_before_a_checkpoint_are_not_reported_again() {
+        let passed = ["01-basics".to_string(), "02-implied".to_string()];
+
+        assert_eq!(
+            unreported(Some(sub_test("02-implied", true)), &passed),
+            None
+        );
+        assert_eq!(
+            unreported(Some(sub_test("03-immediate", true)), &passed),
+            Some(sub_test("03-immediate", true))
+        );
+        // a sub-test that fails after the checkpoint is reported, also when it passed before
+        assert_eq!(
+            unreported(Some(sub_test("02-implied", false)), &passed),
+            Some(sub_test("02-implied", false))
+        );
+        assert_eq!(unreported(Some(sub_test("", true)), &[]), None);
+    }
+
+    #[test]
+    fn four_screen_roms_need_a_mirroring() {
+        let mut rom = ines::vectors_only(0, 0, 0);
+        assert!(check_mirroring("custom", &TestConfig::default(), &rom).is_ok());
+        rom[6] |= 0b1000;
+        let error = check_mirroring("custom", &TestConfig::default(), &rom).unwrap_err();
+        assert!(error.contains("four-screen"), "{error}");
+
+        let config = TestConfig {
+            mirroring: Some(NametableMirroring::Vertical),
+            ..TestConfig::default()
+        };
+        assert!(check_mirroring("custom", &config, &rom).is_ok());
+    }
+
+    #[test]
+    fn joined_failures_are_of_sub_tests_when_all_are() {
+        let sub_tests = Failure::sub_tests("exited with status 2".to_string());
+        let stuck = Failure::from("stuck at $E123 for 100k cycles".to_string());
+
+        let joined = Failure::joined(
+            "2 of the 2 roms failed:".to_string(),
+            &[
+                sub_tests.clone().prefixed("a"),
+                sub_tests.clone().prefixed("b"),
+            ],
+        );
+        assert!(joined.sub_tests);
+        assert_eq!(
+            joined.message,
+            "2 of the 2 roms failed:\na: exited with status 2\nb: exited with status 2"
+        );
+        let joined = Failure::joined(String::new(), &[sub_tests, stuck]);
+        assert!(!joined.sub_tests);
+    }
+
+    struct Unloadable;
+
+    impl Cpu for Unloadable {
+        fn tick(&mut self, _: &mut Ppu) -> Result<(), Box<dyn Error>> {
+            Ok(())
+        }
+
+        fn ppu_read_chr_rom(&self, _: u16) -> u8 {
+            0
+        }
+
+        fn non_maskable_interrupt(&mut self) {}
+    }
+
+    impl TestableCpu for Unloadable {
+        fn get_cpu(rom: &[u8]) -> Result<Self, Box<dyn Error + Send + Sync>> {
+            let mapper = rom.first().ok_or_else(|| anyhow::anyhow!("an empty rom"))?;
+            Err(Box::new(UnsupportedMapper(*mapper)))
+        }
+
+        fn memory_read(&self, _: u16) -> u8 {
+            0
+        }
+    }
+
+    #[test]
+    fn get_cpu_returns_anyhow_and_boxed_errors() {
+        let options = RunOptions::of(&TestConfig::default(), &[]);
+        let error = load_cpu::<Unloadable>(&options, &[]).err().unwrap();
+        assert_eq!(error.to_string(), "an empty rom");
+        let error = load_cpu::<Unloadable>(&options, &[4]).err().unwrap();
+        assert!(matches!(error, TestError::UnsupportedMapper(4)));
+    }
+
+    /// A test that fails with its name, and which roms it runs when it's a set of `roms` roms
+    fn named_test(name: &str, roms: Option<usize>) -> Test {
+        let failing = |message: String| -> Box<TestFn> {
+            Box::new(move |_, _, _| Err(message.clone().into()))
+        };
+        let set = name.to_string();
+        Test {
+            selector: TestSelector::CUSTOM,
+            name: name.to_string(),
+            id: name.to_string(),
+            run: failing(format!("{name} all")),
+            roms: roms.map(|count| SetRoms {
+                count,
+                only: Box::new(move |indices| failing(format!("{set} {indices:?}"))),
+            }),
+        }
+    }
+
+    fn run_shard(shard: Shard, tests: Vec<Test>) -> Vec<String> {
+        shard
+            .select(tests)
+            .iter()
+            .map(|test| (test.run)(&test.name, &TestConfig::default(), &mut |_| {}))
+            .map(|result| result.unwrap_err().to_string())
+            .collect()
+    }
+
+    #[test]
+    fn shards_split_the_roms_of_a_set() {
+        let tests = || {
+            vec![
+                named_test("nestest", None),
+                named_test("vbl_nmi_timing", Some(3)),
+                named_test("all_instrs", None),
+                named_test("single", Some(1)),
+            ]
+        };
+
+        assert_eq!(
+            run_shard(Shard::new(0, 2), tests()),
+            ["nestest all", "vbl_nmi_timing [1]", "all_instrs all"]
+        );
+        assert_eq!(
+            run_shard(Shard::new(1, 2), tests()),
+            ["vbl_nmi_timing [0, 2]", "single all"]
+        );
+        assert_eq!(run_shard(Shard::new(0, 1), tests()).len(), 4);
+    }
 }
@@ -0,0 +1,266 @@
+//! Narrowing a failure of instr_test-v5 down to the instruction that fails, see
+//! [`TestConfig::localize_failures`](crate::TestConfig::localize_failures). Every test of the roms
+//! has a table of the instructions it tests, and the rom of the group that failed runs again with
+//! half of them, and again with half of the half that fails, until one instruction is left. Then
+//! the rom runs once more to see what that instruction did the first time it ran.
+use crate::all_instrs::{
+    all_instrs_finished, all_instrs_status_code, running_group, sub_test_result,
+    without_unofficial, Table, INSTR_GROUPS,
+};
+use crate::config::Budget;
+use crate::log_target;
+use crate::report::Progress;
+use crate::runner::{RunOptions, Runner};
+use crate::status::{read_status_string_at, StatusAddresses};
+use crate::step::StepCallback;
+use crate::trace::registers_text;
+use crate::{
+    load_cpu, run_test, single_rom, Failure, TestConfig, TestError, TestableCpu, TraceFormat,
+    UnofficialOpcodes,
+};
+use std::borrow::Cow;
+use std::fmt::Write as _;
+use std::sync::{Arc, Mutex};
+
+/// The rom of instr_test-v5 that failed
+pub(crate) enum Rom<'a> {
+    /// A single rom, which tests one group
+    Single(&'a [u8]),
+    /// all_instrs.nes or official_only.nes, which run for this budget, and test every group
+    All(&'a [u8], Budget),
+}
+
+/// The group of instr_test-v5 that `progress` shows a rom is at, as an index in [`INSTR_GROUPS`]
+pub(crate) fn group_of(progress: &Progress) -> Option<usize> {
+    match progress {
+        Progress::Status(status) => running_group(status).map(|n| n - 1),
+        Progress::SubTest { name, .. } => INSTR_GROUPS.iter().position(|(g, _)| g == name),
+        _ => None,
+    }
+}
+
+/// Adds the instruction that failed to a `result` that failed in the group with index `group`, with
+/// [`TestConfig::localize_failures`](crate::TestConfig::localize_failures). `keep` are the unofficial
+/// opcodes the rom tests.
+pub(crate) fn localized<T: TestableCpu + 'static>(
+    result: Result<(), Failure>,
+    name: &str,
+    group: Option<usize>,
+    rom: Rom,
+    keep: UnofficialOpcodes,
+    config: &TestConfig,
+) -> Result<(), Failure> {
+    match (result, group) {
+        (Err(e), Some(group)) if config.localize_failures => {
+            let (group_name, _) = INSTR_GROUPS[group];
+            log_target::info!("narrowing the failure of {group_name} down");
+            let localized = localize::<T>(name, group, rom, keep, config);
+            Err(Failure {
+                message: format!("{e}\n{localized}"),
+                ..e
+            })
+        }
+        (result, _) => result,
+    }
+}
+
+fn localize<T: TestableCpu + 'static>(
+    name: &str,
+    group: usize,
+    rom: Rom,
+    keep: UnofficialOpcodes,
+    config: &TestConfig,
+) -> String {
+    let (group_name, selector) = INSTR_GROUPS[group];
+    let single_budget = config.budget(selector, 20_000_000);
+    let mut lines = Vec::new();
+
+    // the single rom of the group runs faster and has only its own table, so it's used when it's there
+    let (rom, bank, budget, stop_at) = match rom {
+        Rom::Single(rom) => (rom.to_vec(), None, single_budget, None),
+        Rom::All(all, budget) => match single_rom(name, group_name, config) {
+            Ok(single) => {
+                let single = without_unofficial(Cow::Owned(single), keep).into_owned();
+                if run::<T>(name, &single, None, single_budget, None, config).is_ok() {
+                    return format!(
+                        "{group_name} passes when its single rom runs on its own, so it only fails \
+                         because of what the groups before it left behind, like the flags, the stack \
+                         or a byte of memory"
+                    );
+                }
+                lines.push(format!(
+                    "{group_name} fails when its single rom runs on its own too"
+                ));
+                (single, None, single_budget, None)
+            }
+            // all_instrs.nes keeps group n in bank n - 1
+            Err(_) => (all.to_vec(), Some(group), budget, Some(group_name)),
+        },
+    };
+
+    let mut tables = Table::find_all(&rom).into_iter();
+    let table = match bank {
+        Some(bank) => tables.find(|t| t.bank == bank),
+        None => tables.next(),
+    };
+    let Some(table) = table else {
+        lines.push(format!(
+            "{group_name} doesn't test its instructions from a table, so it can't be narrowed down \
+             to one of them"
+        ));
+        return lines.join("\n");
+    };
+
+    // an instruction that is the same as one before it, like an unofficial one that was left out,
+    // is only tested once
+    let mut left: Vec<usize> = (0..table.count)
+        .filter(|&i| (0..i).all(|j| !table.same(&rom, i, j)))
+        .collect();
+    let fails = |keep: &[usize]| {
+        log_target::info!(
+            "testing {} of the {} instructions of {group_name}",
+            keep.len(),
+            table.count
+        );
+        let rom = only(&rom, &table, keep);
+        run::<T>(name, &rom, stop_at, budget, None, config).is_err()
+    };
+    while left.len() > 1 {
+        let (first, second) = left.split_at(left.len() / 2);
+        if fails(first) {
+            left = first.to_vec();
+        } else if fails(second) {
+            left = second.to_vec();
+        } else {
+            break;
+        }
+    }
+
+    let describe = |i: usize| {
+        let opcode = table.opcode(&rom, i);
+        match table.name(&rom, i) {
+            Some(name) => format!("{name} (${opcode:02X})"),
+            None => format!("${opcode:02X}"),
+        }
+    };
+    let &[i] = left.as_slice() else {
+        let all: Vec<String> = left.iter().map(|&i| describe(i)).collect();
+        lines.push(format!(
+            "{group_name} fails when it tests all of {}, but not when it tests only half of them",
+            all.join(", ")
+        ));
+        return lines.join("\n");
+    };
+    lines.push(format!(
+        "{group_name} fails when it only tests {}",
+        describe(i)
+    ));
+
+    let rom = only(&rom, &table, &[i]);
+    match first_run::<T>(name, &rom, table.opcode(&rom, i), stop_at, budget, config) {
+        Some(first) => lines.push(format!("the first time the rom ran it:\n{first}")),
+        None => lines.push(
+            "the harness couldn't see the instruction run, that needs TestableCpu::program_counter \
+             and TestableCpu::registers"
+                .to_string(),
+        ),
+    }
+    lines.join("\n")
+}
+
+/// `rom` with only the instructions `keep` of `table` left, the others are replaced by the first of them
+fn only(rom: &[u8], table: &Table, keep: &[usize]) -> Vec<u8> {
+    let mut rom = rom.to_vec();
+    for i in (0..table.count).filter(|i| !keep.contains(i)) {
+        table.replace(&mut rom, i, keep[0]);
+    }
+    rom
+}
+
+/// What the cpu did when it first ran `opcode` from ram, where the roms run the instruction they
+/// test: the line of trace before it, the registers after it, and its bus accesses
+fn first_run<T: TestableCpu + 'static>(
+    name: &str,
+    rom: &[u8],
+    opcode: u8,
+    stop_at: Option<&'static str>,
+    budget: Budget,
+    config: &TestConfig,
+) -> Option<String> {
+    let first = Arc::new(Mutex::new(None));
+    let found = first.clone();
+    let mut before: Option<(u16, String)> = None;
+    let on_step = StepCallback::new(move |step| {
+        if let (Some((pc, line)), Some(after)) = (&before, step.registers) {
+            if *pc < 0x0800 && step.memory_read(*pc) == opcode {
+                let mut text = format!("  {line}\n  after it: {}", registers_text(after));
+                if !step.bus_accesses.is_empty() {
+                    text.push_str("\n  accesses:");
+                    for access in step.bus_accesses {
+                        let kind = if access.write { "write" } else { "read" };
+                        let _ = write!(
+                            text,
+                            " {kind} ${:04X} = ${:02X},",
+                            access.address, access.value
+                        );
+                    }
+                    text.pop();
+                }
+                *found.lock().unwrap_or_else(|e| e.into_inner()) = Some(text);
+                // the rest of the rom doesn't matter anymore
+                return Err("found the instruction".to_string());
+            }
+        }
+        before = step
+            .program_counter
+            .zip(step.trace_line(TraceFormat::Mesen));
+        Ok(())
+    });
+    let _ = run::<T>(name, rom, stop_at, budget, Some(on_step), config);
+    let first = first.lock().unwrap_or_else(|e| e.into_inner()).take();
+    first
+}
+
+/// Runs an instr_test-v5 rom until it's done, or with `stop_at` until all_instrs.nes shows the verdict
+/// of that group, and fails when the rom does
+fn run<T: TestableCpu + 'static>(
+    name: &str,
+    rom: &[u8],
+    stop_at: Option<&'static str>,
+    budget: Budget,
+    on_step: Option<StepCallback>,
+    config: &TestConfig,
+) -> Result<(), Failure> {
+    let rom = rom.to_vec();
+    let at = StatusAddresses::default();
+    let limit = budget.cycles.div_ceil(200_000);
+    let options = RunOptions::of(config, &rom)
+        .instruction_budget(budget.instructions)
+        .on_step(on_step);
+
+    // the final state of the test stays the one of the run that failed, not of these runs
+    run_test(
+        name,
+        config.timeout,
+        &mut |_: &Progress| {},
+        move |progress| {
+            let mut runner = Runner::new(load_cpu::<T>(&options, &rom)?, &progress, &options);
+            for _ in 0..limit {
+                runner.run_for(200_000).map_err(TestError::Custom)?;
+                if runner.stuck() || all_instrs_finished(&runner.cpu, &at) {
+                    break;
+                }
+                let status = read_status_string_at(&runner.cpu, &at);
+                let verdict =
+                    stop_at.and_then(|g| sub_test_result(&status).filter(|s| s.name == g));
+                if let Some(sub_test) = verdict {
+                    return match sub_test.passed {
+                        true => Ok(()),
+                        false => Err(TestError::String(status)),
+                    };
+                }
+            }
+            runner.explain(all_instrs_status_code(&runner.cpu, &at))
+        },
+    )
+}
@@ -0,0 +1,228 @@
+//! Keeping the lines that are logged during a test with its result, instead of mixing the lines of
+//! all tests that run at the same time in one stream, see [`TestConfig::capture_logs`](crate::TestConfig::capture_logs)
+use crate::log_target;
+use log::{Level, Log, Metadata, Record, SetLoggerError};
+use std::cell::RefCell;
+use std::fmt;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex, Once};
+
+/// Which of the lines logged during a test are kept in [`TestResult::logs`](crate::TestResult::logs),
+/// see [`TestConfig::capture_logs`](crate::TestConfig::capture_logs)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(rename_all = "lowercase")
+)]
+pub enum LogCapture {
+    /// None of them, every line goes to the logger
+    #[default]
+    Off,
+    /// The lines the harness logs
+    Harness,
+    /// Everything that is logged on the threads that run the test, so also the lines of your cpu
+    All,
+}
+
+/// Whether the logger of the harness is the logger of the process
+static INSTALLED: AtomicBool = AtomicBool::new(false);
+
+thread_local! {
+    /// Where the lines logged on this thread go, while it runs a test
+    static CURRENT: RefCell<Option<Capture>> = const { RefCell::new(None) };
+}
+
+/// Makes the logger of the harness the logger of the process, which keeps the lines your cpu logs
+/// during a test with [`LogCapture::All`], and passes all other lines to `inner`, like the logger of
+/// `env_logger`. Without `inner` they're dropped. The lines of the harness itself are captured without
+/// it.
+///
+/// The harness installs it without `inner` when it captures all logs and there is no logger yet, so
+/// only call this to keep a logger of your own, before running the tests:
+/// ```no_run
+/// # use tudelft_nes_test::install_logger;
+/// # struct MyLogger;
+/// # impl log::Log for MyLogger {
+/// #     fn enabled(&self, _: &log::Metadata) -> bool { true }
+/// #     fn log(&self, record: &log::Record) { eprintln!("{}", record.args()) }
+/// #     fn flush(&self) {}
+/// # }
+/// install_logger(Some(Box::new(MyLogger))).unwrap();
+/// ```
+///
+/// The maximum level of the `log` crate stays what it is, so set it with [`log::set_max_level`] to
+/// the level of the lines of your cpu you want to capture. Returns an error when the process has a
+/// logger already.
+pub fn install_logger(inner: Option<Box<dyn Log>>) -> Result<(), SetLoggerError> {
+    log::set_boxed_logger(Box::new(Logger { inner }))?;
+    INSTALLED.store(true, Ordering::Relaxed);
+    Ok(())
+}
+
+/// The lines logged during a test, shared with the threads that run it
+#[derive(Clone)]
+pub(crate) struct Capture {
+    what: LogCapture,
+    lines: Arc<Mutex<Vec<String>>>,
+}
+
+impl Capture {
+    /// Starts capturing `what`, or `None` when that's nothing. The lines of the cpu go through the
+    /// logger of the harness, which is installed when there is no logger yet. Without it only the
+    /// lines of the harness are captured.
+    pub(crate) fn start(what: LogCapture) -> Option<Self> {
+        if what == LogCapture::Off {
+            return None;
+        }
+        static INSTALL: Once = Once::new();
+        if what == LogCapture::All {
+            INSTALL.call_once(|| {
+                if !INSTALLED.load(Ordering::Relaxed) && install_logger(None).is_err() {
+                    log_target::warn!(
+                        "only the lines of the harness are captured, since the process has a logger \
+                         of its own, see tudelft_nes_test::install_logger"
+                    );
+                }
+            });
+        }
+        Some(Self {
+            what,
+            lines: Arc::default(),
+        })
+    }
+
+    /// The capture of the test this thread runs, to capture the lines of a thread it starts as well
+    pub(crate) fn current() -> Option<Self> {
+        CURRENT.with(|current| current.borrow().clone())
+    }
+
+    /// Captures the lines logged on this thread, until the guard is dropped
+    pub(crate) fn enter(&self) -> Entered {
+        let previous = CURRENT.with(|current| current.replace(Some(self.clone())));
+        Entered { previous }
+    }
+
+    /// The lines captured so far
+    pub(crate) fn lines(&self) -> Vec<String> {
+        self.lines.lock().map(|l| l.clone()).unwrap_or_default()
+    }
+
+    fn takes(&self, target: &str) -> bool {
+        match self.what {
+            LogCapture::Off => false,
+            LogCapture::Harness => is_harness(target),
+            LogCapture::All => true,
+        }
+    }
+}
+
+/// Adds a line to the capture of the test this thread runs, like one the process of an isolated
+/// test captured
+pub(crate) fn push(line: String) {
+    if let Some(capture) = Capture::current() {
+        if let Ok(mut lines) = capture.lines.lock() {
+            lines.push(line);
+        }
+    }
+}
+
+/// Captures a line the harness logs under `target`, when this thread runs a test that captures it.
+/// Returns whether it did, otherwise the line goes to the logger.
+pub(crate) fn capture(level: Level, target: &str, args: fmt::Arguments<'_>) -> bool {
+    let Some(capture) = Logger::capture(target) else {
+        return false;
+    };
+    if let Ok(mut lines) = capture.lines.lock() {
+        lines.push(format!("{level:<5} {target}: {args}"));
+    }
+    true
+}
+
+/// Restores the capture of the thread from before [`Capture::enter`]
+pub(crate) struct Entered {
+    previous: Option<Capture>,
+}
+
+impl Drop for Entered {
+    fn drop(&mut self) {
+        let previous = self.previous.take();
+        let _ = CURRENT.try_with(|current| current.replace(previous));
+    }
+}
+
+/// Whether a line is logged by the harness, which logs under the targets of [`log_target`]
+fn is_harness(target: &str) -> bool {
+    target == log_target::ROOT
+        || target
+            .strip_prefix(log_target::ROOT)
+            .is_some_and(|t| t.starts_with("::"))
+}
+
+struct Logger {
+    inner: Option<Box<dyn Log>>,
+}
+
+impl Logger {
+    /// The capture that takes lines of `target`, when this thread runs a test
+    fn capture(target: &str) -> Option<Capture> {
+        CURRENT
+            .try_with(|current| current.borrow().clone())
+            .ok()
+            .flatten()
+            .filter(|capture| capture.takes(target))
+    }
+}
+
+impl Log for Logger {
+    fn enabled(&self, metadata: &Metadata<'_>) -> bool {
+        Self::capture(metadata.target()).is_some()
+            || self.inner.as_ref().is_some_and(|i| i.enabled(metadata))
+    }
+
+    fn log(&self, record: &Record<'_>) {
+        match Self::capture(record.target()) {
+            Some(_) => {
+                capture(record.level(), record.target(), *record.args());
+            }
+            None => {
+                if let Some(inner) = self.inner.as_ref().filter(|i| i.enabled(record.metadata())) {
+                    inner.log(record);
+                }
+            }
+        }
+    }
+
+    fn flush(&self) {
+        if let Some(inner) = &self.inner {
+            inner.flush();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn captures_the_harness_without_a_logger() {
+        let test = Capture::start(LogCapture::Harness).unwrap();
+        {
+            let _entered = test.enter();
+            let target = crate::log_target::current();
+            assert!(capture(
+                Level::Info,
+                &target,
+                format_args!("running {}", "nestest")
+            ));
+            assert!(!capture(Level::Info, "my_cpu", format_args!("ignored")));
+        }
+        assert!(!capture(
+            Level::Info,
+            &crate::log_target::current(),
+            format_args!("after")
+        ));
+        assert_eq!(test.lines().len(), 1);
+        assert!(test.lines()[0].ends_with(": running nestest"));
+    }
+}
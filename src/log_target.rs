@@ -0,0 +1,157 @@
+//! The targets the harness logs under, so a filter like the one of `env_logger` can pick the lines
+//! of the test you're debugging. A line logged while a test runs has the target of that test, like
+//! `tudelft_nes_test::official_instrs`, or of the sub-test it's at, like
+//! `tudelft_nes_test::all_instrs::group11` for the groups of instr_test-v5 and
+//! `tudelft_nes_test::vbl_nmi_timing::2_vbl_timing` for the roms of a set. All other lines have the
+//! target `tudelft_nes_test`, the name of the crate, so `RUST_LOG=tudelft_nes_test=...` still picks
+//! all of them. Since a filter matches the start of a target, the groups have two digits. To only
+//! see the progress of group 11:
+//! ```text
+//! RUST_LOG=tudelft_nes_test=warn,tudelft_nes_test::all_instrs::group11=info cargo test
+//! ```
+use crate::all_instrs::running_group;
+use std::cell::RefCell;
+
+/// The target of the lines the harness logs outside of a test, which all its targets start with. It's
+/// the name of the crate, like the targets of `module_path!()`, so a filter on the crate matches all
+/// of them.
+pub(crate) const ROOT: &str = env!("CARGO_CRATE_NAME");
+
+thread_local! {
+    /// The target of the test this thread runs
+    static CURRENT: RefCell<Option<Target>> = const { RefCell::new(None) };
+}
+
+/// The target of a test, and of the sub-test it's at
+#[derive(Clone)]
+pub(crate) struct Target {
+    test: String,
+    sub_test: Option<String>,
+    /// Whether the sub-tests are the groups of instr_test-v5 in a single rom, which runs them one
+    /// after the other
+    groups: bool,
+}
+
+impl Target {
+    /// The target of the test with `id`
+    pub(crate) fn of_test(id: &str) -> Self {
+        Self {
+            test: segment(id),
+            sub_test: None,
+            groups: false,
+        }
+    }
+
+    /// The target of the test this thread runs, to log under it on a thread it starts as well
+    pub(crate) fn current() -> Option<Self> {
+        CURRENT.with(|current| current.borrow().clone())
+    }
+
+    /// The target of the sub-test `name` of this test
+    pub(crate) fn sub_test(&self, name: &str) -> Self {
+        Self {
+            sub_test: Some(segment(name)),
+            groups: false,
+            ..self.clone()
+        }
+    }
+
+    /// The target of a test that runs all groups of instr_test-v5 in a single rom, which is at the
+    /// group the rom runs, see [`running`]
+    pub(crate) fn groups(&self) -> Self {
+        Self {
+            sub_test: None,
+            groups: true,
+            ..self.clone()
+        }
+    }
+
+    /// Logs under this target on this thread, until the guard is dropped
+    pub(crate) fn enter(&self) -> Entered {
+        let previous = CURRENT.with(|current| current.replace(Some(self.clone())));
+        Entered { previous }
+    }
+
+    /// The target itself, like `tudelft_nes_test::all_instrs::group11`
+    pub(crate) fn name(&self) -> String {
+        match &self.sub_test {
+            Some(sub_test) => format!("{ROOT}::{}::{sub_test}", self.test),
+            None => format!("{ROOT}::{}", self.test),
+        }
+    }
+}
+
+/// Restores the target of the thread from before [`Target::enter`]
+pub(crate) struct Entered {
+    previous: Option<Target>,
+}
+
+impl Drop for Entered {
+    fn drop(&mut self) {
+        let previous = self.previous.take();
+        let _ = CURRENT.try_with(|current| current.replace(previous));
+    }
+}
+
+/// The target to log under on this thread
+pub(crate) fn current() -> String {
+    CURRENT
+        .try_with(|current| current.borrow().as_ref().map(Target::name))
+        .ok()
+        .flatten()
+        .unwrap_or_else(|| ROOT.to_string())
+}
+
+/// Logs at `info` under the [`current`] target, or keeps the line with the test that captures it
+macro_rules! log_info {
+    ($($arg:tt)+) => {{
+        let target = $crate::log_target::current();
+        if !$crate::log_capture::capture(log::Level::Info, &target, format_args!($($arg)+)) {
+            log::info!(target: &target, $($arg)+)
+        }
+    }};
+}
+pub(crate) use log_info as info;
+
+/// Logs at `warn` under the [`current`] target, or keeps the line with the test that captures it
+macro_rules! log_warn {
+    ($($arg:tt)+) => {{
+        let target = $crate::log_target::current();
+        if !$crate::log_capture::capture(log::Level::Warn, &target, format_args!($($arg)+)) {
+            log::warn!(target: &target, $($arg)+)
+        }
+    }};
+}
+pub(crate) use log_warn as warn;
+
+/// The sub-test of the target of group `n` of instr_test-v5, counting from 1
+pub(crate) fn group(n: usize) -> String {
+    format!("group{n:02}")
+}
+
+/// Moves the target of a test that runs all groups of instr_test-v5 to the group that the rom says
+/// it runs in `status`, like "Running test 11 of 16"
+pub(crate) fn running(status: &str) {
+    let Some(n) = running_group(status) else {
+        return;
+    };
+    let _ = CURRENT.try_with(|current| {
+        if let Some(target) = current.borrow_mut().as_mut().filter(|t| t.groups) {
+            target.sub_test = Some(group(n));
+        }
+    });
+}
+
+/// A part of a target for an id or a name, which only has the characters a filter expects in a
+/// module path
+fn segment(name: &str) -> String {
+    name.chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() {
+                c.to_ascii_lowercase()
+            } else {
+                '_'
+            }
+        })
+        .collect()
+}
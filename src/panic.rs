@@ -0,0 +1,120 @@
+//! Catches panics of the cpu under test together with where they happened, so the failure can say
+//! more than "index out of bounds"
+use std::backtrace::Backtrace;
+use std::cell::{Cell, RefCell};
+use std::fmt;
+use std::panic::{self, AssertUnwindSafe};
+use std::sync::Once;
+
+thread_local! {
+    /// whether a panic on this thread is caught by [`catch`]
+    static CATCHING: Cell<bool> = const { Cell::new(false) };
+    /// the location and backtrace of the last panic that was caught on this thread
+    static CAUGHT: RefCell<Option<(Option<String>, String)>> = const { RefCell::new(None) };
+}
+
+/// A panic of the cpu under test
+pub(crate) struct Panic {
+    message: String,
+    location: Option<String>,
+    backtrace: String,
+}
+
+impl fmt::Display for Panic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.message)?;
+        if let Some(location) = &self.location {
+            write!(f, "\n  at {location}")?;
+        }
+        if !self.backtrace.is_empty() {
+            write!(f, "\nbacktrace:\n{}", self.backtrace)?;
+        }
+        Ok(())
+    }
+}
+
+/// Runs `f`, and catches it when it panics. Panics that aren't caught like this are left to the
+/// panic hook that was installed before.
+pub(crate) fn catch<R>(f: impl FnOnce() -> R) -> Result<R, Panic> {
+    static INSTALL: Once = Once::new();
+    INSTALL.call_once(|| {
+        let previous = panic::take_hook();
+        panic::set_hook(Box::new(move |info| {
+            if CATCHING.get() {
+                let location = info
+                    .location()
+                    .map(|l| format!("{}:{}:{}", l.file(), l.line(), l.column()));
+                let backtrace = trim(&Backtrace::force_capture().to_string());
+                CAUGHT.set(Some((location, backtrace)));
+            } else {
+                previous(info);
+            }
+        }));
+    });
+
+    CATCHING.set(true);
+    let result = panic::catch_unwind(AssertUnwindSafe(f));
+    CATCHING.set(false);
+
+    result.map_err(|payload| {
+        let message = match (
+            payload.downcast_ref::<&str>(),
+            payload.downcast_ref::<String>(),
+        ) {
+            (Some(&s), _) => s.to_string(),
+            (_, Some(s)) => s.clone(),
+            (None, None) => "<No panic info>".to_string(),
+        };
+        let (location, backtrace) = CAUGHT.take().unwrap_or_default();
+
+        Panic {
+            message,
+            location,
+            backtrace,
+        }
+    })
+}
+
+/// Leaves out the frames of the standard library and of this crate, which are the same for every
+/// panic, so what remains is the code of the cpu
+fn trim(backtrace: &str) -> String {
+    const HIDDEN: &[&str] = &[
+        "std::",
+        "core::",
+        "alloc::",
+        "<std::",
+        "<core::",
+        "<alloc::",
+        "rust_begin_unwind",
+        "__rust",
+        "tudelft_nes_test::",
+        "<tudelft_nes_test::",
+    ];
+
+    let mut trimmed = String::new();
+    let mut show = false;
+    for line in backtrace.lines() {
+        let text = line.trim_start();
+        // frames look like "12: function", followed by "at file:line" when there is debug info
+        match text.split_once(": ") {
+            Some((number, function)) if number.chars().all(|c| c.is_ascii_digit()) => {
+                if function.contains("__rust_begin_short_backtrace") {
+                    break;
+                }
+                // `<usize as core::slice::index::SliceIndex<..>>::index` is std code too
+                let (own, implemented) = function.split_once(" as ").unwrap_or((function, ""));
+                show = !HIDDEN
+                    .iter()
+                    .any(|hidden| own.starts_with(hidden) || implemented.starts_with(hidden));
+            }
+            _ => {}
+        }
+
+        if show {
+            trimmed.push_str(line);
+            trimmed.push('\n');
+        }
+    }
+
+    trimmed.trim_end().to_string()
+}
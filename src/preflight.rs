@@ -0,0 +1,61 @@
+//! Quick checks of the [`TestableCpu`](crate::TestableCpu) implementation itself, run before the
+//! test roms: many confusing failures of the roms come from an adapter that loads the rom, reads
+//! memory or sets the program counter wrong, and not from the cpu.
+use crate::ines;
+
+/// Where the cpu starts: `LDA #$42`, `STA $10`, and a loop at [`RESET_LOOP`]
+pub(crate) const RESET: u16 = 0x8000;
+pub(crate) const RESET_LOOP: u16 = 0x8004;
+/// Where the harness sets the program counter to: `LDA #$99`, `STA $11`, and a loop
+pub(crate) const JUMP: u16 = 0x9000;
+/// The handler of both interrupts, a single `RTI`
+const HANDLER: u16 = 0x8100;
+
+/// The zero page bytes the programs store their results in, and what they store
+pub(crate) const RESET_RESULT: (u16, u8) = (0x0010, 0x42);
+pub(crate) const JUMP_RESULT: (u16, u8) = (0x0011, 0x99);
+
+/// Where the harness writes with [`TestableCpu::memory_write`](crate::TestableCpu::memory_write),
+/// and then reads back
+pub(crate) const WRITE_TEST: (u16, u8) = (0x0200, 0x5A);
+
+#[rustfmt::skip]
+const RESET_PROGRAM: &[u8] = &[
+    0xA9, 0x42,         // 8000: LDA #$42
+    0x85, 0x10,         // 8002: STA $10
+    0x4C, 0x04, 0x80,   // 8004: JMP $8004
+];
+
+#[rustfmt::skip]
+const JUMP_PROGRAM: &[u8] = &[
+    0xA9, 0x99,         // 9000: LDA #$99
+    0x85, 0x11,         // 9002: STA $11
+    0x4C, 0x04, 0x90,   // 9004: JMP $9004
+];
+
+/// An NROM rom with the two programs. The other bytes of prg are a pattern that differs at every
+/// address of a page, so reading the wrong address shows.
+pub(crate) fn rom() -> Vec<u8> {
+    let mut rom = ines::vectors_only(HANDLER, RESET, HANDLER);
+    let prg = &mut rom[16..16 + 0x4000];
+    for (i, byte) in prg[..0x4000 - 6].iter_mut().enumerate() {
+        *byte = (i as u8).wrapping_mul(7) ^ (i >> 8) as u8;
+    }
+
+    let at = |address: u16| usize::from(address - 0x8000);
+    prg[at(RESET)..at(RESET) + RESET_PROGRAM.len()].copy_from_slice(RESET_PROGRAM);
+    prg[at(JUMP)..at(JUMP) + JUMP_PROGRAM.len()].copy_from_slice(JUMP_PROGRAM);
+    prg[at(HANDLER)] = 0x40;
+    rom
+}
+
+/// The first address from $8000 on where `read` doesn't return the byte of prg in `rom`, with what
+/// it returned. The 16KB of prg are mapped at $8000 and mirrored at $C000.
+pub(crate) fn prg_mismatch(rom: &[u8], read: impl Fn(u16) -> u8) -> Option<(u16, u8, u8)> {
+    let prg = &rom[16..16 + 0x4000];
+    (0x8000..=0xFFFF).find_map(|address: u16| {
+        let expected = prg[usize::from(address - 0x8000) % prg.len()];
+        let actual = read(address);
+        (actual != expected).then_some((address, expected, actual))
+    })
+}
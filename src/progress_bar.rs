@@ -0,0 +1,74 @@
+//! Progress bars for long running tests, enabled with the `indicatif` feature
+use crate::report::Progress;
+use indicatif::{ProgressBar, ProgressStyle};
+
+const CYCLES_TEMPLATE: &str = "{prefix:.bold} [{bar:30}] {pos}k/{len}k cycles {msg}";
+const TESTS_TEMPLATE: &str = "{prefix:.bold} [{bar:30}] {pos}/{len} tests {msg}";
+
+/// A progress bar for a single test. It counts cycles, until the rom shows how many
+/// sub-tests it runs, after which it counts those instead.
+pub(crate) struct TestProgressBar {
+    bar: ProgressBar,
+    counting_tests: bool,
+}
+
+/// Parses the "Running test 3 of 16" line the multi-test roms show, into `(3, 16)`
+fn sub_test_progress(status: &str) -> Option<(u64, u64)> {
+    let (current, total) = status
+        .trim()
+        .strip_prefix("Running test ")?
+        .split_once(" of ")?;
+    Some((current.parse().ok()?, total.parse().ok()?))
+}
+
+fn style(template: &str) -> ProgressStyle {
+    ProgressStyle::with_template(template)
+        .expect("progress bar template is valid")
+        .progress_chars("=> ")
+}
+
+impl TestProgressBar {
+    pub(crate) fn new(name: &str) -> Self {
+        let bar = ProgressBar::new(0);
+        bar.set_style(style(CYCLES_TEMPLATE));
+        bar.set_prefix(name.to_string());
+
+        Self {
+            bar,
+            counting_tests: false,
+        }
+    }
+
+    pub(crate) fn update(&mut self, progress: &Progress) {
+        match progress {
+            Progress::Cycles { done, budget } if !self.counting_tests => {
+                self.bar.set_length(budget / 1000);
+                self.bar.set_position(done / 1000);
+            }
+            Progress::Cycles { .. }
+            | Progress::SubTest { .. }
+            | Progress::Finished(_)
+            | Progress::Skipped(_) => {}
+            Progress::Status(status) => {
+                if let Some((current, total)) = sub_test_progress(status) {
+                    if !self.counting_tests {
+                        self.counting_tests = true;
+                        self.bar.set_style(style(TESTS_TEMPLATE));
+                    }
+                    self.bar.set_length(total);
+                    self.bar.set_position(current.saturating_sub(1));
+                }
+                self.bar.set_message(status.clone());
+            }
+        }
+    }
+
+    /// Prints a line above the bar, without messing up the bar itself
+    pub(crate) fn println(&self, line: &str) {
+        self.bar.println(line);
+    }
+
+    pub(crate) fn finish(self) {
+        self.bar.finish_and_clear();
+    }
+}
@@ -0,0 +1,54 @@
+//! Filling the internal ram before a test runs, to catch cpus and tests that depend on ram that
+//! starts out as zeros, see [`RamInit`]
+use std::collections::hash_map::RandomState;
+use std::hash::{BuildHasher, Hasher};
+
+/// What the internal ram, $0000 to $07FF, holds before a test runs, see [`TestConfig::ram_init`](crate::TestConfig::ram_init).
+/// The ram of a real NES holds about random bytes when it's turned on, so a cpu passing a test only
+/// with ram that starts out as zeros, or a test that only passes on such a cpu, has a bug.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum RamInit {
+    /// Whatever [`TestableCpu::get_cpu`](crate::TestableCpu::get_cpu) left in it, usually zeros
+    #[default]
+    Cpu,
+    /// Every byte is this value, like `0xFF`
+    Filled(u8),
+    /// Random bytes from this seed, the same for every test. Without a seed, a run picks one and
+    /// reports it in [`TestReport::ram_seed`](crate::TestReport::ram_seed), so a failure can be
+    /// reproduced by running with that seed.
+    Random(Option<u64>),
+}
+
+impl RamInit {
+    /// The same, but with a seed picked for the run when it's random without one
+    pub(crate) fn with_seed(self) -> Self {
+        match self {
+            RamInit::Random(None) => {
+                RamInit::Random(Some(RandomState::new().build_hasher().finish()))
+            }
+            init => init,
+        }
+    }
+
+    /// The bytes to fill the ram with, or `None` when the cpu keeps its own
+    pub(crate) fn bytes(self) -> Option<Vec<u8>> {
+        match self {
+            RamInit::Cpu => None,
+            RamInit::Filled(value) => Some(vec![value; 0x800]),
+            RamInit::Random(seed) => {
+                // splitmix64, which is random enough for this and the same on every platform
+                let mut state = seed.unwrap_or(0);
+                let mut bytes = Vec::with_capacity(0x800);
+                while bytes.len() < 0x800 {
+                    state = state.wrapping_add(0x9E37_79B9_7F4A_7C15);
+                    let mut z = state;
+                    z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+                    z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+                    bytes.extend((z ^ (z >> 31)).to_le_bytes());
+                }
+                Some(bytes)
+            }
+        }
+    }
+}
@@ -0,0 +1,172 @@
+//! Ranking the test runs of many submissions, for course staff grading all emulators of a course at
+//! once, see [`Ranking`]
+use crate::csv::row;
+use crate::events::Value;
+use crate::grading::GradingProfile;
+use crate::report::{TestReport, TestResult};
+use std::time::Duration;
+
+/// The first row of [`Ranking::csv`], with the names of the columns
+const HEADER: [&str; 8] = [
+    "rank",
+    "submission",
+    "score",
+    "passed",
+    "failed",
+    "skipped",
+    "duration_ms",
+    "error",
+];
+
+/// The results of a submission in a [`Ranking`]
+struct Entry {
+    submission: String,
+    score: f64,
+    passed: usize,
+    failed: usize,
+    skipped: usize,
+    duration: Duration,
+    /// Why the tests of the submission didn't run
+    error: Option<String>,
+}
+
+/// Ranks the reports of many submissions by their score in a [`GradingProfile`], and writes the
+/// ranking as CSV or JSON. Run the tests of every submission with a [`TestConfig::timeout`](crate::TestConfig::timeout),
+/// so a submission that hangs doesn't stop the others, and add its report:
+///
+/// ```no_run
+/// # use tudelft_nes_test::{run_tests_with_config, GradingProfile, Ranking, TestConfig, TestSelector, TestableCpu};
+/// # fn grade<TeamOne: TestableCpu, TeamTwo: TestableCpu>() {
+/// let profile = GradingProfile::new()
+///     .test(TestSelector::OFFICIAL_INSTRS, 60.0)
+///     .test(TestSelector::NESTEST, 40.0);
+/// let config = TestConfig {
+///     timeout: Some(std::time::Duration::from_secs(60)),
+///     ..TestConfig::default()
+/// };
+///
+/// let mut ranking = Ranking::new(profile);
+/// ranking.add("team-01", &run_tests_with_config::<TeamOne>(&config));
+/// ranking.add("team-02", &run_tests_with_config::<TeamTwo>(&config));
+/// ranking.add_error("team-03", "the submission didn't build");
+/// std::fs::write("ranking.csv", ranking.csv()).unwrap();
+/// # }
+/// ```
+///
+/// The submissions with the same score share a rank, the next rank skips as many, like 1, 2, 2, 4.
+/// A submission with an error is last, with a score of 0.
+///
+/// [`TestConfig::isolate`](crate::TestConfig::isolate) only works in a process that tests a single
+/// cpu, so to survive submissions that crash the process, build every submission as a program of its
+/// own and rank them all with [`grade_submissions`](crate::grade_submissions).
+pub struct Ranking {
+    profile: GradingProfile,
+    entries: Vec<Entry>,
+}
+
+impl Ranking {
+    /// A ranking by the score in `profile`
+    pub fn new(profile: GradingProfile) -> Self {
+        Self {
+            profile,
+            entries: Vec::new(),
+        }
+    }
+
+    /// Adds the results of the tests of `submission`
+    pub fn add(&mut self, submission: impl Into<String>, report: &TestReport) {
+        let count =
+            |f: &dyn Fn(&TestResult) -> bool| report.results.iter().filter(|r| f(r)).count();
+        self.entries.push(Entry {
+            submission: submission.into(),
+            score: self.profile.grade(report).score(),
+            passed: count(&|r| r.passed() && r.skipped.is_none()),
+            failed: count(&|r| !r.passed()),
+            skipped: count(&|r| r.skipped.is_some()),
+            duration: report.duration(),
+            error: None,
+        });
+    }
+
+    /// Adds a submission of which the tests didn't run, like one that doesn't build
+    pub fn add_error(&mut self, submission: impl Into<String>, error: impl Into<String>) {
+        self.entries.push(Entry {
+            submission: submission.into(),
+            score: 0.0,
+            passed: 0,
+            failed: 0,
+            skipped: 0,
+            duration: Duration::ZERO,
+            error: Some(error.into()),
+        });
+    }
+
+    /// The submissions with their ranks, the best first, and those with the same score by name
+    fn ranked(&self) -> Vec<(usize, &Entry)> {
+        let mut entries: Vec<&Entry> = self.entries.iter().collect();
+        entries.sort_by(|a, b| {
+            (a.error.is_some(), b.score, &a.submission)
+                .partial_cmp(&(b.error.is_some(), a.score, &b.submission))
+                .unwrap_or(std::cmp::Ordering::Equal)
+        });
+
+        let mut ranked: Vec<(usize, &Entry)> = Vec::with_capacity(entries.len());
+        for (i, entry) in entries.into_iter().enumerate() {
+            let rank = match ranked.last() {
+                Some(&(rank, last))
+                    if last.score == entry.score
+                        && last.error.is_some() == entry.error.is_some() =>
+                {
+                    rank
+                }
+                _ => i + 1,
+            };
+            ranked.push((rank, entry));
+        }
+        ranked
+    }
+
+    /// The ranking as CSV, a row per submission, the best first
+    pub fn csv(&self) -> String {
+        let mut text = String::new();
+        row(&mut text, &HEADER);
+        for (rank, entry) in self.ranked() {
+            row(
+                &mut text,
+                &[
+                    &rank.to_string(),
+                    &entry.submission,
+                    &format!("{:.1}", entry.score),
+                    &entry.passed.to_string(),
+                    &entry.failed.to_string(),
+                    &entry.skipped.to_string(),
+                    &entry.duration.as_millis().to_string(),
+                    entry.error.as_deref().unwrap_or_default(),
+                ],
+            );
+        }
+        text
+    }
+
+    /// The ranking as a JSON array, an object per submission with the same fields as the columns
+    /// of [`csv`](Self::csv), the best first
+    pub fn json(&self) -> String {
+        let entries: Vec<String> = self
+            .ranked()
+            .into_iter()
+            .map(|(rank, entry)| {
+                format!(
+                    "{{\"rank\":{rank},\"submission\":{},\"score\":{:.1},\"passed\":{},\"failed\":{},\"skipped\":{},\"duration_ms\":{},\"error\":{}}}",
+                    Value::Str(&entry.submission),
+                    entry.score,
+                    entry.passed,
+                    entry.failed,
+                    entry.skipped,
+                    entry.duration.as_millis(),
+                    entry.error.as_deref().map_or(Value::Null, Value::Str)
+                )
+            })
+            .collect();
+        format!("[\n{}\n]\n", entries.join(",\n"))
+    }
+}
@@ -0,0 +1,732 @@
+//! A simple cpu the harness tests itself with, see [`self_test`](crate::self_test). It runs a whole
+//! instruction in the first cycle and then waits for the other cycles of it. It doesn't use the ppu:
+//! reading $2002 toggles the vblank bit, which is enough for the test roms that wait for vblank.
+use crate::selftest::Bug;
+use crate::trace::{Mode, MNEMONICS, MODES};
+use crate::{BusAccess, Registers, TestableCpu, UnsupportedMapper};
+use std::error::Error;
+use tudelft_nes_ppu::{Cpu, Ppu};
+
+const CARRY: u8 = 0x01;
+const ZERO: u8 = 0x02;
+const IRQ_DISABLE: u8 = 0x04;
+const DECIMAL: u8 = 0x08;
+const BREAK: u8 = 0x10;
+const UNUSED: u8 = 0x20;
+const OVERFLOW: u8 = 0x40;
+const NEGATIVE: u8 = 0x80;
+
+/// The cycles of the 256 opcodes without page crossings or taken branches, 16 per row
+#[rustfmt::skip]
+const CYCLES: [u8; 256] = [
+    7, 6, 2, 8, 3, 3, 5, 5, 3, 2, 2, 2, 4, 4, 6, 6,
+    2, 5, 2, 8, 4, 4, 6, 6, 2, 4, 2, 7, 4, 4, 7, 7,
+    6, 6, 2, 8, 3, 3, 5, 5, 4, 2, 2, 2, 4, 4, 6, 6,
+    2, 5, 2, 8, 4, 4, 6, 6, 2, 4, 2, 7, 4, 4, 7, 7,
+    6, 6, 2, 8, 3, 3, 5, 5, 3, 2, 2, 2, 3, 4, 6, 6,
+    2, 5, 2, 8, 4, 4, 6, 6, 2, 4, 2, 7, 4, 4, 7, 7,
+    6, 6, 2, 8, 3, 3, 5, 5, 4, 2, 2, 2, 5, 4, 6, 6,
+    2, 5, 2, 8, 4, 4, 6, 6, 2, 4, 2, 7, 4, 4, 7, 7,
+    2, 6, 2, 6, 3, 3, 3, 3, 2, 2, 2, 2, 4, 4, 4, 4,
+    2, 6, 2, 6, 4, 4, 4, 4, 2, 5, 2, 5, 5, 5, 5, 5,
+    2, 6, 2, 6, 3, 3, 3, 3, 2, 2, 2, 2, 4, 4, 4, 4,
+    2, 5, 2, 5, 4, 4, 4, 4, 2, 4, 2, 4, 4, 4, 4, 4,
+    2, 6, 2, 8, 3, 3, 5, 5, 2, 2, 2, 2, 4, 4, 6, 6,
+    2, 5, 2, 8, 4, 4, 6, 6, 2, 4, 2, 7, 4, 4, 7, 7,
+    2, 6, 2, 8, 3, 3, 5, 5, 2, 2, 2, 2, 4, 4, 6, 6,
+    2, 5, 2, 8, 4, 4, 6, 6, 2, 4, 2, 7, 4, 4, 7, 7,
+];
+
+/// The banks of MMC1, which the roms of instr_test use
+struct Mmc1 {
+    shift: u8,
+    writes: u8,
+    control: u8,
+    prg_bank: u8,
+}
+
+struct Bus {
+    ram: [u8; 0x800],
+    prg_ram: [u8; 0x2000],
+    prg: Vec<u8>,
+    chr: Vec<u8>,
+    mmc1: Option<Mmc1>,
+    vblank: bool,
+    dma: bool,
+    accesses: Vec<BusAccess>,
+}
+
+impl Bus {
+    fn prg_offset(&self, address: u16) -> usize {
+        let banks = self.prg.len() / 0x4000;
+        let high = usize::from(address >= 0xC000);
+        let bank = match &self.mmc1 {
+            None => high,
+            Some(mmc1) => {
+                let bank = usize::from(mmc1.prg_bank & 0x0F);
+                match (mmc1.control >> 2) & 0b11 {
+                    0 | 1 => (bank & !1) | high,
+                    2 if high == 0 => 0,
+                    2 => bank,
+                    _ if high == 0 => bank,
+                    _ => banks - 1,
+                }
+            }
+        };
+        (bank % banks) * 0x4000 + usize::from(address & 0x3FFF)
+    }
+
+    /// Reads without side effects
+    fn peek(&self, address: u16) -> u8 {
+        match address {
+            0x0000..=0x1FFF => self.ram[usize::from(address & 0x07FF)],
+            0x2000..=0x3FFF if address & 7 == 2 => u8::from(self.vblank) << 7,
+            0x6000..=0x7FFF => self.prg_ram[usize::from(address - 0x6000)],
+            0x8000..=0xFFFF => self.prg[self.prg_offset(address)],
+            _ => 0,
+        }
+    }
+
+    fn read(&mut self, address: u16) -> u8 {
+        let value = self.peek(address);
+        self.accesses.push(BusAccess {
+            address,
+            value,
+            write: false,
+        });
+        if (0x2000..=0x3FFF).contains(&address) && address & 7 == 2 {
+            self.vblank = !self.vblank;
+        }
+        value
+    }
+
+    fn write(&mut self, address: u16, value: u8) {
+        self.accesses.push(BusAccess {
+            address,
+            value,
+            write: true,
+        });
+        match address {
+            0x0000..=0x1FFF => self.ram[usize::from(address & 0x07FF)] = value,
+            0x4014 => self.dma = true,
+            0x6000..=0x7FFF => self.prg_ram[usize::from(address - 0x6000)] = value,
+            0x8000..=0xFFFF => {
+                if let Some(mmc1) = &mut self.mmc1 {
+                    mmc1.write(address, value);
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+impl Mmc1 {
+    fn write(&mut self, address: u16, value: u8) {
+        if value & 0x80 != 0 {
+            self.shift = 0;
+            self.writes = 0;
+            self.control |= 0x0C;
+            return;
+        }
+
+        self.shift = (self.shift >> 1) | ((value & 1) << 4);
+        self.writes += 1;
+        if self.writes == 5 {
+            match (address >> 13) & 0b11 {
+                0 => self.control = self.shift,
+                3 => self.prg_bank = self.shift,
+                // the banks of chr don't matter without a ppu
+                _ => {}
+            }
+            self.shift = 0;
+            self.writes = 0;
+        }
+    }
+}
+
+/// The cpu, with the bug it has, if any
+pub(crate) struct ReferenceCpu {
+    a: u8,
+    x: u8,
+    y: u8,
+    sp: u8,
+    p: u8,
+    pc: u16,
+    cycles: u64,
+    /// the cycles left of the current instruction
+    stall: u32,
+    nmi_pending: bool,
+    bus: Bus,
+    bug: Option<Bug>,
+}
+
+impl ReferenceCpu {
+    fn new(rom: &[u8], bug: Option<Bug>) -> Result<Self, Box<dyn Error + Send + Sync>> {
+        if rom.len() < 16 || &rom[0..4] != b"NES\x1a" {
+            return Err("not an iNES rom".into());
+        }
+        let mmc1 = match (rom[6] >> 4) | (rom[7] & 0xF0) {
+            0 => None,
+            1 => Some(Mmc1 {
+                shift: 0,
+                writes: 0,
+                control: 0x0C,
+                prg_bank: 0,
+            }),
+            mapper => return Err(UnsupportedMapper(mapper).into()),
+        };
+
+        let prg_len = usize::from(rom[4]) * 0x4000;
+        let chr_len = usize::from(rom[5]) * 0x2000;
+        let start = if rom[6] & 0b100 != 0 { 16 + 512 } else { 16 };
+        if prg_len == 0 || rom.len() < start + prg_len + chr_len {
+            return Err("the rom is shorter than its header says".into());
+        }
+        let chr = match chr_len {
+            0 => vec![0; 0x2000],
+            _ => rom[start + prg_len..start + prg_len + chr_len].to_vec(),
+        };
+
+        let mut cpu = Self {
+            a: 0,
+            x: 0,
+            y: 0,
+            sp: 0xFD,
+            p: IRQ_DISABLE | UNUSED,
+            pc: 0,
+            cycles: 7,
+            stall: 0,
+            nmi_pending: false,
+            bus: Bus {
+                ram: [0; 0x800],
+                prg_ram: [0; 0x2000],
+                prg: rom[start..start + prg_len].to_vec(),
+                chr,
+                mmc1,
+                vblank: false,
+                dma: false,
+                accesses: Vec::new(),
+            },
+            bug,
+        };
+        cpu.pc = cpu.read16(0xFFFC);
+        cpu.bus.accesses.clear();
+        Ok(cpu)
+    }
+
+    fn has(&self, bug: Bug) -> bool {
+        self.bug == Some(bug)
+    }
+
+    fn read(&mut self, address: u16) -> u8 {
+        self.bus.read(address)
+    }
+
+    fn read16(&mut self, address: u16) -> u16 {
+        u16::from_le_bytes([self.read(address), self.read(address.wrapping_add(1))])
+    }
+
+    /// Reads a pointer from the zero page, which wraps around within it
+    fn read16_zero_page(&mut self, address: u8) -> u16 {
+        u16::from_le_bytes([
+            self.read(u16::from(address)),
+            self.read(u16::from(address.wrapping_add(1))),
+        ])
+    }
+
+    fn fetch(&mut self) -> u8 {
+        let value = self.read(self.pc);
+        self.pc = self.pc.wrapping_add(1);
+        value
+    }
+
+    fn fetch16(&mut self) -> u16 {
+        u16::from_le_bytes([self.fetch(), self.fetch()])
+    }
+
+    fn push(&mut self, value: u8) {
+        self.bus.write(0x0100 | u16::from(self.sp), value);
+        self.sp = self.sp.wrapping_sub(1);
+    }
+
+    fn pull(&mut self) -> u8 {
+        self.sp = self.sp.wrapping_add(1);
+        self.read(0x0100 | u16::from(self.sp))
+    }
+
+    fn push16(&mut self, value: u16) {
+        let [low, high] = value.to_le_bytes();
+        self.push(high);
+        self.push(low);
+    }
+
+    fn pull16(&mut self) -> u16 {
+        u16::from_le_bytes([self.pull(), self.pull()])
+    }
+
+    fn set_flag(&mut self, flag: u8, value: bool) {
+        if value {
+            self.p |= flag;
+        } else {
+            self.p &= !flag;
+        }
+    }
+
+    fn flag(&self, flag: u8) -> bool {
+        self.p & flag != 0
+    }
+
+    fn set_zn(&mut self, value: u8) -> u8 {
+        self.set_flag(ZERO, value == 0);
+        self.set_flag(NEGATIVE, value & 0x80 != 0);
+        value
+    }
+
+    /// The address of the operand, and whether indexing it crossed a page. For a branch, it's the
+    /// address the branch goes to.
+    fn operand(&mut self, mode: Mode) -> (u16, bool) {
+        let zero_page_indexed = |cpu: &mut Self, index: u8| {
+            let base = cpu.fetch();
+            if cpu.has(Bug::ZeroPageIndexCarry) {
+                u16::from(base) + u16::from(index)
+            } else {
+                u16::from(base.wrapping_add(index))
+            }
+        };
+
+        match mode {
+            Mode::Implied | Mode::Accumulator => (0, false),
+            Mode::Immediate => {
+                let address = self.pc;
+                self.pc = self.pc.wrapping_add(1);
+                (address, false)
+            }
+            Mode::ZeroPage => (u16::from(self.fetch()), false),
+            Mode::ZeroPageX => (zero_page_indexed(self, self.x), false),
+            Mode::ZeroPageY => (zero_page_indexed(self, self.y), false),
+            Mode::Absolute => (self.fetch16(), false),
+            Mode::AbsoluteX | Mode::AbsoluteY => {
+                let base = self.fetch16();
+                let index = if mode == Mode::AbsoluteX {
+                    self.x
+                } else {
+                    self.y
+                };
+                let address = base.wrapping_add(u16::from(index));
+                (address, base & 0xFF00 != address & 0xFF00)
+            }
+            Mode::Indirect => {
+                let pointer = self.fetch16();
+                // the high byte of the pointer doesn't change when the low byte wraps around
+                let high = (pointer & 0xFF00) | (pointer.wrapping_add(1) & 0x00FF);
+                let address = u16::from_le_bytes([self.read(pointer), self.read(high)]);
+                (address, false)
+            }
+            Mode::IndirectX => {
+                let pointer = self.fetch().wrapping_add(self.x);
+                (self.read16_zero_page(pointer), false)
+            }
+            Mode::IndirectY => {
+                let pointer = self.fetch();
+                let base = self.read16_zero_page(pointer);
+                let address = base.wrapping_add(u16::from(self.y));
+                (address, base & 0xFF00 != address & 0xFF00)
+            }
+            Mode::Relative => {
+                let offset = self.fetch() as i8;
+                let target = self.pc.wrapping_add(offset as u16);
+                (target, target & 0xFF00 != self.pc & 0xFF00)
+            }
+        }
+    }
+
+    fn adc(&mut self, value: u8) {
+        let sum = u16::from(self.a) + u16::from(value) + u16::from(self.flag(CARRY));
+        let result = sum as u8;
+        self.set_flag(CARRY, sum > 0xFF);
+        self.set_flag(
+            OVERFLOW,
+            (!(self.a ^ value) & (self.a ^ result)) & 0x80 != 0,
+        );
+        self.a = self.set_zn(result);
+    }
+
+    fn sbc(&mut self, value: u8) {
+        self.adc(!value);
+        if self.has(Bug::SbcCarry) {
+            // the carry of a subtraction is the inverse of the borrow, which this gets wrong
+            self.p ^= CARRY;
+        }
+    }
+
+    fn compare(&mut self, register: u8, value: u8) {
+        self.set_flag(CARRY, register >= value);
+        self.set_zn(register.wrapping_sub(value));
+    }
+
+    fn asl(&mut self, value: u8) -> u8 {
+        self.set_flag(CARRY, value & 0x80 != 0);
+        self.set_zn(value << 1)
+    }
+
+    fn lsr(&mut self, value: u8) -> u8 {
+        self.set_flag(CARRY, value & 1 != 0);
+        self.set_zn(value >> 1)
+    }
+
+    fn rol(&mut self, value: u8) -> u8 {
+        let carry = u8::from(self.flag(CARRY));
+        self.set_flag(CARRY, value & 0x80 != 0);
+        self.set_zn((value << 1) | carry)
+    }
+
+    fn ror(&mut self, value: u8) -> u8 {
+        let carry = u8::from(self.flag(CARRY)) << 7;
+        self.set_flag(CARRY, value & 1 != 0);
+        self.set_zn((value >> 1) | carry)
+    }
+
+    /// Reads, changes and writes back the operand, in memory or in the accumulator
+    fn modify(&mut self, mode: Mode, address: u16, f: impl FnOnce(&mut Self, u8) -> u8) -> u8 {
+        if mode == Mode::Accumulator {
+            self.a = f(self, self.a);
+            return self.a;
+        }
+        let value = self.read(address);
+        let result = f(self, value);
+        self.bus.write(address, result);
+        result
+    }
+
+    /// The unstable stores like `SHX`: the value is and-ed with the high byte of the address plus
+    /// one, and replaces that high byte when indexing crossed a page
+    fn unstable_store(&mut self, address: u16, crossed: bool, value: u8) {
+        let high = (address >> 8) as u8;
+        let base_high = if crossed { high.wrapping_sub(1) } else { high };
+        let value = value & base_high.wrapping_add(1);
+        let address = if crossed {
+            (u16::from(value) << 8) | (address & 0xFF)
+        } else {
+            address
+        };
+        self.bus.write(address, value);
+    }
+
+    fn branch(&mut self, condition: bool, target: u16, crossed: bool) -> u32 {
+        if !condition {
+            return 0;
+        }
+        self.pc = target;
+        1 + u32::from(crossed)
+    }
+
+    fn interrupt(&mut self, vector: u16, brk: bool) {
+        self.push16(self.pc);
+        let status = if brk {
+            self.p | BREAK | UNUSED
+        } else {
+            (self.p & !BREAK) | UNUSED
+        };
+        self.push(status);
+        self.p |= IRQ_DISABLE;
+        self.pc = self.read16(vector);
+    }
+
+    /// Runs an instruction, or the NMI when one is pending, and returns how many cycles it takes
+    fn step(&mut self) -> u32 {
+        if self.nmi_pending {
+            self.nmi_pending = false;
+            self.interrupt(0xFFFA, false);
+            return 7;
+        }
+
+        let opcode = usize::from(self.fetch());
+        let mode = MODES[opcode];
+        let (address, crossed) = self.operand(mode);
+        let mut cycles = u32::from(CYCLES[opcode]);
+        // indexed reads take a cycle more when they cross a page: they first read from the wrong page
+        let page_crossing = u32::from(
+            crossed
+                && matches!(mode, Mode::AbsoluteX | Mode::AbsoluteY | Mode::IndirectY)
+                && !self.has(Bug::MissingDummyRead),
+        );
+
+        match MNEMONICS[opcode] {
+            "LDA" | "LDX" | "LDY" | "LAX" => {
+                let value = self.read(address);
+                self.set_zn(value);
+                match MNEMONICS[opcode] {
+                    "LDA" => self.a = value,
+                    "LDX" => self.x = value,
+                    "LDY" => self.y = value,
+                    _ => (self.a, self.x) = (value, value),
+                }
+                cycles += page_crossing;
+            }
+            "STA" => self.bus.write(address, self.a),
+            "STX" => self.bus.write(address, self.x),
+            "STY" => self.bus.write(address, self.y),
+            "SAX" => self.bus.write(address, self.a & self.x),
+            "ADC" => {
+                let value = self.read(address);
+                self.adc(value);
+                cycles += page_crossing;
+            }
+            "SBC" => {
+                let value = self.read(address);
+                self.sbc(value);
+                cycles += page_crossing;
+            }
+            "AND" => {
+                let value = self.read(address);
+                self.a = self.set_zn(self.a & value);
+                cycles += page_crossing;
+            }
+            "ORA" => {
+                let value = self.read(address);
+                self.a = self.set_zn(self.a | value);
+                cycles += page_crossing;
+            }
+            "EOR" => {
+                let value = self.read(address);
+                self.a = self.set_zn(self.a ^ value);
+                cycles += page_crossing;
+            }
+            "CMP" => {
+                let value = self.read(address);
+                self.compare(self.a, value);
+                cycles += page_crossing;
+            }
+            "CPX" => {
+                let value = self.read(address);
+                self.compare(self.x, value);
+            }
+            "CPY" => {
+                let value = self.read(address);
+                self.compare(self.y, value);
+            }
+            "BIT" => {
+                let value = self.read(address);
+                self.set_flag(ZERO, self.a & value == 0);
+                self.set_flag(OVERFLOW, value & 0x40 != 0);
+                self.set_flag(NEGATIVE, value & 0x80 != 0);
+            }
+            "ASL" => _ = self.modify(mode, address, Self::asl),
+            "LSR" => _ = self.modify(mode, address, Self::lsr),
+            "ROL" => _ = self.modify(mode, address, Self::rol),
+            "ROR" => _ = self.modify(mode, address, Self::ror),
+            "INC" => _ = self.modify(mode, address, |cpu, v| cpu.set_zn(v.wrapping_add(1))),
+            "DEC" => _ = self.modify(mode, address, |cpu, v| cpu.set_zn(v.wrapping_sub(1))),
+            "SLO" => {
+                let value = self.modify(mode, address, Self::asl);
+                self.a = self.set_zn(self.a | value);
+                if self.has(Bug::SloResult) {
+                    self.a ^= 1;
+                }
+            }
+            "RLA" => {
+                let value = self.modify(mode, address, Self::rol);
+                self.a = self.set_zn(self.a & value);
+            }
+            "SRE" => {
+                let value = self.modify(mode, address, Self::lsr);
+                self.a = self.set_zn(self.a ^ value);
+            }
+            "RRA" => {
+                let value = self.modify(mode, address, Self::ror);
+                self.adc(value);
+            }
+            "DCP" => {
+                let value = self.modify(mode, address, |_, v| v.wrapping_sub(1));
+                self.compare(self.a, value);
+            }
+            "ISB" => {
+                let value = self.modify(mode, address, |_, v| v.wrapping_add(1));
+                self.sbc(value);
+            }
+            "ANC" => {
+                let value = self.read(address);
+                self.a = self.set_zn(self.a & value);
+                self.set_flag(CARRY, self.a & 0x80 != 0);
+            }
+            "ALR" => {
+                let value = self.read(address);
+                self.a = self.lsr(self.a & value);
+            }
+            "ARR" => {
+                let value = self.read(address);
+                let carry = u8::from(self.flag(CARRY)) << 7;
+                self.a = self.set_zn(((self.a & value) >> 1) | carry);
+                self.set_flag(CARRY, self.a & 0x40 != 0);
+                self.set_flag(OVERFLOW, ((self.a >> 6) ^ (self.a >> 5)) & 1 != 0);
+            }
+            "AXS" => {
+                let value = self.read(address);
+                let and = self.a & self.x;
+                self.set_flag(CARRY, and >= value);
+                self.x = self.set_zn(and.wrapping_sub(value));
+            }
+            "XAA" => {
+                let value = self.read(address);
+                self.a = self.set_zn(self.x & value);
+            }
+            "LAS" => {
+                let value = self.read(address) & self.sp;
+                self.a = self.set_zn(value);
+                (self.x, self.sp) = (value, value);
+                cycles += page_crossing;
+            }
+            "TAS" => {
+                self.sp = self.a & self.x;
+                self.unstable_store(address, crossed, self.a & self.x);
+            }
+            "SHA" => self.unstable_store(address, crossed, self.a & self.x),
+            "SHX" => self.unstable_store(address, crossed, self.x),
+            "SHY" => self.unstable_store(address, crossed, self.y),
+            "TAX" => self.x = self.set_zn(self.a),
+            "TAY" => self.y = self.set_zn(self.a),
+            "TXA" => self.a = self.set_zn(self.x),
+            "TYA" => self.a = self.set_zn(self.y),
+            "TSX" => self.x = self.set_zn(self.sp),
+            "TXS" => self.sp = self.x,
+            "INX" => self.x = self.set_zn(self.x.wrapping_add(1)),
+            "INY" => self.y = self.set_zn(self.y.wrapping_add(1)),
+            "DEX" => self.x = self.set_zn(self.x.wrapping_sub(1)),
+            "DEY" => self.y = self.set_zn(self.y.wrapping_sub(1)),
+            "PHA" => self.push(self.a),
+            "PHP" if self.has(Bug::BreakFlag) => self.push(self.p | UNUSED),
+            "PHP" => self.push(self.p | BREAK | UNUSED),
+            "PLA" => {
+                let value = self.pull();
+                self.a = self.set_zn(value);
+            }
+            "PLP" => self.p = (self.pull() & !BREAK) | UNUSED,
+            "JMP" => self.pc = address,
+            "JSR" => {
+                self.push16(self.pc.wrapping_sub(1));
+                self.pc = address;
+            }
+            "RTS" => self.pc = self.pull16().wrapping_add(1),
+            "RTI" => {
+                self.p = (self.pull() & !BREAK) | UNUSED;
+                self.pc = self.pull16();
+            }
+            "BRK" => {
+                self.pc = self.pc.wrapping_add(1);
+                self.interrupt(0xFFFE, true);
+            }
+            "BCC" => cycles += self.branch(!self.flag(CARRY), address, crossed),
+            "BCS" => cycles += self.branch(self.flag(CARRY), address, crossed),
+            "BNE" => cycles += self.branch(!self.flag(ZERO), address, crossed),
+            "BEQ" => cycles += self.branch(self.flag(ZERO), address, crossed),
+            "BPL" => cycles += self.branch(!self.flag(NEGATIVE), address, crossed),
+            "BMI" => cycles += self.branch(self.flag(NEGATIVE), address, crossed),
+            "BVC" => cycles += self.branch(!self.flag(OVERFLOW), address, crossed),
+            "BVS" => cycles += self.branch(self.flag(OVERFLOW), address, crossed),
+            "CLC" => self.set_flag(CARRY, false),
+            "SEC" => self.set_flag(CARRY, true),
+            "CLI" => self.set_flag(IRQ_DISABLE, false),
+            "SEI" => self.set_flag(IRQ_DISABLE, true),
+            "CLV" => self.set_flag(OVERFLOW, false),
+            "CLD" => self.set_flag(DECIMAL, false),
+            "SED" => self.set_flag(DECIMAL, true),
+            "NOP" => {
+                if mode != Mode::Implied {
+                    self.read(address);
+                }
+                cycles += page_crossing;
+            }
+            // JAM: the cpu gets stuck on the opcode
+            _ => self.pc = self.pc.wrapping_sub(1),
+        }
+
+        if self.bus.dma {
+            self.bus.dma = false;
+            cycles += 513 + u32::from((self.cycles + u64::from(cycles)) % 2 == 1);
+        }
+        cycles
+    }
+}
+
+impl TestableCpu for ReferenceCpu {
+    fn get_cpu(rom: &[u8]) -> Result<Self, Box<dyn Error + Send + Sync>> {
+        Self::new(rom, crate::selftest::current_bug())
+    }
+
+    fn set_program_counter(&mut self, value: u16) {
+        self.pc = value;
+        self.stall = 0;
+    }
+
+    fn memory_read(&self, address: u16) -> u8 {
+        if self.has(Bug::MemoryReadRamOnly) && address >= 0x2000 {
+            return 0;
+        }
+        self.bus.peek(address)
+    }
+
+    fn program_counter(&self) -> Option<u16> {
+        Some(self.pc)
+    }
+
+    fn memory_write(&mut self, address: u16, value: u8) -> bool {
+        self.bus.write(address, value);
+        true
+    }
+
+    fn reset(&mut self) -> bool {
+        self.sp = self.sp.wrapping_sub(3);
+        self.p |= IRQ_DISABLE;
+        if !self.has(Bug::ResetKeepsPc) {
+            self.pc = self.read16(0xFFFC);
+        }
+        self.stall = 0;
+        self.nmi_pending = false;
+        self.bus.write(0x8000, 0x80);
+        true
+    }
+
+    fn bus_accesses(&mut self, on_access: &mut dyn FnMut(BusAccess)) -> bool {
+        self.bus.accesses.drain(..).for_each(on_access);
+        true
+    }
+
+    fn finished_instruction(&self) -> Option<bool> {
+        Some(self.stall == 0)
+    }
+
+    fn registers(&self) -> Option<Registers> {
+        Some(Registers {
+            a: self.a,
+            x: self.x,
+            y: self.y,
+            sp: self.sp,
+            p: self.p,
+        })
+    }
+
+    fn cycles_executed(&self) -> Option<u64> {
+        Some(self.cycles)
+    }
+}
+
+impl Cpu for ReferenceCpu {
+    fn tick(&mut self, _ppu: &mut Ppu) -> Result<(), Box<dyn Error>> {
+        if self.stall == 0 {
+            self.stall = self.step();
+        }
+        self.stall -= 1;
+        self.cycles += 1;
+        Ok(())
+    }
+
+    fn ppu_read_chr_rom(&self, offset: u16) -> u8 {
+        self.bus.chr[usize::from(offset) % self.bus.chr.len()]
+    }
+
+    fn non_maskable_interrupt(&mut self) {
+        if !self.has(Bug::IgnoresNmi) {
+            self.nmi_pending = true;
+        }
+    }
+}
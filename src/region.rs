@@ -0,0 +1,87 @@
+//! The timing of the NES of a region, which the tests run with as [`TestConfig::region`](crate::TestConfig::region) says
+use std::fmt;
+
+/// The cpu cycles of two frames of an NTSC NES, which the default cycle budgets are counted in
+const NTSC_CYCLES_PER_TWO_FRAMES: u64 = 59_561;
+
+/// The region of an NES, which decides how fast its cpu runs, and how many ppu dots go in a cpu cycle
+/// and how many scanlines in a frame
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(rename_all = "lowercase")
+)]
+pub enum Region {
+    /// The NES of North America and Japan, which nearly all test roms are written for
+    #[default]
+    Ntsc,
+    /// The NES of most of Europe and Australia, with a slower cpu and longer frames
+    Pal,
+    /// The Dendy, a famiclone with the scanlines of a PAL NES, but the ppu dots per cpu cycle of an
+    /// NTSC one, so a cpu clock of about 1.773 MHz, between the 1.662 MHz of PAL and the 1.790 MHz
+    /// of NTSC, and its vblank later in the frame
+    Dendy,
+}
+
+impl Region {
+    /// All regions
+    pub const ALL: [Region; 3] = [Region::Ntsc, Region::Pal, Region::Dendy];
+
+    /// The number of cpu cycles per second
+    pub fn cpu_clock(self) -> u32 {
+        match self {
+            Region::Ntsc => 1_789_773,
+            Region::Pal => 1_662_607,
+            Region::Dendy => 1_773_448,
+        }
+    }
+
+    /// The number of ppu dots per cpu cycle
+    pub fn ppu_dots_per_cpu_cycle(self) -> f64 {
+        match self {
+            Region::Ntsc | Region::Dendy => 3.0,
+            Region::Pal => 3.2,
+        }
+    }
+
+    /// The number of scanlines in a frame, of 341 dots each
+    pub fn scanlines(self) -> u16 {
+        match self {
+            Region::Ntsc => 262,
+            Region::Pal | Region::Dendy => 312,
+        }
+    }
+
+    /// The number of cpu cycles in a frame, 29780.5 on an NTSC NES, which skips a dot every other frame
+    pub fn cycles_per_frame(self) -> f64 {
+        self.cycles_per_two_frames() as f64 / 2.0
+    }
+
+    pub(crate) fn cycles_per_two_frames(self) -> u64 {
+        match self {
+            Region::Ntsc => NTSC_CYCLES_PER_TWO_FRAMES,
+            // 341 dots on 312 scanlines, at 3.2 dots per cycle
+            Region::Pal => 66_495,
+            Region::Dendy => 70_928,
+        }
+    }
+
+    /// A cycle budget for an NTSC NES, for a rom that waits for frames, which take longer in the
+    /// other regions
+    pub(crate) fn scale(self, ntsc_cycles: u64) -> u64 {
+        let scaled = u128::from(ntsc_cycles) * u128::from(self.cycles_per_two_frames())
+            / u128::from(NTSC_CYCLES_PER_TWO_FRAMES);
+        u64::try_from(scaled).unwrap_or(u64::MAX)
+    }
+}
+
+impl fmt::Display for Region {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Region::Ntsc => "NTSC",
+            Region::Pal => "PAL",
+            Region::Dendy => "Dendy",
+        })
+    }
+}
@@ -0,0 +1,247 @@
+//! Results of a test run
+use crate::accesses::MemoryAccesses;
+use crate::grading::{Grade, GradingProfile};
+use crate::watch::WatchpointHit;
+use crate::TestSelector;
+use std::time::Duration;
+
+/// The result of running a single test
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct TestResult {
+    /// Which test this is the result of
+    pub test: TestSelector,
+    /// The name of the test, as used in messages
+    pub name: String,
+    /// The stable id of the test, see [`TestConfig::filters`](crate::TestConfig::filters)
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub id: String,
+    /// `Ok` if the cpu passed the test, otherwise a message explaining why it didn't. The message ends
+    /// with lines that start with `hint: ` when the failure looks like one that has a common cause, like
+    /// an ADC that sets the overflow flag wrong.
+    pub outcome: Result<(), String>,
+    /// How long it took to run the test
+    pub duration: Duration,
+    /// The sub-tests the test rom reported on, in the order in which they ran
+    pub sub_tests: Vec<SubTestResult>,
+    /// Sub-tests that failed, but were allowed to fail by [`TestConfig::allowed_failures`](crate::TestConfig::allowed_failures).
+    /// The test still passes when only these failed, but the sub-tests after them didn't run.
+    pub expected_failures: Vec<String>,
+    /// Why the test didn't run, when it was skipped. A skipped test passes, but isn't graded as such.
+    pub skipped: Option<String>,
+    /// The messages of the attempts that failed before the last attempt, with [`TestConfig::retries`](crate::TestConfig::retries).
+    /// A test that passed with failed attempts is flaky.
+    pub failed_attempts: Vec<String>,
+    /// The accesses to the addresses of [`TestConfig::watchpoints`](crate::TestConfig::watchpoints) in the last
+    /// attempt, at most the last 100 of every time the cpu was started during the test
+    pub watchpoint_hits: Vec<WatchpointHit>,
+    /// How often the cpu read and wrote every region of memory in the last attempt, or `None` when it
+    /// doesn't implement [`TestableCpu::bus_accesses`](crate::TestableCpu::bus_accesses). Counts that
+    /// are way off hint at what the cpu doesn't implement, see [`MemoryAccesses::hints`].
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub memory_accesses: Option<MemoryAccesses>,
+    /// When the test failed with [`TestConfig::failure_trace`](crate::TestConfig::failure_trace), the
+    /// trace of the last cycles the cpu ran, in the format of [`TraceFormat::Mesen`](crate::TraceFormat::Mesen)
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub failure_trace: Vec<String>,
+    /// The lines logged during the last attempt, with [`TestConfig::capture_logs`](crate::TestConfig::capture_logs)
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub logs: Vec<String>,
+    /// Whether the test didn't run, because it passed for the same build before, see [`TestConfig::cache_dir`](crate::TestConfig::cache_dir)
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub cached: bool,
+}
+
+/// The result of one of the sub-tests of a test rom that runs multiple tests, like `all_instrs`
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct SubTestResult {
+    /// Name of the sub-test, like `01-basics`
+    pub name: String,
+    /// Whether the cpu passed the sub-test
+    pub passed: bool,
+    /// What went wrong, when the sub-test failed and the rom said so, like `"07 SLO z"` for the
+    /// instruction that gave a wrong result
+    pub detail: Option<String>,
+}
+
+impl TestResult {
+    /// Whether the cpu passed this test
+    pub fn passed(&self) -> bool {
+        self.outcome.is_ok()
+    }
+
+    /// The stable id of one of the sub-tests of this test, like `all_instrs/11-stack`
+    pub fn sub_test_id(&self, sub_test: &SubTestResult) -> String {
+        format!("{}/{}", self.id, sub_test.name)
+    }
+
+    /// Whether the test passed, but only after failing at least once
+    pub fn flaky(&self) -> bool {
+        self.passed() && !self.failed_attempts.is_empty()
+    }
+
+    /// A result of `test` without sub-tests and attempts, for the tests of what reads the results
+    #[cfg(test)]
+    pub(crate) fn of(test: TestSelector, name: &str, outcome: Result<(), String>) -> Self {
+        Self {
+            test,
+            name: name.to_string(),
+            id: name.to_string(),
+            outcome,
+            duration: Duration::ZERO,
+            sub_tests: Vec::new(),
+            expected_failures: Vec::new(),
+            skipped: None,
+            failed_attempts: Vec::new(),
+            watchpoint_hits: Vec::new(),
+            memory_accesses: None,
+            failure_trace: Vec::new(),
+            logs: Vec::new(),
+            cached: false,
+        }
+    }
+}
+
+/// The results of all tests in a run, in the order in which they ran
+#[derive(Debug, Clone, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct TestReport {
+    /// The result of each test that ran
+    pub results: Vec<TestResult>,
+    /// The percentage of sub-tests that has to pass for the run to pass, from [`TestConfig::pass_threshold`](crate::TestConfig::pass_threshold)
+    pub pass_threshold: Option<f64>,
+    /// The seed the ram was filled from with [`RamInit::Random`](crate::RamInit::Random), which runs the
+    /// tests with the same ram again
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub ram_seed: Option<u64>,
+}
+
+impl TestReport {
+    /// Whether the cpu passed every test that ran, or with a [`pass_threshold`](Self::pass_threshold),
+    /// whether it passed enough of the sub-tests
+    pub fn passed(&self) -> bool {
+        match self.pass_threshold {
+            Some(threshold) => self.sub_test_pass_rate() >= threshold,
+            None => self.results.iter().all(TestResult::passed),
+        }
+    }
+
+    /// The percentage of the sub-tests the cpu passed, from 0 to 100. A test without sub-tests counts as
+    /// a single sub-test, and skipped tests don't count.
+    pub fn sub_test_pass_rate(&self) -> f64 {
+        let (passed, total) = self
+            .results
+            .iter()
+            .filter(|r| r.skipped.is_none())
+            .map(|r| match r.sub_tests.len() {
+                0 => (usize::from(r.passed()), 1),
+                n => (r.sub_tests.iter().filter(|s| s.passed).count(), n),
+            })
+            .fold((0, 0), |(p, t), (passed, total)| (p + passed, t + total));
+        if total == 0 {
+            return 100.0;
+        }
+
+        passed as f64 / total as f64 * 100.0
+    }
+
+    /// The results of the tests the cpu didn't pass
+    pub fn failures(&self) -> impl Iterator<Item = &TestResult> {
+        self.results.iter().filter(|r| !r.passed())
+    }
+
+    /// The total time it took to run all tests
+    pub fn duration(&self) -> Duration {
+        self.results.iter().map(|r| r.duration).sum()
+    }
+
+    /// Scores the results according to `profile`, see [`GradingProfile`]
+    pub fn grade(&self, profile: &GradingProfile) -> Grade {
+        profile.grade(self)
+    }
+
+    /// Converts the report into what [`run_tests`](crate::run_tests) returns: the first failure, if any.
+    /// A run that passed because of its [`pass_threshold`](Self::pass_threshold) is `Ok`.
+    pub fn into_result(self) -> Result<(), String> {
+        if self.pass_threshold.is_some() && self.passed() {
+            return Ok(());
+        }
+        self.results.into_iter().try_for_each(|r| r.outcome)
+    }
+}
+
+/// How far along a running test is, see [`Reporter::progress`](crate::Reporter::progress)
+#[derive(Debug, Clone)]
+#[non_exhaustive]
+pub enum Progress {
+    /// The status text of the test rom changed
+    Status(String),
+    /// `done` out of at most `budget` cycles have been run
+    Cycles {
+        /// Cycles run so far
+        done: u64,
+        /// The maximum number of cycles the test may run
+        budget: u64,
+    },
+    /// The rom finished running one of its sub-tests
+    SubTest {
+        /// Name of the sub-test, like `01-basics`
+        name: String,
+        /// Whether the cpu passed the sub-test
+        passed: bool,
+        /// What went wrong, when it failed and the rom said so
+        detail: Option<String>,
+    },
+    /// The test is done running the cpu, this is what the cpu looked like at the end
+    Finished(FinalState),
+    /// The test won't run, because the cpu lacks something it needs
+    Skipped(String),
+}
+
+/// What the cpu looked like when a test was done running it,
+/// used by [`TestConfig::check_determinism`](crate::TestConfig::check_determinism)
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FinalState {
+    /// The number of cycles the cpu ran
+    pub cycles: u64,
+    /// The status text the test rom wrote at $6004, or an empty string for roms that don't
+    pub status: String,
+    /// The contents of the internal ram, $0000 to $07FF
+    pub ram: Vec<u8>,
+    /// The last accesses to the addresses of [`TestConfig::watchpoints`](crate::TestConfig::watchpoints)
+    pub watchpoint_hits: Vec<WatchpointHit>,
+    /// How often the cpu read and wrote every region of memory, or `None` when it doesn't implement
+    /// [`TestableCpu::bus_accesses`](crate::TestableCpu::bus_accesses)
+    pub memory_accesses: Option<MemoryAccesses>,
+    /// The trace of the last cycles the cpu ran, with [`TestConfig::failure_trace`](crate::TestConfig::failure_trace),
+    /// and empty otherwise
+    pub trace: Vec<String>,
+}
+
+impl FinalState {
+    /// Explains the first difference between two final states, if there is one
+    pub(crate) fn difference(&self, other: &FinalState) -> Option<String> {
+        if self.cycles != other.cycles {
+            Some(format!(
+                "it ran {} cycles the first time and {} cycles the second time",
+                self.cycles, other.cycles
+            ))
+        } else if self.status != other.status {
+            Some(format!(
+                "the status text was {:?} the first time and {:?} the second time",
+                self.status, other.status
+            ))
+        } else {
+            let (address, (a, b)) = self
+                .ram
+                .iter()
+                .zip(&other.ram)
+                .enumerate()
+                .find(|(_, (a, b))| a != b)?;
+            Some(format!(
+                "memory at ${address:04X} was ${a:02X} the first time and ${b:02X} the second time"
+            ))
+        }
+    }
+}
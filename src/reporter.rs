@@ -0,0 +1,70 @@
+//! Reporters receive the results of a test run while it happens
+use crate::report::{Progress, TestReport, TestResult};
+
+/// Implement this trait to receive the results of a test run, for example to send them to a
+/// grading service. Pass it to [`run_tests_with_reporter`](crate::run_tests_with_reporter).
+/// Every method has an empty default implementation, so you only need to implement what you use.
+///
+/// [`TextReporter`](crate::TextReporter) writes a human-readable report to any [`std::io::Write`].
+/// [`JsonReporter`](crate::JsonReporter) writes every event as a line of JSON, for frontends following a run live.
+/// [`SummaryReporter`](crate::SummaryReporter) writes a summary in Markdown or HTML, for a pull request or a CI page.
+pub trait Reporter {
+    /// Called once before any test runs, with the number of tests that will run
+    fn run_started(&mut self, _tests: usize) {}
+
+    /// Called when a test starts
+    fn test_started(&mut self, _name: &str) {}
+
+    /// Called whenever a running test makes progress
+    fn progress(&mut self, _name: &str, _progress: &Progress) {}
+
+    /// Called when a test finished, with its result
+    fn test_finished(&mut self, _result: &TestResult) {}
+
+    /// Called once after all tests ran, with the results of all of them
+    fn run_finished(&mut self, _report: &TestReport) {}
+}
+
+impl<R: Reporter + ?Sized> Reporter for &mut R {
+    fn run_started(&mut self, tests: usize) {
+        (**self).run_started(tests)
+    }
+
+    fn test_started(&mut self, name: &str) {
+        (**self).test_started(name)
+    }
+
+    fn progress(&mut self, name: &str, progress: &Progress) {
+        (**self).progress(name, progress)
+    }
+
+    fn test_finished(&mut self, result: &TestResult) {
+        (**self).test_finished(result)
+    }
+
+    fn run_finished(&mut self, report: &TestReport) {
+        (**self).run_finished(report)
+    }
+}
+
+impl<R: Reporter + ?Sized> Reporter for Box<R> {
+    fn run_started(&mut self, tests: usize) {
+        (**self).run_started(tests)
+    }
+
+    fn test_started(&mut self, name: &str) {
+        (**self).test_started(name)
+    }
+
+    fn progress(&mut self, name: &str, progress: &Progress) {
+        (**self).progress(name, progress)
+    }
+
+    fn test_finished(&mut self, result: &TestResult) {
+        (**self).test_finished(result)
+    }
+
+    fn run_finished(&mut self, report: &TestReport) {
+        (**self).run_finished(report)
+    }
+}
@@ -0,0 +1,153 @@
+//! Tracing only the end of a test: the state of the cpu is saved every so often while it runs, and
+//! when it's done, the cpu goes back to a saved state and runs to the end again with tracing, see
+//! [`TestConfig::failure_trace`](crate::TestConfig::failure_trace)
+use crate::log_target;
+use crate::step::Step;
+use crate::{Executor, Machine, NametableMirroring, TestableCpu, TraceFormat};
+use std::collections::VecDeque;
+use std::error::Error;
+use std::fmt;
+use tudelft_nes_ppu::{run_cpu_headless_for, Cpu, Ppu};
+
+/// A state of the cpu from [`TestableCpu::save_state`], after `cycles` cycles
+struct Save {
+    cycles: u64,
+    state: Vec<u8>,
+}
+
+/// The last two states of the cpu, saved every `interval` cycles. Going back to the older one traces
+/// at least `interval` cycles, even when the newer one was saved just before the end.
+pub(crate) struct Rewind {
+    interval: u64,
+    saves: VecDeque<Save>,
+}
+
+impl Rewind {
+    pub(crate) fn new(interval: u64) -> Self {
+        Self {
+            interval: interval.max(1),
+            saves: VecDeque::with_capacity(2),
+        }
+    }
+
+    /// Saves the state of `cpu` when another interval started after `cycles` cycles
+    pub(crate) fn observe<T: TestableCpu>(&mut self, cpu: &T, cycles: u64) {
+        if cycles % self.interval != 0 {
+            return;
+        }
+        let Some(state) = cpu.save_state() else {
+            return;
+        };
+        if self.saves.len() == 2 {
+            self.saves.pop_front();
+        }
+        self.saves.push_back(Save { cycles, state });
+    }
+
+    /// Loads the older saved state into `cpu` and runs it with `executor` until `cycles`, with a line of trace for every
+    /// instruction. Returns `None` when there is no state to go back to, because the cpu doesn't
+    /// implement [`TestableCpu::save_state`] or can't load its own state.
+    pub(crate) fn replay<T: TestableCpu>(
+        &self,
+        cpu: &mut T,
+        executor: &dyn Executor,
+        mirroring: NametableMirroring,
+        cycles: u64,
+    ) -> Option<Vec<String>> {
+        let save = self.saves.front()?;
+        if !cpu.load_state(&save.state) {
+            log_target::warn!(
+                "couldn't trace the end of the test, the cpu didn't load its own state"
+            );
+            return None;
+        }
+
+        let mut replay = Replay {
+            cpu,
+            mirroring,
+            cycles: save.cycles,
+            until: cycles,
+            previous_pc: None,
+            lines: Vec::new(),
+        };
+        let remaining = usize::try_from(cycles - save.cycles).unwrap_or(usize::MAX);
+        // the replay ends with our own `Done`, or an error of the cpu, which ends the trace as well
+        let _ = executor.run(&mut replay, remaining);
+        Some(replay.lines)
+    }
+}
+
+/// Returned from [`Cpu::tick`] once the replay got to the end
+#[derive(Debug)]
+struct Done;
+
+impl fmt::Display for Done {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "the replay is done")
+    }
+}
+
+impl Error for Done {}
+
+/// Runs the cpu from a saved state, and traces every instruction it runs
+struct Replay<'a, T: TestableCpu> {
+    cpu: &'a mut T,
+    mirroring: NametableMirroring,
+    cycles: u64,
+    until: u64,
+    previous_pc: Option<u16>,
+    lines: Vec<String>,
+}
+
+impl<T: TestableCpu> Cpu for Replay<'_, T> {
+    fn tick(&mut self, ppu: &mut Ppu) -> Result<(), Box<dyn Error>> {
+        if self.cycles >= self.until {
+            return Err(Box::new(Done));
+        }
+        self.cpu.tick(ppu)?;
+        self.cycles += 1;
+        // the accesses are taken, so the cpu doesn't keep them around
+        self.cpu.bus_accesses(&mut |_| {});
+
+        let pc = self.cpu.program_counter();
+        let changed = pc.is_some() && pc != self.previous_pc;
+        self.previous_pc = pc;
+        if self.cpu.finished_instruction().unwrap_or(changed) {
+            let cpu = &*self.cpu;
+            let step = Step {
+                cycle: self.cycles,
+                instruction: self.lines.len() as u64 + 1,
+                program_counter: pc,
+                registers: cpu.registers(),
+                bus_accesses: &[],
+                cpu,
+                memory: &|address| cpu.memory_peek(address),
+            };
+            self.lines.extend(step.trace_line(TraceFormat::Mesen));
+        }
+        Ok(())
+    }
+
+    fn ppu_read_chr_rom(&self, offset: u16) -> u8 {
+        self.cpu.ppu_read_chr_rom(offset)
+    }
+
+    fn non_maskable_interrupt(&mut self) {
+        self.cpu.non_maskable_interrupt()
+    }
+}
+
+impl<T: TestableCpu> Machine for Replay<'_, T> {
+    fn run_cycles(&mut self, cycles: usize) -> Result<(), Box<dyn Error>> {
+        let mirroring = self.mirroring.into();
+        run_cpu_headless_for(self, mirroring, cycles)
+    }
+
+    fn peek(&self, address: u16) -> u8 {
+        self.cpu.memory_peek(address)
+    }
+
+    fn cycles(&self) -> u64 {
+        self.cycles
+    }
+}
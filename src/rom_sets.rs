@@ -0,0 +1,354 @@
+//! Sets of test roms that aren't bundled with this crate, but are loaded from the rom directory.
+//! Every rom of a set runs as a sub-test, so the results show which of them failed. The sets are
+//! described by a manifest, `rom_sets.toml`, so adding one doesn't take any code, and suites of your
+//! own are described the same way, see [`TestConfig::suites`](crate::TestConfig::suites).
+use crate::config::{parse_address, parse_region, parse_test, ConfigError};
+use crate::{Buttons, InputScript, Region, StatusAddresses, TestSelector};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+
+/// The manifest of the sets of roms of nes-test-roms
+const MANIFEST: &str = include_str!("rom_sets.toml");
+
+/// How the roms of a set report their result
+pub(crate) enum Protocol {
+    /// The status at $6000 of the newer roms of blargg
+    Status,
+    /// The older roms of blargg only store a result code at this address: 1 when they passed, or
+    /// else a number that tells which check failed, explained in the readme of the roms
+    ResultCode(u16),
+    /// The rom only draws its result on the screen, which the harness can't see, so it can only be
+    /// watched in the window, see [`run_tests_with_window`](crate::run_tests_with_window)
+    Visual,
+    /// The rom only draws its result on the screen, and is checked by the SHA-256 of the frame it drew
+    /// after this many frames, in [`RomSet::frame_sha256`]. The harness sees the frame with
+    /// [`TestableCpu::frame`](crate::TestableCpu::frame).
+    Frames(u64),
+}
+
+/// A set of test roms, like one from [nes-test-roms](https://github.com/christopherpow/nes-test-roms)
+pub(crate) struct RomSet {
+    pub(crate) selector: TestSelector,
+    pub(crate) name: String,
+    /// The directory of the roms in nes-test-roms. They're looked up in this directory inside the
+    /// rom directory, and in the rom directory itself.
+    pub(crate) dir: String,
+    /// The directory of the manifest of a suite of your own, which `dir` is relative to instead of
+    /// the rom directory
+    pub(crate) root: Option<PathBuf>,
+    pub(crate) roms: Vec<String>,
+    pub(crate) protocol: Protocol,
+    /// Where the roms report their status with [`Protocol::Status`], $6000 unless a suite of your own
+    /// says otherwise
+    pub(crate) status_addresses: StatusAddresses,
+    /// Whether the roms need a cpu that steals cycles for DMA, see [`TestableCpu::supports_dma`](crate::TestableCpu::supports_dma)
+    pub(crate) needs_dma: bool,
+    /// The buttons the roms need to be pressed, if any
+    pub(crate) input: Option<InputScript>,
+    /// The default cycle budget of a single rom
+    pub(crate) cycles: u64,
+    /// The default instruction budget of a single rom, if it has one
+    pub(crate) instructions: Option<u64>,
+    /// The regions the roms work in, see [`TestConfig::region`](crate::TestConfig::region)
+    pub(crate) regions: Vec<Region>,
+    /// The SHA-256 of the roms that are known, in lowercase hexadecimal, to check them when they're
+    /// downloaded, see [`TestConfig::download_dir`](crate::TestConfig::download_dir)
+    pub(crate) sha256: HashMap<String, String>,
+    /// The SHA-256 of the frame every rom draws, in lowercase hexadecimal, with [`Protocol::Frames`]
+    pub(crate) frame_sha256: HashMap<String, String>,
+}
+
+/// The sets of roms of nes-test-roms, in the order in which they run
+pub(crate) fn bundled() -> &'static [RomSet] {
+    static SETS: OnceLock<Vec<RomSet>> = OnceLock::new();
+    SETS.get_or_init(|| {
+        let table = MANIFEST
+            .parse::<toml::Table>()
+            .expect("rom_sets.toml is valid toml");
+        parse(&table, None).unwrap_or_else(|e| panic!("rom_sets.toml is invalid: {e}"))
+    })
+}
+
+/// Reads the suites of your own from the manifest at `path`, see [`TestConfig::suites`](crate::TestConfig::suites)
+pub(crate) fn load(path: &Path) -> Result<Vec<RomSet>, ConfigError> {
+    let text = std::fs::read_to_string(path).map_err(|source| ConfigError::Io {
+        path: path.to_path_buf(),
+        source,
+    })?;
+    let table = text
+        .parse::<toml::Table>()
+        .map_err(|source| ConfigError::Toml {
+            path: path.to_path_buf(),
+            source,
+        })?;
+    let root = path.parent().unwrap_or(Path::new("")).to_path_buf();
+    parse(&table, Some(root))
+}
+
+/// The sets in the `[[suite]]` tables of a manifest. The suites of your own, with a `root`, are
+/// [`TestSelector::CUSTOM`] unless they say otherwise.
+fn parse(manifest: &toml::Table, root: Option<PathBuf>) -> Result<Vec<RomSet>, ConfigError> {
+    let invalid = |key: &str, message: &str| ConfigError::Invalid {
+        key: key.to_string(),
+        message: message.to_string(),
+    };
+    if let Some(key) = manifest.keys().find(|key| *key != "suite") {
+        return Err(invalid(key, "unknown setting, expected [[suite]] tables"));
+    }
+    let Some(suites) = manifest.get("suite") else {
+        return Ok(Vec::new());
+    };
+    let suites = suites
+        .as_array()
+        .ok_or_else(|| invalid("suite", "expected [[suite]] tables"))?;
+
+    suites
+        .iter()
+        .enumerate()
+        .map(|(i, suite)| {
+            let suite = suite
+                .as_table()
+                .ok_or_else(|| invalid(&format!("suite.{i}"), "expected a table"))?;
+            parse_suite(&format!("suite.{i}"), suite, root.clone())
+        })
+        .collect()
+}
+
+fn parse_suite(
+    key: &str,
+    suite: &toml::Table,
+    root: Option<PathBuf>,
+) -> Result<RomSet, ConfigError> {
+    let invalid = |field: &str, message: &str| ConfigError::Invalid {
+        key: format!("{key}.{field}"),
+        message: message.to_string(),
+    };
+    let string = |field: &str| match suite.get(field) {
+        Some(value) => value
+            .as_str()
+            .ok_or_else(|| invalid(field, "expected a string")),
+        None => Err(invalid(field, "missing setting")),
+    };
+    let strings = |field: &str| match suite.get(field) {
+        Some(value) => value
+            .as_array()
+            .ok_or_else(|| invalid(field, "expected a list of strings"))?
+            .iter()
+            .map(|s| {
+                s.as_str()
+                    .map(str::to_string)
+                    .ok_or_else(|| invalid(field, "expected a list of strings"))
+            })
+            .collect(),
+        None => Ok(Vec::new()),
+    };
+    let number = |field: &str| {
+        suite
+            .get(field)
+            .map(|value| {
+                value
+                    .as_integer()
+                    .and_then(|n| u64::try_from(n).ok())
+                    .ok_or_else(|| invalid(field, "expected a number"))
+            })
+            .transpose()
+    };
+
+    let hashes = |field: &str, value: &toml::Value| {
+        let hashes = value
+            .as_table()
+            .ok_or_else(|| invalid(field, "expected a table of roms and their sha256"))?;
+        hashes
+            .iter()
+            .map(|(rom, hash)| {
+                let hash = hash
+                    .as_str()
+                    .filter(|h| h.len() == 64 && h.bytes().all(|b| b.is_ascii_hexdigit()))
+                    .ok_or_else(|| {
+                        invalid(&format!("{field}.{rom}"), "expected 64 hexadecimal digits")
+                    })?;
+                Ok((rom.clone(), hash.to_lowercase()))
+            })
+            .collect::<Result<HashMap<_, _>, ConfigError>>()
+    };
+
+    let mut set = RomSet {
+        selector: TestSelector::CUSTOM,
+        name: string("name")?.to_string(),
+        dir: match suite.get("dir") {
+            Some(_) => string("dir")?.to_string(),
+            None => String::new(),
+        },
+        root,
+        roms: strings("roms")?,
+        protocol: Protocol::Status,
+        status_addresses: StatusAddresses::default(),
+        needs_dma: false,
+        input: None,
+        cycles: number("cycles")?.unwrap_or(10_000_000),
+        instructions: number("instructions")?,
+        regions: Region::ALL.to_vec(),
+        sha256: HashMap::new(),
+        frame_sha256: HashMap::new(),
+    };
+    if set.roms.is_empty() {
+        return Err(invalid("roms", "expected the file names of the roms"));
+    }
+
+    for (field, value) in suite {
+        match field.as_str() {
+            "name" | "dir" | "roms" | "cycles" | "instructions" | "result_address" | "frame" => {}
+            "test" => set.selector = parse_test(&format!("{key}.test"), string("test")?)?,
+            "protocol" => {
+                set.protocol = match string("protocol")? {
+                    "status" => Protocol::Status,
+                    "result_code" => Protocol::ResultCode(parse_address(
+                        &format!("{key}.result_address"),
+                        string("result_address")?,
+                    )?),
+                    "visual" => Protocol::Visual,
+                    "frames" => Protocol::Frames(
+                        number("frame")?.ok_or_else(|| invalid("frame", "missing setting"))?,
+                    ),
+                    other => {
+                        return Err(invalid(
+                            field,
+                            &format!(
+                                "unknown protocol '{other}', expected status, result_code, visual \
+                                 or frames"
+                            ),
+                        ))
+                    }
+                }
+            }
+            "sha256" => set.sha256 = hashes(field, value)?,
+            "frame_sha256" => set.frame_sha256 = hashes(field, value)?,
+            "regions" => {
+                set.regions = strings("regions")?
+                    .iter()
+                    .map(|region| parse_region(&format!("{key}.regions"), region))
+                    .collect::<Result<_, _>>()?;
+            }
+            "status_address" => {
+                let address = parse_address(&format!("{key}.{field}"), string(field)?)?;
+                set.status_addresses = StatusAddresses::at(address);
+            }
+            "dma" => {
+                set.needs_dma = value
+                    .as_bool()
+                    .ok_or_else(|| invalid(field, "expected true or false"))?;
+            }
+            "presses" => {
+                let presses = strings("presses")?;
+                let script = presses.iter().try_fold(InputScript::new(), |script, press| {
+                    let (frame, buttons) = parse_press(press).ok_or_else(|| {
+                        invalid(
+                            field,
+                            &format!("expected a frame and buttons like \"60 a+start\", got '{press}'"),
+                        )
+                    })?;
+                    Ok(script.press(frame, buttons))
+                })?;
+                set.input = Some(script);
+            }
+            _ => return Err(invalid(field, "unknown setting")),
+        }
+    }
+
+    Ok(set)
+}
+
+/// Parses a press of buttons on controller 1, like `60 a+start`, into its frame and its buttons
+fn parse_press(press: &str) -> Option<(u64, Buttons)> {
+    let (frame, buttons) = press.trim().split_once(' ')?;
+    let buttons = buttons
+        .split('+')
+        .map(|button| match button.trim().to_lowercase().as_str() {
+            "a" => Some(Buttons::A),
+            "b" => Some(Buttons::B),
+            "select" => Some(Buttons::SELECT),
+            "start" => Some(Buttons::START),
+            "up" => Some(Buttons::UP),
+            "down" => Some(Buttons::DOWN),
+            "left" => Some(Buttons::LEFT),
+            "right" => Some(Buttons::RIGHT),
+            _ => None,
+        })
+        .try_fold(Buttons::empty(), |all, button| Some(all | button?))?;
+    Some((frame.trim().parse().ok()?, buttons))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn suites(manifest: &str) -> Result<Vec<RomSet>, ConfigError> {
+        let table = manifest.parse::<toml::Table>().expect("valid toml");
+        parse(&table, Some(PathBuf::from("roms")))
+    }
+
+    #[test]
+    fn the_bundled_sets_report_their_status_at_6000() {
+        assert!(bundled()
+            .iter()
+            .all(|set| set.status_addresses == StatusAddresses::default()));
+    }
+
+    #[test]
+    fn suites_can_report_their_status_somewhere_else() {
+        let sets = suites(
+            r#"
+            [[suite]]
+            name = "mine"
+            roms = ["a.nes"]
+            status_address = "$7000"
+            "#,
+        )
+        .unwrap();
+        assert_eq!(sets[0].status_addresses, StatusAddresses::at(0x7000));
+        assert_eq!(sets[0].selector, TestSelector::CUSTOM);
+
+        let Err(error) = suites(
+            r#"
+            [[suite]]
+            name = "mine"
+            roms = ["a.nes"]
+            status_address = "7000"
+            "#,
+        ) else {
+            panic!("an address without $ isn't an address");
+        };
+        assert!(error.to_string().contains("suite.0.status_address"));
+    }
+
+    #[test]
+    fn suites_can_compare_the_frames_of_their_roms() {
+        let hash = "AB".repeat(32);
+        let sets = suites(&format!(
+            r#"
+            [[suite]]
+            name = "mine"
+            roms = ["a.nes"]
+            protocol = "frames"
+            frame = 60
+            frame_sha256 = {{ "a.nes" = "{hash}" }}
+            "#
+        ))
+        .unwrap();
+        assert!(matches!(sets[0].protocol, Protocol::Frames(60)));
+        assert_eq!(sets[0].frame_sha256["a.nes"], hash.to_lowercase());
+
+        let Err(error) = suites(
+            r#"
+            [[suite]]
+            name = "mine"
+            roms = ["a.nes"]
+            protocol = "frames"
+            "#,
+        ) else {
+            panic!("a suite that compares frames needs to say which frame");
+        };
+        assert!(error.to_string().contains("suite.0.frame"));
+    }
+}
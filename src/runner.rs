@@ -0,0 +1,434 @@
+//! Runs a [`TestableCpu`] on the ppu while keeping an eye on it
+use crate::accesses::MemoryAccesses;
+use crate::all_instrs::{has_status, reset_requested};
+use crate::closures::CpuClosures;
+use crate::halt::HaltDetector;
+use crate::input::{Buttons, InputEvent, InputScript};
+use crate::log_target;
+use crate::report::{FinalState, Progress};
+use crate::rewind::Rewind;
+use crate::status::{read_status_string_at, StatusAddresses};
+use crate::step::{Step, StepCallback};
+use crate::watch::{BusAccess, Watcher, Watchpoint};
+use crate::{
+    ines, CancelToken, Executor, HeadlessExecutor, Machine, NametableMirroring, Region, TestConfig,
+    TestError, TestableCpu,
+};
+use std::error::Error;
+use std::fmt;
+use std::sync::mpsc::Sender;
+use std::sync::Arc;
+use std::thread;
+use tudelft_nes_ppu::{run_cpu_headless_for, Cpu, Ppu};
+
+/// Wraps the cpu under test, so the harness can observe it on every cycle.
+/// When the test is done with it, the final state of the cpu is sent as [`Progress::Finished`].
+pub(crate) struct Runner<T: TestableCpu> {
+    pub(crate) cpu: T,
+    halt: Option<HaltDetector>,
+    stuck: bool,
+    cycles: u64,
+    progress: Sender<Progress>,
+    /// the button presses that are still to come, in reverse order
+    input: Vec<InputEvent>,
+    /// the buttons held on controller 1 and 2
+    held: [Buttons; 2],
+    watcher: Watcher,
+    on_step: Option<StepCallback>,
+    mirroring: NametableMirroring,
+    region: Region,
+    cancel: Option<CancelToken>,
+    status_addresses: StatusAddresses,
+    instructions: u64,
+    /// the number of instructions after which the cpu stops, and whether it got there
+    instruction_budget: Option<u64>,
+    out_of_instructions: bool,
+    /// the bus accesses of the instruction that is running, for the step callback
+    accesses: Vec<BusAccess>,
+    /// the program counter after the previous tick, to see when an instruction finished
+    previous_pc: Option<u16>,
+    /// why the step callback or cancelling the test run stopped the cpu
+    stopped: Option<String>,
+    /// the program counter at which the cpu is stopped, and whether it got there
+    stop_at: Option<u16>,
+    reached_stop: bool,
+    /// a program counter of which is remembered whether the cpu ran the instruction there
+    watched_pc: Option<u16>,
+    visited: bool,
+    /// the reads and writes of every region of memory, when the cpu reports its bus accesses
+    memory_accesses: Option<MemoryAccesses>,
+    /// the saved states to trace the end of the test from, with [`TestConfig::failure_trace`]
+    rewind: Option<Rewind>,
+    executor: Arc<dyn Executor>,
+}
+
+/// How the harness runs the cpu in a test, taken from the [`TestConfig`] before the test moves to
+/// its own thread
+pub(crate) struct RunOptions {
+    watchpoints: Vec<Watchpoint>,
+    on_step: Option<StepCallback>,
+    pub(crate) mirroring: NametableMirroring,
+    region: Region,
+    cancel: Option<CancelToken>,
+    status_addresses: StatusAddresses,
+    failure_trace: Option<u64>,
+    instruction_budget: Option<u64>,
+    /// the bytes to fill the ram with before the cpu runs
+    ram: Option<Vec<u8>>,
+    executor: Arc<dyn Executor>,
+    /// what creates the cpus instead of [`TestableCpu::get_cpu`], see [`TestConfig::closures`]
+    pub(crate) closures: Option<CpuClosures>,
+}
+
+impl RunOptions {
+    /// The options for running `rom`, which has the mirroring of its header unless the configuration
+    /// overrides it
+    pub(crate) fn of(config: &TestConfig, rom: &[u8]) -> Self {
+        Self {
+            watchpoints: config.watchpoints.clone(),
+            on_step: config.on_step.clone(),
+            mirroring: config.mirroring.unwrap_or_else(|| ines::mirroring(rom)),
+            region: config.region,
+            cancel: config.cancel.clone(),
+            status_addresses: StatusAddresses::default(),
+            failure_trace: config.failure_trace,
+            instruction_budget: None,
+            ram: config.ram_init.bytes(),
+            executor: config
+                .executor
+                .clone()
+                .unwrap_or_else(|| Arc::new(HeadlessExecutor)),
+            closures: config.closures.clone(),
+        }
+    }
+
+    /// Stops the cpu after `budget` instructions, see [`TestConfig::instruction_budgets`]
+    pub(crate) fn instruction_budget(mut self, budget: Option<u64>) -> Self {
+        self.instruction_budget = budget;
+        self
+    }
+
+    /// Reads the status of a rom that reports it like the roms of blargg do at `at`, instead of at $6000
+    pub(crate) fn status_addresses(mut self, at: StatusAddresses) -> Self {
+        self.status_addresses = at;
+        self
+    }
+
+    /// Calls `on_step` after every instruction, instead of the [`TestConfig::on_step`] callback
+    pub(crate) fn on_step(mut self, on_step: Option<StepCallback>) -> Self {
+        self.on_step = on_step;
+        self
+    }
+}
+
+/// Returned from [`Cpu::tick`] to break out of the [`Executor`] early
+#[derive(Debug)]
+struct Stuck;
+
+impl fmt::Display for Stuck {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "cpu is stuck")
+    }
+}
+
+impl Error for Stuck {}
+
+impl<T: TestableCpu> Runner<T> {
+    pub(crate) fn new(mut cpu: T, progress: &Sender<Progress>, options: &RunOptions) -> Self {
+        cpu.set_region(options.region);
+        if let Some(ram) = &options.ram {
+            if !cpu.init_ram(ram) {
+                log_target::warn!("the ram isn't filled as TestConfig::ram_init says, the cpu needs TestableCpu::memory_write or TestableCpu::init_ram");
+            }
+            // the writes filling the ram aren't accesses of the test
+            cpu.bus_accesses(&mut |_| {});
+        }
+
+        Self {
+            cpu,
+            halt: None,
+            stuck: false,
+            cycles: 0,
+            progress: progress.clone(),
+            input: Vec::new(),
+            held: [Buttons::empty(); 2],
+            watcher: Watcher::new(&options.watchpoints),
+            on_step: options.on_step.clone(),
+            mirroring: options.mirroring,
+            region: options.region,
+            cancel: options.cancel.clone(),
+            status_addresses: options.status_addresses,
+            instructions: 0,
+            instruction_budget: options.instruction_budget,
+            out_of_instructions: false,
+            accesses: Vec::new(),
+            previous_pc: None,
+            stopped: None,
+            stop_at: None,
+            reached_stop: false,
+            watched_pc: None,
+            visited: false,
+            memory_accesses: None,
+            rewind: options.failure_trace.map(Rewind::new),
+            executor: options.executor.clone(),
+        }
+    }
+
+    /// Remembers whether the cpu gets to `pc`, see [`visited`](Self::visited)
+    pub(crate) fn watch_pc(mut self, pc: u16) -> Self {
+        self.watched_pc = Some(pc);
+        self
+    }
+
+    /// Whether the cpu got to the program counter of [`watch_pc`](Self::watch_pc), `None` when the cpu
+    /// doesn't implement [`TestableCpu::program_counter`]
+    pub(crate) fn visited(&self) -> Option<bool> {
+        self.cpu.program_counter().map(|_| self.visited)
+    }
+
+    /// Stops the cpu once it finished the instruction before `pc`, so it's about to run the instruction
+    /// at `pc`. It doesn't run any further after that.
+    pub(crate) fn stop_at(mut self, pc: u16) -> Self {
+        self.stop_at = Some(pc);
+        self
+    }
+
+    /// Whether the cpu got to the program counter of [`stop_at`](Self::stop_at)
+    pub(crate) fn reached_stop(&self) -> bool {
+        self.reached_stop
+    }
+
+    /// Presses the reset button with [`TestableCpu::reset`], and returns whether the cpu implements it.
+    /// From then on, [`visited`](Self::visited) says whether the cpu got to the watched program counter
+    /// after the reset.
+    pub(crate) fn reset(&mut self) -> bool {
+        self.visited = false;
+        if !self.cpu.reset() {
+            return false;
+        }
+        // a cpu that jumps to the reset vector right away doesn't tick to get there
+        self.visited = self.watched_pc.is_some() && self.cpu.program_counter() == self.watched_pc;
+        true
+    }
+
+    /// Presses buttons according to `script` while the cpu runs. Fails when the cpu doesn't
+    /// implement [`TestableCpu::set_buttons`].
+    pub(crate) fn with_input(mut self, script: &InputScript) -> Result<Self, TestError> {
+        if !self.cpu.set_buttons(1, Buttons::empty()) {
+            return Err(TestError::String(
+                "this rom needs TestableCpu::set_buttons to press buttons on the controller"
+                    .to_owned(),
+            ));
+        }
+
+        self.input = script.events();
+        self.input.reverse();
+        Ok(self)
+    }
+
+    /// Runs the cpu for `cycles` cycles. Returns early, without an error, once the cpu is stuck.
+    pub(crate) fn run_for(&mut self, cycles: usize) -> Result<(), String> {
+        if self.stuck() || self.reached_stop {
+            return Ok(());
+        }
+
+        let executor = self.executor.clone();
+        match executor.run(self, cycles) {
+            Err(_) if self.stopped.is_some() => Err(self.stopped.clone().unwrap_or_default()),
+            // the error is our own `Stuck`, which may have been wrapped by the ppu
+            Err(_) if self.stuck() || self.reached_stop => Ok(()),
+            Err(e) => Err(e.to_string()),
+            Ok(()) => Ok(()),
+        }
+    }
+
+    /// Whether the cpu ran all instructions of its budget, see [`RunOptions::instruction_budget`]
+    pub(crate) fn out_of_instructions(&self) -> bool {
+        self.out_of_instructions
+    }
+
+    /// Whether the cpu got stuck in a tight loop or ran out of its instructions, which also means it
+    /// won't run any further
+    pub(crate) fn stuck(&self) -> bool {
+        self.stuck || self.out_of_instructions
+    }
+
+    /// When the cpu got stuck or ran out of its instructions, adds that to the error of a failed test
+    pub(crate) fn explain(&self, result: Result<(), TestError>) -> Result<(), TestError> {
+        match (result, &self.halt) {
+            // a rom that reported its result is stuck in the loop at its end, which just adds to the message
+            (Err(e), Some(halt)) if self.stuck => Err(e.map(|e| format!("{}: {e}", halt.report()))),
+            (Err(e), _) if self.out_of_instructions => Err(e.map(|e| {
+                format!(
+                    "the cpu ran out of its budget of {} instructions: {e}",
+                    self.instructions
+                )
+            })),
+            (result, _) => result,
+        }
+    }
+}
+
+impl<T: TestableCpu> Runner<T> {
+    /// Calls the step callback when the cpu finished an instruction in the last tick, and counts the
+    /// instructions of the budget
+    fn step(&mut self, pc: Option<u16>) -> Result<(), Box<dyn Error>> {
+        let changed = pc.is_some() && pc != self.previous_pc;
+        self.previous_pc = pc;
+        if !self.cpu.finished_instruction().unwrap_or(changed) {
+            return Ok(());
+        }
+
+        self.instructions += 1;
+        let cpu = &self.cpu;
+        let step = Step {
+            cycle: self.cycles,
+            instruction: self.instructions,
+            program_counter: pc,
+            registers: cpu.registers(),
+            bus_accesses: &self.accesses,
+            cpu,
+            memory: &|address| cpu.memory_peek(address),
+        };
+        let result = self.on_step.as_ref().map(|on_step| on_step.call(&step));
+        self.accesses.clear();
+        if let Some(Err(e)) = result {
+            let stopped = format!(
+                "stopped by the step callback after {} instructions: {e}",
+                self.instructions
+            );
+            self.stopped = Some(stopped.clone());
+            return Err(stopped.into());
+        }
+
+        // the cpu stops once the rest of the tick saw where it got to
+        self.out_of_instructions |= self
+            .instruction_budget
+            .is_some_and(|budget| self.instructions >= budget);
+        Ok(())
+    }
+}
+
+impl<T: TestableCpu> Cpu for Runner<T> {
+    fn tick(&mut self, ppu: &mut Ppu) -> Result<(), Box<dyn Error>> {
+        if self.cancel.as_ref().is_some_and(CancelToken::is_cancelled) {
+            let stopped = "the test run was cancelled".to_owned();
+            self.stopped = Some(stopped.clone());
+            return Err(stopped.into());
+        }
+        if let Some(rewind) = &mut self.rewind {
+            rewind.observe(&self.cpu, self.cycles);
+        }
+        self.cpu.tick(ppu)?;
+        self.cycles += 1;
+
+        while let Some(event) = self
+            .input
+            .last()
+            .filter(|e| e.cycle(self.region) <= self.cycles)
+        {
+            let held = &mut self.held[event.controller.index()];
+            held.set(event.buttons, event.down);
+            self.cpu.set_buttons(event.controller.number(), *held);
+            self.input.pop();
+        }
+
+        let pc = self.cpu.program_counter();
+        let (watcher, accesses, cycles) = (&mut self.watcher, &mut self.accesses, self.cycles);
+        let stepping = self.on_step.is_some();
+        let mut counted = self.memory_accesses.unwrap_or_default();
+        // the accesses are taken even without watchpoints, so the cpu doesn't keep them around
+        let reported = self.cpu.bus_accesses(&mut |access| {
+            watcher.observe(access, cycles, pc);
+            counted.count(access);
+            if stepping {
+                accesses.push(access);
+            }
+        });
+        if reported {
+            self.memory_accesses = Some(counted);
+        }
+        if !reported && !self.watcher.is_empty() {
+            let cpu = &self.cpu;
+            self.watcher.poll(|a| cpu.memory_peek(a), cycles, pc);
+        }
+
+        if self.on_step.is_some() || self.instruction_budget.is_some() {
+            self.step(pc)?;
+        }
+
+        self.visited |= pc.is_some() && pc == self.watched_pc;
+        if pc.is_some() && pc == self.stop_at && self.cpu.finished_instruction().unwrap_or(true) {
+            self.reached_stop = true;
+            return Err(Box::new(Stuck));
+        }
+        if self.out_of_instructions {
+            return Err(Box::new(Stuck));
+        }
+
+        if let Some(pc) = pc {
+            let halt = self.halt.get_or_insert_with(|| HaltDetector::new(pc));
+            // a rom waiting for the reset button to be pressed isn't stuck
+            if halt.observe(pc) && !reset_requested(&self.cpu, &self.status_addresses) {
+                self.stuck = true;
+                return Err(Box::new(Stuck));
+            }
+        }
+
+        Ok(())
+    }
+
+    fn ppu_read_chr_rom(&self, offset: u16) -> u8 {
+        self.cpu.ppu_read_chr_rom(offset)
+    }
+
+    fn non_maskable_interrupt(&mut self) {
+        self.cpu.non_maskable_interrupt()
+    }
+}
+
+impl<T: TestableCpu> Machine for Runner<T> {
+    fn run_cycles(&mut self, cycles: usize) -> Result<(), Box<dyn Error>> {
+        let mirroring = self.mirroring.into();
+        run_cpu_headless_for(self, mirroring, cycles)
+    }
+
+    fn peek(&self, address: u16) -> u8 {
+        self.cpu.memory_peek(address)
+    }
+
+    fn cycles(&self) -> u64 {
+        self.cycles
+    }
+}
+
+impl<T: TestableCpu> Drop for Runner<T> {
+    fn drop(&mut self) {
+        // a cpu that panicked can't be trusted to read its memory
+        if thread::panicking() {
+            return;
+        }
+
+        let status = if has_status(&self.cpu, &self.status_addresses) {
+            read_status_string_at(&self.cpu, &self.status_addresses)
+        } else {
+            String::new()
+        };
+        let mut state = FinalState {
+            cycles: self.cycles,
+            status,
+            ram: (0..0x0800).map(|a| self.cpu.memory_peek(a)).collect(),
+            watchpoint_hits: self.watcher.hits(),
+            memory_accesses: self.memory_accesses,
+            trace: Vec::new(),
+        };
+        // the cpu goes back in time only after the rest of its final state is taken
+        if let Some(rewind) = self.rewind.take() {
+            let (cpu, cycles) = (&mut self.cpu, self.cycles);
+            state.trace = rewind
+                .replay(cpu, &*self.executor, self.mirroring, cycles)
+                .unwrap_or_default();
+        }
+        let _ = self.progress.send(Progress::Finished(state));
+    }
+}
@@ -0,0 +1,195 @@
+//! Tests of the harness itself: cpus with a bug on purpose have to fail the tests that are meant to
+//! catch that bug, and the same cpu without it has to pass them, see [`self_test`]. The same cpu
+//! without bugs writes the golden log of nestest, see [`nestest_golden_log`].
+use crate::log_target;
+use crate::reference::ReferenceCpu;
+use crate::step::Step;
+use crate::{
+    nestest_rom, run_tests_with_reporter, StepCallback, TestConfig, TestSelector, TestableCpu,
+    TextReporter, TraceFormat, Verbosity,
+};
+use std::fmt;
+use std::sync::{Arc, Mutex};
+
+/// A bug in the [`ReferenceCpu`], which one of the tests has to catch
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Bug {
+    /// The carry of `SBC` is inverted
+    SbcCarry,
+    /// An indexed read that crosses a page doesn't do the dummy read from the wrong page first,
+    /// so it takes a cycle less
+    MissingDummyRead,
+    /// Indexing in the zero page carries into the high byte, instead of wrapping around
+    ZeroPageIndexCarry,
+    /// `PHP` pushes the status without the break flag
+    BreakFlag,
+    /// The unofficial `SLO` leaves a wrong value in the accumulator
+    SloResult,
+    /// The NMI never happens
+    IgnoresNmi,
+    /// A reset doesn't jump to the reset vector, the cpu keeps running where it was
+    ResetKeepsPc,
+    /// [`TestableCpu::memory_read`](crate::TestableCpu::memory_read) only reads ram, and 0 everywhere else
+    MemoryReadRamOnly,
+}
+
+impl fmt::Display for Bug {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let bug = match self {
+            Bug::SbcCarry => "a wrong carry after SBC",
+            Bug::MissingDummyRead => "no dummy read on page crossings",
+            Bug::ZeroPageIndexCarry => "zero page indexing that doesn't wrap",
+            Bug::BreakFlag => "PHP without the break flag",
+            Bug::SloResult => "a wrong result of SLO",
+            Bug::IgnoresNmi => "no NMI",
+            Bug::ResetKeepsPc => "a reset that doesn't jump to the reset vector",
+            Bug::MemoryReadRamOnly => "a memory_read that only reads ram",
+        };
+        f.write_str(bug)
+    }
+}
+
+/// The bugs and the tests that have to catch them
+const CASES: &[(Bug, TestSelector)] = &[
+    (Bug::SbcCarry, TestSelector::OFFICIAL_INSTRS),
+    (Bug::SbcCarry, TestSelector::NESTEST),
+    (Bug::MissingDummyRead, TestSelector::NESTEST),
+    (Bug::ZeroPageIndexCarry, TestSelector::OFFICIAL_INSTRS),
+    (Bug::ZeroPageIndexCarry, TestSelector::WRAPAROUND),
+    (Bug::BreakFlag, TestSelector::OFFICIAL_INSTRS),
+    (Bug::BreakFlag, TestSelector::INTERRUPTS),
+    (Bug::SloResult, TestSelector::ALL_INSTRS),
+    (Bug::IgnoresNmi, TestSelector::INTERRUPTS),
+    (Bug::ResetKeepsPc, TestSelector::NESTEST_RESET),
+    (Bug::MemoryReadRamOnly, TestSelector::PREFLIGHT),
+];
+
+/// The bug of the [`ReferenceCpu`]s that are created, [`TestableCpu::get_cpu`](crate::TestableCpu::get_cpu)
+/// has no other way to get it
+static BUG: Mutex<Option<Bug>> = Mutex::new(None);
+/// Held while the harness tests itself, so the bug isn't changed in between
+static RUNNING: Mutex<()> = Mutex::new(());
+
+pub(crate) fn current_bug() -> Option<Bug> {
+    *BUG.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+/// Runs `selector` on the reference cpu with `bug`, and returns the message of the first test that
+/// failed, or `None` when they all passed
+fn first_failure(bug: Option<Bug>, selector: TestSelector) -> Option<String> {
+    *BUG.lock().unwrap_or_else(|e| e.into_inner()) = bug;
+    let config = TestConfig {
+        selector,
+        ..TestConfig::default()
+    };
+    let mut reporter = TextReporter::new(std::io::sink(), Verbosity::Quiet);
+    let report = run_tests_with_reporter::<ReferenceCpu>(&config, &mut reporter);
+    let failure = report
+        .failures()
+        .next()
+        .map(|r| format!("{}: {}", r.name, r.outcome.clone().unwrap_err()));
+    failure
+}
+
+/// Tests the harness itself, with a simple cpu of its own. Without bugs that cpu has to pass all
+/// tests with bundled roms that a bug is planted for, and with every bug it has to fail the tests
+/// meant to catch it, like [`TestSelector::OFFICIAL_INSTRS`] for a wrong carry after `SBC`. Changes
+/// to the harness, like to its cycle budgets or to how it reads the status of a rom, can break a test
+/// without it failing for any cpu you try, which this finds out.
+///
+/// Returns an error saying what went wrong, when the cpu without bugs fails or a bug isn't caught.
+/// It needs the `selftest` feature, and runs for a while: `ALL_INSTRS` alone takes seconds in a
+/// release build.
+/// ```no_run
+/// tudelft_nes_test::self_test().unwrap();
+/// ```
+pub fn self_test() -> Result<(), String> {
+    // a panic in a test isn't a reason to stop the next one
+    let _running = RUNNING.lock().unwrap_or_else(|e| e.into_inner());
+
+    let all = CASES
+        .iter()
+        .fold(TestSelector::empty(), |all, &(_, selector)| all | selector);
+    let mut problems = Vec::new();
+    if let Some(failure) = first_failure(None, all) {
+        problems.push(format!("the cpu without bugs fails {failure}"));
+    }
+    for &(bug, selector) in CASES {
+        let test = crate::config::test_name(selector).unwrap_or("a test");
+        match first_failure(Some(bug), selector) {
+            Some(failure) => {
+                log_target::info!("{test} catches {bug}, {failure}")
+            }
+            None => problems.push(format!("{test} doesn't catch {bug}")),
+        }
+    }
+    *BUG.lock().unwrap_or_else(|e| e.into_inner()) = None;
+
+    if problems.is_empty() {
+        Ok(())
+    } else {
+        Err(format!(
+            "the harness doesn't work as it should:\n{}",
+            problems.join("\n")
+        ))
+    }
+}
+
+/// The trace of the automated tests of nestest on the harness's own cpu, a line in `format` for every
+/// instruction from the one at $C000 to the `RTS` at the end, like the lines of `nestest.log`. Write
+/// it to a file to update a golden log after a change to the trace formats or to the rom, so it's
+/// reproducible, or run the example that does:
+/// ```text
+/// cargo run --example nestest_golden_log --features selftest -- nestest-golden.log mesen
+/// ```
+/// Returns an error when the cpu doesn't pass nestest, since the log would be wrong then. It needs
+/// the `selftest` feature.
+/// ```no_run
+/// use tudelft_nes_test::{nestest_golden_log, TraceFormat};
+///
+/// let log = nestest_golden_log(TraceFormat::Mesen)?;
+/// std::fs::write("nestest-golden.log", log.join("\n") + "\n").map_err(|e| e.to_string())?;
+/// # Ok::<(), String>(())
+/// ```
+pub fn nestest_golden_log(format: TraceFormat) -> Result<Vec<String>, String> {
+    let _running = RUNNING.lock().unwrap_or_else(|e| e.into_inner());
+    *BUG.lock().unwrap_or_else(|e| e.into_inner()) = None;
+
+    // the step callback only sees the cpu after an instruction, so the line of the first one comes
+    // from the fresh cpu
+    let rom = nestest_rom(&TestConfig::default())?;
+    let mut cpu = ReferenceCpu::get_cpu(&rom).map_err(|e| e.to_string())?;
+    cpu.set_program_counter(0xC000);
+    let first = Step {
+        cycle: 0,
+        instruction: 0,
+        program_counter: cpu.program_counter(),
+        registers: cpu.registers(),
+        bus_accesses: &[],
+        cpu: &cpu,
+        memory: &|address| cpu.memory_peek(address),
+    };
+    let lines = Arc::new(Mutex::new(Vec::from_iter(first.trace_line(format))));
+
+    let traced = Arc::clone(&lines);
+    let config = TestConfig {
+        selector: TestSelector::NESTEST,
+        on_step: Some(StepCallback::new(move |step| {
+            let mut lines = traced.lock().unwrap_or_else(|e| e.into_inner());
+            lines.extend(step.trace_line(format));
+            Ok(())
+        })),
+        ..TestConfig::default()
+    };
+    let mut reporter = TextReporter::new(std::io::sink(), Verbosity::Quiet);
+    let report = run_tests_with_reporter::<ReferenceCpu>(&config, &mut reporter);
+    if let Some(failure) = report.failures().next() {
+        return Err(format!(
+            "the reference cpu fails nestest, so its log is wrong: {}",
+            failure.outcome.clone().unwrap_err()
+        ));
+    }
+
+    let lines = lines.lock().unwrap_or_else(|e| e.into_inner());
+    Ok(lines.clone())
+}
@@ -0,0 +1,64 @@
+//! Serde support, so tools like graders and dashboards can store and exchange selectors,
+//! configurations and reports. Tests are serialized by the names used in configuration files.
+use crate::config::{parse_tests, parse_unofficial_opcodes, test_names, unofficial_opcode_names};
+use crate::{TestSelector, UnofficialOpcodes};
+use serde::{de, Deserialize, Deserializer, Serialize, Serializer};
+
+/// A selector is serialized as the list of names of the tests it selects, like `["nestest"]`
+impl Serialize for TestSelector {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        test_names(*self).serialize(serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for TestSelector {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let names = Vec::<String>::deserialize(deserializer)?;
+        parse_tests("tests", names.iter().map(String::as_str)).map_err(de::Error::custom)
+    }
+}
+
+/// Like selectors, the categories of unofficial opcodes are serialized as a list of names, like `["nops", "lax_sax"]`
+impl Serialize for UnofficialOpcodes {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        unofficial_opcode_names(*self).serialize(serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for UnofficialOpcodes {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let names = Vec::<String>::deserialize(deserializer)?;
+        parse_unofficial_opcodes("unofficial_opcodes", names.iter().map(String::as_str))
+            .map_err(de::Error::custom)
+    }
+}
+
+/// Settings per test, like the cycle budgets, are a map from the names of tests to the setting,
+/// since most formats only allow strings as keys
+pub(crate) mod per_test {
+    use super::*;
+    use std::collections::{BTreeMap, HashMap};
+
+    pub(crate) fn serialize<S: Serializer, V: Serialize>(
+        settings: &HashMap<TestSelector, V>,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        settings
+            .iter()
+            .map(|(test, setting)| (test_names(*test).join(","), setting))
+            .collect::<BTreeMap<_, _>>()
+            .serialize(serializer)
+    }
+
+    pub(crate) fn deserialize<'de, D: Deserializer<'de>, V: Deserialize<'de>>(
+        deserializer: D,
+    ) -> Result<HashMap<TestSelector, V>, D::Error> {
+        BTreeMap::<String, V>::deserialize(deserializer)?
+            .into_iter()
+            .map(|(tests, setting)| {
+                let test = parse_tests("tests", tests.split(',')).map_err(de::Error::custom)?;
+                Ok((test, setting))
+            })
+            .collect()
+    }
+}
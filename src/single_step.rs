@@ -0,0 +1,298 @@
+//! Reproductions of a single instruction that went wrong, in the JSON format of the
+//! [SingleStepTests](https://github.com/SingleStepTests/65x02) of Tom Harte, so they can be replayed
+//! in other 6502 emulators and debuggers, see [`SingleStepCase`]
+use crate::events::Value;
+use crate::step::Step;
+use crate::trace::{instruction_length, Registers};
+use crate::watch::BusAccess;
+use std::fmt::Write as _;
+use std::io;
+use std::path::Path;
+
+/// The program counter, the registers and some bytes of memory of a cpu, like the `initial` and
+/// `final` states of SingleStepTests
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct CpuState {
+    /// The program counter
+    pub pc: u16,
+    /// The registers
+    pub registers: Registers,
+    /// Addresses and the bytes in memory there, in the order in which they should be written
+    pub ram: Vec<(u16, u8)>,
+}
+
+/// A single instruction: where the cpu started, what it should look like after the instruction,
+/// and the accesses it made on its bus. When you found the instruction where your cpu goes wrong,
+/// for example by comparing a trace log in an [`on_step`](crate::TestConfig::on_step) callback, you
+/// can record the instruction and write it to a file:
+/// ```no_run
+/// use tudelft_nes_test::{write_single_step_tests, CpuState, SingleStepCase, StepCallback};
+///
+/// let mut before = None;
+/// let mut cases = Vec::new();
+/// let callback = StepCallback::new(move |step| {
+///     if let (Some(before), Some(pc)) = (&before, step.program_counter) {
+///         if pc == 0xC72D {
+///             // what the cpu should look like, from the log of another emulator
+///             let expected = CpuState { pc: 0xC72E, ..step.cpu_state().unwrap() };
+///             cases.push(SingleStepCase::from_step(before, step, expected));
+///             write_single_step_tests("c72d.json", &cases).map_err(|e| e.to_string())?;
+///         }
+///     }
+///     before = step.cpu_state();
+///     Ok(())
+/// });
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct SingleStepCase {
+    /// The name of the case, the bytes of the instruction like `a9 42`
+    pub name: String,
+    /// The state before the instruction, with the bytes it reads
+    pub initial: CpuState,
+    /// The state the cpu should be in after the instruction, with the bytes it should have written
+    pub expected: CpuState,
+    /// The reads and writes of the instruction, one per cycle
+    pub cycles: Vec<BusAccess>,
+}
+
+impl Step<'_> {
+    /// The program counter and registers of the cpu after this instruction, without memory. Returns
+    /// `None` when the cpu doesn't implement both [`TestableCpu::program_counter`](crate::TestableCpu::program_counter)
+    /// and [`TestableCpu::registers`](crate::TestableCpu::registers).
+    pub fn cpu_state(&self) -> Option<CpuState> {
+        Some(CpuState {
+            pc: self.program_counter?,
+            registers: self.registers?,
+            ram: Vec::new(),
+        })
+    }
+}
+
+impl SingleStepCase {
+    /// The instruction the cpu ran in `step`, starting from the state `before` it, which should have
+    /// ended in the registers and at the program counter of `expected`.
+    ///
+    /// The memory of the initial state is what the instruction read, and the memory of the expected
+    /// state adds what it wrote, unless `expected` has memory of its own. Both have the same addresses,
+    /// like the schema wants, so an address the instruction only wrote starts out as 0, which doesn't
+    /// change what it does. When the cpu doesn't implement
+    /// [`TestableCpu::bus_accesses`](crate::TestableCpu::bus_accesses) only the bytes of the instruction
+    /// itself are known, and the case has no cycles.
+    pub fn from_step(before: &CpuState, step: &Step, expected: CpuState) -> Self {
+        let mut initial = before.clone();
+
+        // the first read of an address saw what was there before the instruction
+        let mut written = Vec::new();
+        for access in step.bus_accesses {
+            if access.write {
+                written.push(access.address);
+            } else if !written.contains(&access.address)
+                && initial_byte(&initial, access.address).is_none()
+            {
+                initial.ram.push((access.address, access.value));
+            }
+        }
+        if step.bus_accesses.is_empty() {
+            let length = instruction_length(step.memory_read(before.pc));
+            for i in 0..length {
+                let address = before.pc.wrapping_add(i);
+                if initial_byte(&initial, address).is_none() {
+                    initial.ram.push((address, step.memory_read(address)));
+                }
+            }
+        }
+
+        // what was at an address before the instruction wrote it is gone, and didn't matter
+        for &address in &written {
+            if initial_byte(&initial, address).is_none() {
+                initial.ram.push((address, 0));
+            }
+        }
+
+        let mut expected = expected;
+        if expected.ram.is_empty() {
+            expected.ram = initial.ram.clone();
+            for access in step.bus_accesses.iter().filter(|a| a.write) {
+                match expected.ram.iter_mut().find(|(a, _)| *a == access.address) {
+                    Some((_, value)) => *value = access.value,
+                    None => expected.ram.push((access.address, access.value)),
+                }
+            }
+        } else {
+            for &(address, value) in &initial.ram {
+                if initial_byte(&expected, address).is_none() {
+                    expected.ram.push((address, value));
+                }
+            }
+            for &(address, _) in &expected.ram {
+                if initial_byte(&initial, address).is_none() {
+                    initial.ram.push((address, 0));
+                }
+            }
+        }
+
+        let bytes = instruction_length(initial_byte(&initial, before.pc).unwrap_or_default());
+        let name = (0..bytes)
+            .filter_map(|i| initial_byte(&initial, before.pc.wrapping_add(i)))
+            .map(|b| format!("{b:02x}"))
+            .collect::<Vec<_>>()
+            .join(" ");
+
+        Self {
+            name,
+            initial,
+            expected,
+            cycles: step.bus_accesses.to_vec(),
+        }
+    }
+
+    /// The case as a JSON object of SingleStepTests
+    pub fn to_json(&self) -> String {
+        let cycles = self
+            .cycles
+            .iter()
+            .map(|c| {
+                let kind = if c.write { "write" } else { "read" };
+                format!("[{},{},\"{kind}\"]", c.address, c.value)
+            })
+            .collect::<Vec<_>>()
+            .join(",");
+        format!(
+            "{{\"name\":{},\"initial\":{},\"final\":{},\"cycles\":[{cycles}]}}",
+            Value::Str(&self.name),
+            state_json(&self.initial),
+            state_json(&self.expected)
+        )
+    }
+}
+
+/// Writes `cases` to the file at `path` as a JSON array, like the files of SingleStepTests with the
+/// cases of one opcode
+pub fn write_single_step_tests(path: impl AsRef<Path>, cases: &[SingleStepCase]) -> io::Result<()> {
+    let cases: Vec<String> = cases.iter().map(SingleStepCase::to_json).collect();
+    std::fs::write(path, format!("[\n{}\n]\n", cases.join(",\n")))
+}
+
+fn initial_byte(state: &CpuState, address: u16) -> Option<u8> {
+    state
+        .ram
+        .iter()
+        .find(|&&(a, _)| a == address)
+        .map(|&(_, value)| value)
+}
+
+fn state_json(state: &CpuState) -> String {
+    let Registers { a, x, y, sp, p } = state.registers;
+    let mut ram = String::new();
+    for (i, (address, value)) in state.ram.iter().enumerate() {
+        let comma = if i == 0 { "" } else { "," };
+        let _ = write!(ram, "{comma}[{address},{value}]");
+    }
+    format!(
+        "{{\"pc\":{},\"s\":{sp},\"a\":{a},\"x\":{x},\"y\":{y},\"p\":{p},\"ram\":[{ram}]}}",
+        state.pc
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn state(pc: u16, a: u8, ram: &[(u16, u8)]) -> CpuState {
+        CpuState {
+            pc,
+            registers: Registers {
+                a,
+                x: 0,
+                y: 0,
+                sp: 0xFD,
+                p: 0x24,
+            },
+            ram: ram.to_vec(),
+        }
+    }
+
+    #[test]
+    fn serializes_like_single_step_tests() {
+        let case = SingleStepCase {
+            name: "85 10".to_string(),
+            initial: state(0x0200, 0x42, &[(0x0200, 0x85), (0x0201, 0x10), (0x0010, 0)]),
+            expected: state(
+                0x0202,
+                0x42,
+                &[(0x0200, 0x85), (0x0201, 0x10), (0x0010, 0x42)],
+            ),
+            cycles: vec![
+                BusAccess {
+                    address: 0x0200,
+                    value: 0x85,
+                    write: false,
+                },
+                BusAccess {
+                    address: 0x0201,
+                    value: 0x10,
+                    write: false,
+                },
+                BusAccess {
+                    address: 0x0010,
+                    value: 0x42,
+                    write: true,
+                },
+            ],
+        };
+        assert_eq!(
+            case.to_json(),
+            concat!(
+                r#"{"name":"85 10","#,
+                r#""initial":{"pc":512,"s":253,"a":66,"x":0,"y":0,"p":36,"ram":[[512,133],[513,16],[16,0]]},"#,
+                r#""final":{"pc":514,"s":253,"a":66,"x":0,"y":0,"p":36,"ram":[[512,133],[513,16],[16,66]]},"#,
+                r#""cycles":[[512,133,"read"],[513,16,"read"],[16,66,"write"]]}"#
+            )
+        );
+    }
+
+    #[test]
+    fn initial_and_final_have_the_same_addresses() {
+        let accesses = [
+            BusAccess {
+                address: 0x0200,
+                value: 0x85,
+                write: false,
+            },
+            BusAccess {
+                address: 0x0201,
+                value: 0x10,
+                write: false,
+            },
+            BusAccess {
+                address: 0x0010,
+                value: 0x42,
+                write: true,
+            },
+        ];
+        let step = Step {
+            cycle: 3,
+            instruction: 1,
+            program_counter: Some(0x0202),
+            registers: None,
+            bus_accesses: &accesses,
+            cpu: &(),
+            memory: &|_| 0,
+        };
+        let case =
+            SingleStepCase::from_step(&state(0x0200, 0x42, &[]), &step, state(0x0202, 0x42, &[]));
+
+        assert_eq!(case.name, "85 10");
+        assert_eq!(
+            case.initial.ram,
+            [(0x0200, 0x85), (0x0201, 0x10), (0x0010, 0)]
+        );
+        assert_eq!(
+            case.expected.ram,
+            [(0x0200, 0x85), (0x0201, 0x10), (0x0010, 0x42)]
+        );
+        assert_eq!(case.cycles, accesses);
+    }
+}
@@ -0,0 +1,102 @@
+//! Reading the results test roms leave in memory, for your own tests of roms the harness doesn't run:
+//! ```no_run
+//! # use tudelft_nes_test::{blargg_status, read_status_string, BlarggStatus, TestableCpu};
+//! # fn test<MyCpu: TestableCpu>(cpu: MyCpu) {
+//! // after running a blargg rom for a while
+//! match blargg_status(&cpu) {
+//!     Some(BlarggStatus::Finished(0)) => println!("passed"),
+//!     Some(BlarggStatus::Finished(code)) => panic!("failed with {code}: {}", read_status_string(&cpu)),
+//!     _ => println!("still running"),
+//! }
+//! # }
+//! ```
+use crate::nestest::nestest_status_code;
+use crate::TestableCpu;
+
+/// The status the test roms of blargg report at $6000
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BlarggStatus {
+    /// The rom is still running its tests, which it reports with $80
+    Running,
+    /// The rom asks for the reset button to be pressed, with $81
+    ResetRequested,
+    /// The rom is done: 0 when it passed, otherwise the code of the test that failed
+    Finished(u8),
+}
+
+/// Where a blargg rom reports its status, see [`CustomRom::status_addresses`](crate::CustomRom::status_addresses).
+/// The roms of blargg use $6000, some other roms put the same block somewhere else.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct StatusAddresses {
+    /// The status byte, $6000 by default
+    pub status: u16,
+    /// The first byte of the magic sequence `de b0 61`, $6001 by default
+    pub magic: u16,
+    /// The start of the status text, $6004 by default
+    pub text: u16,
+}
+
+impl StatusAddresses {
+    /// The addresses of a status block like that of blargg that starts at `status` instead of at $6000
+    pub fn at(status: u16) -> Self {
+        Self {
+            status,
+            magic: status.wrapping_add(1),
+            text: status.wrapping_add(4),
+        }
+    }
+}
+
+impl Default for StatusAddresses {
+    fn default() -> Self {
+        Self::at(0x6000)
+    }
+}
+
+/// Reads the status a blargg rom reports at $6000. Returns `None` when the rom didn't write the magic
+/// sequence `de b0 61` at $6001 yet, which it does before it writes its first status.
+pub fn blargg_status(cpu: &impl TestableCpu) -> Option<BlarggStatus> {
+    blargg_status_at(cpu, &StatusAddresses::default())
+}
+
+/// Like [`blargg_status`], for a rom that reports its status at other addresses
+pub fn blargg_status_at(cpu: &impl TestableCpu, at: &StatusAddresses) -> Option<BlarggStatus> {
+    let magic = [0, 1, 2].map(|i| cpu.memory_peek(at.magic.wrapping_add(i)));
+    if magic != [0xde, 0xb0, 0x61] {
+        return None;
+    }
+
+    Some(match cpu.memory_peek(at.status) {
+        0x81 => BlarggStatus::ResetRequested,
+        status if status >= 0x80 => BlarggStatus::Running,
+        status => BlarggStatus::Finished(status),
+    })
+}
+
+/// Reads the text a blargg rom writes from $6004 on, like the console of the rom shows it
+pub fn read_status_string(cpu: &impl TestableCpu) -> String {
+    read_status_string_at(cpu, &StatusAddresses::default())
+}
+
+/// Like [`read_status_string`], for a rom that reports its status at other addresses
+pub fn read_status_string_at(cpu: &impl TestableCpu, at: &StatusAddresses) -> String {
+    let mut res = String::new();
+    // at most the 4k of text the roms of blargg have room for
+    for address in (0..=0x0FFC).map(|i| at.text.wrapping_add(i)) {
+        let b = cpu.memory_peek(address);
+        if b == 0 {
+            break;
+        }
+
+        res.push(char::from_u32(u32::from(b)).unwrap_or('�'))
+    }
+
+    res
+}
+
+/// Reads the result codes nestest leaves at $02 and $03, and explains what failed when they aren't 0.
+/// Nestest only writes them when it is started at $C000, like the harness does.
+pub fn nestest_result(cpu: &impl TestableCpu) -> Result<(), String> {
+    nestest_status_code(cpu.memory_peek(0x0002), cpu.memory_peek(0x0003)).map_err(|e| e.to_string())
+}
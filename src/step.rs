@@ -0,0 +1,75 @@
+//! Calling back into the user of the harness after every instruction of the cpu, to build trace
+//! comparators or conditional breakpoints on top of the tests
+use crate::trace::Registers;
+use crate::watch::BusAccess;
+use std::any::Any;
+use std::fmt;
+use std::sync::{Arc, Mutex};
+
+/// What the cpu looks like after an instruction, passed to a [`StepCallback`]
+pub struct Step<'a> {
+    /// The number of cycles the cpu ran in this test so far
+    pub cycle: u64,
+    /// The number of instructions the cpu ran in this test so far, including this one
+    pub instruction: u64,
+    /// Where the cpu continues, when it implements [`TestableCpu::program_counter`](crate::TestableCpu::program_counter)
+    pub program_counter: Option<u16>,
+    /// The registers of the cpu, when it implements [`TestableCpu::registers`](crate::TestableCpu::registers)
+    pub registers: Option<Registers>,
+    /// The reads and writes of this instruction, in order, when the cpu implements
+    /// [`TestableCpu::bus_accesses`](crate::TestableCpu::bus_accesses)
+    pub bus_accesses: &'a [BusAccess],
+    pub(crate) cpu: &'a dyn Any,
+    pub(crate) memory: &'a dyn Fn(u16) -> u8,
+}
+
+impl Step<'_> {
+    /// Reads memory with [`TestableCpu::memory_peek`](crate::TestableCpu::memory_peek), so it doesn't change what the cpu does
+    pub fn memory_read(&self, address: u16) -> u8 {
+        (self.memory)(address)
+    }
+
+    /// The cpu under test, so you can look at its registers. Returns `None` when `T` isn't the type of the cpu.
+    pub fn cpu<T: 'static>(&self) -> Option<&T> {
+        self.cpu.downcast_ref()
+    }
+}
+
+type StepFn = dyn FnMut(&Step) -> Result<(), String> + Send;
+
+/// A function the harness calls after every instruction of the cpu, see [`TestConfig::on_step`](crate::TestConfig::on_step).
+/// When it returns an error, the test stops and fails with that message:
+/// ```
+/// use tudelft_nes_test::{StepCallback, TestConfig};
+///
+/// let config = TestConfig {
+///     // breaks when the cpu jumps into the zero page
+///     on_step: Some(StepCallback::new(|step| match step.program_counter {
+///         Some(pc) if pc < 0x100 => Err(format!("jumped to ${pc:04X}")),
+///         _ => Ok(()),
+///     })),
+///     ..TestConfig::default()
+/// };
+/// ```
+/// The same function is called for all tests, which run one after the other.
+#[derive(Clone)]
+pub struct StepCallback(Arc<Mutex<StepFn>>);
+
+impl StepCallback {
+    /// Wraps `callback`
+    pub fn new(callback: impl FnMut(&Step) -> Result<(), String> + Send + 'static) -> Self {
+        Self(Arc::new(Mutex::new(callback)))
+    }
+
+    pub(crate) fn call(&self, step: &Step) -> Result<(), String> {
+        // a callback that panicked in an earlier test is still called
+        let mut callback = self.0.lock().unwrap_or_else(|e| e.into_inner());
+        callback(step)
+    }
+}
+
+impl fmt::Debug for StepCallback {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("StepCallback")
+    }
+}
@@ -0,0 +1,306 @@
+//! A summary of a test run in Markdown or HTML, to post as a comment on a pull request or to publish
+//! as a page of CI, see [`SummaryReporter`]
+use crate::log_target;
+use crate::report::{TestReport, TestResult};
+use crate::reporter::Reporter;
+use std::fmt::Write as _;
+use std::io::Write;
+
+/// How many lines of the message of a failure are in the summary
+const EXCERPT_LINES: usize = 20;
+
+/// The format of a [`SummaryReporter`]
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+enum Format {
+    Markdown,
+    Html,
+}
+
+/// A [`Reporter`] writing a summary of the run once all tests ran: a table of the tests with
+/// their results, sub-tests and durations, the sub-tests of every test, and an excerpt of every
+/// failure. [`TestReport::markdown`] and [`TestReport::html`] give the same summary.
+/// ```no_run
+/// # use tudelft_nes_test::{run_tests_with_reporter, SummaryReporter, TestConfig, TestableCpu};
+/// # fn test<MyCpu: TestableCpu>() -> std::io::Result<()> {
+/// let file = std::fs::File::create("nes-tests.md")?;
+/// let mut reporter = SummaryReporter::markdown(file);
+/// run_tests_with_reporter::<MyCpu>(&TestConfig::default(), &mut reporter);
+/// # Ok(())
+/// # }
+/// ```
+pub struct SummaryReporter<W: Write> {
+    out: W,
+    format: Format,
+}
+
+impl<W: Write> SummaryReporter<W> {
+    /// A reporter writing the summary as Markdown to `out`, like GitHub and GitLab show it
+    pub fn markdown(out: W) -> Self {
+        Self {
+            out,
+            format: Format::Markdown,
+        }
+    }
+
+    /// A reporter writing the summary as a standalone HTML page to `out`
+    pub fn html(out: W) -> Self {
+        Self {
+            out,
+            format: Format::Html,
+        }
+    }
+
+    /// Returns the writer the summary was written to
+    pub fn into_inner(self) -> W {
+        self.out
+    }
+}
+
+impl<W: Write> Reporter for SummaryReporter<W> {
+    fn run_finished(&mut self, report: &TestReport) {
+        let summary = match self.format {
+            Format::Markdown => markdown(report),
+            Format::Html => html(report),
+        };
+        if let Err(e) = self
+            .out
+            .write_all(summary.as_bytes())
+            .and_then(|()| self.out.flush())
+        {
+            log_target::warn!("couldn't write the summary of the tests: {e}");
+        }
+    }
+}
+
+impl TestReport {
+    /// A summary of the report in Markdown, see [`SummaryReporter`]
+    pub fn markdown(&self) -> String {
+        markdown(self)
+    }
+
+    /// A summary of the report as a standalone HTML page, see [`SummaryReporter`]
+    pub fn html(&self) -> String {
+        html(self)
+    }
+}
+
+/// What the summary says of a test, like the table of [`TextReporter`](crate::TextReporter)
+fn result(result: &TestResult) -> &'static str {
+    if !result.passed() {
+        "FAILED"
+    } else if result.skipped.is_some() {
+        "skipped"
+    } else if result.flaky() {
+        "flaky"
+    } else if result.cached {
+        "cached"
+    } else if !result.expected_failures.is_empty() {
+        "expected failure"
+    } else {
+        "ok"
+    }
+}
+
+/// How many of the sub-tests passed, like `10/16`, or nothing for a test without sub-tests
+fn sub_tests(result: &TestResult) -> String {
+    match result.sub_tests.len() {
+        0 => String::new(),
+        n => format!(
+            "{}/{n}",
+            result.sub_tests.iter().filter(|s| s.passed).count()
+        ),
+    }
+}
+
+/// The first lines of the message of a failure
+fn excerpt(message: &str) -> String {
+    let lines: Vec<&str> = message.lines().collect();
+    match lines.len() {
+        n if n > EXCERPT_LINES => format!(
+            "{}\n... {} more lines",
+            lines[..EXCERPT_LINES].join("\n"),
+            n - EXCERPT_LINES
+        ),
+        _ => message.to_string(),
+    }
+}
+
+/// The line of totals at the top of the summary
+fn totals(report: &TestReport) -> String {
+    let failed = report.failures().count();
+    let skipped = report
+        .results
+        .iter()
+        .filter(|r| r.skipped.is_some())
+        .count();
+    let passed = report.results.len() - failed - skipped;
+    let mut totals = format!("{passed} passed, {failed} failed");
+    if skipped > 0 {
+        let _ = write!(totals, ", {skipped} skipped");
+    }
+    let _ = write!(totals, ", finished in {:.2?}", report.duration());
+    if let Some(threshold) = report.pass_threshold {
+        let _ = write!(
+            totals,
+            ". {:.1}% of the sub-tests passed, {threshold}% have to",
+            report.sub_test_pass_rate()
+        );
+    }
+    totals
+}
+
+fn markdown(report: &TestReport) -> String {
+    // a `|` would end the cell of a table
+    let cell = |text: &str| text.replace('|', "\\|").replace('\n', " ");
+    let status = if report.passed() { "ok" } else { "FAILED" };
+
+    let mut text = format!("## nes test result: {status}\n\n{}\n\n", totals(report));
+    text.push_str("| test | result | sub-tests | duration |\n");
+    text.push_str("| ---- | ------ | --------: | -------: |\n");
+    for r in &report.results {
+        let _ = writeln!(
+            text,
+            "| {} | {} | {} | {:.2?} |",
+            cell(&r.name),
+            result(r),
+            sub_tests(r),
+            r.duration
+        );
+    }
+
+    let failures: Vec<_> = report.failures().collect();
+    if !failures.is_empty() {
+        text.push_str("\n### Failures\n");
+        for r in failures {
+            let _ = writeln!(text, "\n#### {}\n", r.name);
+            if let Err(e) = &r.outcome {
+                // a fence longer than any run of backticks in the message
+                let fence = "`".repeat(3.max(longest_run(e, '`') + 1));
+                let _ = writeln!(text, "{fence}\n{}\n{fence}", excerpt(e));
+            }
+        }
+    }
+
+    let with_sub_tests: Vec<_> = report
+        .results
+        .iter()
+        .filter(|r| !r.sub_tests.is_empty())
+        .collect();
+    if !with_sub_tests.is_empty() {
+        text.push_str("\n### Sub-tests\n");
+        for r in with_sub_tests {
+            let _ = write!(
+                text,
+                "\n<details><summary>{} ({})</summary>\n\n| sub-test | result |\n| -------- | ------ |\n",
+                r.name,
+                sub_tests(r)
+            );
+            for s in &r.sub_tests {
+                let result = match (&s.detail, s.passed) {
+                    (_, true) => "ok".to_string(),
+                    (Some(detail), false) => format!("FAILED: {}", cell(detail)),
+                    (None, false) => "FAILED".to_string(),
+                };
+                let _ = writeln!(text, "| {} | {result} |", cell(&s.name));
+            }
+            text.push_str("\n</details>\n");
+        }
+    }
+
+    text
+}
+
+fn html(report: &TestReport) -> String {
+    let status = if report.passed() { "ok" } else { "FAILED" };
+    let mut page = format!(
+        "<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n<title>nes test result: {status}</title>\n\
+         <style>\n\
+         body {{ font-family: sans-serif; margin: 2em; }}\n\
+         table {{ border-collapse: collapse; }}\n\
+         th, td {{ border: 1px solid #ccc; padding: 0.2em 0.6em; text-align: left; }}\n\
+         .ok {{ color: #080; }} .FAILED {{ color: #c00; }} .other {{ color: #a60; }}\n\
+         pre {{ background: #f4f4f4; padding: 0.6em; }}\n\
+         </style>\n</head>\n<body>\n\
+         <h1>nes test result: <span class=\"{status}\">{status}</span></h1>\n<p>{}</p>\n",
+        escape(&totals(report))
+    );
+
+    page.push_str(
+        "<table>\n<tr><th>test</th><th>result</th><th>sub-tests</th><th>duration</th></tr>\n",
+    );
+    for r in &report.results {
+        let result = result(r);
+        let class = match result {
+            "ok" | "cached" => "ok",
+            "FAILED" => "FAILED",
+            _ => "other",
+        };
+        let _ = writeln!(
+            page,
+            "<tr><td>{}</td><td class=\"{class}\">{result}</td><td>{}</td><td>{:.2?}</td></tr>",
+            escape(&r.name),
+            sub_tests(r),
+            r.duration
+        );
+    }
+    page.push_str("</table>\n");
+
+    let failures: Vec<_> = report.failures().collect();
+    if !failures.is_empty() {
+        page.push_str("<h2>Failures</h2>\n");
+        for r in failures {
+            let _ = writeln!(page, "<h3>{}</h3>", escape(&r.name));
+            if let Err(e) = &r.outcome {
+                let _ = writeln!(page, "<pre>{}</pre>", escape(&excerpt(e)));
+            }
+        }
+    }
+
+    let with_sub_tests: Vec<_> = report
+        .results
+        .iter()
+        .filter(|r| !r.sub_tests.is_empty())
+        .collect();
+    if !with_sub_tests.is_empty() {
+        page.push_str("<h2>Sub-tests</h2>\n");
+        for r in with_sub_tests {
+            let _ = writeln!(
+                page,
+                "<details><summary>{} ({})</summary>\n<table>",
+                escape(&r.name),
+                sub_tests(r)
+            );
+            for s in &r.sub_tests {
+                let (class, result) = match (&s.detail, s.passed) {
+                    (_, true) => ("ok", "ok".to_string()),
+                    (Some(detail), false) => ("FAILED", format!("FAILED: {}", escape(detail))),
+                    (None, false) => ("FAILED", "FAILED".to_string()),
+                };
+                let _ = writeln!(
+                    page,
+                    "<tr><td>{}</td><td class=\"{class}\">{result}</td></tr>",
+                    escape(&s.name)
+                );
+            }
+            page.push_str("</table>\n</details>\n");
+        }
+    }
+
+    page.push_str("</body>\n</html>\n");
+    page
+}
+
+/// The longest run of `c` in `text`
+fn longest_run(text: &str, c: char) -> usize {
+    text.split(|other| other != c)
+        .map(str::len)
+        .max()
+        .unwrap_or(0)
+}
+
+pub(crate) fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
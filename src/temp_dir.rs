@@ -0,0 +1,32 @@
+//! Directories for the tests that write files, which are removed again when the test is done
+use std::ops::Deref;
+use std::path::{Path, PathBuf};
+
+/// An empty directory of its own for every test, since they run at the same time, which is removed
+/// when it's dropped, even when the test panics
+pub(crate) struct TempDir(PathBuf);
+
+impl TempDir {
+    /// A directory for the test called `test`, with the id of the process in its name for the
+    /// other test executables that run at the same time
+    pub(crate) fn new(test: &str) -> Self {
+        let dir = std::env::temp_dir().join(format!("nestest-n-{test}-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        Self(dir)
+    }
+}
+
+impl Deref for TempDir {
+    type Target = Path;
+
+    fn deref(&self) -> &Path {
+        &self.0
+    }
+}
+
+impl Drop for TempDir {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_dir_all(&self.0);
+    }
+}
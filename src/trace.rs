@@ -0,0 +1,524 @@
+//! Trace logs of the instructions the cpu ran, in the formats of the trace loggers of Mesen and FCEUX,
+//! so they can be diffed against the logs of those emulators, see [`StepCallback::trace_log`]
+use crate::log_target;
+use crate::single_step::{write_single_step_tests, CpuState, SingleStepCase};
+use crate::step::{Step, StepCallback};
+use crate::trace_diff;
+use bitflags::bitflags;
+use std::fmt::Write as _;
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::path::{Path, PathBuf};
+
+/// The registers of the cpu, see [`TestableCpu::registers`](crate::TestableCpu::registers)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Registers {
+    /// The accumulator
+    pub a: u8,
+    /// The x index register
+    pub x: u8,
+    /// The y index register
+    pub y: u8,
+    /// The stack pointer
+    pub sp: u8,
+    /// The status flags, `NV-BDIZC` from bit 7 to bit 0
+    pub p: u8,
+}
+
+/// The format of a trace log
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TraceFormat {
+    /// Like the trace logger of Mesen, the address first:
+    /// `C5F5  A2 00     LDX #$00 ... A:00 X:00 Y:00 S:FD P:nvUbdIzc Cycle:10`.
+    /// Mesen's columns of the ppu aren't there, so turn them off in its format.
+    Mesen,
+    /// Like the trace logger of FCEUX, the registers first:
+    /// `A:00 X:00 Y:00 S:FD P:nvUbdIzc  $C5F5:A2 00     LDX #$00`
+    Fceux,
+}
+
+bitflags! {
+    /// The columns of a trace log that [`StepCallback::compare_trace_columns`] compares, so a cpu can be
+    /// compared to a golden log at the accuracy it has: `TraceColumns::all() - TraceColumns::CYCLES` for
+    /// a cpu that isn't cycle accurate, for example. The columns are found by what they look like, so
+    /// logs of Mesen, FCEUX and `nestest.log` can all be compared.
+    pub struct TraceColumns: u16 {
+        /// The program counter, like `C5F5`
+        const ADDRESS     = 0b0000_0000_0001;
+        /// The bytes of the instruction, like `A2 00`
+        const BYTES       = 0b0000_0000_0010;
+        /// The instruction in assembly, like `LDX #$00`, and the columns that aren't recognized
+        const INSTRUCTION = 0b0000_0000_0100;
+        /// The accumulator, `A:00`
+        const A           = 0b0000_0000_1000;
+        /// The x index register, `X:00`
+        const X           = 0b0000_0001_0000;
+        /// The y index register, `Y:00`
+        const Y           = 0b0000_0010_0000;
+        /// The stack pointer, `S:FD` or `SP:FD`
+        const SP          = 0b0000_0100_0000;
+        /// The status flags in `P:nvUbdIzc` or `P:24`, apart from bits 4 and 5
+        const FLAGS       = 0b0000_1000_0000;
+        /// Bit 5 of the status flags, which is always set on a real NES, but not in every emulator
+        const UNUSED_FLAG = 0b0001_0000_0000;
+        /// Bit 4 of the status flags, the break flag, which only exists on the stack
+        const BREAK_FLAG  = 0b0010_0000_0000;
+        /// The cycle count, `Cycle:7` or `CYC:7`
+        const CYCLES      = 0b0100_0000_0000;
+        /// The columns of the ppu, like `PPU:  0, 21` in nestest.log, or `V:0 H:21 Fr:0` in Mesen
+        const PPU         = 0b1000_0000_0000;
+        /// All registers, including all of the status flags
+        const REGISTERS   = Self::A.bits | Self::X.bits | Self::Y.bits | Self::SP.bits
+            | Self::FLAGS.bits | Self::UNUSED_FLAG.bits | Self::BREAK_FLAG.bits;
+    }
+}
+
+/// By default, all columns are compared
+impl Default for TraceColumns {
+    fn default() -> Self {
+        Self::all()
+    }
+}
+
+/// The columns of `line` that are compared, in order, with the status flags as a byte of only the
+/// bits that are compared
+fn compared_columns(line: &str, columns: TraceColumns) -> Vec<(TraceColumns, String)> {
+    let all_flags = TraceColumns::FLAGS | TraceColumns::UNUSED_FLAG | TraceColumns::BREAK_FLAG;
+    let mut compared = Vec::new();
+    let mut ppu = false;
+    let mut instruction = false;
+    for (i, token) in line.split_whitespace().enumerate() {
+        // FCEUX puts the address in front of the first byte, like `$C5F5:A2`
+        let fceux_address = token
+            .strip_prefix('$')
+            .and_then(|t| t.split_once(':'))
+            .filter(|(address, _)| address.len() == 4);
+        if let Some((address, byte)) = fceux_address {
+            compared.push((TraceColumns::ADDRESS, address.to_string()));
+            compared.push((TraceColumns::BYTES, byte.to_string()));
+            continue;
+        }
+
+        let (key, value) = token.split_once(':').unwrap_or_default();
+        let column = match key {
+            "A" => TraceColumns::A,
+            "X" => TraceColumns::X,
+            "Y" => TraceColumns::Y,
+            "S" | "SP" => TraceColumns::SP,
+            "P" => match parse_flags(value) {
+                Some(p) => {
+                    let mask = [
+                        (TraceColumns::FLAGS, 0b1100_1111),
+                        (TraceColumns::UNUSED_FLAG, 0b0010_0000),
+                        (TraceColumns::BREAK_FLAG, 0b0001_0000),
+                    ]
+                    .iter()
+                    .filter(|(column, _)| columns.contains(*column))
+                    .fold(0, |mask, (_, bits)| mask | bits);
+                    compared.push((all_flags, format!("P:{:02X}", p & mask)));
+                    continue;
+                }
+                None => TraceColumns::INSTRUCTION,
+            },
+            "Cycle" | "CYC" => TraceColumns::CYCLES,
+            "PPU" | "V" | "H" | "Fr" | "SL" | "FC" | "Scanline" | "Dot" => TraceColumns::PPU,
+            // the numbers after `PPU:` in nestest.log, like `0,` and `21`
+            _ if ppu
+                && token
+                    .trim_end_matches(',')
+                    .bytes()
+                    .all(|b| b.is_ascii_digit()) =>
+            {
+                TraceColumns::PPU
+            }
+            _ if i == 0 && token.len() == 4 && u16::from_str_radix(token, 16).is_ok() => {
+                TraceColumns::ADDRESS
+            }
+            _ if !instruction && token.len() == 2 && u8::from_str_radix(token, 16).is_ok() => {
+                TraceColumns::BYTES
+            }
+            _ => {
+                instruction = true;
+                TraceColumns::INSTRUCTION
+            }
+        };
+        ppu = column == TraceColumns::PPU;
+        compared.push((column, token.to_string()));
+    }
+    compared.retain(|(column, _)| columns.intersects(*column));
+    compared
+}
+
+/// The program counter and the registers in `line` of a trace, without memory, or `None` when the
+/// line doesn't have all of them
+fn line_state(line: &str) -> Option<CpuState> {
+    let columns = compared_columns(line, TraceColumns::all());
+    let value = |column: TraceColumns| {
+        let (_, token) = columns.iter().find(|(c, _)| *c == column)?;
+        token.rsplit(':').next()
+    };
+    let byte = |column| u8::from_str_radix(value(column)?, 16).ok();
+    Some(CpuState {
+        pc: u16::from_str_radix(value(TraceColumns::ADDRESS)?, 16).ok()?,
+        registers: Registers {
+            a: byte(TraceColumns::A)?,
+            x: byte(TraceColumns::X)?,
+            y: byte(TraceColumns::Y)?,
+            sp: byte(TraceColumns::SP)?,
+            p: byte(TraceColumns::FLAGS | TraceColumns::UNUSED_FLAG | TraceColumns::BREAK_FLAG)?,
+        },
+        ram: Vec::new(),
+    })
+}
+
+/// The status flags of a trace, as letters like `nvUbdIzc` or as a byte like `24`
+fn parse_flags(p: &str) -> Option<u8> {
+    if p.len() == 8 {
+        let letters = p.chars().zip("NVUBDIZC".chars());
+        if letters
+            .clone()
+            .all(|(flag, name)| flag.eq_ignore_ascii_case(&name))
+        {
+            return Some(letters.fold(0, |p, (flag, _)| {
+                p << 1 | u8::from(flag.is_ascii_uppercase())
+            }));
+        }
+    }
+    u8::from_str_radix(p, 16).ok()
+}
+
+/// How an instruction finds its operand
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Mode {
+    Implied,
+    Accumulator,
+    Immediate,
+    ZeroPage,
+    ZeroPageX,
+    ZeroPageY,
+    Absolute,
+    AbsoluteX,
+    AbsoluteY,
+    Indirect,
+    IndirectX,
+    IndirectY,
+    Relative,
+}
+
+use Mode::*;
+
+/// The mnemonics of the 256 opcodes, 16 per row. The unofficial ones have the names nestest.log gives them.
+#[rustfmt::skip]
+pub(crate) const MNEMONICS: [&str; 256] = [
+    "BRK", "ORA", "JAM", "SLO", "NOP", "ORA", "ASL", "SLO", "PHP", "ORA", "ASL", "ANC", "NOP", "ORA", "ASL", "SLO",
+    "BPL", "ORA", "JAM", "SLO", "NOP", "ORA", "ASL", "SLO", "CLC", "ORA", "NOP", "SLO", "NOP", "ORA", "ASL", "SLO",
+    "JSR", "AND", "JAM", "RLA", "BIT", "AND", "ROL", "RLA", "PLP", "AND", "ROL", "ANC", "BIT", "AND", "ROL", "RLA",
+    "BMI", "AND", "JAM", "RLA", "NOP", "AND", "ROL", "RLA", "SEC", "AND", "NOP", "RLA", "NOP", "AND", "ROL", "RLA",
+    "RTI", "EOR", "JAM", "SRE", "NOP", "EOR", "LSR", "SRE", "PHA", "EOR", "LSR", "ALR", "JMP", "EOR", "LSR", "SRE",
+    "BVC", "EOR", "JAM", "SRE", "NOP", "EOR", "LSR", "SRE", "CLI", "EOR", "NOP", "SRE", "NOP", "EOR", "LSR", "SRE",
+    "RTS", "ADC", "JAM", "RRA", "NOP", "ADC", "ROR", "RRA", "PLA", "ADC", "ROR", "ARR", "JMP", "ADC", "ROR", "RRA",
+    "BVS", "ADC", "JAM", "RRA", "NOP", "ADC", "ROR", "RRA", "SEI", "ADC", "NOP", "RRA", "NOP", "ADC", "ROR", "RRA",
+    "NOP", "STA", "NOP", "SAX", "STY", "STA", "STX", "SAX", "DEY", "NOP", "TXA", "XAA", "STY", "STA", "STX", "SAX",
+    "BCC", "STA", "JAM", "SHA", "STY", "STA", "STX", "SAX", "TYA", "STA", "TXS", "TAS", "SHY", "STA", "SHX", "SHA",
+    "LDY", "LDA", "LDX", "LAX", "LDY", "LDA", "LDX", "LAX", "TAY", "LDA", "TAX", "LAX", "LDY", "LDA", "LDX", "LAX",
+    "BCS", "LDA", "JAM", "LAX", "LDY", "LDA", "LDX", "LAX", "CLV", "LDA", "TSX", "LAS", "LDY", "LDA", "LDX", "LAX",
+    "CPY", "CMP", "NOP", "DCP", "CPY", "CMP", "DEC", "DCP", "INY", "CMP", "DEX", "AXS", "CPY", "CMP", "DEC", "DCP",
+    "BNE", "CMP", "JAM", "DCP", "NOP", "CMP", "DEC", "DCP", "CLD", "CMP", "NOP", "DCP", "NOP", "CMP", "DEC", "DCP",
+    "CPX", "SBC", "NOP", "ISB", "CPX", "SBC", "INC", "ISB", "INX", "SBC", "NOP", "SBC", "CPX", "SBC", "INC", "ISB",
+    "BEQ", "SBC", "JAM", "ISB", "NOP", "SBC", "INC", "ISB", "SED", "SBC", "NOP", "ISB", "NOP", "SBC", "INC", "ISB",
+];
+
+/// The addressing modes of the 256 opcodes, 16 per row
+#[rustfmt::skip]
+pub(crate) const MODES: [Mode; 256] = [
+    Implied, IndirectX, Implied, IndirectX, ZeroPage, ZeroPage, ZeroPage, ZeroPage, Implied, Immediate, Accumulator, Immediate, Absolute, Absolute, Absolute, Absolute,
+    Relative, IndirectY, Implied, IndirectY, ZeroPageX, ZeroPageX, ZeroPageX, ZeroPageX, Implied, AbsoluteY, Implied, AbsoluteY, AbsoluteX, AbsoluteX, AbsoluteX, AbsoluteX,
+    Absolute, IndirectX, Implied, IndirectX, ZeroPage, ZeroPage, ZeroPage, ZeroPage, Implied, Immediate, Accumulator, Immediate, Absolute, Absolute, Absolute, Absolute,
+    Relative, IndirectY, Implied, IndirectY, ZeroPageX, ZeroPageX, ZeroPageX, ZeroPageX, Implied, AbsoluteY, Implied, AbsoluteY, AbsoluteX, AbsoluteX, AbsoluteX, AbsoluteX,
+    Implied, IndirectX, Implied, IndirectX, ZeroPage, ZeroPage, ZeroPage, ZeroPage, Implied, Immediate, Accumulator, Immediate, Absolute, Absolute, Absolute, Absolute,
+    Relative, IndirectY, Implied, IndirectY, ZeroPageX, ZeroPageX, ZeroPageX, ZeroPageX, Implied, AbsoluteY, Implied, AbsoluteY, AbsoluteX, AbsoluteX, AbsoluteX, AbsoluteX,
+    Implied, IndirectX, Implied, IndirectX, ZeroPage, ZeroPage, ZeroPage, ZeroPage, Implied, Immediate, Accumulator, Immediate, Indirect, Absolute, Absolute, Absolute,
+    Relative, IndirectY, Implied, IndirectY, ZeroPageX, ZeroPageX, ZeroPageX, ZeroPageX, Implied, AbsoluteY, Implied, AbsoluteY, AbsoluteX, AbsoluteX, AbsoluteX, AbsoluteX,
+    Immediate, IndirectX, Immediate, IndirectX, ZeroPage, ZeroPage, ZeroPage, ZeroPage, Implied, Immediate, Implied, Immediate, Absolute, Absolute, Absolute, Absolute,
+    Relative, IndirectY, Implied, IndirectY, ZeroPageX, ZeroPageX, ZeroPageY, ZeroPageY, Implied, AbsoluteY, Implied, AbsoluteY, AbsoluteX, AbsoluteX, AbsoluteY, AbsoluteY,
+    Immediate, IndirectX, Immediate, IndirectX, ZeroPage, ZeroPage, ZeroPage, ZeroPage, Implied, Immediate, Implied, Immediate, Absolute, Absolute, Absolute, Absolute,
+    Relative, IndirectY, Implied, IndirectY, ZeroPageX, ZeroPageX, ZeroPageY, ZeroPageY, Implied, AbsoluteY, Implied, AbsoluteY, AbsoluteX, AbsoluteX, AbsoluteY, AbsoluteY,
+    Immediate, IndirectX, Immediate, IndirectX, ZeroPage, ZeroPage, ZeroPage, ZeroPage, Implied, Immediate, Implied, Immediate, Absolute, Absolute, Absolute, Absolute,
+    Relative, IndirectY, Implied, IndirectY, ZeroPageX, ZeroPageX, ZeroPageX, ZeroPageX, Implied, AbsoluteY, Implied, AbsoluteY, AbsoluteX, AbsoluteX, AbsoluteX, AbsoluteX,
+    Immediate, IndirectX, Immediate, IndirectX, ZeroPage, ZeroPage, ZeroPage, ZeroPage, Implied, Immediate, Implied, Immediate, Absolute, Absolute, Absolute, Absolute,
+    Relative, IndirectY, Implied, IndirectY, ZeroPageX, ZeroPageX, ZeroPageX, ZeroPageX, Implied, AbsoluteY, Implied, AbsoluteY, AbsoluteX, AbsoluteX, AbsoluteX, AbsoluteX,
+];
+
+impl Mode {
+    /// The number of bytes of an instruction with this mode, including the opcode
+    fn length(self) -> u16 {
+        match self {
+            Implied | Accumulator => 1,
+            Absolute | AbsoluteX | AbsoluteY | Indirect => 3,
+            _ => 2,
+        }
+    }
+}
+
+/// The number of bytes of the instruction starting with `opcode`
+pub(crate) fn instruction_length(opcode: u8) -> u16 {
+    MODES[usize::from(opcode)].length()
+}
+
+/// The instruction at `pc` in assembly, like `LDA ($80),Y`, with the bytes it's made of
+fn disassemble(pc: u16, read: impl Fn(u16) -> u8) -> (String, String) {
+    let opcode = read(pc);
+    let mode = MODES[usize::from(opcode)];
+    let bytes: Vec<u8> = (0..mode.length())
+        .map(|i| read(pc.wrapping_add(i)))
+        .collect();
+    let byte = bytes.get(1).copied().unwrap_or_default();
+    let word = u16::from_le_bytes([byte, bytes.get(2).copied().unwrap_or_default()]);
+
+    let mnemonic = MNEMONICS[usize::from(opcode)];
+    let operand = match mode {
+        Implied | Accumulator => String::new(),
+        Immediate => format!(" #${byte:02X}"),
+        ZeroPage => format!(" ${byte:02X}"),
+        ZeroPageX => format!(" ${byte:02X},X"),
+        ZeroPageY => format!(" ${byte:02X},Y"),
+        Absolute => format!(" ${word:04X}"),
+        AbsoluteX => format!(" ${word:04X},X"),
+        AbsoluteY => format!(" ${word:04X},Y"),
+        Indirect => format!(" (${word:04X})"),
+        IndirectX => format!(" (${byte:02X},X)"),
+        IndirectY => format!(" (${byte:02X}),Y"),
+        Relative => {
+            let target = pc
+                .wrapping_add(2)
+                .wrapping_add_signed(i16::from(byte as i8));
+            format!(" ${target:04X}")
+        }
+    };
+
+    let hex = bytes.iter().fold(String::new(), |mut text, b| {
+        let _ = write!(text, "{b:02X} ");
+        text
+    });
+    (hex.trim_end().to_string(), format!("{mnemonic}{operand}"))
+}
+
+/// The flags like both emulators show them, upper case when they're set: `nvUbdIzc`
+fn flags(p: u8) -> String {
+    "NVUBDIZC"
+        .chars()
+        .enumerate()
+        .map(|(i, flag)| match p & (0x80 >> i) {
+            0 => flag.to_ascii_lowercase(),
+            _ => flag,
+        })
+        .collect()
+}
+
+/// The registers like the columns of a trace log, `A:00 X:00 Y:00 S:FD P:nvUbdIzc`
+pub(crate) fn registers_text(r: Registers) -> String {
+    format!(
+        "A:{:02X} X:{:02X} Y:{:02X} S:{:02X} P:{}",
+        r.a,
+        r.x,
+        r.y,
+        r.sp,
+        flags(r.p)
+    )
+}
+
+impl Step<'_> {
+    /// The line of a trace log in `format`, of the instruction the cpu runs next, with the registers it
+    /// runs it with when the cpu implements [`TestableCpu::registers`](crate::TestableCpu::registers).
+    /// Returns `None` when the cpu doesn't implement [`TestableCpu::program_counter`](crate::TestableCpu::program_counter).
+    pub fn trace_line(&self, format: TraceFormat) -> Option<String> {
+        let pc = self.program_counter?;
+        let (bytes, instruction) = disassemble(pc, |address| self.memory_read(address));
+        let registers = self.registers.map(registers_text);
+
+        let line = match (format, registers) {
+            (TraceFormat::Mesen, Some(registers)) => format!(
+                "{pc:04X}  {bytes:<8}  {instruction:<32}{registers} Cycle:{}",
+                self.cycle
+            ),
+            (TraceFormat::Mesen, None) => {
+                format!(
+                    "{pc:04X}  {bytes:<8}  {instruction:<32}Cycle:{}",
+                    self.cycle
+                )
+            }
+            (TraceFormat::Fceux, Some(registers)) => {
+                format!("{registers}  ${pc:04X}:{bytes:<8}  {instruction}")
+            }
+            (TraceFormat::Fceux, None) => format!("${pc:04X}:{bytes:<8}  {instruction}"),
+        };
+        Some(line)
+    }
+}
+
+impl StepCallback {
+    /// Writes a trace log in `format` to the file at `path`, with a line for every instruction the cpu
+    /// runs after the first one of a test, see [`Step::trace_line`]. All tests write to the same file,
+    /// one after the other, so select only the test you want to compare. The file is written completely
+    /// once the configuration with the callback is dropped.
+    /// ```no_run
+    /// use tudelft_nes_test::{StepCallback, TestConfig, TestSelector, TraceFormat};
+    ///
+    /// let config = TestConfig {
+    ///     selector: TestSelector::NESTEST,
+    ///     on_step: Some(StepCallback::trace_log("nestest-trace.txt", TraceFormat::Mesen)?),
+    ///     ..TestConfig::default()
+    /// };
+    /// # Ok::<(), std::io::Error>(())
+    /// ```
+    pub fn trace_log(path: impl AsRef<Path>, format: TraceFormat) -> io::Result<Self> {
+        let mut file = BufWriter::new(File::create(path)?);
+        Ok(Self::new(move |step| {
+            let Some(line) = step.trace_line(format) else {
+                return Ok(());
+            };
+            writeln!(file, "{line}").map_err(|e| format!("couldn't write the trace log: {e}"))
+        }))
+    }
+
+    /// Compares the trace of the cpu in `format` to the log at `expected`, like one of
+    /// [`nestest_golden_log`](crate::nestest_golden_log), and stops the test at the first line that
+    /// differs. The first line of the log is the instruction a test starts with, and the lines the
+    /// cpu runs past the end of the log aren't compared. Like [`trace_log`](Self::trace_log), select
+    /// only the test of the log.
+    /// ```no_run
+    /// use tudelft_nes_test::{StepCallback, TestConfig, TestSelector, TraceFormat};
+    ///
+    /// let config = TestConfig {
+    ///     selector: TestSelector::NESTEST,
+    ///     on_step: Some(StepCallback::compare_trace("nestest-golden.log", TraceFormat::Mesen)?),
+    ///     ..TestConfig::default()
+    /// };
+    /// # Ok::<(), std::io::Error>(())
+    /// ```
+    pub fn compare_trace(expected: impl AsRef<Path>, format: TraceFormat) -> io::Result<Self> {
+        Self::compare(expected.as_ref(), format, TraceColumns::all(), None)
+    }
+
+    /// Like [`compare_trace`](Self::compare_trace), and when the trace differs, also writes a
+    /// standalone HTML page to `diff` with the expected and the actual trace side by side around the
+    /// line that differs, with the columns that differ marked. Next to it, with the extension `json`,
+    /// it writes the instruction that went wrong as a case of SingleStepTests, see [`SingleStepCase`],
+    /// when the log has the registers.
+    pub fn compare_trace_with_diff(
+        expected: impl AsRef<Path>,
+        format: TraceFormat,
+        diff: impl AsRef<Path>,
+    ) -> io::Result<Self> {
+        Self::compare(
+            expected.as_ref(),
+            format,
+            TraceColumns::all(),
+            Some(diff.as_ref().to_path_buf()),
+        )
+    }
+
+    /// Like [`compare_trace`](Self::compare_trace), but only compares `columns` of the lines, so the
+    /// trace of a cpu that isn't cycle accurate, or that doesn't set bit 5 of the status flags, can
+    /// still be compared to a golden log. With `diff`, it also writes the HTML page of
+    /// [`compare_trace_with_diff`](Self::compare_trace_with_diff) there.
+    /// ```no_run
+    /// use tudelft_nes_test::{StepCallback, TestConfig, TestSelector, TraceColumns, TraceFormat};
+    ///
+    /// let columns = TraceColumns::all() - TraceColumns::CYCLES - TraceColumns::UNUSED_FLAG;
+    /// let config = TestConfig {
+    ///     selector: TestSelector::NESTEST,
+    ///     on_step: Some(StepCallback::compare_trace_columns(
+    ///         "nestest.log",
+    ///         TraceFormat::Mesen,
+    ///         columns,
+    ///         None,
+    ///     )?),
+    ///     ..TestConfig::default()
+    /// };
+    /// # Ok::<(), std::io::Error>(())
+    /// ```
+    pub fn compare_trace_columns(
+        expected: impl AsRef<Path>,
+        format: TraceFormat,
+        columns: TraceColumns,
+        diff: Option<&Path>,
+    ) -> io::Result<Self> {
+        Self::compare(
+            expected.as_ref(),
+            format,
+            columns,
+            diff.map(Path::to_path_buf),
+        )
+    }
+
+    fn compare(
+        expected: &Path,
+        format: TraceFormat,
+        columns: TraceColumns,
+        diff: Option<PathBuf>,
+    ) -> io::Result<Self> {
+        let name = expected.display().to_string();
+        let expected: Vec<String> = std::fs::read_to_string(expected)?
+            .lines()
+            .map(str::to_owned)
+            .collect();
+
+        let mut before = None;
+        Ok(Self::new(move |step| {
+            let Some(line) = step.trace_line(format) else {
+                return Ok(());
+            };
+            // the first line is the instruction before the first step
+            let index = step.instruction as usize;
+            let previous = std::mem::replace(&mut before, step.cpu_state());
+            match expected.get(index) {
+                Some(wanted)
+                    if *wanted != line
+                        && (columns.is_all()
+                            || compared_columns(wanted, columns)
+                                != compared_columns(&line, columns)) => {}
+                _ => return Ok(()),
+            }
+
+            let mut message = format!(
+                "the trace differs from {name} at line {}:\n    expected: {}\n    actual:   {line}",
+                index + 1,
+                expected[index]
+            );
+            if let Some(path) = &diff {
+                let page = trace_diff::html(&name, &expected, index, &line);
+                match std::fs::write(path, page) {
+                    Ok(()) => {
+                        let _ = write!(message, "\n    the diff is in {}", path.display());
+                    }
+                    Err(e) => {
+                        log_target::warn!("couldn't write the diff to {}: {e}", path.display())
+                    }
+                }
+
+                // the lines before this one matched, so the instruction started like the log says
+                let initial = previous.or_else(|| line_state(expected.get(index.checked_sub(1)?)?));
+                if let Some((initial, wanted)) = initial.zip(line_state(&expected[index])) {
+                    let case = SingleStepCase::from_step(&initial, step, wanted);
+                    let json = path.with_extension("json");
+                    match write_single_step_tests(&json, &[case]) {
+                        Ok(()) => {
+                            let _ = write!(
+                                message,
+                                "\n    the instruction is in {} as a case of SingleStepTests",
+                                json.display()
+                            );
+                        }
+                        Err(e) => log_target::warn!(
+                            "couldn't write the instruction to {}: {e}",
+                            json.display()
+                        ),
+                    }
+                }
+            }
+            Err(message)
+        }))
+    }
+}
@@ -0,0 +1,113 @@
+//! A standalone HTML page with the expected and the actual trace side by side, around the line where
+//! they differ, see [`StepCallback::compare_trace_with_diff`](crate::StepCallback::compare_trace_with_diff)
+use crate::summary::escape;
+use std::cmp::Ordering;
+use std::fmt::Write;
+
+/// How many lines of the trace are shown before and after the line that differs
+const CONTEXT: usize = 20;
+
+/// The page for the trace that matched `expected` up to line `at`, where the cpu traced `actual`
+pub(crate) fn html(name: &str, expected: &[String], at: usize, actual: &str) -> String {
+    let mut page = format!(
+        "<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n<title>trace diff: {name}</title>\n\
+         <style>\n\
+         body {{ font-family: sans-serif; margin: 2em; }}\n\
+         table {{ border-collapse: collapse; font-family: monospace; white-space: pre; }}\n\
+         th, td {{ padding: 0 0.6em; text-align: left; }}\n\
+         td.line {{ color: #888; text-align: right; }}\n\
+         tr.differs {{ background: #fdd; }}\n\
+         .pc {{ color: #00a; }} .bytes {{ color: #888; }} .op {{ color: #a0a; font-weight: bold; }}\n\
+         .arg {{ color: #080; }} .reg {{ color: #333; }} .cycle {{ color: #a60; }}\n\
+         .diff {{ background: #f66; color: #fff; }}\n\
+         </style>\n</head>\n<body>\n\
+         <h1>The trace differs from {name} at line {}</h1>\n\
+         <table>\n<tr><th></th><th>expected</th><th>actual</th></tr>\n",
+        at + 1,
+        name = escape(name)
+    );
+
+    let end = expected.len().min(at + CONTEXT + 1);
+    for (i, line) in expected
+        .iter()
+        .enumerate()
+        .take(end)
+        .skip(at.saturating_sub(CONTEXT))
+    {
+        let (class, left, right) = match i.cmp(&at) {
+            // the lines before the one that differs are those of the log
+            Ordering::Less => ("", highlight(line, None), highlight(line, None)),
+            Ordering::Equal => (
+                " class=\"differs\"",
+                highlight(line, Some(actual)),
+                highlight(actual, Some(line)),
+            ),
+            // the cpu stopped at the line that differs
+            Ordering::Greater => ("", highlight(line, None), String::new()),
+        };
+        let _ = writeln!(
+            page,
+            "<tr{class}><td class=\"line\">{}</td><td>{left}</td><td>{right}</td></tr>",
+            i + 1
+        );
+    }
+
+    page.push_str("</table>\n</body>\n</html>\n");
+    page
+}
+
+/// `line` in HTML, with a class for every column, and the columns that differ from `other` marked
+fn highlight(line: &str, other: Option<&str>) -> String {
+    let others: Vec<&str> = other.map_or(Vec::new(), |o| o.split_whitespace().collect());
+    let mut html = String::new();
+    let mut column = 0;
+    for token in tokens(line) {
+        if token.trim().is_empty() {
+            html.push_str(token);
+            continue;
+        }
+
+        let class = if column == 0 && token.len() == 4 && u16::from_str_radix(token, 16).is_ok() {
+            "pc"
+        } else if token.starts_with("Cycle:") {
+            "cycle"
+        } else if token.len() > 2 && token.as_bytes()[1] == b':' {
+            "reg"
+        } else if token.len() == 3 && token.bytes().all(|b| b.is_ascii_uppercase()) {
+            "op"
+        } else if token.starts_with(['$', '#', '(']) {
+            "arg"
+        } else {
+            "bytes"
+        };
+        let differs = other.is_some() && others.get(column) != Some(&token);
+        let diff = if differs { " diff" } else { "" };
+        let _ = write!(
+            html,
+            "<span class=\"{class}{diff}\">{}</span>",
+            escape(token)
+        );
+        column += 1;
+    }
+    html
+}
+
+/// `line` split into runs of whitespace and runs of everything else
+fn tokens(line: &str) -> Vec<&str> {
+    let mut tokens = Vec::new();
+    let mut start = 0;
+    for (i, c) in line.char_indices().skip(1) {
+        let previous = line[..i]
+            .chars()
+            .next_back()
+            .is_some_and(char::is_whitespace);
+        if c.is_whitespace() != previous {
+            tokens.push(&line[start..i]);
+            start = i;
+        }
+    }
+    if start < line.len() {
+        tokens.push(&line[start..]);
+    }
+    tokens
+}
@@ -0,0 +1,249 @@
+//! Tests of the unstable unofficial opcodes ATX, SYA and SXA that accept every documented variant of
+//! them, instead of the single variant the roms of instr_test-v5 expect, see [`UnstablePolicy`].
+//! Every test is a program in an NROM rom, which stores what the instruction did in ram, where the
+//! harness reads it with [`TestableCpu::memory_peek`](crate::TestableCpu::memory_peek).
+use crate::ines;
+
+/// How `ALL_INSTRS` and the `INSTR_*` groups test the unstable unofficial opcodes, ATX ($AB), SYA
+/// ($9C) and SXA ($9E), see [`TestConfig::unstable_opcodes`](crate::TestConfig::unstable_opcodes).
+/// What they do depends on analog effects in the cpu, so it differs between consoles, and several
+/// variants of them are documented. It only matters when [`TestConfig::unofficial_opcodes`](crate::TestConfig::unofficial_opcodes)
+/// has [`UnofficialOpcodes::UNSTABLE`](crate::UnofficialOpcodes::UNSTABLE).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(rename_all = "snake_case")
+)]
+pub enum UnstablePolicy {
+    /// The roms test them, which only pass the variant they expect
+    #[default]
+    Strict,
+    /// The harness tests them instead of the roms, with programs of its own that pass every
+    /// documented variant. They're sub-tests like `unstable_atx`.
+    AnyVariant,
+    /// They aren't tested at all, like without `UnofficialOpcodes::UNSTABLE`
+    Skip,
+}
+
+/// Where the programs start, the reset vector of the rom points here
+const PROGRAM: u16 = 0x8000;
+/// The handler of both interrupts, a single `RTI`
+const HANDLER: u16 = 0x8F00;
+/// Where the programs store what the instructions left in the registers, 3 bytes for every case
+const RESULTS: u16 = 0x0010;
+/// The byte the programs store at the end, so the harness knows they ran to it
+const DONE: (u16, u8) = (0x000F, 0x5A);
+/// What the programs first store at the addresses an instruction can store at
+const SENTINEL: u8 = 0xA5;
+
+/// The constants that ATX ORs A with, in the documented variants of it
+const MAGICS: [u8; 3] = [0x00, 0xEE, 0xFF];
+
+const NEGATIVE: u8 = 0x80;
+const ZERO: u8 = 0x02;
+
+/// A test of an unstable opcode
+pub(crate) struct UnstableTest {
+    pub(crate) name: &'static str,
+    /// The group of instr_test-v5 that tests the opcode
+    pub(crate) group: &'static str,
+    kind: Kind,
+}
+
+enum Kind {
+    /// ATX #i loads (A OR a constant) AND #i into A and X, and sets N and Z by it. The cases are
+    /// the value of A and the operand.
+    Atx(&'static [(u8, u8)]),
+    /// SYA abs,X and SXA abs,Y store Y and X AND the high byte of the operand plus 1 at the address.
+    /// When the index crosses a page, the high byte of the address may be replaced by that value.
+    /// The cases are the register that's stored, the index and the operand.
+    Store {
+        opcode: u8,
+        mnemonic: &'static str,
+        register: char,
+        index: char,
+        cases: &'static [(u8, u8, u16)],
+    },
+}
+
+/// The tests, every one of them passes all documented variants of its opcode
+pub(crate) const UNSTABLE_TESTS: &[UnstableTest] = &[
+    UnstableTest {
+        name: "unstable_atx",
+        group: "03-immediate",
+        kind: Kind::Atx(&[
+            (0x00, 0xFF),
+            (0xFF, 0x0F),
+            (0x10, 0xF3),
+            (0x00, 0x00),
+            (0x80, 0x80),
+        ]),
+    },
+    UnstableTest {
+        name: "unstable_sya",
+        group: "07-abs_xy",
+        kind: Kind::Store {
+            opcode: 0x9C,
+            mnemonic: "SYA",
+            register: 'Y',
+            index: 'X',
+            cases: &[
+                (0xFF, 0x05, 0x0300),
+                (0x3D, 0x10, 0x0440),
+                (0x01, 0x20, 0x02F0),
+                (0x02, 0x80, 0x03C0),
+            ],
+        },
+    },
+    UnstableTest {
+        name: "unstable_sxa",
+        group: "07-abs_xy",
+        kind: Kind::Store {
+            opcode: 0x9E,
+            mnemonic: "SXA",
+            register: 'X',
+            index: 'Y',
+            cases: &[
+                (0xFF, 0x07, 0x0500),
+                (0x03, 0x30, 0x05E0),
+                (0x14, 0x01, 0x06FF),
+            ],
+        },
+    },
+];
+
+/// Where a store of SYA or SXA goes: the address, the value, and the address with its high byte
+/// replaced by the value when the index crosses a page
+fn store(register: u8, index: u8, operand: u16) -> (u16, u8, Option<u16>) {
+    let address = operand.wrapping_add(u16::from(index));
+    let value = register & ((operand >> 8) as u8).wrapping_add(1);
+    let crossed = address & 0xFF00 != operand & 0xFF00;
+    let glitched = crossed.then_some(u16::from(value) << 8 | address & 0x00FF);
+    (address, value, glitched)
+}
+
+impl UnstableTest {
+    /// An NROM rom with the program of the test at its reset vector
+    pub(crate) fn rom(&self) -> Vec<u8> {
+        let mut program = vec![0xA9, 0x00, 0x85, DONE.0 as u8]; // LDA #0, STA done
+        match &self.kind {
+            Kind::Atx(cases) => {
+                program.extend([0xA2, 0xFF, 0x9A]); // LDX #$FF, TXS
+                for (i, &(a, operand)) in cases.iter().enumerate() {
+                    let at = RESULTS as u8 + 3 * i as u8;
+                    #[rustfmt::skip]
+                    let case = [
+                        0xA9, a,            // LDA #a
+                        0xA2, 0x55,         // LDX #$55
+                        0xAB, operand,      // ATX #i
+                        0x85, at,           // STA results
+                        0x86, at + 1,       // STX results + 1
+                        0x08,               // PHP
+                        0x68,               // PLA
+                        0x85, at + 2,       // STA results + 2
+                    ];
+                    program.extend(case);
+                }
+            }
+            Kind::Store { opcode, cases, .. } => {
+                program.extend([0xA9, SENTINEL]); // LDA #sentinel
+                for &(register, index, operand) in *cases {
+                    let (address, _, glitched) = store(register, index, operand);
+                    for address in std::iter::once(address).chain(glitched) {
+                        program.push(0x8D); // STA address
+                        program.extend(address.to_le_bytes());
+                    }
+                }
+                // SYA stores Y with index X, SXA stores X with index Y
+                let (register_load, index_load) = match opcode {
+                    0x9C => (0xA0, 0xA2),
+                    _ => (0xA2, 0xA0),
+                };
+                for &(register, index, operand) in *cases {
+                    program.extend([register_load, register, index_load, index, *opcode]);
+                    program.extend(operand.to_le_bytes());
+                }
+            }
+        }
+        program.extend([0xA9, DONE.1, 0x85, DONE.0 as u8]); // LDA #done, STA done
+        let end = PROGRAM + program.len() as u16;
+        program.push(0x4C); // JMP end
+        program.extend(end.to_le_bytes());
+
+        let mut rom = ines::vectors_only(HANDLER, PROGRAM, HANDLER);
+        let at = |address: u16| 16 + usize::from(address - 0x8000);
+        rom[at(PROGRAM)..at(PROGRAM) + program.len()].copy_from_slice(&program);
+        rom[at(HANDLER)] = 0x40;
+        rom
+    }
+
+    /// Checks what the program left in memory, given a way to read it
+    pub(crate) fn check(&self, read: &dyn Fn(u16) -> u8) -> Result<(), String> {
+        if read(DONE.0) != DONE.1 {
+            return Err(format!(
+                "the cpu didn't finish the program of {} it started at the reset vector",
+                self.name
+            ));
+        }
+
+        match &self.kind {
+            Kind::Atx(cases) => {
+                let mut magics = MAGICS.to_vec();
+                for (i, &(a, operand)) in cases.iter().enumerate() {
+                    let at = RESULTS + 3 * i as u16;
+                    let (got_a, got_x, status) = (read(at), read(at + 1), read(at + 2));
+                    let fits = |&magic: &u8| {
+                        let result = (a | magic) & operand;
+                        let flags = if result == 0 { ZERO } else { result & NEGATIVE };
+                        got_a == result && got_x == result && status & (NEGATIVE | ZERO) == flags
+                    };
+                    let left: Vec<u8> = magics.iter().copied().filter(fits).collect();
+                    if left.is_empty() {
+                        let expected: Vec<String> = magics
+                            .iter()
+                            .map(|&magic| format!("${:02X}", (a | magic) & operand))
+                            .collect();
+                        return Err(format!(
+                            "ATX #${operand:02X} with A = ${a:02X} left A = ${got_a:02X}, X = ${got_x:02X} \
+                             and the status ${status:02X}, but it loads (A OR a constant) AND the operand \
+                             into A and X, and sets N and Z by it. With the constant of the cases before \
+                             it, that's {}: the constant is $00, $EE or $FF, and the same every time",
+                            expected.join(" or ")
+                        ));
+                    }
+                    magics = left;
+                }
+            }
+            Kind::Store {
+                mnemonic,
+                register: r,
+                index: x,
+                cases,
+                ..
+            } => {
+                for &(register, index, operand) in *cases {
+                    let (address, value, glitched) = store(register, index, operand);
+                    let stored = read(address) == value
+                        || glitched.is_some_and(|glitched| read(glitched) == value);
+                    if stored {
+                        continue;
+                    }
+                    let at = match glitched {
+                        Some(glitched) => format!(
+                            "${address:04X}, or at ${glitched:04X} since the index crosses a page"
+                        ),
+                        None => format!("${address:04X}"),
+                    };
+                    return Err(format!(
+                        "{mnemonic} ${operand:04X},{x} with {r} = ${register:02X} and {x} = ${index:02X} \
+                         left ${:02X} at ${address:04X}, but it stores {r} AND the high byte of the \
+                         operand plus 1, ${value:02X}, at {at}",
+                        read(address)
+                    ));
+                }
+            }
+        }
+        Ok(())
+    }
+}
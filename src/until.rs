@@ -0,0 +1,100 @@
+//! Running a cpu until it gets to an address, for your own tests of roms that say they're done by
+//! getting somewhere instead of by writing a status, see [`run_until_pc`]
+use crate::TestableCpu;
+use std::error::Error;
+use std::fmt;
+use tudelft_nes_ppu::{run_cpu_headless_for, Cpu, Mirroring, Ppu};
+
+/// Runs `cpu` on the ppu until it's about to run the instruction at `target_pc`, for at most
+/// `max_cycles` cycles, and returns the number of cycles that took. When the cpu is at `target_pc`
+/// already, it doesn't run at all.
+///
+/// Returns an error when the cpu didn't get there in time, or when it doesn't implement
+/// [`TestableCpu::program_counter`], which is how the harness sees where it is:
+/// ```no_run
+/// # use tudelft_nes_test::{run_until_pc, TestableCpu};
+/// # fn test<MyCpu: TestableCpu>() -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+/// let rom = std::fs::read("roms/my_test.nes")?;
+/// let mut cpu = MyCpu::get_cpu(&rom)?;
+/// // the rom jumps to `done` at $8123 once it ran its tests
+/// let cycles = run_until_pc(&mut cpu, 0x8123, 1_000_000)?;
+/// assert_eq!(cpu.memory_read(0x0010), 0x42, "wrong result after {cycles} cycles");
+/// # Ok(())
+/// # }
+/// ```
+/// The ppu uses horizontal nametable mirroring, which doesn't change what the cpu does.
+pub fn run_until_pc<T: TestableCpu>(
+    cpu: &mut T,
+    target_pc: u16,
+    max_cycles: u64,
+) -> Result<u64, String> {
+    let Some(pc) = cpu.program_counter() else {
+        return Err(
+            "run_until_pc needs TestableCpu::program_counter to see where the cpu is".to_owned(),
+        );
+    };
+    if pc == target_pc {
+        return Ok(0);
+    }
+
+    let mut until = UntilPc {
+        cpu,
+        target_pc,
+        cycles: 0,
+        reached: false,
+    };
+    let cycles = usize::try_from(max_cycles).unwrap_or(usize::MAX);
+    match run_cpu_headless_for(&mut until, Mirroring::Horizontal, cycles) {
+        // the error is our own `Reached`, which may have been wrapped by the ppu
+        _ if until.reached => Ok(until.cycles),
+        Err(e) => Err(e.to_string()),
+        Ok(()) => Err(format!(
+            "the cpu didn't get to ${target_pc:04X} in {max_cycles} cycles, it's at ${:04X}",
+            until.cpu.program_counter().unwrap_or_default()
+        )),
+    }
+}
+
+/// Wraps the cpu, to stop it once it gets to the address
+struct UntilPc<'a, T> {
+    cpu: &'a mut T,
+    target_pc: u16,
+    cycles: u64,
+    reached: bool,
+}
+
+/// Returned from [`Cpu::tick`] to break out of [`run_cpu_headless_for`] once the cpu got there
+#[derive(Debug)]
+struct Reached;
+
+impl fmt::Display for Reached {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "cpu reached the address")
+    }
+}
+
+impl Error for Reached {}
+
+impl<T: TestableCpu> Cpu for UntilPc<'_, T> {
+    fn tick(&mut self, ppu: &mut Ppu) -> Result<(), Box<dyn Error>> {
+        self.cpu.tick(ppu)?;
+        self.cycles += 1;
+        // the accesses are taken, so the cpu doesn't keep them around
+        self.cpu.bus_accesses(&mut |_| {});
+
+        let pc = self.cpu.program_counter();
+        if pc == Some(self.target_pc) && self.cpu.finished_instruction().unwrap_or(true) {
+            self.reached = true;
+            return Err(Box::new(Reached));
+        }
+        Ok(())
+    }
+
+    fn ppu_read_chr_rom(&self, offset: u16) -> u8 {
+        self.cpu.ppu_read_chr_rom(offset)
+    }
+
+    fn non_maskable_interrupt(&mut self) {
+        self.cpu.non_maskable_interrupt()
+    }
+}
@@ -0,0 +1,226 @@
+//! Watchpoints on addresses, of which the harness records the accesses while a test runs, so a
+//! failure can say which code wrote to the address it's about
+use crate::log_target;
+use std::collections::VecDeque;
+use std::fmt;
+
+/// How many hits are kept per run of the cpu, the older ones are dropped
+pub(crate) const MAX_HITS: usize = 100;
+
+/// The kinds of access a [`Watchpoint`] records
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(rename_all = "lowercase")
+)]
+pub enum Access {
+    /// Only reads
+    Read,
+    /// Only writes
+    Write,
+    /// Reads and writes
+    ReadWrite,
+}
+
+/// Addresses of which the accesses are recorded while the tests run, see [`TestConfig::watchpoints`](crate::TestConfig::watchpoints):
+/// ```
+/// use tudelft_nes_test::Watchpoint;
+///
+/// let oam_dma = Watchpoint::writes(0x4014);
+/// let status_text = Watchpoint::writes(0x6000).through(0x6003);
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Watchpoint {
+    /// The first address that is watched
+    pub first: u16,
+    /// The last address that is watched, the same as `first` for a single address
+    pub last: u16,
+    /// Which accesses are recorded
+    pub access: Access,
+}
+
+impl Watchpoint {
+    /// Watches the writes to `address`
+    pub fn writes(address: u16) -> Self {
+        Self::new(address, Access::Write)
+    }
+
+    /// Watches the reads of `address`
+    pub fn reads(address: u16) -> Self {
+        Self::new(address, Access::Read)
+    }
+
+    /// Watches both the reads of and the writes to `address`
+    pub fn accesses(address: u16) -> Self {
+        Self::new(address, Access::ReadWrite)
+    }
+
+    /// Also watches the addresses after the first, up to and including `last`
+    ///
+    /// # Panics
+    /// When `last` comes before the first address
+    pub fn through(self, last: u16) -> Self {
+        assert!(
+            last >= self.first,
+            "${last:04X} comes before ${:04X}, the first address of the watchpoint",
+            self.first
+        );
+        Self { last, ..self }
+    }
+
+    fn new(address: u16, access: Access) -> Self {
+        Self {
+            first: address,
+            last: address,
+            access,
+        }
+    }
+
+    fn matches(&self, access: &BusAccess) -> bool {
+        let kind = match self.access {
+            Access::Read => !access.write,
+            Access::Write => access.write,
+            Access::ReadWrite => true,
+        };
+        kind && (self.first..=self.last).contains(&access.address)
+    }
+}
+
+/// A read or write of the cpu on its bus, see [`TestableCpu::bus_accesses`](crate::TestableCpu::bus_accesses)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct BusAccess {
+    /// The address that was accessed
+    pub address: u16,
+    /// The value that was read or written
+    pub value: u8,
+    /// Whether it was a write, otherwise it was a read
+    pub write: bool,
+}
+
+/// An access to a watched address
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct WatchpointHit {
+    /// The access
+    pub access: BusAccess,
+    /// The cycle of the test in which it happened
+    pub cycle: u64,
+    /// Where the cpu was executing just after the access, when it implements
+    /// [`TestableCpu::program_counter`](crate::TestableCpu::program_counter)
+    pub program_counter: Option<u16>,
+}
+
+impl fmt::Display for WatchpointHit {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let BusAccess { address, value, .. } = self.access;
+        if self.access.write {
+            write!(
+                f,
+                "cycle {}: wrote ${value:02X} to ${address:04X}",
+                self.cycle
+            )?;
+        } else {
+            write!(
+                f,
+                "cycle {}: read ${value:02X} from ${address:04X}",
+                self.cycle
+            )?;
+        }
+        if let Some(pc) = self.program_counter {
+            write!(f, " near pc ${pc:04X}")?;
+        }
+        Ok(())
+    }
+}
+
+/// Records the hits of the watchpoints while a cpu runs
+pub(crate) struct Watcher {
+    watchpoints: Vec<Watchpoint>,
+    hits: VecDeque<WatchpointHit>,
+    /// the watched addresses and their values, when the cpu doesn't report its bus accesses and
+    /// the writes are found by watching the values change
+    polled: Option<Vec<(u16, u8)>>,
+}
+
+impl Watcher {
+    pub(crate) fn new(watchpoints: &[Watchpoint]) -> Self {
+        Self {
+            watchpoints: watchpoints.to_vec(),
+            hits: VecDeque::new(),
+            polled: None,
+        }
+    }
+
+    pub(crate) fn is_empty(&self) -> bool {
+        self.watchpoints.is_empty()
+    }
+
+    /// Records `access` when it hits a watchpoint
+    pub(crate) fn observe(&mut self, access: BusAccess, cycle: u64, program_counter: Option<u16>) {
+        if self.watchpoints.iter().any(|w| w.matches(&access)) {
+            if self.hits.len() == MAX_HITS {
+                self.hits.pop_front();
+            }
+            self.hits.push_back(WatchpointHit {
+                access,
+                cycle,
+                program_counter,
+            });
+        }
+    }
+
+    /// Finds writes by reading the watched addresses with `read`, for a cpu that doesn't report
+    /// its bus accesses. Only ram is read: reading the registers of the ppu or apu changes them.
+    pub(crate) fn poll(
+        &mut self,
+        read: impl Fn(u16) -> u8,
+        cycle: u64,
+        program_counter: Option<u16>,
+    ) {
+        let polled = self.polled.get_or_insert_with(|| {
+            for w in &self.watchpoints {
+                if w.access == Access::Read || !(w.first..=w.last).all(is_ram) {
+                    log_target::warn!("only the writes to ram of watchpoint ${:04X}-${:04X} are seen, since the cpu doesn't implement TestableCpu::bus_accesses",
+                        w.first,
+                        w.last
+                    );
+                }
+            }
+            self.watchpoints
+                .iter()
+                .filter(|w| w.access != Access::Read)
+                .flat_map(|w| w.first..=w.last)
+                .filter(|&a| is_ram(a))
+                .map(|a| (a, read(a)))
+                .collect()
+        });
+
+        let mut changes = Vec::new();
+        for (address, value) in polled.iter_mut() {
+            let new = read(*address);
+            if new != *value {
+                *value = new;
+                changes.push(BusAccess {
+                    address: *address,
+                    value: new,
+                    write: true,
+                });
+            }
+        }
+        for access in changes {
+            self.observe(access, cycle, program_counter);
+        }
+    }
+
+    pub(crate) fn hits(&self) -> Vec<WatchpointHit> {
+        self.hits.iter().copied().collect()
+    }
+}
+
+/// The internal ram and the ram on the cartridge
+fn is_ram(address: u16) -> bool {
+    address < 0x2000 || (0x6000..0x8000).contains(&address)
+}
@@ -0,0 +1,272 @@
+//! Runs a test rom in the window of the ppu, so you can watch what it draws while it runs
+use crate::all_instrs::{all_instrs_status_code, without_unofficial, INSTR_GROUPS};
+use crate::log_target;
+use crate::nestest::nestest_status_code;
+use crate::rom_sets::{self, Protocol};
+use crate::runner::{RunOptions, Runner};
+use crate::status::{blargg_status_at, read_status_string_at, BlarggStatus, StatusAddresses};
+use crate::{
+    check_mapper, load_cpu, load_rom, nestest_rom, rom_path, InputScript, TestConfig, TestError,
+    TestSelector, TestableCpu, ALL_INSTR, OFFICIAL_ONLY,
+};
+use std::borrow::Cow;
+use std::error::Error;
+use std::sync::mpsc;
+use tudelft_nes_ppu::{run_cpu, Cpu, Ppu};
+
+/// Every how many cycles the status of the rom is read
+const CHECK_CYCLES: u64 = 10_000;
+
+/// How the harness sees that the rom in the window is done
+enum Finish {
+    /// The rom reports its status at $6000
+    Status,
+    /// The rom stores a result code at this address, and ends in a loop
+    ResultCode(u16),
+    /// Nestest, which has stored its result codes after this many cycles
+    Nestest(u64),
+    /// The rom only shows its result on the screen, so it runs until the window is closed
+    Never,
+}
+
+/// A rom of the selected tests that can be shown in the window
+struct Shown {
+    name: String,
+    rom: Cow<'static, [u8]>,
+    finish: Finish,
+    input: Option<InputScript>,
+    status_addresses: StatusAddresses,
+}
+
+/// Finds the first of the selected tests that runs a rom with something to look at. Of a set of
+/// roms, that is the first rom of the set.
+fn shown_rom(config: &TestConfig) -> Result<Option<Shown>, String> {
+    let selector = config.selector;
+    let shown = |name: &str, rom, finish| Shown {
+        name: name.to_string(),
+        rom,
+        finish,
+        input: None,
+        status_addresses: StatusAddresses::default(),
+    };
+
+    if selector.contains(TestSelector::OFFICIAL_INSTRS) {
+        let rom = load_rom(config, "official_only.nes", &OFFICIAL_ONLY)?;
+        return Ok(Some(shown("official_only", rom, Finish::Status)));
+    }
+    if selector.contains(TestSelector::ALL_INSTRS) {
+        let rom = load_rom(config, "all_instrs.nes", &ALL_INSTR)?;
+        let rom = without_unofficial(rom, config.rom_opcodes());
+        return Ok(Some(shown("all_instrs", rom, Finish::Status)));
+    }
+
+    for &(group, group_selector) in &INSTR_GROUPS {
+        if !selector.contains(group_selector) {
+            continue;
+        }
+        let Some(rom_dir) = &config.rom_dir else {
+            return Err(format!(
+                "instr_test {group} needs {group}.nes from instr_test-v5/rom_singles, set a rom directory to load it from"
+            ));
+        };
+        let path = rom_dir.join(format!("{group}.nes"));
+        let rom = std::fs::read(&path)
+            .map_err(|e| format!("couldn't read rom {}: {e}", path.display()))?;
+        let rom = without_unofficial(Cow::Owned(rom), config.rom_opcodes());
+        return Ok(Some(shown(group, rom, Finish::Status)));
+    }
+
+    for set in rom_sets::bundled() {
+        if !selector.contains(set.selector) {
+            continue;
+        }
+        let file_name = &set.roms[0];
+        let path = rom_path(&set.name, set, file_name, config)?;
+        let rom = std::fs::read(&path)
+            .map_err(|e| format!("couldn't read rom {}: {e}", path.display()))?;
+        let finish = match set.protocol {
+            Protocol::Status => Finish::Status,
+            Protocol::ResultCode(address) => Finish::ResultCode(address),
+            Protocol::Visual | Protocol::Frames(_) => Finish::Never,
+        };
+        let input = match config.input_scripts.get(&set.selector) {
+            Some(script) => Some(script.clone()),
+            None => set.input.clone(),
+        };
+        let name = file_name.trim_end_matches(".nes");
+        return Ok(Some(Shown {
+            input,
+            status_addresses: set.status_addresses,
+            ..shown(name, Cow::Owned(rom), finish)
+        }));
+    }
+
+    if selector.contains(TestSelector::NESTEST) {
+        let rom = nestest_rom(config)?;
+        let cycles = config.cycle_budget(TestSelector::NESTEST, 1_000_000);
+        return Ok(Some(shown("nestest", rom, Finish::Nestest(cycles))));
+    }
+
+    Ok(None)
+}
+
+/// Runs the cpu in the window, and prints the result once the rom is done. After that the cpu
+/// doesn't run anymore, so the window keeps showing what the rom drew last.
+struct Windowed<T: TestableCpu> {
+    runner: Runner<T>,
+    name: String,
+    finish: Finish,
+    cycles: u64,
+    /// the cycle in which the rom asked for the reset button to be pressed
+    reset_requested: Option<u64>,
+    status_addresses: StatusAddresses,
+    status: String,
+    done: bool,
+}
+
+impl<T: TestableCpu> Windowed<T> {
+    /// The result of the rom, once it is done
+    fn result(&mut self, stuck: bool) -> Option<Result<(), TestError>> {
+        let (cpu, at) = (&self.runner.cpu, &self.status_addresses);
+        let result = match self.finish {
+            Finish::Status => match blargg_status_at(cpu, at) {
+                Some(BlarggStatus::Finished(_)) => all_instrs_status_code(cpu, at),
+                _ if stuck => Err(TestError::String(format!(
+                    "the rom stopped before it finished: '{}'",
+                    read_status_string_at(cpu, at).trim()
+                ))),
+                _ => return None,
+            },
+            Finish::ResultCode(address) if stuck => match cpu.memory_peek(address) {
+                1 => Ok(()),
+                0 => Err(TestError::String(format!(
+                    "the rom didn't store a result code at ${address:04X}"
+                ))),
+                code => Err(TestError::String(format!(
+                    "failed with result code {code}, the readme of the rom explains what it means"
+                ))),
+            },
+            Finish::Nestest(cycles) if stuck || self.cycles >= cycles => {
+                nestest_status_code(cpu.memory_peek(0x0002), cpu.memory_peek(0x0003))
+            }
+            Finish::Never if stuck => Err(TestError::String(
+                "the rom stopped running, look at the window to see what it showed".to_owned(),
+            )),
+            _ => return None,
+        };
+        Some(self.runner.explain(result))
+    }
+
+    fn check(&mut self) -> Result<(), TestError> {
+        let status = read_status_string_at(&self.runner.cpu, &self.status_addresses);
+        let status = status.split('\n').next().unwrap_or_default().trim();
+        if !status.is_empty() && status != self.status {
+            log_target::info!("{:05}k cycles passed: {status}", self.cycles / 1000);
+            self.status = status.to_string();
+        }
+
+        match (
+            blargg_status_at(&self.runner.cpu, &self.status_addresses),
+            self.reset_requested,
+        ) {
+            (Some(BlarggStatus::ResetRequested), None) => {
+                self.reset_requested = Some(self.cycles);
+            }
+            // the rom wants the button to be pressed after at least 100ms, this is about 110ms
+            (Some(BlarggStatus::ResetRequested), Some(cycle)) if self.cycles - cycle >= 200_000 => {
+                if !self.runner.cpu.reset() {
+                    return Err(TestError::String(
+                        "this rom needs TestableCpu::reset to press the reset button".to_owned(),
+                    ));
+                }
+                log_target::info!("{:05}k cycles passed: pressed reset", self.cycles / 1000);
+                self.reset_requested = None;
+            }
+            _ => {}
+        }
+        Ok(())
+    }
+
+    fn stop(&mut self, result: Result<(), TestError>) {
+        match result {
+            Ok(()) => log_target::info!("{} passed after {} cycles", self.name, self.cycles),
+            Err(e) => log_target::warn!("{} failed after {} cycles: {e}", self.name, self.cycles),
+        }
+        self.done = true;
+    }
+}
+
+impl<T: TestableCpu> Cpu for Windowed<T> {
+    fn tick(&mut self, ppu: &mut Ppu) -> Result<(), Box<dyn Error>> {
+        if self.done {
+            return Ok(());
+        }
+
+        if let Err(e) = self.runner.tick(ppu) {
+            let result = match self.result(self.runner.stuck()) {
+                Some(result) => result,
+                None => Err(TestError::Custom(e.to_string())),
+            };
+            self.stop(result);
+            return Ok(());
+        }
+        self.cycles += 1;
+
+        if self.cycles % CHECK_CYCLES == 0 {
+            let result = match self.check() {
+                Ok(()) => self.result(false),
+                Err(e) => Some(Err(e)),
+            };
+            if let Some(result) = result {
+                self.stop(result);
+            }
+        }
+        Ok(())
+    }
+
+    fn ppu_read_chr_rom(&self, offset: u16) -> u8 {
+        self.runner.ppu_read_chr_rom(offset)
+    }
+
+    fn non_maskable_interrupt(&mut self) {
+        if !self.done {
+            self.runner.non_maskable_interrupt()
+        }
+    }
+}
+
+/// Runs the first test rom of `config.selector` in a window, see [`run_tests_with_window`](crate::run_tests_with_window)
+pub(crate) fn run<T: TestableCpu>(config: &TestConfig) -> Result<(), String> {
+    let Some(shown) = shown_rom(config)? else {
+        return Err(
+            "none of the selected tests runs a rom to show, select one like official_instrs or nestest"
+                .to_owned(),
+        );
+    };
+    check_mapper::<T>(&shown.name, &shown.rom)?;
+
+    let options = RunOptions::of(config, &shown.rom);
+    // nobody listens to the progress, the result is printed instead
+    let (progress, _) = mpsc::channel();
+    let cpu = load_cpu::<T>(&options, &shown.rom).map_err(|e| e.to_string())?;
+    let mut runner = Runner::new(cpu, &progress, &options);
+    if let Some(input) = &shown.input {
+        runner = runner.with_input(input).map_err(|e| e.to_string())?;
+    }
+    if let Finish::Nestest(_) = shown.finish {
+        runner.cpu.set_program_counter(0xC000);
+    }
+
+    let windowed = Windowed {
+        runner,
+        name: shown.name,
+        finish: shown.finish,
+        cycles: 0,
+        reset_requested: None,
+        status_addresses: shown.status_addresses,
+        status: String::new(),
+        done: false,
+    };
+    run_cpu(windowed, options.mirroring.into());
+    Ok(())
+}
@@ -0,0 +1,240 @@
+//! Small programs that test where addresses wrap around: the stack pointer, indexing in the zero
+//! page, pointers at the end of the zero page, and the pointer of `JMP ($xxFF)`. They run like the
+//! micro tests of interrupts, and catch in milliseconds what the test roms only show as one failing
+//! group after minutes.
+use crate::interrupts::{MicroTest, UNUSED_HANDLER};
+
+/// The micro tests, every one is a sub-test
+pub(crate) const MICRO_TESTS: &[MicroTest] = &[
+    MicroTest {
+        name: "stack_wrap",
+        #[rustfmt::skip]
+        program: &[
+            0xA2, 0x00,         // 0200: LDX #$00
+            0x9A,               // 0202: TXS
+            0xA9, 0x5A,         // 0203: LDA #$5A
+            0x48,               // 0205: PHA         S is $00, so this pushes to $0100
+            0xBA,               // 0206: TSX
+            0x86, 0x10,         // 0207: STX $10
+            0xAD, 0x00, 0x01,   // 0209: LDA $0100
+            0x85, 0x11,         // 020C: STA $11
+            0xA9, 0xA5,         // 020E: LDA #$A5
+            0x8D, 0x00, 0x01,   // 0210: STA $0100
+            0x68,               // 0213: PLA         S is $FF, so this pulls from $0100
+            0x85, 0x12,         // 0214: STA $12
+            0xBA,               // 0216: TSX
+            0x86, 0x13,         // 0217: STX $13
+            0xE6, 0x14,         // 0219: INC $14
+            0x4C, 0x1B, 0x02,   // 021B: JMP $021B
+        ],
+        nmi_handler: UNUSED_HANDLER,
+        irq_handler: UNUSED_HANDLER,
+        nmi: false,
+        check: |read| {
+            if read(0x14) != 1 {
+                return Err("the cpu didn't finish the PHA/PLA program with S = $00".into());
+            }
+            if read(0x11) != 0x5A {
+                return Err(format!(
+                    "PHA with S = $00 left ${:02X} at $0100, it should push to $0100",
+                    read(0x11)
+                ));
+            }
+            if read(0x10) != 0xFF {
+                return Err(format!(
+                    "the stack pointer was ${:02X} after PHA with S = $00, it should wrap around to $FF",
+                    read(0x10)
+                ));
+            }
+            if read(0x12) != 0xA5 {
+                return Err(format!(
+                    "PLA with S = $FF pulled ${:02X}, it should wrap around and pull $A5 from $0100",
+                    read(0x12)
+                ));
+            }
+            if read(0x13) != 0x00 {
+                return Err(format!(
+                    "the stack pointer was ${:02X} after PLA with S = $FF, it should wrap around to $00",
+                    read(0x13)
+                ));
+            }
+            Ok(())
+        },
+    },
+    MicroTest {
+        name: "zero_page_indexed_wrap",
+        #[rustfmt::skip]
+        program: &[
+            0xA9, 0x77,         // 0200: LDA #$77
+            0x85, 0x15,         // 0202: STA $15
+            0xA9, 0x66,         // 0204: LDA #$66
+            0x8D, 0x15, 0x01,   // 0206: STA $0115
+            0xA9, 0x55,         // 0209: LDA #$55
+            0x85, 0x17,         // 020B: STA $17
+            0xA9, 0x44,         // 020D: LDA #$44
+            0x8D, 0x17, 0x01,   // 020F: STA $0117
+            0xA2, 0x20,         // 0212: LDX #$20
+            0xA0, 0x20,         // 0214: LDY #$20
+            0xB5, 0xF5,         // 0216: LDA $F5,X   reads $0015
+            0x85, 0x20,         // 0218: STA $20
+            0xA9, 0x99,         // 021A: LDA #$99
+            0x95, 0xF6,         // 021C: STA $F6,X   writes $0016
+            0xB6, 0xF7,         // 021E: LDX $F7,Y   reads $0017
+            0x86, 0x21,         // 0220: STX $21
+            0xE6, 0x22,         // 0222: INC $22
+            0x4C, 0x24, 0x02,   // 0224: JMP $0224
+        ],
+        nmi_handler: UNUSED_HANDLER,
+        irq_handler: UNUSED_HANDLER,
+        nmi: false,
+        check: |read| {
+            if read(0x22) != 1 {
+                return Err("the cpu didn't finish the zero page indexing program".into());
+            }
+            if read(0x20) != 0x77 {
+                let from = if read(0x20) == 0x66 {
+                    "$0115"
+                } else {
+                    "somewhere else"
+                };
+                return Err(format!(
+                    "LDA $F5,X with X = $20 read from {from}, the address wraps around in the zero page: it should read $0015"
+                ));
+            }
+            if read(0x16) != 0x99 {
+                let to = if read(0x0116) == 0x99 {
+                    "$0116"
+                } else {
+                    "somewhere else"
+                };
+                return Err(format!(
+                    "STA $F6,X with X = $20 wrote to {to}, the address wraps around in the zero page: it should write $0016"
+                ));
+            }
+            if read(0x21) != 0x55 {
+                let from = if read(0x21) == 0x44 {
+                    "$0117"
+                } else {
+                    "somewhere else"
+                };
+                return Err(format!(
+                    "LDX $F7,Y with Y = $20 read from {from}, the address wraps around in the zero page: it should read $0017"
+                ));
+            }
+            Ok(())
+        },
+    },
+    MicroTest {
+        name: "indirect_pointer_wrap",
+        #[rustfmt::skip]
+        program: &[
+            0xA9, 0x30,         // 0200: LDA #$30
+            0x85, 0xFF,         // 0202: STA $FF
+            0x85, 0x10,         // 0204: STA $10
+            0x8D, 0x10, 0x01,   // 0206: STA $0110
+            0xA9, 0x04,         // 0209: LDA #$04
+            0x85, 0x00,         // 020B: STA $00     pointers to $0430 in the zero page
+            0x85, 0x11,         // 020D: STA $11
+            0xA9, 0x05,         // 020F: LDA #$05
+            0x8D, 0x00, 0x01,   // 0211: STA $0100   pointers to $0530 outside of it
+            0x8D, 0x11, 0x01,   // 0214: STA $0111
+            0xA9, 0x3C,         // 0217: LDA #$3C
+            0x8D, 0x30, 0x04,   // 0219: STA $0430
+            0xA9, 0xC3,         // 021C: LDA #$C3
+            0x8D, 0x30, 0x05,   // 021E: STA $0530
+            0xA0, 0x00,         // 0221: LDY #$00
+            0xB1, 0xFF,         // 0223: LDA ($FF),Y pointer in $FF and $00
+            0x85, 0x20,         // 0225: STA $20
+            0xA2, 0x01,         // 0227: LDX #$01
+            0xA1, 0xFE,         // 0229: LDA ($FE,X) pointer in $FF and $00
+            0x85, 0x21,         // 022B: STA $21
+            0xA2, 0x20,         // 022D: LDX #$20
+            0xA1, 0xF0,         // 022F: LDA ($F0,X) pointer in $10 and $11
+            0x85, 0x22,         // 0231: STA $22
+            0xE6, 0x23,         // 0233: INC $23
+            0x4C, 0x35, 0x02,   // 0235: JMP $0235
+        ],
+        nmi_handler: UNUSED_HANDLER,
+        irq_handler: UNUSED_HANDLER,
+        nmi: false,
+        check: |read| {
+            if read(0x23) != 1 {
+                return Err("the cpu didn't finish the indirect addressing program".into());
+            }
+            // what it loaded, the instruction, and where its pointer should and shouldn't be
+            let cases = [
+                (read(0x20), "LDA ($FF),Y", "$FF and $00", "$FF and $0100"),
+                (
+                    read(0x21),
+                    "LDA ($FE,X) with X = $01",
+                    "$FF and $00",
+                    "$FF and $0100",
+                ),
+                (
+                    read(0x22),
+                    "LDA ($F0,X) with X = $20",
+                    "$10 and $11",
+                    "$0110 and $0111",
+                ),
+            ];
+            for (loaded, instruction, right, wrong) in cases {
+                let error = match loaded {
+                    0x3C => continue,
+                    0xC3 => format!(
+                        "{instruction} read its pointer from {wrong}, the pointer wraps around in \
+                         the zero page: it should read it from {right}"
+                    ),
+                    _ => format!(
+                        "{instruction} loaded ${loaded:02X}, it should load $3C from $0430, where \
+                         its pointer in {right} points"
+                    ),
+                };
+                return Err(error);
+            }
+            Ok(())
+        },
+    },
+    MicroTest {
+        name: "jmp_indirect_page",
+        #[rustfmt::skip]
+        program: &[
+            0xA9, 0x2B,         // 0200: LDA #$2B
+            0x8D, 0xFF, 0x04,   // 0202: STA $04FF   low byte of the address
+            0xA9, 0x02,         // 0205: LDA #$02
+            0x8D, 0x00, 0x04,   // 0207: STA $0400   high byte, at the start of the page of the pointer
+            0xA9, 0x05,         // 020A: LDA #$05
+            0x8D, 0x00, 0x05,   // 020C: STA $0500   high byte, in the next page
+            0xA9, 0xE6,         // 020F: LDA #$E6
+            0x8D, 0x2B, 0x05,   // 0211: STA $052B   INC $21 at $052B
+            0xA9, 0x21,         // 0214: LDA #$21
+            0x8D, 0x2C, 0x05,   // 0216: STA $052C
+            0xA9, 0x4C,         // 0219: LDA #$4C
+            0x8D, 0x2D, 0x05,   // 021B: STA $052D   JMP $052D after it
+            0xA9, 0x2D,         // 021E: LDA #$2D
+            0x8D, 0x2E, 0x05,   // 0220: STA $052E
+            0xA9, 0x05,         // 0223: LDA #$05
+            0x8D, 0x2F, 0x05,   // 0225: STA $052F
+            0x6C, 0xFF, 0x04,   // 0228: JMP ($04FF)
+            0xE6, 0x20,         // 022B: INC $20
+            0x4C, 0x2D, 0x02,   // 022D: JMP $022D
+        ],
+        nmi_handler: UNUSED_HANDLER,
+        irq_handler: UNUSED_HANDLER,
+        nmi: false,
+        check: |read| {
+            if read(0x21) != 0 {
+                return Err(
+                    "JMP ($04FF) read the high byte of the address from $0500, but the 6502 doesn't \
+                     carry into the high byte of the pointer: it reads it from $0400"
+                        .into(),
+                );
+            }
+            if read(0x20) != 1 {
+                return Err(
+                    "JMP ($04FF) didn't jump to $022B, the address in $04FF and $0400".into(),
+                );
+            }
+            Ok(())
+        },
+    },
+];
@@ -0,0 +1,9 @@
+//! Checks that the tests catch the bugs they're meant to, run it with `cargo test --features selftest`
+#![cfg(feature = "selftest")]
+
+#[test]
+fn harness_catches_buggy_cpus() {
+    if let Err(e) = tudelft_nes_test::self_test() {
+        panic!("{e}");
+    }
+}